@@ -0,0 +1,76 @@
+use lt_world::{LtWorld, Project};
+use typst::World;
+use typst_languagetool::{config::Config, CheckOptions};
+
+use crate::{Args, Format};
+
+/// Print every chunk of the files with the language code, the converted text and a table of its
+/// pieces, as UTF-16 range in the chunk and byte range in the source with both texts.
+pub fn run(
+	args: &Args,
+	world: &LtWorld,
+	config: &Config,
+	project: Option<&Project>,
+) -> anyhow::Result<()> {
+	let Some(path) = &args.path else {
+		return Err(anyhow::anyhow!("Missing `--path` to dump."));
+	};
+	let paths = crate::checked_files(path, project)?;
+	let formats = paths
+		.iter()
+		.map(|path| Format::of(path).unwrap_or(Format::Typst))
+		.collect::<Vec<_>>();
+	let Some(docs) = crate::documents(args, world, &formats) else {
+		return Err(anyhow::anyhow!("Failed to compile document."));
+	};
+	let options = config.convert_options();
+	let chunk_size = args
+		.chunk_size
+		.unwrap_or(CheckOptions::default().chunk_size);
+	let (language, lang) = crate::language(args, config);
+	for (path, format) in paths.iter().zip(formats) {
+		let source = world.source(world.file_id(path))?;
+		let chunks = crate::convert_file(
+			format,
+			&source,
+			&docs,
+			args.submission.chunk_size(chunk_size),
+			lang,
+			&options,
+			&args.lines,
+		);
+		let chunks = args.submission.chunks(chunks);
+		for (index, (text, mapping)) in chunks.iter().enumerate() {
+			println!(
+				"{} chunk {}/{} {}",
+				path.display(),
+				index + 1,
+				chunks.len(),
+				crate::chunk_code(mapping, language.as_deref(), config)
+			);
+			println!("{}", text);
+			println!();
+			let units = text.encode_utf16().collect::<Vec<_>>();
+			for (range, location) in mapping.pieces(&source) {
+				let piece = String::from_utf16_lossy(&units[range.clone()]);
+				match location {
+					Some(location) => println!(
+						"{:>6}..{:<6} -> {:>6}..{:<6} {:?} {:?}",
+						range.start,
+						range.end,
+						location.start,
+						location.end,
+						piece,
+						&source.text()[location.clone()]
+					),
+					None => println!(
+						"{:>6}..{:<6} -> {:^14} {:?}",
+						range.start, range.end, "-", piece
+					),
+				}
+			}
+			println!();
+		}
+	}
+	Ok(())
+}
@@ -0,0 +1,95 @@
+use std::{
+	ops::{Not, Range},
+	path::Path,
+	process::Command,
+};
+
+use typst::syntax::Source;
+use typst_languagetool::Diagnostic;
+
+/// Zero-based line ranges added or modified since `base`.
+/// Returns `None` for files not tracked by git, which count as completely changed.
+pub fn changed_lines(path: &Path, base: &str) -> anyhow::Result<Option<Vec<Range<usize>>>> {
+	let Ok(path) = path.canonicalize() else {
+		return Ok(None);
+	};
+	let dir = path.parent().unwrap_or(Path::new("/"));
+	let tracked = Command::new("git")
+		.current_dir(dir)
+		.args(["ls-files", "--error-unmatch"])
+		.arg(&path)
+		.output()?;
+	if tracked.status.success().not() {
+		return Ok(None);
+	}
+
+	let output = Command::new("git")
+		.current_dir(dir)
+		.args([
+			"diff",
+			"--no-ext-diff",
+			"--no-color",
+			"--no-prefix",
+			"--unified=0",
+			base,
+			"--",
+		])
+		.arg(&path)
+		.output()?;
+	if output.status.success().not() {
+		return Err(anyhow::anyhow!(
+			"git diff failed: {}",
+			String::from_utf8_lossy(&output.stderr)
+		));
+	}
+	let diff = String::from_utf8(output.stdout)?;
+	Ok(Some(diff.lines().filter_map(parse_hunk).collect()))
+}
+
+/// Parse the added lines from a hunk header like `@@ -10,2 +12,3 @@`.
+fn parse_hunk(line: &str) -> Option<Range<usize>> {
+	let header = line.strip_prefix("@@ ")?;
+	let (_, added) = header.split_once(" +")?;
+	let (added, _) = added.split_once(' ')?;
+	let (start, count) = match added.split_once(',') {
+		Some((start, count)) => (start.parse::<usize>().ok()?, count.parse::<usize>().ok()?),
+		None => (added.parse::<usize>().ok()?, 1),
+	};
+	if count == 0 {
+		return None;
+	}
+	Some((start - 1)..(start - 1 + count))
+}
+
+/// Keep only diagnostics intersecting the lines, like the changed lines.
+pub fn filter(source: &Source, diagnostics: &mut Vec<Diagnostic>, lines: &[Range<usize>]) {
+	diagnostics.retain(|diagnostic| intersects(source, &diagnostic.locations, lines));
+}
+
+/// Whether one of the byte ranges of the source is on the lines.
+pub fn intersects(source: &Source, ranges: &[Range<usize>], lines: &[Range<usize>]) -> bool {
+	ranges.iter().any(|range| {
+		let (Some(start), Some(end)) = (
+			source.byte_to_line(range.start),
+			source.byte_to_line(range.end),
+		) else {
+			return false;
+		};
+		lines
+			.iter()
+			.any(|lines| lines.start <= end && start < lines.end)
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_parse_hunk() {
+		assert_eq!(parse_hunk("@@ -10 +12 @@"), Some(11..12));
+		assert_eq!(parse_hunk("@@ -10,2 +12,3 @@ fn main() {"), Some(11..14));
+		assert_eq!(parse_hunk("@@ -10,2 +12,0 @@"), None);
+		assert_eq!(parse_hunk("+@@ -10 +12 @@"), None);
+	}
+}
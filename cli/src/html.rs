@@ -0,0 +1,229 @@
+use std::{fmt::Write, ops::Not, path::Path};
+
+use typst_languagetool::readability::Readability;
+
+use crate::{output::MAX_SUGGESTIONS, report::FileReport, Format};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 0; display: flex; }
+nav { position: sticky; top: 0; height: 100vh; overflow-y: auto; min-width: 16em; padding: 1em; background: #f4f4f4; box-sizing: border-box; }
+nav ul { padding-left: 1em; }
+main { padding: 1em; overflow-x: auto; }
+pre { white-space: pre-wrap; line-height: 1.5; }
+mark { background: #ffe08a; position: relative; cursor: help; }
+mark.spelling { background: #ffb3b3; }
+mark .tip { display: none; position: absolute; left: 0; top: 1.5em; z-index: 1; width: 24em; padding: 0.5em; background: #333; color: #fff; border-radius: 4px; white-space: normal; font-family: sans-serif; }
+mark:hover .tip { display: block; }
+.rule { color: #aaa; font-size: 0.8em; }
+.example { display: block; margin-top: 0.3em; font-size: 0.9em; color: #ddd; }
+table { border-collapse: collapse; margin-right: 2em; }
+td { padding: 0 0.5em; }
+";
+
+/// Number of example sentences of each kind shown in a tip.
+const MAX_EXAMPLES: usize = 2;
+
+/// Write a self-contained page with the sources and the highlighted diagnostics,
+/// preceded by the readability metrics.
+pub fn write(
+	path: &Path,
+	files: &[FileReport],
+	readability: Option<&Readability>,
+) -> anyhow::Result<()> {
+	let mut nav = String::new();
+	let mut main = String::new();
+	if let Some(readability) = readability {
+		nav.push_str("<li><a href=\"#readability\">Readability</a></li>");
+		metrics(&mut main, readability)?;
+	}
+	for (index, file) in files.iter().enumerate() {
+		let headings = headings(file);
+		let id = format!("file-{}", index);
+		let name = escape(&file.path.display().to_string());
+		write!(
+			nav,
+			"<li><a href=\"#{}\">{}</a> ({})",
+			id,
+			name,
+			file.diagnostics.len()
+		)?;
+		if headings.is_empty().not() {
+			nav.push_str("<ul>");
+			for (heading, (_, level, title)) in headings.iter().enumerate() {
+				write!(
+					nav,
+					"<li style=\"margin-left: {}em\"><a href=\"#{}-{}\">{}</a></li>",
+					level - 1,
+					id,
+					heading,
+					escape(title)
+				)?;
+			}
+			nav.push_str("</ul>");
+		}
+		nav.push_str("</li>");
+
+		write!(main, "<section id=\"{}\"><h2>{}</h2><pre>", id, name)?;
+		source(&mut main, file, &id, &headings)?;
+		main.push_str("</pre></section>");
+	}
+
+	let html = format!(
+		"<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>typst-languagetool report</title>\n<style>{}</style>\n</head>\n<body>\n<nav><ul>{}</ul></nav>\n<main>{}</main>\n</body>\n</html>\n",
+		STYLE, nav, main
+	);
+	std::fs::write(path, html)?;
+	Ok(())
+}
+
+/// Section with the sentence lengths and the frequent words.
+fn metrics(out: &mut String, readability: &Readability) -> anyhow::Result<()> {
+	write!(
+		out,
+		"<section id=\"readability\"><h2>Readability</h2><p>{} sentences with {:.1} words on average, the longest with {}, {} passive voice diagnostics</p>",
+		readability.sentences(),
+		readability.average(),
+		readability.longest(),
+		readability.passive
+	)?;
+	out.push_str("<div style=\"display: flex\"><table><tr><th>Words</th><th>Sentences</th></tr>");
+	for (lengths, count) in readability.distribution() {
+		write!(out, "<tr><td>{}</td><td>{}</td></tr>", lengths, count)?;
+	}
+	out.push_str("</table><table><tr><th>Word</th><th>Count</th></tr>");
+	for (word, count) in readability.frequent(crate::summary::FREQUENT_WORDS) {
+		write!(out, "<tr><td>{}</td><td>{}</td></tr>", escape(word), count)?;
+	}
+	out.push_str("</table></div></section>");
+	Ok(())
+}
+
+/// Source with the diagnostics as marks and anchors before the headings.
+fn source(
+	out: &mut String,
+	file: &FileReport,
+	id: &str,
+	headings: &[(usize, usize, String)],
+) -> anyhow::Result<()> {
+	let text = file.source.text();
+	let mut diagnostics = file
+		.diagnostics
+		.iter()
+		.filter(|diagnostic| diagnostic.locations.is_empty().not())
+		.collect::<Vec<_>>();
+	diagnostics.sort_by_key(|diagnostic| diagnostic.locations[0].start);
+
+	let mut headings = headings.iter().enumerate().peekable();
+	let mut position = 0;
+	for diagnostic in diagnostics {
+		let range = diagnostic.locations[0].clone();
+		// Overlapping diagnostics can not be nested, only the first is shown.
+		if range.start < position {
+			continue;
+		}
+		anchors(out, &mut headings, text, &mut position, range.start, id)?;
+		let class = if diagnostic.is_spelling() {
+			" class=\"spelling\""
+		} else {
+			""
+		};
+		write!(out, "<mark{}>", class)?;
+		anchors(out, &mut headings, text, &mut position, range.end, id)?;
+		write!(
+			out,
+			"<span class=\"tip\">{}<br><a class=\"rule\" href=\"{}\">{}</a>",
+			escape(&diagnostic.message),
+			escape(&diagnostic.rule_url()),
+			escape(&diagnostic.rule_id)
+		)?;
+		let replacements = diagnostic
+			.replacements
+			.iter()
+			.filter(|replacement| replacement.trim().is_empty().not())
+			.take(MAX_SUGGESTIONS)
+			.map(|replacement| escape(replacement))
+			.collect::<Vec<_>>();
+		if replacements.is_empty().not() {
+			write!(out, "<br>Replacements: {}", replacements.join(", "))?;
+		}
+		let examples = [
+			("Incorrect", &diagnostic.incorrect_examples),
+			("Correct", &diagnostic.correct_examples),
+		];
+		for (title, examples) in examples {
+			for example in examples.iter().take(MAX_EXAMPLES) {
+				let (before, marked, after) = typst_languagetool::marked_example(example);
+				write!(
+					out,
+					"<span class=\"example\">{}: {}<b>{}</b>{}</span>",
+					title,
+					escape(before),
+					escape(marked),
+					escape(after)
+				)?;
+			}
+		}
+		out.push_str("</span></mark>");
+	}
+	anchors(out, &mut headings, text, &mut position, text.len(), id)
+}
+
+/// Copy the text up to `end` and add the anchors of the headings in between.
+fn anchors<'a>(
+	out: &mut String,
+	headings: &mut std::iter::Peekable<impl Iterator<Item = (usize, &'a (usize, usize, String))>>,
+	text: &str,
+	position: &mut usize,
+	end: usize,
+	id: &str,
+) -> anyhow::Result<()> {
+	while let Some(&(index, &(start, _, _))) = headings.peek() {
+		if start > end {
+			break;
+		}
+		out.push_str(&escape(&text[*position..start]));
+		write!(out, "<a id=\"{}-{}\"></a>", id, index)?;
+		*position = start;
+		headings.next();
+	}
+	out.push_str(&escape(&text[*position..end]));
+	*position = end;
+	Ok(())
+}
+
+/// Byte offset, level and title of the headings in the file.
+fn headings(file: &FileReport) -> Vec<(usize, usize, String)> {
+	let marker = match Format::of(&file.path) {
+		Some(Format::Markdown) => '#',
+		Some(Format::Text) => return Vec::new(),
+		_ => '=',
+	};
+	let text = file.source.text();
+	let mut headings = Vec::new();
+	let mut start = 0;
+	for line in text.split_inclusive('\n') {
+		let trimmed = line.trim_start();
+		let level = trimmed.chars().take_while(|&c| c == marker).count();
+		let title = trimmed[level..].trim();
+		if level > 0 && trimmed[level..].starts_with(' ') && title.is_empty().not() {
+			headings.push((start, level, title.to_owned()));
+		}
+		start += line.len();
+	}
+	headings
+}
+
+/// Escape the text for HTML and XML.
+pub fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'&' => escaped.push_str("&amp;"),
+			'"' => escaped.push_str("&quot;"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
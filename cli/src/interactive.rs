@@ -0,0 +1,155 @@
+use std::{
+	collections::HashSet,
+	io::{stdin, stdout, Write},
+	ops::Not,
+	path::Path,
+};
+
+use typst::syntax::Source;
+use typst_languagetool::{
+	config::{Config, FalsePositive, Severity},
+	fix::{self, Fix},
+	Diagnostic, LanguageTool, LanguageToolBackend,
+};
+
+use crate::output;
+
+enum Choice {
+	Replace(usize),
+	Skip,
+	AddWord,
+	DisableRule,
+	HideFalsePositive,
+	Quit,
+}
+
+/// Ask for every diagnostic how to handle it and write accepted fixes after every choice.
+pub async fn run(
+	path: &Path,
+	source: &Source,
+	diagnostics: Vec<Diagnostic>,
+	lt: &LanguageTool,
+	config: &mut Config,
+	config_path: Option<&Path>,
+) -> anyhow::Result<()> {
+	let mut fixes = Vec::<Fix>::new();
+	let mut allowed = HashSet::<(String, String)>::new();
+	let mut disabled = HashSet::<(String, String)>::new();
+	let mut hidden = Vec::<FalsePositive>::new();
+
+	for diagnostic in diagnostics {
+		let word = flagged_text(source, &diagnostic);
+		if allowed.contains(&(diagnostic.language.clone(), word.clone()))
+			|| disabled.contains(&(diagnostic.language.clone(), diagnostic.rule_id.clone()))
+			|| hidden.contains(&FalsePositive::of(&diagnostic))
+		{
+			continue;
+		}
+
+		let severity = Severity::of(&config.severities, &diagnostic);
+		output::pretty(path, source, diagnostic.clone(), severity);
+		match ask(&diagnostic)? {
+			Choice::Replace(index) => {
+				let Some(edits) = fix::fixes(source.text(), &diagnostic, index) else {
+					println!("Diagnostic spans markup and can not be fixed automatically.");
+					continue;
+				};
+				let previous = fixes.len();
+				fixes.extend(edits);
+				let fixed = crate::apply_fixes(path, source.text(), &fixes);
+				if fixed.broken.is_empty().not() {
+					println!("The fix would break the document and is not applied.");
+					fixes.truncate(previous);
+					continue;
+				}
+				std::fs::write(path, fixed.text)?;
+			},
+			Choice::Skip => {},
+			Choice::AddWord => {
+				config.allow_word(&diagnostic.language, &word);
+				let words = &config.dictionary[&diagnostic.language];
+				lt.allow_words(diagnostic.language.parse()?, words).await?;
+				if let Some(path) = config_path {
+					Config::save_word(path, &diagnostic.language, &word)?;
+				}
+				allowed.insert((diagnostic.language, word));
+			},
+			Choice::DisableRule => {
+				config.disable_check(&diagnostic.language, &diagnostic.rule_id);
+				let checks = &config.disabled_checks[&diagnostic.language];
+				lt.disable_checks(diagnostic.language.parse()?, checks)
+					.await?;
+				if let Some(path) = config_path {
+					Config::save_disabled_check(path, &diagnostic.language, &diagnostic.rule_id)?;
+				}
+				disabled.insert((diagnostic.language, diagnostic.rule_id));
+			},
+			Choice::HideFalsePositive => {
+				let false_positive = FalsePositive::of(&diagnostic);
+				if let Some(path) = config_path {
+					Config::save_false_positive(path, &false_positive)?;
+				}
+				config.hide_false_positive(false_positive.clone());
+				hidden.push(false_positive);
+			},
+			Choice::Quit => break,
+		}
+	}
+	Ok(())
+}
+
+fn flagged_text(source: &Source, diagnostic: &Diagnostic) -> String {
+	diagnostic
+		.locations
+		.iter()
+		.filter_map(|range| source.get(range.clone()))
+		.collect()
+}
+
+fn ask(diagnostic: &Diagnostic) -> anyhow::Result<Choice> {
+	let replacements = diagnostic
+		.replacements
+		.iter()
+		.enumerate()
+		.filter(|(_, replacement)| replacement.trim().is_empty().not())
+		.take(output::MAX_SUGGESTIONS)
+		.collect::<Vec<_>>();
+	for (i, (index, replacement)) in replacements.iter().enumerate() {
+		let preview = diagnostic.previews.get(*index).cloned().flatten();
+		print!(
+			"[{}] {}  ",
+			i + 1,
+			preview.as_deref().unwrap_or(replacement)
+		);
+	}
+	println!();
+	println!(
+		"[s] skip  [a] add to dictionary  [d] disable rule  [h] hide in this sentence  [q] quit"
+	);
+
+	loop {
+		print!("> ");
+		stdout().flush()?;
+		let mut line = String::new();
+		if stdin().read_line(&mut line)? == 0 {
+			return Ok(Choice::Quit);
+		}
+		let choice = match line.trim() {
+			"" | "s" => Choice::Skip,
+			"a" => Choice::AddWord,
+			"d" => Choice::DisableRule,
+			"h" => Choice::HideFalsePositive,
+			"q" => Choice::Quit,
+			number => match number.parse::<usize>() {
+				Ok(n) if (1..=replacements.len()).contains(&n) => {
+					Choice::Replace(replacements[n - 1].0)
+				},
+				_ => {
+					println!("Unknown choice '{}'.", number);
+					continue;
+				},
+			},
+		};
+		return Ok(choice);
+	}
+}
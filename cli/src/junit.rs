@@ -0,0 +1,80 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use typst_languagetool::Diagnostic;
+
+use crate::{html::escape, output::byte_to_position, report::FileReport};
+
+/// Write a JUnit report with a test suite for every file and a failed test case for every rule.
+/// Files without diagnostics have a single passed test case.
+pub fn write(path: &Path, files: &[FileReport]) -> anyhow::Result<()> {
+	let tests = files
+		.iter()
+		.map(|file| rules(file).len().max(1))
+		.sum::<usize>();
+	let failures = files.iter().map(|file| rules(file).len()).sum::<usize>();
+	let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+	writeln!(
+		xml,
+		"<testsuites name=\"typst-languagetool\" tests=\"{}\" failures=\"{}\">",
+		tests, failures
+	)?;
+	for file in files {
+		let name = escape(&file.path.display().to_string());
+		let rules = rules(file);
+		writeln!(
+			xml,
+			"\t<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">",
+			name,
+			rules.len().max(1),
+			rules.len()
+		)?;
+		if rules.is_empty() {
+			writeln!(
+				xml,
+				"\t\t<testcase name=\"languagetool\" classname=\"{}\"/>",
+				name
+			)?;
+		}
+		for (rule_id, diagnostics) in rules {
+			writeln!(
+				xml,
+				"\t\t<testcase name=\"{}\" classname=\"{}\">",
+				escape(rule_id),
+				name
+			)?;
+			writeln!(
+				xml,
+				"\t\t\t<failure message=\"{} diagnostics\" type=\"{}\">",
+				diagnostics.len(),
+				escape(&diagnostics[0].rule_category)
+			)?;
+			for diagnostic in diagnostics {
+				let (line, column) = byte_to_position(&file.source, diagnostic.locations[0].start);
+				writeln!(
+					xml,
+					"{}:{}:{} {}",
+					name,
+					line + 1,
+					column + 1,
+					escape(&diagnostic.message)
+				)?;
+			}
+			writeln!(xml, "\t\t\t</failure>\n\t\t</testcase>")?;
+		}
+		writeln!(xml, "\t</testsuite>")?;
+	}
+	writeln!(xml, "</testsuites>")?;
+	std::fs::write(path, xml)?;
+	Ok(())
+}
+
+fn rules(file: &FileReport) -> BTreeMap<&str, Vec<&Diagnostic>> {
+	let mut rules = BTreeMap::<_, Vec<_>>::new();
+	for diagnostic in &file.diagnostics {
+		rules
+			.entry(diagnostic.rule_id.as_str())
+			.or_default()
+			.push(diagnostic);
+	}
+	rules
+}
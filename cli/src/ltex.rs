@@ -0,0 +1,139 @@
+use std::{
+	ops::Not,
+	path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+use typst_languagetool::config::{Config, FalsePositive};
+
+/// Number of entries added to the configuration.
+#[derive(Debug, Default)]
+pub struct Imported {
+	pub words: usize,
+	pub rules: usize,
+	pub false_positives: usize,
+}
+
+/// Add `ltex.dictionary`, `ltex.disabledRules` and `ltex.hiddenFalsePositives` of the LTeX
+/// settings, like `.vscode/settings.json`, to the configuration file, keeping its comments.
+/// The settings may have comments and trailing commas like in VS Code.
+/// Dictionary entries starting with `:` are files with a word per line, relative to the
+/// workspace folder, entries starting with `-` remove words in LTeX and are skipped.
+pub fn import(settings: &Path, config: &Path) -> anyhow::Result<Imported> {
+	let content = std::fs::read_to_string(settings)?;
+	let value = serde_json::from_str::<Value>(&strip_jsonc(&content))
+		.map_err(|err| anyhow::anyhow!("Invalid settings {}: {}", settings.display(), err))?;
+	let workspace = workspace(settings);
+	let mut imported = Imported::default();
+	for (lang, entries) in languages(&value, "dictionary") {
+		for entry in entries {
+			if let Some(file) = entry.strip_prefix(':') {
+				for word in std::fs::read_to_string(workspace.join(file))?.lines() {
+					let word = word.trim();
+					if word.is_empty().not() && Config::save_word(config, &lang, word)? {
+						imported.words += 1;
+					}
+				}
+			} else if entry.starts_with('-').not() && Config::save_word(config, &lang, &entry)? {
+				imported.words += 1;
+			}
+		}
+	}
+	for (lang, rules) in languages(&value, "disabledRules") {
+		for rule in rules {
+			if Config::save_disabled_check(config, &lang, &rule)? {
+				imported.rules += 1;
+			}
+		}
+	}
+	for (_, entries) in languages(&value, "hiddenFalsePositives") {
+		for entry in entries {
+			let false_positive = serde_json::from_str::<Value>(&entry)
+				.ok()
+				.and_then(|value| {
+					Some(FalsePositive {
+						rule: value.get("rule")?.as_str()?.into(),
+						sentence: value.get("sentence")?.as_str()?.into(),
+					})
+				})
+				.ok_or_else(|| anyhow::anyhow!("Invalid hidden false positive: {}", entry))?;
+			if Config::save_false_positive(config, &false_positive)? {
+				imported.false_positives += 1;
+			}
+		}
+	}
+	Ok(imported)
+}
+
+/// The strings for every language of the setting, as flat `ltex.<name>` key or nested in `ltex`.
+fn languages(value: &Value, name: &str) -> Vec<(String, Vec<String>)> {
+	let setting = value
+		.get(format!("ltex.{}", name))
+		.or_else(|| value.get("ltex")?.get(name));
+	let Some(languages) = setting.and_then(Value::as_object) else {
+		return Vec::new();
+	};
+	let strings = |values: &Value| {
+		let values = values.as_array().map(Vec::as_slice).unwrap_or_default();
+		values
+			.iter()
+			.filter_map(|value| Some(value.as_str()?.to_owned()))
+			.collect()
+	};
+	let mut languages = languages
+		.iter()
+		.map(|(lang, values)| (lang.clone(), strings(values)))
+		.collect::<Vec<_>>();
+	languages.sort();
+	languages
+}
+
+/// The folder of the settings, the parent of `.vscode` for the settings of VS Code.
+fn workspace(settings: &Path) -> PathBuf {
+	let dir = settings.parent().unwrap_or(Path::new(""));
+	match dir.file_name() {
+		Some(name) if name == ".vscode" => dir.parent().unwrap_or(Path::new("")).into(),
+		_ => dir.into(),
+	}
+}
+
+/// Remove the comments and trailing commas of JSON with comments, strings are kept.
+fn strip_jsonc(text: &str) -> String {
+	let mut res = String::with_capacity(text.len());
+	let mut chars = text.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'"' => {
+				res.push(c);
+				while let Some(c) = chars.next() {
+					res.push(c);
+					match c {
+						'\\' => res.extend(chars.next()),
+						'"' => break,
+						_ => {},
+					}
+				}
+			},
+			'/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+			'/' if chars.peek() == Some(&'*') => {
+				chars.next();
+				let mut last = ' ';
+				for c in chars.by_ref() {
+					if last == '*' && c == '/' {
+						break;
+					}
+					last = c;
+				}
+			},
+			'}' | ']' => {
+				let trimmed = res.trim_end().len();
+				if res[..trimmed].ends_with(',') {
+					res.truncate(trimmed - 1);
+				}
+				res.push(c);
+			},
+			_ => res.push(c),
+		}
+	}
+	res
+}
@@ -1,30 +1,91 @@
+mod dump;
+mod git;
+mod html;
+mod interactive;
+mod junit;
+mod ltex;
 mod output;
+mod report;
+#[cfg(feature = "serve")]
+mod serve;
+mod summary;
 
 use clap::{Parser, ValueEnum};
+use futures_util::{stream, StreamExt};
 
 use colored::Colorize;
-use lt_world::LtWorld;
+use lt_world::{Ignore, LtWorld, Project};
 use notify::RecursiveMode;
 use notify_debouncer_mini::new_debouncer;
-use typst_languagetool::{LanguageTool, LanguageToolBackend, Suggestion};
+use typst::{model::Document, syntax::Source, text::Lang, World};
+use typst_languagetool::{
+	cache::Cache,
+	config::{self, Config, Severity},
+	convert::{self, Mapping, Reason, Skipped},
+	fix, glossary, language_entries, merge_adjacent,
+	rank::Ranking,
+	readability::Readability,
+	suppress::Contexts,
+	Diagnostic, LanguageFallback, LanguageTool, LanguageToolBackend, LanguageToolBuilder,
+	Locations, Progress, Quota, RateLimit, Submission,
+};
+
+use report::Report;
 
 use std::{
-	collections::HashMap,
+	collections::{BTreeMap, HashMap, HashSet},
+	ops::{Not, Range},
 	path::{Path, PathBuf},
-	time::Duration,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	time::{Duration, Instant},
 };
 
 #[derive(ValueEnum, Clone, Debug)]
 enum Task {
 	Check,
 	Watch,
+	/// List the languages supported by the backend.
+	Languages,
+	/// List the rules of the backend for `--language`.
+	Rules,
+	/// Answer `GET /check?file=<path>` on `--listen` with the diagnostics as JSON.
+	/// Requires the feature `serve`.
+	Serve,
+	/// Print the converted text of the files with the source range of every piece,
+	/// like it is sent to the backend. No backend is started.
+	Dump,
+	/// Add the dictionary, disabled rules and hidden false positives of the LTeX settings in
+	/// `--path` to the `--config` file. Defaults to `.vscode/settings.json`.
+	ImportLtex,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+	Typst,
+	Text,
+	Markdown,
+}
+
+impl Format {
+	fn of(path: &Path) -> Option<Self> {
+		match path.extension()?.to_str()? {
+			"typ" => Some(Self::Typst),
+			"txt" => Some(Self::Text),
+			"md" | "markdown" => Some(Self::Markdown),
+			_ => None,
+		}
+	}
 }
 
 #[derive(Parser, Debug)]
 struct Args {
 	task: Task,
 
-	/// File to check, may be a folder with `watch`.
+	/// File or folder to check, `.txt` and `.md` files are checked as plain text and Markdown.
+	/// With `--stdin` the virtual path for the input, defaults to `stdin.typ`.
 	#[clap(short, long, default_value = None)]
 	path: Option<PathBuf>,
 
@@ -32,127 +93,740 @@ struct Args {
 	#[clap(short, long, default_value = None)]
 	root: Option<PathBuf>,
 
+	/// Read the markup for `path` from stdin.
+	#[clap(long, default_value_t = false)]
+	stdin: bool,
+
 	/// Main file for the document.
-	/// Defaults to `path`.
-	#[clap(short, long, default_value = None)]
-	main: Option<PathBuf>,
+	/// Defaults to `path`. Repeat it for a project with several documents,
+	/// files included by several of them are checked once with the first including document.
+	#[clap(short, long)]
+	main: Vec<PathBuf>,
 
 	/// Delay for file changes.
 	#[clap(long, default_value_t = 0.1, id = "SECONDS")]
 	delay: f64,
 
-	/// Length in chars to seperate chunks
-	#[clap(long, default_value_t = 1000)]
-	chunk_size: usize,
+	/// Number of files converted in parallel and of requests sent to the backend at the same time.
+	/// Defaults to the number of available threads.
+	#[clap(short, long, default_value = None)]
+	jobs: Option<usize>,
+
+	/// Target length in chars of the chunks, they are only split after sentences or paragraphs.
+	/// Defaults to a size suited to the backend.
+	#[clap(long)]
+	chunk_size: Option<usize>,
+
+	/// Maximum number of changed paragraphs of a chunk sent in one request.
+	#[clap(long)]
+	paragraphs_per_request: Option<usize>,
+
+	/// Send the text by `paragraphs`, only changed paragraphs are checked again,
+	/// or as whole `document` for rules across paragraphs like repeated words.
+	#[clap(long, value_name = "SUBMISSION", default_value = "paragraphs")]
+	submission: Submission,
 
 	/// Print results without annotations for easy regex evaluation.
 	#[clap(long, default_value_t = false)]
 	plain: bool,
 
+	/// Print `path:line:column-column: RULE_ID: message` lines for the quickfix list of vim
+	/// or compilation buffers of emacs.
+	#[clap(long, default_value_t = false, conflicts_with = "plain")]
+	errorformat: bool,
+
+	/// Apply the first replacement of every diagnostic to the file.
+	#[clap(long, default_value_t = false)]
+	fix: bool,
+
+	/// List the fixes of `--fix` instead of applying them.
+	#[clap(long, default_value_t = false, requires = "fix")]
+	dry_run: bool,
+
+	/// Print a unified diff of the fixes instead of applying them.
+	#[clap(long, default_value_t = false)]
+	diff: bool,
+
+	/// Choose for every diagnostic how to fix it.
+	#[clap(short, long, default_value_t = false)]
+	interactive: bool,
+
+	/// Only report diagnostics on lines changed since the git reference.
+	#[clap(long, default_value = None, id = "REF")]
+	git_base: Option<String>,
+
+	/// Only check the lines, like `--lines=120-180`. Repeat it for several ranges.
+	/// The document is still compiled as a whole, but only its pages with the lines are converted.
+	#[clap(long, value_name = "START-END", value_parser = parse_lines)]
+	lines: Vec<Range<usize>>,
+
+	/// Stop sending text to the backend after the seconds, like `--time-budget=30` for a
+	/// pre-commit hook. The chunks on the lines changed since `--git-base` are checked first,
+	/// the paragraphs without cached results afterwards are reported with the reason
+	/// `time-budget` as JSON lines to stderr.
+	#[clap(long, value_name = "SECONDS", value_parser = parse_budget)]
+	time_budget: Option<Duration>,
+
+	/// Supported languages of the backend, set once the backend runs.
+	#[clap(skip)]
+	fallback: LanguageFallback,
+
+	/// Project configuration with dictionary and disabled checks.
+	#[clap(long, default_value = None, env = "TYPST_LT_CONFIG")]
+	config: Option<PathBuf>,
+
+	/// Profile of disabled rules, picky level and checked parts: `academic`, `technical`, `casual`
+	/// or one of `[profiles]` of the configuration. Defaults to `TYPST_LT_PROFILE` and the
+	/// `profile` of the configuration.
+	#[clap(long, default_value = None)]
+	profile: Option<String>,
+
+	/// Keep the results of unchanged paragraphs in the directory for the next run, like in CI.
+	/// Results of paragraphs not checked in a run are removed.
+	#[clap(long, default_value = None, id = "DIR", env = "TYPST_LT_CACHE_DIR")]
+	cache_dir: Option<PathBuf>,
+
+	/// Show the progress of the check.
+	#[clap(long, default_value_t = false)]
+	progress: bool,
+
+	/// Fail with exit code 1 if an issue has this severity or a higher one,
+	/// see `[severities]` of the configuration.
+	#[clap(long, value_name = "SEVERITY")]
+	fail_on: Option<Severity>,
+
+	/// Show the checked text of every diagnostic, to report diagnostics at the wrong location.
+	#[clap(long, default_value_t = false)]
+	debug_mapping: bool,
+
+	/// Locations of the diagnostics as mapped with `keep`, split at line breaks with `lines`
+	/// or merged on the same line with `merge`.
+	#[clap(long, value_name = "LOCATIONS", default_value = "keep")]
+	locations: Locations,
+
+	/// Print a JSON line to stderr for every range of the files that is not checked, with the
+	/// reason like `math`, `comment` or `function`.
+	#[clap(long, default_value_t = false)]
+	log_skipped: bool,
+
+	/// Print the diagnostics per rule, category and file and the checked words after the run.
+	#[clap(long, default_value_t = false)]
+	summary: bool,
+
+	/// Collect the sentence lengths, passive voice diagnostics and frequent words of the checked
+	/// text, shown with `--summary` and in the `--html` report.
+	#[clap(long, default_value_t = false)]
+	readability: bool,
+
+	/// Write the readability metrics of `--readability` as JSON to the file.
+	#[clap(long, default_value = None, id = "JSON")]
+	readability_json: Option<PathBuf>,
+
+	/// Append the raw response of the backend to the file as a JSON line for every check, with
+	/// fields of LanguageTool the diagnostics do not show. Cached paragraphs are not sent.
+	#[clap(long, default_value = None, id = "JSONL")]
+	raw_responses: Option<PathBuf>,
+
+	/// Keep at most this many replacements of a diagnostic.
+	#[clap(long, default_value = None)]
+	max_replacements: Option<usize>,
+
+	/// Show the replacements with the case and length of the flagged text and words of the
+	/// dictionary first.
+	#[clap(long, default_value_t = false)]
+	rank_replacements: bool,
+
+	/// Write a self-contained HTML report with the highlighted diagnostics to the file.
+	#[clap(long, default_value = None, id = "FILE")]
+	html: Option<PathBuf>,
+
+	/// Write a JUnit XML report with a test suite for every file and a test case for every rule.
+	#[clap(long, default_value = None, id = "XML")]
+	junit: Option<PathBuf>,
+
 	/// Use bundled languagetool jar.
 	#[clap(long, default_value_t = false)]
 	bundled: bool,
 
 	/// Custom location for the languagetool jar.
+	/// Defaults to `TYPST_LT_JAR_LOCATION` without another backend.
 	#[clap(long, default_value = None)]
 	jar_location: Option<String>,
 
+	/// Download the LanguageTool release zip with the SHA-256 to the cache directory on first use.
+	#[clap(long, default_value = None, id = "SHA256", conflicts_with_all = ["bundled", "jar_location", "host", "port"])]
+	download_jar: Option<String>,
+
+	/// Version of the downloaded LanguageTool release.
+	#[clap(long, default_value = "6.4", requires = "SHA256")]
+	jar_version: String,
+
+	/// LanguageTool command line jar, run with `java -jar` for every check.
+	#[clap(long, default_value = None, conflicts_with_all = ["bundled", "jar_location", "host", "port"])]
+	commandline_jar: Option<String>,
+
+	/// Start a LanguageTool server in docker, defaults to the image `erikvl87/languagetool`.
+	#[clap(long, default_value = None, num_args = 0..=1, default_missing_value = "erikvl87/languagetool", id = "IMAGE", conflicts_with_all = ["bundled", "jar_location", "host", "port", "commandline_jar"])]
+	docker: Option<String>,
+
+	/// Check English text with the Harper language server, defaults to `harper-ls`.
+	#[clap(long, default_value = None, num_args = 0..=1, default_missing_value = "harper-ls", id = "COMMAND", conflicts_with_all = ["bundled", "jar_location", "host", "port", "commandline_jar", "IMAGE"])]
+	harper: Option<String>,
+
+	/// Language of the messages of the built-in rules, like `de`, independent of the checked
+	/// language. Only the jar backends support it.
+	#[clap(long, default_value = None, id = "LANG")]
+	interface_language: Option<String>,
+
 	/// Host for remote languagetool server.
+	/// Defaults to `TYPST_LT_HOST` without another backend.
 	#[clap(long, default_value = None)]
 	host: Option<String>,
 
-	/// Port for remote languagetool server.
+	/// Port for remote languagetool server, defaults to `TYPST_LT_PORT` with the host.
 	#[clap(long, default_value = None)]
 	port: Option<String>,
+
+	/// Username of the account on the remote server, its dictionary is used for all checks.
+	/// Defaults to `TYPST_LT_USERNAME`.
+	#[clap(long, default_value = None, requires_all = ["host", "api_key"])]
+	username: Option<String>,
+
+	/// API key of the account on the remote server.
+	/// Defaults to `TYPST_LT_API_KEY`.
+	#[clap(long, default_value = None, requires = "username")]
+	api_key: Option<String>,
+
+	/// Header sent with every request to the remote server, like `--header="Cookie: session=..."`.
+	#[clap(long = "header", value_name = "NAME: VALUE", value_parser = parse_header, requires = "host")]
+	headers: Vec<(String, String)>,
+
+	/// Bearer token sent as `Authorization` header, for a server behind an authenticating proxy.
+	/// Defaults to `TYPST_LT_BEARER_TOKEN`.
+	#[clap(long, default_value = None, requires = "host")]
+	bearer_token: Option<String>,
+
+	/// Proxy for the remote server, defaults to the proxy of `HTTP_PROXY` and `HTTPS_PROXY`.
+	#[clap(long, default_value = None, requires = "host")]
+	proxy: Option<String>,
+
+	/// Send the text gzip compressed to the remote server, it or a proxy in front of it must accept it.
+	#[clap(long, requires = "host")]
+	compress_requests: bool,
+
+	/// Maximum requests per minute to the remote server, the public API is limited by default.
+	#[clap(long, default_value = None)]
+	requests_per_minute: Option<usize>,
+
+	/// Maximum characters per minute send to the remote server.
+	#[clap(long, default_value = None)]
+	characters_per_minute: Option<usize>,
+
+	/// Maximum requests to the remote server within `--quota-hours`, a request exceeding it
+	/// fails instead of being sent.
+	#[clap(long, default_value = None)]
+	quota_requests: Option<usize>,
+
+	/// Maximum characters send to the remote server within `--quota-hours`.
+	#[clap(long, default_value = None)]
+	quota_characters: Option<usize>,
+
+	/// Window of the quota in hours.
+	#[clap(long, default_value_t = 24)]
+	quota_hours: u64,
+
+	/// Language code used for all text instead of the language set in the document, also for
+	/// plain text and Markdown files. Defaults to the `language` of the configuration.
+	/// The rules are listed for it, for `en-US` without one.
+	#[clap(long, default_value = None)]
+	language: Option<String>,
+
+	/// Address of the server of `serve`.
+	#[clap(long, default_value = "127.0.0.1:8750", id = "ADDRESS")]
+	listen: String,
+}
+
+/// Lines like `120-180` or `120`, counted from 1, as a range of line indices.
+fn parse_lines(lines: &str) -> Result<Range<usize>, String> {
+	let (start, end) = lines.split_once('-').unwrap_or((lines, lines));
+	let parse = |line: &str| match line.trim().parse::<usize>() {
+		Ok(line) if line > 0 => Ok(line),
+		_ => Err(format!(
+			"expected `START-END` with lines from 1, found `{}`",
+			lines
+		)),
+	};
+	let (start, end) = (parse(start)?, parse(end)?);
+	if start > end {
+		return Err(format!("the range `{}` ends before it starts", lines));
+	}
+	Ok((start - 1)..end)
+}
+
+/// Seconds like `90` or `1.5` as a duration.
+fn parse_budget(seconds: &str) -> Result<Duration, String> {
+	match seconds.trim().parse::<f64>() {
+		Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+			Ok(Duration::from_secs_f64(seconds))
+		},
+		_ => Err(format!("expected a number of seconds, found `{}`", seconds)),
+	}
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+	let (name, value) = header
+		.split_once(':')
+		.ok_or_else(|| format!("expected `NAME: VALUE`, found `{}`", header))?;
+	Ok((name.trim().into(), value.trim().into()))
+}
+
+/// Settings of the backend from `TYPST_LT_*` variables, like in CI. Flags take precedence and
+/// the variables of the host and the jar are skipped when a flag selects another backend.
+fn merge_env(args: &mut Args) {
+	let var = |name: &str| std::env::var(format!("{}{}", config::ENV_PREFIX, name)).ok();
+	let backend = args.bundled
+		|| args.download_jar.is_some()
+		|| args.commandline_jar.is_some()
+		|| args.docker.is_some()
+		|| args.harper.is_some();
+	if backend.not() && args.host.is_none() && args.jar_location.is_none() {
+		args.host = var("HOST");
+		args.jar_location = var("JAR_LOCATION");
+	}
+	if args.host.is_some() && args.port.is_none() {
+		args.port = var("PORT");
+	}
+	if args.username.is_none() && args.api_key.is_none() {
+		args.username = var("USERNAME");
+		args.api_key = var("API_KEY");
+	}
+	if args.bearer_token.is_none() {
+		args.bearer_token = var("BEARER_TOKEN");
+	}
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-	let args = Args::parse();
+	#[cfg(feature = "tracing")]
+	typst_languagetool::trace::print_timings()?;
+	let mut args = Args::parse();
+	merge_env(&mut args);
+	if args.stdin {
+		if args.interactive {
+			return Err(anyhow::anyhow!(
+				"Interactive mode can not be used with stdin."
+			));
+		}
+		args.path.get_or_insert_with(|| PathBuf::from("stdin.typ"));
+	}
+	if cfg!(feature = "serve").not() && matches!(args.task, Task::Serve) {
+		return Err(anyhow::anyhow!("The server requires the feature `serve`."));
+	}
+	if let Task::ImportLtex = args.task {
+		let settings = args
+			.path
+			.clone()
+			.unwrap_or_else(|| PathBuf::from(".vscode/settings.json"));
+		let config = args
+			.config
+			.as_ref()
+			.ok_or_else(|| anyhow::anyhow!("The import needs the --config file to write to."))?;
+		let imported = ltex::import(&settings, config)?;
+		println!(
+			"Added {} words, {} disabled rules and {} false positives to {}.",
+			imported.words,
+			imported.rules,
+			imported.false_positives,
+			config.display()
+		);
+		return Ok(());
+	}
+	if let Task::Dump = args.task {
+		let (world, config, project) = document(&mut args)?;
+		return dump::run(&args, &world, &config, project.as_ref());
+	}
 
-	let lt = LanguageTool::new(
-		args.bundled,
-		args.jar_location.as_ref(),
-		args.host.as_ref(),
-		args.port.as_ref(),
-	)?;
+	// The builder already checks the version of its backends.
+	let built = args.download_jar.is_some()
+		|| args.commandline_jar.is_some()
+		|| args.docker.is_some()
+		|| args.harper.is_some();
+	let mut lt = match (&args.commandline_jar, &args.docker, &args.harper) {
+		_ if args.download_jar.is_some() => {
+			let cache_dir = dirs::cache_dir()
+				.ok_or_else(|| anyhow::anyhow!("No cache directory."))?
+				.join("typst-languagetool");
+			let sha256 = args.download_jar.clone().unwrap_or_default();
+			LanguageToolBuilder::download(cache_dir, &args.jar_version, sha256)
+				.build()
+				.await?
+		},
+		(Some(jar), _, _) => LanguageToolBuilder::commandline(jar).build().await?,
+		(_, Some(image), _) => LanguageToolBuilder::docker(image).build().await?,
+		(_, _, Some(command)) => LanguageToolBuilder::harper(command).build().await?,
+		(None, None, None) => LanguageTool::new(
+			args.bundled,
+			args.jar_location.as_ref(),
+			args.host.as_ref(),
+			args.port.as_ref(),
+		)?,
+	};
+	if let (Some(username), Some(api_key)) = (&args.username, &args.api_key) {
+		lt.set_login(username, api_key);
+	}
+	let mut headers = args.headers.clone();
+	if let Some(token) = &args.bearer_token {
+		headers.push(("Authorization".into(), format!("Bearer {}", token)));
+	}
+	if headers.is_empty().not() {
+		lt.set_headers(&headers)?;
+	}
+	if let Some(url) = &args.proxy {
+		lt.set_proxy(url)?;
+	}
+	lt.set_compress_requests(args.compress_requests);
+	if args.requests_per_minute.is_some() || args.characters_per_minute.is_some() {
+		lt.set_rate_limit(RateLimit {
+			requests_per_minute: args.requests_per_minute,
+			characters_per_minute: args.characters_per_minute,
+		});
+	}
+	if args.quota_requests.is_some() || args.quota_characters.is_some() {
+		lt.set_quota(Some(Quota {
+			requests: args.quota_requests,
+			characters: args.quota_characters,
+			window: Duration::from_secs(args.quota_hours * 60 * 60),
+		}));
+	}
+	if built.not() {
+		lt.check_version().await?;
+	}
+	if args.interface_language.is_some() {
+		lt.set_interface_language(args.interface_language.clone())
+			.await?;
+	}
+	if let Some(path) = &args.raw_responses {
+		lt.set_raw_hook(Some(output::raw_responses(path)?)).await?;
+	}
+	if let Task::Languages = args.task {
+		for language in lt.languages().await? {
+			println!("{:<12} {}", language.long_code, language.name);
+		}
+		return Ok(());
+	}
+	if let Task::Rules = args.task {
+		let language = args.language.as_deref().unwrap_or("en-US");
+		for rule in lt.rules(language.parse()?).await? {
+			let state = if rule.enabled {
+				""
+			} else {
+				" (off by default)"
+			};
+			println!(
+				"{} [{}]{}: {}",
+				rule.id, rule.category, state, rule.description
+			);
+		}
+		return Ok(());
+	}
 
-	let world = match (args.path.clone(), args.main.clone()) {
+	let (world, config, project) = document(&mut args)?;
+	let synced = config.apply(&lt).await?;
+	// Without a list of the languages, unsupported languages fail in the check.
+	let languages = match lt.languages().await {
+		Ok(languages) => languages,
+		Err(err) if config.languages.is_empty().not() => return Err(err.into()),
+		Err(_) => Vec::new(),
+	};
+	args.fallback = LanguageFallback::new(&languages);
+	if let Some(report) = synced.report() {
+		eprintln!("{}", report);
+	}
+
+	match args.task {
+		Task::Check => check(args, lt, world, config, project).await?,
+		Task::Watch => watch(args, lt, world, config, project).await?,
+		#[cfg(feature = "serve")]
+		Task::Serve => serve::run(args, lt, world, config, project).await?,
+		#[cfg(not(feature = "serve"))]
+		Task::Serve => {},
+		Task::Languages | Task::Rules | Task::Dump | Task::ImportLtex => {},
+	}
+
+	Ok(())
+}
+
+/// World, configuration and project for `--path` and `--main`.
+fn document(args: &mut Args) -> anyhow::Result<(LtWorld, Config, Option<Project>)> {
+	let project = args
+		.main
+		.first()
+		.or(args.path.as_ref())
+		.and_then(|path| Project::find(path));
+	if let Some(project) = &project {
+		args.root.get_or_insert_with(|| project.root.clone());
+		if args.path.as_ref().is_some_and(|path| path.is_dir()) && args.main.is_empty() {
+			args.main.extend(project.entrypoint.clone());
+		}
+	}
+
+	let mut world = match (args.path.clone(), args.main.first().cloned()) {
 		(_, Some(main)) => lt_world::LtWorld::new(main, args.root.clone()),
 		(Some(main), None) => lt_world::LtWorld::new(main, args.root.clone()),
 
 		_ => return Err(anyhow::anyhow!("Invalid typst settings.")),
 	};
 
-	// if let Some(path) = &args.dictionary {
-	// 	let content = std::fs::read_to_string(path)?;
-	// 	let words = content
-	// 		.lines()
-	// 		.map(|line| String::from(line))
-	// 		.collect::<Vec<_>>();
-	// 	lt.allow_words(&words).await?;
-	// }
-	// lt.disable_checks(&args.disabled_checks).await?;
-
-	match args.task {
-		Task::Check => check(args, lt, world).await?,
-		Task::Watch => watch(args, lt, world).await?,
+	if args.stdin {
+		let text = std::io::read_to_string(std::io::stdin())?;
+		world.use_shadow_file(args.path.as_ref().unwrap(), text);
 	}
 
+	let mut config = match &args.config {
+		Some(path) if path.exists() => Config::load(path)?,
+		_ => Config::default(),
+	};
+	config.merge_env();
+	select_profile(args, &mut config)?;
+	Ok((world, config, project))
+}
+
+/// Select the profile of `--profile`, failing for unknown profiles.
+fn select_profile(args: &Args, config: &mut Config) -> anyhow::Result<()> {
+	if let Some(profile) = &args.profile {
+		config.profile = Some(profile.clone());
+	}
+	config.profile()?;
 	Ok(())
 }
 
-async fn check(args: Args, mut lt: LanguageTool, mut world: LtWorld) -> anyhow::Result<()> {
-	handle_file(
-		args.path.as_ref().unwrap(),
-		&mut lt,
+async fn check(
+	args: Args,
+	lt: LanguageTool,
+	world: LtWorld,
+	mut config: Config,
+	project: Option<Project>,
+) -> anyhow::Result<()> {
+	let paths = checked_files(args.path.as_ref().unwrap(), project.as_ref())?;
+	let mut report = Report::new(args.html.is_some() || args.junit.is_some());
+	let (mut cache, version) = new_cache(&args, &lt).await?;
+	handle_files(
+		&paths,
+		&lt,
 		&args,
-		&mut world,
-		args.chunk_size,
-		&mut Cache::new(),
+		&world,
+		&mut cache,
+		&mut config,
+		&mut report,
 	)
 	.await?;
+	save_cache(&args, &cache, version.as_deref())?;
+	finish(&args, &report, &cache)?;
+	match args.fail_on {
+		Some(fail_on) if report.severity >= Some(fail_on) => Err(anyhow::anyhow!(
+			"Found issues with severity {} or higher.",
+			fail_on.as_str()
+		)),
+		_ => Ok(()),
+	}
+}
+
+/// Cache for the run, with the results saved in `--cache-dir` for the version of the backend.
+async fn new_cache(args: &Args, lt: &LanguageTool) -> anyhow::Result<(Cache, Option<String>)> {
+	let mut cache = Cache::new();
+	cache.set_paragraphs_per_request(args.paragraphs_per_request);
+	cache.set_submission(args.submission);
+	let Some(dir) = &args.cache_dir else {
+		return Ok((cache, None));
+	};
+	// The messages of the cached results are in the interface language.
+	let version = match &args.interface_language {
+		Some(lang) => format!("{} {}", lt.version().await?, lang),
+		None => lt.version().await?,
+	};
+	cache.load(dir, &version)?;
+	Ok((cache, Some(version)))
+}
+
+fn save_cache(args: &Args, cache: &Cache, version: Option<&str>) -> anyhow::Result<()> {
+	if let (Some(dir), Some(version)) = (&args.cache_dir, version) {
+		cache.save(dir, version)?;
+	}
 	Ok(())
 }
 
-async fn watch(args: Args, mut lt: LanguageTool, mut world: LtWorld) -> anyhow::Result<()> {
+/// Files excluded by `typst.toml`, `.gitignore` or `.ltignore` and files of downloaded packages
+/// are skipped. The file or the project files in the folder.
+fn checked_files(path: &Path, project: Option<&Project>) -> anyhow::Result<Vec<PathBuf>> {
+	if path.is_dir().not() {
+		return Ok(vec![path.to_owned()]);
+	}
+	let mut paths = Vec::new();
+	typst_files(path, &Ignore::new(path), &mut paths)?;
+	paths.retain(|path| is_project_file(project, path));
+	paths.sort();
+	Ok(paths)
+}
+
+fn is_project_file(project: Option<&Project>, path: &Path) -> bool {
+	Format::of(path).is_some()
+		&& lt_world::is_package_file(path).not()
+		&& project
+			.is_some_and(|project| project.is_excluded(path))
+			.not()
+}
+
+fn typst_files(dir: &Path, ignore: &Ignore, paths: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+	for entry in std::fs::read_dir(dir)? {
+		let path = entry?.path();
+		if ignore.is_ignored(&path, path.is_dir()) {
+			continue;
+		}
+		if path.is_dir() {
+			typst_files(&path, &ignore.child(&path), paths)?;
+		} else if Format::of(&path).is_some() {
+			paths.push(path);
+		}
+	}
+	Ok(())
+}
+
+async fn watch(
+	args: Args,
+	lt: LanguageTool,
+	world: LtWorld,
+	mut config: Config,
+	project: Option<Project>,
+) -> anyhow::Result<()> {
 	let (tx, rx) = std::sync::mpsc::channel();
 	let mut watcher = new_debouncer(Duration::from_secs_f64(args.delay), tx)?;
-	let mut cache = Cache::new();
+	let (mut cache, version) = new_cache(&args, &lt).await?;
+	let mut suggested = false;
+	let ignore = args
+		.path
+		.as_ref()
+		.filter(|path| path.is_dir())
+		.map(|path| Ignore::new(path));
 	watcher
 		.watcher()
 		.watch(world.root(), RecursiveMode::Recursive)?;
+	let config_path = args
+		.config
+		.as_ref()
+		.and_then(|path| path.canonicalize().ok());
+	if let Some(path) = &config_path {
+		if path.starts_with(world.root()).not() {
+			watcher.watcher().watch(path, RecursiveMode::NonRecursive)?;
+		}
+	}
 
 	for events in rx {
-		for event in events.unwrap() {
-			match event.path.extension() {
-				Some(ext) if ext == "typ" => {},
-				_ => continue,
+		let events = events.unwrap();
+		let config_changed = events
+			.iter()
+			.any(|event| config_path.is_some() && event.path.canonicalize().ok() == config_path);
+		let paths = if config_changed {
+			// The new dictionary and disabled checks apply to all files.
+			let path = config_path.as_ref().unwrap();
+			let new_config = match Config::load(path) {
+				Ok(mut new_config) => {
+					new_config.merge_env();
+					if let Err(err) = select_profile(&args, &mut new_config) {
+						eprintln!("Could not load the changed configuration: {}", err);
+						continue;
+					}
+					new_config
+				},
+				Err(err) => {
+					eprintln!("Could not load the changed configuration: {}", err);
+					continue;
+				},
+			};
+			if let Some(report) = new_config.apply_changes(&config, &lt).await?.report() {
+				eprintln!("{}", report);
 			}
-
-			handle_file(
-				&event.path,
-				&mut lt,
-				&args,
-				&mut world,
-				args.chunk_size,
-				&mut cache,
-			)
-			.await?;
+			config = new_config;
+			checked_files(args.path.as_ref().unwrap(), project.as_ref())?
+		} else {
+			events
+				.into_iter()
+				.map(|event| event.path)
+				.filter(|path| is_project_file(project.as_ref(), path))
+				.filter(|path| {
+					let ignored = ignore.as_ref().map(|ignore| ignore.is_ignored_below(path));
+					ignored != Some(true)
+				})
+				.collect::<Vec<_>>()
+		};
+		if paths.is_empty() {
+			continue;
 		}
+		let mut report = Report::new(args.html.is_some() || args.junit.is_some());
+		// The language is only suggested for the first checked file of the session.
+		report.language_suggested = suggested;
+		cache.reset_metrics();
+		handle_files(
+			&paths,
+			&lt,
+			&args,
+			&world,
+			&mut cache,
+			&mut config,
+			&mut report,
+		)
+		.await?;
+		suggested = report.language_suggested;
+		save_cache(&args, &cache, version.as_deref())?;
+		finish(&args, &report, &cache)?;
 	}
 	Ok(())
 }
 
-async fn handle_file(
-	path: &Path,
-	lt: &mut LanguageTool,
+/// Print the summary and write the report files of a run.
+fn finish(args: &Args, report: &Report, cache: &Cache) -> anyhow::Result<()> {
+	if args.summary {
+		report.summary.print(args.plain, cache.metrics());
+	}
+	if let (Some(path), Some(files)) = (&args.html, &report.files) {
+		html::write(path, files, report.summary.readability.as_ref())?;
+	}
+	if let (Some(path), Some(json)) = (&args.readability_json, report.summary.readability_json()) {
+		std::fs::write(path, serde_json::to_string_pretty(&json)?)?;
+	}
+	if let (Some(path), Some(files)) = (&args.junit, &report.files) {
+		junit::write(path, files)?;
+	}
+	Ok(())
+}
+
+/// Convert the files in parallel and check them in order as soon as they are converted,
+/// the chunks of a file are sent with up to `--jobs` requests at the same time.
+async fn handle_files(
+	paths: &[PathBuf],
+	lt: &LanguageTool,
 	args: &Args,
 	world: &LtWorld,
-	chunk_size: usize,
 	cache: &mut Cache,
+	config: &mut Config,
+	report: &mut Report,
 ) -> anyhow::Result<()> {
-	let Some(doc) = world.compile() else {
+	if args.readability || args.readability_json.is_some() {
+		report
+			.summary
+			.readability
+			.get_or_insert_with(Readability::default);
+	}
+	// Files listed twice are checked once.
+	let mut seen = HashSet::new();
+	let paths = paths
+		.iter()
+		.filter(|path| seen.insert(world.file_id(path)))
+		.collect::<Vec<_>>();
+	let formats = paths
+		.iter()
+		.map(|path| Format::of(path).unwrap_or(Format::Typst))
+		.collect::<Vec<_>>();
+	let Some(docs) = documents(args, world, &formats) else {
 		if args.plain {
 			println!("Failed to compile document!");
 		} else {
@@ -161,56 +835,451 @@ async fn handle_file(
 		return Ok(());
 	};
 
-	let file_id = world.file_id(path);
-	let paragraphs = typst_languagetool::convert::document(&doc, chunk_size, file_id);
-	let mut collector = typst_languagetool::FileCollector::new(file_id, world);
-	let mut next_cache = Cache::new();
-	for (text, mapping) in paragraphs {
-		let lang = mapping.long_language();
-		let suggestions = if let Some(suggestions) = cache.get(&text) {
-			suggestions
-		} else {
-			lt.check_text(lang, &text).await?
+	let docs = Arc::new(docs);
+	let file_ids = paths
+		.iter()
+		.map(|path| world.file_id(path))
+		.collect::<Vec<_>>();
+	let mut inputs = Vec::with_capacity(paths.len());
+	for (&file_id, &format) in file_ids.iter().zip(&formats) {
+		inputs.push((format, world.source(file_id)?));
+	}
+	// The Typst files are converted from the compiled documents, so the text of other files is missing.
+	for (path, (format, source)) in paths.iter().zip(&inputs) {
+		if *format == Format::Typst
+			&& docs
+				.iter()
+				.all(|doc| convert::includes(doc, source.id()).not())
+		{
+			eprintln!(
+				"'{}' is not included by the compiled document, add its document with `--main` to check it.",
+				path.display()
+			);
+		}
+	}
+	let inputs = Arc::new(inputs);
+	let options = Arc::new(config.convert_options());
+	let mut ignore = config.ignored()?;
+	for (format, source) in inputs.iter() {
+		if *format == Format::Typst {
+			ignore.add_terms(glossary::terms(source));
+		}
+	}
+	ignore.add_terms(glossary::terms(&world.main()));
+	if args.log_skipped {
+		for (path, (format, source)) in paths.iter().zip(inputs.iter()) {
+			let skipped = match format {
+				Format::Typst => convert::skipped(source, &options),
+				Format::Markdown => convert::markdown::skipped(source.text()),
+				Format::Text => Vec::new(),
+			};
+			output::skipped(path, source, &skipped);
+		}
+	}
+	let ranking = Ranking::new(args.max_replacements, args.rank_replacements);
+	let jobs = args
+		.jobs
+		.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+		.max(1);
+
+	let (language, lang) = language(args, config);
+	let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+	let next = Arc::new(AtomicUsize::new(0));
+	for _ in 0..jobs.min(paths.len()) {
+		let (docs, inputs, next, tx) = (docs.clone(), inputs.clone(), next.clone(), tx.clone());
+		let options = options.clone();
+		let lines = args.lines.clone();
+		let submission = args.submission;
+		let chunk_size = submission.chunk_size(args.chunk_size.unwrap_or(lt.default_chunk_size()));
+		std::thread::spawn(move || loop {
+			let index = next.fetch_add(1, Ordering::Relaxed);
+			let Some((format, source)) = inputs.get(index) else {
+				break;
+			};
+			let paragraphs =
+				convert_file(*format, source, &docs, chunk_size, lang, &options, &lines);
+			let paragraphs = submission.chunks(paragraphs);
+			if tx.send((index, paragraphs)).is_err() {
+				break;
+			}
+		});
+	}
+	drop(tx);
+
+	let mut converted = BTreeMap::new();
+	let mut next_output = 0;
+	let mut warned = HashSet::new();
+	// The rule and spelling files are read once per language and file.
+	let mut hashes = HashMap::new();
+	let deadline = args.time_budget.map(|budget| Instant::now() + budget);
+	let mut over_budget = 0;
+	while let Some((index, paragraphs)) = rx.recv().await {
+		converted.insert(index, paragraphs);
+		while let Some(paragraphs) = converted.remove(&next_output) {
+			let path = paths[next_output];
+			let file_id = file_ids[next_output];
+			next_output += 1;
+			// The lines changed since `--git-base` order the chunks and filter the diagnostics.
+			let changed = match &args.git_base {
+				Some(base) => git::changed_lines(path, base)?,
+				None => None,
+			};
+
+			let mut collector = typst_languagetool::FileCollector::new(file_id, world);
+			collector.set_debug(args.debug_mapping);
+			collector.set_locations(args.locations);
+			collector.set_contexts(
+				Contexts::new(config.disabled_contexts.clone()).with_fragments(&config.fragments),
+			);
+			if report.language_suggested.not() {
+				report.language_suggested = true;
+				suggest_language(lt, &paragraphs, config).await;
+			}
+			let paragraphs_total = paragraphs.len();
+			let mut chunks = Vec::with_capacity(paragraphs_total);
+			for (text, mapping) in paragraphs {
+				let code = chunk_code(&mapping, language.as_deref(), config);
+				let lang = args.fallback.resolve(&code, &config.language_fallbacks);
+				if lang != code && warned.insert(code.clone()) {
+					eprintln!(
+						"Language '{}' is not supported by the backend, checking with '{}'.",
+						code, lang
+					);
+				}
+				let lang = lang.into_owned();
+				let settings = *hashes
+					.entry(lang.clone())
+					.or_insert_with(|| config.settings().hash(&lang));
+				chunks.push((text, mapping, lang, settings));
+			}
+			let order = match deadline {
+				Some(_) => check_order(changed.as_deref(), collector.source(), cache, &chunks),
+				None => (0..chunks.len()).collect(),
+			};
+			let mut checked = std::iter::repeat_with(|| None)
+				.take(chunks.len())
+				.collect::<Vec<_>>();
+			// Up to `--jobs` requests are sent at the same time, the results arrive in order.
+			let requests = order
+				.into_iter()
+				.map(|index| {
+					let (text, _, lang, settings) = &chunks[index];
+					(index, cache.prepare(lang, *settings, text))
+				})
+				.collect::<Vec<_>>();
+			let mut requests = stream::iter(requests)
+				.map(|(index, pending)| async move { (index, pending.send(lt).await) })
+				.buffered(jobs);
+			let mut done = 0;
+			while deadline.is_none_or(|deadline| Instant::now() < deadline) {
+				let Some((index, sent)) = requests.next().await else {
+					break;
+				};
+				checked[index] = Some(cache.finish(sent)?);
+				done += 1;
+				if args.progress {
+					let progress = Progress {
+						file: file_id,
+						files_done: next_output - 1,
+						files_total: paths.len(),
+						paragraphs_done: done,
+						paragraphs_total,
+					};
+					output::progress(path, progress);
+				}
+			}
+			drop(requests);
+			let mut unchecked = Vec::new();
+			for ((text, mapping, lang, settings), checked) in chunks.into_iter().zip(checked) {
+				// Out of time only the cached results of the chunk are used.
+				let mut suggestions = match checked {
+					Some(suggestions) => suggestions,
+					None => {
+						let (suggestions, missing) = cache.lookup(&lang, settings, &text);
+						for (offset, paragraph) in missing {
+							let end = offset + paragraph.encode_utf16().count();
+							let ranges = mapping.range_location(offset..end, collector.source());
+							if let (Some(first), Some(last)) = (ranges.first(), ranges.last()) {
+								unchecked.push(Skipped {
+									range: first.start..last.end,
+									reason: Reason::TimeBudget,
+								});
+							}
+						}
+						suggestions
+					},
+				};
+				ignore.retain(&text, &mut suggestions);
+				if config.keep_adjacent.not() {
+					merge_adjacent(&text, &mut suggestions);
+				}
+				let dictionary = language_entries(&config.dictionary, &lang);
+				let dictionary = dictionary
+					.into_iter()
+					.flatten()
+					.map(String::as_str)
+					.collect();
+				ranking.apply(&text, &mut suggestions, &dictionary);
+				report.summary.add_text(&text);
+
+				collector.add(&text, suggestions, mapping, &lang);
+			}
+			output::skipped(path, collector.source(), &unchecked);
+			over_budget += unchecked.len();
+			if args.progress {
+				output::clear_progress();
+			}
+			let (source, mut diagnostics) = collector.finish();
+			if let Some(lines) = &changed {
+				git::filter(&source, &mut diagnostics, lines);
+			}
+			handle_diagnostics(path, source, diagnostics, lt, args, config, report).await?;
+			// Words added to the dictionary change the settings.
+			hashes.clear();
+		}
+	}
+	if over_budget > 0 {
+		eprintln!(
+			"The time budget was used up, {} paragraphs were not checked.",
+			over_budget
+		);
+	}
+	cache.evict();
+	Ok(())
+}
+
+/// Indices of the chunks in the order they are checked with a time budget, the chunks on the
+/// lines changed since `--git-base` first. Chunks with cached results come before all, since
+/// they do not take time.
+fn check_order(
+	changed: Option<&[Range<usize>]>,
+	source: &Source,
+	cache: &Cache,
+	chunks: &[(String, Mapping, String, u64)],
+) -> Vec<usize> {
+	let mut order = (0..chunks.len()).collect::<Vec<_>>();
+	order.sort_by_cached_key(|&index| {
+		let (text, mapping, lang, settings) = &chunks[index];
+		if cache.lookup(lang, *settings, text).1.is_empty() {
+			return 0;
+		}
+		let Some(changed) = changed else {
+			return 1;
 		};
+		let ranges = mapping.range_location(0..text.encode_utf16().count(), source);
+		if git::intersects(source, &ranges, changed) {
+			1
+		} else {
+			2
+		}
+	});
+	order
+}
 
-		collector.add(&suggestions, mapping);
-		next_cache.insert(text, suggestions);
+/// Print the language detected by the backend for a document without a language.
+/// Failures of the detection are ignored, they do not stop the check.
+async fn suggest_language(lt: &LanguageTool, paragraphs: &[(String, Mapping)], config: &Config) {
+	let Ok(Some(detected)) =
+		typst_languagetool::suggest_language(lt, paragraphs, &config.languages).await
+	else {
+		return;
+	};
+	eprintln!(
+		"The text seems to be {}, set its language with `#set text(lang: \"{}\")` or the `[languages]` section of the configuration.",
+		detected.name,
+		detected.code.language()
+	);
+}
+
+/// Compiled documents of the mains, `None` if one does not compile.
+/// Without Typst files nothing is compiled.
+fn documents(args: &Args, world: &LtWorld, formats: &[Format]) -> Option<Vec<Document>> {
+	if formats.contains(&Format::Typst).not() {
+		return Some(Vec::new());
+	}
+	let mains = std::iter::once(None).chain(args.main.iter().skip(1).map(Some));
+	mains
+		.map(|main| match main {
+			Some(main) => world.compile_main(main),
+			None => world.compile(),
+		})
+		.collect()
+}
+
+/// Code of `--language` or the `language` of the configuration for all text, and its language
+/// for plain text and Markdown files, English without a code.
+fn language(args: &Args, config: &Config) -> (Option<String>, Lang) {
+	let language = args.language.clone().or_else(|| config.language.clone());
+	let lang = (language.as_deref())
+		.and_then(|code| code.split('-').next())
+		.and_then(|lang| lang.to_ascii_lowercase().parse().ok())
+		.unwrap_or(Lang::ENGLISH);
+	(language, lang)
+}
+
+/// Language code of the chunk, a [`convert::FILE_LANGUAGE`] comment takes precedence over the
+/// `language` of all text.
+fn chunk_code(mapping: &Mapping, language: Option<&str>, config: &Config) -> String {
+	match (mapping.file_language(), language) {
+		(Some(code), _) => code.to_owned(),
+		(None, Some(language)) => language.to_owned(),
+		(None, None) => mapping.language_code(&config.languages),
 	}
-	*cache = next_cache;
+}
 
-	let (source, diagnostics) = collector.finish();
+/// Chunks of the file before they are grouped for the submission, only the chunks on `lines`
+/// are kept and only the pages with them are converted. Plain text and Markdown files are in the
+/// language `lang`.
+fn convert_file(
+	format: Format,
+	source: &Source,
+	docs: &[Document],
+	chunk_size: usize,
+	lang: Lang,
+	options: &convert::Options,
+	lines: &[Range<usize>],
+) -> Vec<(String, Mapping)> {
+	let text = source.text();
+	// Files included by several documents are only checked with the first one.
+	let doc = docs
+		.iter()
+		.find(|doc| convert::includes(doc, source.id()))
+		.or(docs.first());
+	let ranges = lines
+		.iter()
+		.filter_map(|lines| line_bytes(source, lines))
+		.collect::<Vec<_>>();
+	let mut paragraphs = match (format, doc) {
+		// Only the pages with the lines are converted.
+		(Format::Typst, Some(doc)) if lines.is_empty().not() => {
+			convert::document_ranges(doc, chunk_size, source, options, &ranges)
+		},
+		(Format::Typst, Some(doc)) => convert::document(doc, chunk_size, source, options),
+		(Format::Typst, None) => Vec::new(),
+		(Format::Text, _) => convert::plain::document(text, chunk_size, lang),
+		(Format::Markdown, _) => convert::markdown::document(text, chunk_size, lang),
+	};
+	if options.links.not() && format != Format::Typst {
+		convert::placeholders(&mut paragraphs);
+	}
+	if options.normalize && format != Format::Typst {
+		convert::normalize(&mut paragraphs);
+	}
+	if lines.is_empty().not() && format != Format::Typst {
+		convert::retain_ranges(&mut paragraphs, source, &ranges);
+	}
+	paragraphs
+}
+
+/// Byte range of the lines in the source.
+fn line_bytes(source: &Source, lines: &Range<usize>) -> Option<Range<usize>> {
+	let start = source.line_to_byte(lines.start)?;
+	let end = source.line_to_byte(lines.end).unwrap_or(source.len_bytes());
+	Some(start..end)
+}
 
-	if args.plain {
+async fn handle_diagnostics(
+	path: &Path,
+	source: Source,
+	mut diagnostics: Vec<Diagnostic>,
+	lt: &LanguageTool,
+	args: &Args,
+	config: &mut Config,
+	report: &mut Report,
+) -> anyhow::Result<()> {
+	if args.lines.is_empty().not() {
+		git::filter(&source, &mut diagnostics, &args.lines);
+	}
+	report.add_file(path, &source, &diagnostics);
+	let severity = diagnostics
+		.iter()
+		.map(|diagnostic| Severity::of(&config.severities, diagnostic))
+		.max();
+	report.severity = report.severity.max(severity);
+	if let Task::Serve = args.task {
+		return Ok(());
+	}
+
+	if args.interactive {
+		let config_path = args.config.as_deref();
+		return interactive::run(path, &source, diagnostics, lt, config, config_path).await;
+	}
+
+	if args.diff {
+		let fixes = fix::select_fixes(source.text(), &diagnostics, |_| Some(0));
+		let fixed = apply_fixes(path, source.text(), &fixes);
+		output::diff(path, source.text(), &fixed.text);
+		return Ok(());
+	}
+
+	if args.fix && args.stdin && args.dry_run.not() {
+		let fixes = fix::select_fixes(source.text(), &diagnostics, |_| Some(0));
+		let fixed = apply_fixes(path, source.text(), &fixes);
+		print!("{}", fixed.text);
+		return Ok(());
+	}
+
+	if args.fix {
+		let fixes = fix::select_fixes(source.text(), &diagnostics, |_| Some(0));
+		let fixed = apply_fixes(path, source.text(), &fixes);
+		for fix in &fixed.broken {
+			output::fix(
+				path,
+				&source,
+				fix,
+				"not applied, the document would not parse",
+			);
+		}
+		if args.dry_run {
+			for fix in &fixed.applied {
+				output::fix(path, &source, fix, "");
+			}
+			println!(
+				"Would apply {} fixes, skip {} overlapping and {} breaking fixes.",
+				fixed.applied.len(),
+				fixed.skipped.len(),
+				fixed.broken.len()
+			);
+		} else {
+			// An unchanged file is not written, so its modification time stays.
+			if fixed.applied.is_empty().not() && fixed.text != source.text() {
+				std::fs::write(path, fixed.text)?;
+			}
+			println!(
+				"Applied {} fixes, skipped {} overlapping and {} breaking fixes.",
+				fixed.applied.len(),
+				fixed.skipped.len(),
+				fixed.broken.len()
+			);
+		}
+	}
+
+	if args.errorformat {
+		for diagnostic in diagnostics {
+			output::errorformat(path, &source, diagnostic);
+		}
+	} else if args.plain {
 		println!("START");
 		for diagnostic in diagnostics {
-			output::plain(&path, &source, diagnostic);
+			let severity = Severity::of(&config.severities, &diagnostic);
+			output::plain(path, &source, diagnostic, severity);
 		}
 		println!("END");
 	} else {
 		println!("{}", "\n\nChecking Document\n".green().bold());
 		for diagnostic in diagnostics {
-			output::pretty(&path, &source, diagnostic);
+			let severity = Severity::of(&config.severities, &diagnostic);
+			output::pretty(path, &source, diagnostic, severity);
 		}
 	}
 
 	Ok(())
 }
 
-#[derive(Debug)]
-struct Cache {
-	cache: HashMap<String, Vec<Suggestion>>,
-}
-
-impl Cache {
-	pub fn new() -> Self {
-		Self { cache: HashMap::new() }
-	}
-
-	pub fn get(&mut self, text: &str) -> Option<Vec<Suggestion>> {
-		self.cache.remove(text)
-	}
-
-	pub fn insert(&mut self, text: String, suggestions: Vec<Suggestion>) {
-		self.cache.insert(text, suggestions);
+/// Apply the fixes to the text of the file, fixes breaking the markup of Typst files are skipped.
+fn apply_fixes(path: &Path, text: &str, fixes: &[fix::Fix]) -> fix::Fixed {
+	match Format::of(path) {
+		Some(Format::Text | Format::Markdown) => fix::apply_fixes(text, fixes),
+		Some(Format::Typst) | None => fix::apply_typst_fixes(text, fixes),
 	}
 }
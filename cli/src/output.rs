@@ -1,24 +1,29 @@
-use std::{io::stdout, io::Write, ops::Not, path::Path};
+use std::{fs::OpenOptions, io::stderr, io::stdout, io::Write, ops::Not, path::Path, sync::Mutex};
 
 use annotate_snippets::{Level, Renderer, Snippet};
+use similar::TextDiff;
 use typst::syntax::Source;
-use typst_languagetool::Diagnostic;
+use typst_languagetool::{
+	config::Severity, convert::Skipped, fix::Fix, Diagnostic, MappingDebug, Progress, RawHook,
+	RawResponse,
+};
 
-const MAX_SUGGESTIONS: usize = 20;
+pub const MAX_SUGGESTIONS: usize = 20;
 
-pub fn plain(file: &Path, source: &Source, diagnostic: Diagnostic) {
+pub fn plain(file: &Path, source: &Source, diagnostic: Diagnostic, severity: Severity) {
 	let mut out = stdout().lock();
 
 	let (start_line, start_column) = byte_to_position(source, diagnostic.locations[0].start);
 	let (end_line, end_column) = byte_to_position(source, diagnostic.locations[0].end);
 	write!(
 		out,
-		"{} {}:{}-{}:{} info {}",
+		"{} {}:{}-{}:{} {} {}",
 		file.display(),
 		start_line + 1,
 		start_column + 1,
 		end_line + 1,
 		end_column + 1,
+		severity.as_str(),
 		diagnostic.message,
 	)
 	.unwrap();
@@ -37,9 +42,70 @@ pub fn plain(file: &Path, source: &Source, diagnostic: Diagnostic) {
 	} else {
 		writeln!(out).unwrap();
 	}
+	if let Some(debug) = &diagnostic.debug {
+		writeln!(out, "  {}", mapping_debug(debug)).unwrap();
+	}
+}
+
+/// Range in UTF-16 code units and flagged text of the checked chunk, with its context.
+fn mapping_debug(debug: &MappingDebug) -> String {
+	format!(
+		"checked {}..{} {:?} in {:?}",
+		debug.start, debug.end, debug.flagged, debug.snippet
+	)
+}
+
+/// One line per diagnostic, the end column is on the start line for diagnostics over several lines.
+pub fn errorformat(file: &Path, source: &Source, diagnostic: Diagnostic) {
+	let (start_line, start_column) = byte_to_position(source, diagnostic.locations[0].start);
+	let (end_line, end_column) = byte_to_position(source, diagnostic.locations[0].end);
+	let end_column = if end_line == start_line {
+		end_column
+	} else {
+		let range = source.line_to_range(start_line).unwrap();
+		source.text()[range]
+			.trim_end_matches(['\n', '\r'])
+			.chars()
+			.count()
+	};
+	println!(
+		"{}:{}:{}-{}: {}: {}",
+		file.display(),
+		start_line + 1,
+		start_column + 1,
+		end_column + 1,
+		diagnostic.rule_id,
+		diagnostic.message.replace('\n', " "),
+	);
+}
+
+/// Position, replaced text and replacement of a fix, with an optional note.
+pub fn fix(file: &Path, source: &Source, fix: &Fix, note: &str) {
+	let (line, column) = byte_to_position(source, fix.range.start);
+	let replaced = source.text().get(fix.range.clone()).unwrap_or_default();
+	let note = if note.is_empty() {
+		String::new()
+	} else {
+		format!(" ({})", note)
+	};
+	println!(
+		"{}:{}:{}: {:?} -> {:?}{}",
+		file.display(),
+		line + 1,
+		column + 1,
+		replaced,
+		fix.replacement,
+		note
+	);
 }
 
-pub fn pretty(file: &Path, source: &Source, diagnostic: Diagnostic) {
+pub fn pretty(file: &Path, source: &Source, diagnostic: Diagnostic, severity: Severity) {
+	let level = match severity {
+		Severity::Hint => Level::Note,
+		Severity::Info => Level::Info,
+		Severity::Warning => Level::Warning,
+		Severity::Error => Level::Error,
+	};
 	let file_name = format!("{}", file.display());
 
 	let (start_line, _) = byte_to_position(source, diagnostic.locations[0].start);
@@ -61,7 +127,7 @@ pub fn pretty(file: &Path, source: &Source, diagnostic: Diagnostic) {
 	let start = diagnostic.locations[0].start - context.start;
 	let end = diagnostic.locations[0].end - context.start;
 
-	snippet = snippet.annotation(Level::Info.span(start..end).label(&diagnostic.message));
+	snippet = snippet.annotation(level.span(start..end).label(&diagnostic.message));
 
 	for replacement in diagnostic
 		.replacements
@@ -69,18 +135,85 @@ pub fn pretty(file: &Path, source: &Source, diagnostic: Diagnostic) {
 		.filter(|replacement| replacement.trim().is_empty().not())
 		.take(MAX_SUGGESTIONS)
 	{
-		snippet = snippet.annotation(Level::Help.span(end..end).label(&replacement));
+		snippet = snippet.annotation(Level::Help.span(end..end).label(replacement));
 	}
-	let message = Level::Info
+	let debug = diagnostic.debug.as_ref().map(mapping_debug);
+	let mut message = level
 		.title(&diagnostic.rule_description)
 		.id(&diagnostic.rule_id)
 		.snippet(snippet);
+	if let Some(debug) = &debug {
+		message = message.footer(Level::Note.title(debug));
+	}
 
 	let renderer = Renderer::styled();
 	println!("{}", renderer.render(message));
 }
 
-fn byte_to_position(source: &Source, index: usize) -> (usize, usize) {
+pub fn progress(file: &Path, progress: Progress) {
+	let mut out = stderr().lock();
+	write!(
+		out,
+		"\r\x1b[2K{:>3}% [{}/{}] {} {}/{}",
+		progress.percentage(),
+		progress.files_done + 1,
+		progress.files_total,
+		file.display(),
+		progress.paragraphs_done,
+		progress.paragraphs_total,
+	)
+	.unwrap();
+	out.flush().unwrap();
+}
+
+pub fn clear_progress() {
+	eprint!("\r\x1b[2K");
+}
+
+pub fn diff(file: &Path, old: &str, new: &str) {
+	let name = file.display();
+	let diff = TextDiff::from_lines(old, new);
+	let diff = diff
+		.unified_diff()
+		.header(&format!("a/{}", name), &format!("b/{}", name))
+		.to_string();
+	print!("{}", diff);
+}
+
+/// One JSON line per skipped range, with the path, 1-based lines and columns and the reason.
+pub fn skipped(file: &Path, source: &Source, skipped: &[Skipped]) {
+	let mut err = stderr().lock();
+	for skipped in skipped {
+		let (start_line, start_column) = byte_to_position(source, skipped.range.start);
+		let (end_line, end_column) = byte_to_position(source, skipped.range.end);
+		let mut value = serde_json::to_value(&skipped.reason).unwrap();
+		value["path"] = file.display().to_string().into();
+		value["start"] = serde_json::json!({ "line": start_line + 1, "column": start_column + 1 });
+		value["end"] = serde_json::json!({ "line": end_line + 1, "column": end_column + 1 });
+		writeln!(err, "{}", value).unwrap();
+	}
+}
+
+/// Append a JSON line with the language and the response of the backend to the file for every
+/// check, the JSON of the servers as it is and the matches of the JNI backend as strings.
+pub fn raw_responses(path: &Path) -> anyhow::Result<RawHook> {
+	let file = OpenOptions::new().create(true).append(true).open(path)?;
+	let file = Mutex::new(file);
+	Ok(RawHook::new(move |lang, response| {
+		let response = match response {
+			RawResponse::Json(json) => {
+				serde_json::from_str(json).unwrap_or_else(|_| json.as_str().into())
+			},
+			RawResponse::Matches(matches) => serde_json::json!(matches),
+		};
+		let line = serde_json::json!({ "language": lang.as_str(), "response": response });
+		if let Err(err) = writeln!(file.lock().unwrap(), "{}", line) {
+			eprintln!("could not write the raw response: {}", err);
+		}
+	}))
+}
+
+pub fn byte_to_position(source: &Source, index: usize) -> (usize, usize) {
 	let line = source.byte_to_line(index).unwrap();
 	let start = source.line_to_byte(line).unwrap();
 	let head = source.get(start..index).unwrap();
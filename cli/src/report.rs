@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+
+use typst::syntax::Source;
+use typst_languagetool::{config::Severity, Diagnostic};
+
+use crate::summary::Summary;
+
+/// Results of a run, the files are only kept for the report files.
+#[derive(Debug, Default)]
+pub struct Report {
+	pub summary: Summary,
+	pub files: Option<Vec<FileReport>>,
+	/// Highest severity of the reported diagnostics.
+	pub severity: Option<Severity>,
+	/// The language detected by the backend was suggested, it is only suggested for the first
+	/// checked file.
+	pub language_suggested: bool,
+}
+
+#[derive(Debug)]
+pub struct FileReport {
+	pub path: PathBuf,
+	pub source: Source,
+	pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+	pub fn new(keep_files: bool) -> Self {
+		Self {
+			summary: Summary::default(),
+			files: keep_files.then(Vec::new),
+			severity: None,
+			language_suggested: false,
+		}
+	}
+
+	pub fn add_file(&mut self, path: &Path, source: &Source, diagnostics: &[Diagnostic]) {
+		self.summary.add_file(path, diagnostics);
+		if let Some(files) = &mut self.files {
+			files.push(FileReport {
+				path: path.to_owned(),
+				source: source.clone(),
+				diagnostics: diagnostics.to_vec(),
+			});
+		}
+	}
+}
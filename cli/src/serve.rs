@@ -0,0 +1,220 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt::Display,
+	ops::Not,
+	path::{Component, Path},
+	time::Duration,
+};
+
+use lt_world::{LtWorld, Project};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::{TcpListener, TcpStream},
+	sync::mpsc,
+	time::timeout,
+};
+use typst_languagetool::{cache::Cache, config::Config, Diagnostic, LanguageTool};
+
+use crate::{report::Report, Args};
+
+/// Size limit of the request line and the headers.
+const MAX_REQUEST: u64 = 16 * 1024;
+/// Time a client has to send the request line and the headers.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Answers `GET /check?file=<path>` with the diagnostics of the file or the files of the folder
+/// as JSON object by path, relative paths are relative to the project root. The backend and the
+/// cache are kept between the requests.
+pub async fn run(
+	args: Args,
+	lt: LanguageTool,
+	world: LtWorld,
+	config: Config,
+	project: Option<Project>,
+) -> anyhow::Result<()> {
+	let listener = TcpListener::bind(&args.listen).await?;
+	eprintln!("Listening on http://{}", listener.local_addr()?);
+	let (cache, version) = crate::new_cache(&args, &lt).await?;
+	let mut server = Server {
+		args,
+		lt,
+		world,
+		config,
+		project,
+		cache,
+		version,
+	};
+	let (requests, mut received) = mpsc::channel(16);
+	let accept = tokio::spawn(accept(listener, requests));
+	// Requests are checked one after another, they share the backend and the cache.
+	while let Some((target, mut stream)) = received.recv().await {
+		server.respond(&target).await.send(&mut stream).await;
+	}
+	accept.await??;
+	Ok(())
+}
+
+/// Read the requests of the connections concurrently, a client that is slow to send its request
+/// does not hold up the others.
+async fn accept(
+	listener: TcpListener,
+	requests: mpsc::Sender<(String, TcpStream)>,
+) -> std::io::Result<()> {
+	loop {
+		let (mut stream, _) = listener.accept().await?;
+		let requests = requests.clone();
+		tokio::spawn(async move {
+			let response = match timeout(REQUEST_TIMEOUT, request_target(&mut stream)).await {
+				Ok(Ok(target)) => {
+					requests.send((target, stream)).await.ok();
+					return;
+				},
+				Ok(Err(response)) => response,
+				Err(_) => Response::error(408, "Request Timeout", "No request received in time."),
+			};
+			response.send(&mut stream).await;
+		});
+	}
+}
+
+struct Server {
+	args: Args,
+	lt: LanguageTool,
+	world: LtWorld,
+	config: Config,
+	project: Option<Project>,
+	cache: Cache,
+	version: Option<String>,
+}
+
+impl Server {
+	async fn respond(&mut self, target: &str) -> Response {
+		let (path, query) = target.split_once('?').unwrap_or((target, ""));
+		if path != "/check" {
+			return Response::error(404, "Not Found", format!("Unknown path `{}`.", path));
+		}
+		let query = match serde_urlencoded::from_str::<HashMap<String, String>>(query) {
+			Ok(query) => query,
+			Err(err) => return Response::error(400, "Bad Request", err),
+		};
+		let Some(file) = query.get("file") else {
+			return Response::error(400, "Bad Request", "Missing parameter `file`.");
+		};
+		// Relative paths are relative to the project root.
+		let path = self.world.root().join(file);
+		if self.in_root(&path).not() {
+			return Response::error(403, "Forbidden", "The file is outside of the project root.");
+		}
+		if path.exists().not() {
+			return Response::error(404, "Not Found", format!("No file `{}`.", file));
+		}
+		match self.check(&path).await {
+			Ok(Some(files)) => Response::json(&files),
+			Ok(None) => Response::error(422, "Unprocessable Entity", "Failed to compile document."),
+			Err(err) => Response::error(500, "Internal Server Error", err),
+		}
+	}
+
+	fn in_root(&self, path: &Path) -> bool {
+		path.starts_with(self.world.root())
+			&& path
+				.components()
+				.all(|component| component != Component::ParentDir)
+	}
+
+	/// Diagnostics by path, `None` if the document does not compile.
+	async fn check(&mut self, path: &Path) -> anyhow::Result<Option<serde_json::Value>> {
+		let paths = crate::checked_files(path, self.project.as_ref())?;
+		let mut report = Report::new(true);
+		self.cache.reset_metrics();
+		crate::handle_files(
+			&paths,
+			&self.lt,
+			&self.args,
+			&self.world,
+			&mut self.cache,
+			&mut self.config,
+			&mut report,
+		)
+		.await?;
+		crate::save_cache(&self.args, &self.cache, self.version.as_deref())?;
+		let files = report.files.unwrap_or_default();
+		if files.is_empty() && paths.is_empty().not() {
+			return Ok(None);
+		}
+		let files = files
+			.into_iter()
+			.map(|file| (file.path.display().to_string(), file.diagnostics))
+			.collect::<BTreeMap<String, Vec<Diagnostic>>>();
+		Ok(Some(serde_json::to_value(files)?))
+	}
+}
+
+/// Target of a `GET` request, like `/check?file=main.typ`. The headers are skipped.
+async fn request_target(stream: &mut TcpStream) -> Result<String, Response> {
+	let bad_request = |err: std::io::Error| Response::error(400, "Bad Request", err);
+	let mut reader = BufReader::new(stream).take(MAX_REQUEST);
+	let mut request_line = String::new();
+	reader
+		.read_line(&mut request_line)
+		.await
+		.map_err(bad_request)?;
+	loop {
+		let mut header = String::new();
+		let read = reader.read_line(&mut header).await.map_err(bad_request)?;
+		if read == 0 || header.trim().is_empty() {
+			break;
+		}
+	}
+	let mut parts = request_line.split_whitespace();
+	let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+		return Err(Response::error(400, "Bad Request", "Invalid request line."));
+	};
+	if method != "GET" {
+		return Err(Response::error(
+			405,
+			"Method Not Allowed",
+			"Only `GET` is supported.",
+		));
+	}
+	Ok(target.to_owned())
+}
+
+struct Response {
+	status: u16,
+	reason: &'static str,
+	body: String,
+}
+
+impl Response {
+	fn json(value: &serde_json::Value) -> Self {
+		Self {
+			status: 200,
+			reason: "OK",
+			body: value.to_string(),
+		}
+	}
+
+	fn error(status: u16, reason: &'static str, message: impl Display) -> Self {
+		let body = serde_json::json!({ "error": message.to_string() });
+		Self { status, reason, body: body.to_string() }
+	}
+
+	async fn write(&self, stream: &mut TcpStream) -> std::io::Result<()> {
+		let head = format!(
+			"HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+			self.status,
+			self.reason,
+			self.body.len()
+		);
+		stream.write_all(head.as_bytes()).await?;
+		stream.write_all(self.body.as_bytes()).await?;
+		stream.shutdown().await
+	}
+
+	async fn send(&self, stream: &mut TcpStream) {
+		if let Err(err) = self.write(stream).await {
+			eprintln!("Could not answer the request: {}", err);
+		}
+	}
+}
@@ -0,0 +1,166 @@
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+};
+
+use colored::Colorize;
+use typst_languagetool::{cache::Metrics, readability::Readability, Diagnostic};
+
+/// Number of frequent words of the readability metrics.
+pub const FREQUENT_WORDS: usize = 10;
+
+/// Counts of the reported diagnostics, printed after a run.
+#[derive(Debug, Default)]
+pub struct Summary {
+	words: usize,
+	diagnostics: usize,
+	rules: HashMap<String, usize>,
+	categories: HashMap<String, usize>,
+	files: Vec<(PathBuf, usize)>,
+	/// Readability of the checked text, only collected with `--readability`.
+	pub readability: Option<Readability>,
+}
+
+impl Summary {
+	/// Count the words of a checked chunk.
+	pub fn add_text(&mut self, text: &str) {
+		self.words += text.split_whitespace().count();
+		if let Some(readability) = &mut self.readability {
+			readability.add_text(text);
+		}
+	}
+
+	pub fn add_file(&mut self, path: &Path, diagnostics: &[Diagnostic]) {
+		self.diagnostics += diagnostics.len();
+		for diagnostic in diagnostics {
+			*self.rules.entry(diagnostic.rule_id.clone()).or_default() += 1;
+			let category = diagnostic.rule_category.clone();
+			*self.categories.entry(category).or_default() += 1;
+			if let Some(readability) = &mut self.readability {
+				readability.add_rule(&diagnostic.rule_id);
+			}
+		}
+		self.files.push((path.to_owned(), diagnostics.len()));
+	}
+
+	pub fn print(&self, plain: bool, metrics: &Metrics) {
+		let rules = sorted(self.rules.clone());
+		let categories = sorted(self.categories.clone());
+		let files = self.files.iter();
+		let files = sorted(files.map(|(path, count)| (path.display().to_string(), *count)));
+
+		if plain {
+			println!("SUMMARY");
+			println!("words {}", self.words);
+			println!("diagnostics {}", self.diagnostics);
+			println!("requests {}", metrics.requests);
+			println!("failures {}", metrics.failures);
+			println!("rate_limited {}", metrics.rate_limited);
+			println!("quota_exceeded {}", metrics.quota_exceeded);
+			println!("chars {}", metrics.chars);
+			println!("cache_hits {}", metrics.hits);
+			println!("cache_misses {}", metrics.misses);
+			println!("latency_ms {}", metrics.latency.as_millis());
+			for (name, values) in [("rule", rules), ("category", categories), ("file", files)] {
+				for (value, count) in values {
+					println!("{} {} {}", name, value, count);
+				}
+			}
+			if let Some(readability) = &self.readability {
+				println!("sentences {}", readability.sentences());
+				println!("words_per_sentence {:.1}", readability.average());
+				println!("longest_sentence {}", readability.longest());
+				println!("passive {}", readability.passive);
+				for (lengths, count) in readability.distribution() {
+					println!("sentence_length {} {}", lengths, count);
+				}
+				for (word, count) in readability.frequent(FREQUENT_WORDS) {
+					println!("word {} {}", word, count);
+				}
+			}
+			println!("END");
+			return;
+		}
+
+		println!("{}", "\nSummary\n".green().bold());
+		println!(
+			"{} diagnostics in {} files, {} words checked",
+			self.diagnostics,
+			self.files.len(),
+			self.words
+		);
+		println!(
+			"{} requests with {} characters in {:.2?}, {} of {} paragraphs cached",
+			metrics.requests,
+			metrics.chars,
+			metrics.latency,
+			metrics.hits,
+			metrics.hits + metrics.misses
+		);
+		if metrics.failures > 0 {
+			println!(
+				"{} requests failed, {} rate limited, {} over the quota",
+				metrics.failures, metrics.rate_limited, metrics.quota_exceeded
+			);
+		}
+		for (title, values) in [
+			("Rules", rules),
+			("Categories", categories),
+			("Files", files),
+		] {
+			println!("\n{}", title.bold());
+			for (value, count) in values {
+				println!("{:>6}  {}", count, value);
+			}
+		}
+		if let Some(readability) = &self.readability {
+			println!("\n{}", "Readability".bold());
+			println!(
+				"{} sentences with {:.1} words on average, the longest with {}, {} passive voice diagnostics",
+				readability.sentences(),
+				readability.average(),
+				readability.longest(),
+				readability.passive
+			);
+			println!("\n{}", "Sentence lengths".bold());
+			for (lengths, count) in readability.distribution() {
+				println!("{:>6}  {} words", count, lengths);
+			}
+			println!("\n{}", "Frequent words".bold());
+			for (word, count) in readability.frequent(FREQUENT_WORDS) {
+				println!("{:>6}  {}", count, word);
+			}
+		}
+	}
+
+	/// The readability metrics for `--readability-json`.
+	pub fn readability_json(&self) -> Option<serde_json::Value> {
+		let readability = self.readability.as_ref()?;
+		let distribution = readability
+			.distribution()
+			.into_iter()
+			.map(|(lengths, count)| serde_json::json!({ "words": lengths, "sentences": count }))
+			.collect::<Vec<_>>();
+		let frequent = readability
+			.frequent(FREQUENT_WORDS)
+			.into_iter()
+			.map(|(word, count)| serde_json::json!({ "word": word, "count": count }))
+			.collect::<Vec<_>>();
+		Some(serde_json::json!({
+			"sentences": readability.sentences(),
+			"words": readability.words(),
+			"words_per_sentence": readability.average(),
+			"longest_sentence": readability.longest(),
+			"passive": readability.passive,
+			"sentence_lengths": distribution,
+			"frequent_words": frequent,
+		}))
+	}
+}
+
+/// Most frequent first, ties by name.
+fn sorted(counts: impl IntoIterator<Item = (String, usize)>) -> Vec<(String, usize)> {
+	let mut counts = counts.into_iter().collect::<Vec<_>>();
+	counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+	counts
+}
@@ -0,0 +1,325 @@
+use std::{collections::HashMap, ops::Not};
+
+use lsp_types::{
+	CodeDescription, Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range, TextEdit, Url,
+};
+use typst::syntax::Source;
+use typst_languagetool::{config::Severity, fix, Locations, RuleSource};
+
+/// Additional data stored on the published diagnostics for code actions.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Data {
+	pub replacements: Vec<String>,
+	/// Source text of the flagged text with the replacements applied, see
+	/// [`typst_languagetool::Diagnostic::previews`].
+	#[serde(default)]
+	pub previews: Vec<Option<String>>,
+	pub language: String,
+	pub rule_id: String,
+	/// Flagged text for spelling rules.
+	pub word: Option<String>,
+	/// Ranges of the flagged text, seperated by markup.
+	pub locations: Vec<Range>,
+	/// Sentence containing the flagged text, without markup.
+	#[serde(default)]
+	pub sentence: String,
+	pub rule_description: String,
+	pub rule_category: String,
+	pub rule_urls: Vec<String>,
+	pub incorrect_examples: Vec<String>,
+	pub correct_examples: Vec<String>,
+	/// For clients showing stylistic suggestions differently from errors.
+	#[serde(default)]
+	pub rule_source: RuleSource,
+	#[serde(default)]
+	pub context_for_sure_match: Option<isize>,
+}
+
+impl Data {
+	pub fn from_diagnostic(diagnostic: &Diagnostic) -> Option<Self> {
+		let data = diagnostic.data.clone()?;
+		match serde_json::from_value(data) {
+			Ok(data) => Some(data),
+			Err(err) => {
+				eprintln!("{}", err);
+				None
+			},
+		}
+	}
+
+	/// Edits to replace the flagged text, only the changed words with the source of the document.
+	/// Otherwise the replacement is placed in the first location and the other locations are
+	/// cleared, the markup between the locations is kept.
+	pub fn edits(&self, source: Option<&Source>, replacement: &str) -> Vec<TextEdit> {
+		let minimal = source.and_then(|source| {
+			let locations = self
+				.locations
+				.iter()
+				.map(|range| {
+					let start = range.start;
+					let end = range.end;
+					let start =
+						source.line_column_to_byte(start.line as _, start.character as _)?;
+					let end = source.line_column_to_byte(end.line as _, end.character as _)?;
+					Some(start..end)
+				})
+				.collect::<Option<Vec<_>>>()?;
+			let edits = fix::minimal_edits(source.text(), &locations, replacement)?;
+			let edits = edits.into_iter().map(|fix| TextEdit {
+				range: byte_range_to_range(source, fix.range.start, fix.range.end),
+				new_text: fix.replacement,
+			});
+			Some(edits.collect())
+		});
+		if let Some(edits) = minimal {
+			return edits;
+		}
+		self.locations
+			.iter()
+			.enumerate()
+			.map(|(i, &range)| TextEdit {
+				range,
+				new_text: if i == 0 {
+					replacement.into()
+				} else {
+					String::new()
+				},
+			})
+			.collect()
+	}
+
+	/// Markdown explaining the rule of the diagnostic.
+	pub fn hover(&self, message: &str) -> String {
+		let mut text = format!(
+			"**{}** ({})\n\n{}\n",
+			self.rule_description, self.rule_category, message
+		);
+		match self.rule_source {
+			RuleSource::Standard => {},
+			RuleSource::Picky => text += "\nStylistic rule of the picky level.\n",
+			RuleSource::Premium => text += "\nRule of LanguageTool Premium.\n",
+		}
+		if self.sentence.trim().is_empty().not() {
+			text += &format!("\nIn the sentence: {}\n", self.sentence.trim());
+		}
+		let examples = [
+			("Incorrect", &self.incorrect_examples),
+			("Correct", &self.correct_examples),
+		];
+		for (title, examples) in examples {
+			for example in examples.iter().take(MAX_EXAMPLES) {
+				let (before, marked, after) = typst_languagetool::marked_example(example);
+				let example = if marked.is_empty() {
+					before.to_owned()
+				} else {
+					format!("{}**{}**{}", before, marked, after)
+				};
+				text += &format!("\n{}: {}\n", title, example);
+			}
+		}
+		text += "\n";
+		for url in &self.rule_urls {
+			text += &format!("[More information]({}) · ", url);
+		}
+		text += &format!(
+			"[{}]({})",
+			self.rule_id,
+			typst_languagetool::community_url(&self.rule_id, &self.language)
+		);
+		text
+	}
+}
+
+/// Number of example sentences of each kind shown on hover.
+const MAX_EXAMPLES: usize = 2;
+
+/// Name shown by the editor as origin of the diagnostics.
+pub const SOURCE: &str = "typst-languagetool";
+
+/// Convert a collected diagnostic, the range covers all locations. With [`Locations::Lines`]
+/// every location is a diagnostic with the same data, for editors showing one line per range.
+pub fn to_lsp(
+	source: &Source,
+	diagnostic: typst_languagetool::Diagnostic,
+	severities: &HashMap<String, Severity>,
+	locations: Locations,
+) -> Vec<Diagnostic> {
+	let severity = match Severity::of(severities, &diagnostic) {
+		Severity::Hint => DiagnosticSeverity::HINT,
+		Severity::Info => DiagnosticSeverity::INFORMATION,
+		Severity::Warning => DiagnosticSeverity::WARNING,
+		Severity::Error => DiagnosticSeverity::ERROR,
+	};
+	let start = diagnostic.locations.first().map_or(0, |range| range.start);
+	let end = diagnostic.locations.last().map_or(0, |range| range.end);
+	let word = diagnostic.is_spelling().then(|| {
+		diagnostic
+			.locations
+			.iter()
+			.filter_map(|range| source.get(range.clone()))
+			.collect()
+	});
+	let code_description = Url::parse(&diagnostic.rule_url())
+		.ok()
+		.map(|href| CodeDescription { href });
+	let message = match &diagnostic.debug {
+		Some(debug) => format!(
+			"{}\n(checked {}..{} {:?} in {:?})",
+			diagnostic.message, debug.start, debug.end, debug.flagged, debug.snippet
+		),
+		None => diagnostic.message,
+	};
+	let data = Data {
+		replacements: diagnostic.replacements,
+		previews: diagnostic.previews,
+		language: diagnostic.language,
+		rule_id: diagnostic.rule_id.clone(),
+		word,
+		locations: diagnostic
+			.locations
+			.iter()
+			.map(|range| byte_range_to_range(source, range.start, range.end))
+			.collect(),
+		sentence: diagnostic.sentence,
+		rule_description: diagnostic.rule_description,
+		rule_category: diagnostic.rule_category,
+		rule_urls: diagnostic.rule_urls,
+		incorrect_examples: diagnostic.incorrect_examples,
+		correct_examples: diagnostic.correct_examples,
+		rule_source: diagnostic.rule_source,
+		context_for_sure_match: diagnostic.context_for_sure_match,
+	};
+
+	let ranges = match locations {
+		Locations::Lines => data.locations.clone(),
+		Locations::Keep | Locations::Merge => vec![byte_range_to_range(source, start, end)],
+	};
+	let diagnostic = Diagnostic {
+		range: byte_range_to_range(source, start, end),
+		severity: Some(severity),
+		code: Some(NumberOrString::String(diagnostic.rule_id)),
+		code_description,
+		source: Some(SOURCE.into()),
+		message,
+		related_information: None,
+		tags: None,
+		data: serde_json::to_value(data).ok(),
+	};
+	ranges
+		.into_iter()
+		.map(|range| Diagnostic { range, ..diagnostic.clone() })
+		.collect()
+}
+
+pub fn byte_range_to_range(source: &Source, start: usize, end: usize) -> Range {
+	Range {
+		start: byte_to_position(source, start),
+		end: byte_to_position(source, end),
+	}
+}
+
+pub fn byte_to_position(source: &Source, index: usize) -> Position {
+	let line = source.byte_to_line(index).unwrap();
+	let start = source.line_to_byte(line).unwrap();
+	let head = source.get(start..index).unwrap();
+	let column = head.chars().count();
+	Position {
+		line: line as u32,
+		character: column as u32,
+	}
+}
+
+/// Range of the `source` replaced by the `text` of the whole document and its replacement,
+/// without the start and end the texts have in common.
+pub fn replaced<'a>(source: &Source, text: &'a str) -> (Range, &'a str) {
+	let old = source.text();
+	let prefix = (old.char_indices().zip(text.chars()))
+		.find(|((_, a), b)| a != b)
+		.map_or(old.len().min(text.len()), |((index, _), _)| index);
+	let suffix = (old[prefix..].chars().rev())
+		.zip(text[prefix..].chars().rev())
+		.take_while(|(a, b)| a == b)
+		.map(|(c, _)| c.len_utf8())
+		.sum::<usize>();
+	let range = byte_range_to_range(source, prefix, old.len() - suffix);
+	(range, &text[prefix..text.len() - suffix])
+}
+
+/// Move the published diagnostics after the replacement of the `range` with the `text`, so they
+/// keep their place until the document is checked again. Diagnostics overlapping the range are
+/// dropped.
+pub fn carry_forward(diagnostics: &mut Vec<Diagnostic>, range: Range, text: &str) {
+	let lines = text.split('\n').collect::<Vec<_>>();
+	let last = lines.last().map_or(0, |line| line.chars().count()) as u32;
+	let end = match lines.len() {
+		1 => Position {
+			line: range.start.line,
+			character: range.start.character + last,
+		},
+		len => Position {
+			line: range.start.line + len as u32 - 1,
+			character: last,
+		},
+	};
+	let shift = |position: Position| match position.line == range.end.line {
+		true => Position {
+			line: end.line,
+			character: end.character + position.character - range.end.character,
+		},
+		false => Position {
+			line: (position.line as i64 + end.line as i64 - range.end.line as i64) as u32,
+			character: position.character,
+		},
+	};
+	let shift_range = |moved: Range| Range {
+		start: shift(moved.start),
+		end: shift(moved.end),
+	};
+	diagnostics.retain_mut(|diagnostic| {
+		if diagnostic.range.end <= range.start {
+			return true;
+		}
+		if diagnostic.range.start < range.end {
+			return false;
+		}
+		diagnostic.range = shift_range(diagnostic.range);
+		if let Some(mut data) = Data::from_diagnostic(diagnostic) {
+			for location in &mut data.locations {
+				if location.start >= range.end {
+					*location = shift_range(*location);
+				}
+			}
+			diagnostic.data = serde_json::to_value(data).ok();
+		}
+		true
+	});
+}
+
+/// Appended to the message of the diagnostics kept while the backend is unavailable.
+const STALE: &str = "\n(not checked again, LanguageTool is unavailable)";
+
+/// The `previous` diagnostics inside the `ranges` of text that could not be checked again,
+/// marked as stale in their message. Diagnostics in `current` are not repeated.
+pub fn stale(previous: &[Diagnostic], ranges: &[Range], current: &[Diagnostic]) -> Vec<Diagnostic> {
+	let inside = |diagnostic: &Diagnostic| {
+		ranges
+			.iter()
+			.any(|range| range.start <= diagnostic.range.start && diagnostic.range.end <= range.end)
+	};
+	let repeated = |diagnostic: &Diagnostic| {
+		current
+			.iter()
+			.any(|other| other.range == diagnostic.range && other.code == diagnostic.code)
+	};
+	previous
+		.iter()
+		.filter(|diagnostic| inside(diagnostic) && repeated(diagnostic).not())
+		.map(|diagnostic| {
+			let mut diagnostic = diagnostic.clone();
+			if diagnostic.message.ends_with(STALE).not() {
+				diagnostic.message += STALE;
+			}
+			diagnostic
+		})
+		.collect()
+}
@@ -1,66 +1,254 @@
-use std::collections::HashMap;
+mod diagnostics;
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Not;
 use std::path::{Path, PathBuf};
 
 use anyhow::Context;
-use crossbeam_channel::RecvTimeoutError;
+use crossbeam_channel::{RecvTimeoutError, TryRecvError};
 use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
 use lsp_types::notification::*;
 use lsp_types::request::*;
 use lsp_types::*;
-use lt_world::LtWorld;
+use lt_world::{LtWorld, Project};
 use serde_json::Value;
-use typst::syntax::Source;
-use typst_languagetool::{LanguageTool, LanguageToolBackend, Suggestion};
-
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+use typst::{
+	syntax::{FileId, Source},
+	World,
+};
+use typst_languagetool::{
+	cache::{self, Cache},
+	config::{self, Config, FalsePositive, Profile, Severity},
+	convert, glossary,
+	ignore::Ignore,
+	language_entries, merge_adjacent,
+	rank::Ranking,
+	spelling::Speller,
+	suppress::{Contexts, Suppressions},
+	Cancellation, LanguageFallback, LanguageTool, LanguageToolBackend, LanguageToolBuilder,
+	Locations, Quota, RateLimit, RuleValue, SpellingFiles, Submission, Suggestion,
+};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
 #[serde(default)]
 struct InitOptions {
 	/// Additional allowed words
 	dictionary: HashMap<String, Vec<String>>,
 	/// Languagetool rules to ignore (WHITESPACE_RULE, ...)
 	disabled_checks: HashMap<String, Vec<String>>,
+	/// Rules or categories disabled in parts of the document, like `"heading": ["Capitalization"]`
+	disabled_contexts: HashMap<String, Vec<String>>,
+	/// Parts of the document, like `"figure.caption"`, where rules expecting complete sentences
+	/// are disabled
+	fragments: Vec<String>,
+	/// Severity of the diagnostics by rule id or category, like `"Possible Typo": "error"`
+	severities: HashMap<String, Severity>,
+	/// Values of configurable rules for each language, only used by the JNI backend
+	rule_options: HashMap<String, HashMap<String, Vec<RuleValue>>>,
+	/// Rule XML files for each language, only used by the JNI backend
+	rule_files: HashMap<String, Vec<PathBuf>>,
+	/// Files of accepted and prohibited words for each language, only used by the JNI backend
+	spelling_files: HashMap<String, SpellingFiles>,
+	/// Hunspell `.dic` files or word lists for each language, checked locally while typing
+	/// with `check_on: "spelling"`
+	spelling_dictionaries: HashMap<String, PathBuf>,
 
 	languages: Vec<String>,
+	/// LanguageTool codes for languages with an optional region like "de-CH", replacing `languages`
+	language_codes: HashMap<String, String>,
+	/// Codes checked instead of a code the backend does not support, like `"de-AT": ["de-DE"]`
+	language_fallbacks: HashMap<String, Vec<String>>,
+	/// Language code used for all text instead of the language set in the document
+	language: Option<String>,
+	/// Regexes for tokens like ticket ids, diagnostics inside their matches are dropped
+	ignore: Vec<String>,
+	/// Drop spelling diagnostics of uppercase words like "RISC-V"
+	ignore_acronyms: bool,
+	/// Drop spelling diagnostics of words containing digits like "GPT4"
+	ignore_digits: bool,
+	/// Glossary files whose terms are allowed in all languages
+	glossary: Vec<PathBuf>,
+	/// Diagnostics of a rule in sentences matching a regex, like `{ "rule": "TYPO", "sentence": "^See .*$" }`
+	false_positives: Vec<FalsePositive>,
+	/// Keep the diagnostics of a rule for adjacent tokens apart instead of merging them
+	keep_adjacent: bool,
+	/// Maximum number of replacements of a diagnostic shown as quick fixes
+	max_replacements: Option<usize>,
+	/// Show the replacements with the case and length of the flagged text and words of the dictionary first
+	rank_replacements: bool,
+	/// Also report the stricter rules of the picky level
+	picky: bool,
+	/// Profile of disabled rules, picky level and checked parts, "academic", "technical", "casual"
+	/// or one of `profiles`
+	profile: Option<String>,
+	/// Profiles by name, they replace built-in profiles of the same name
+	profiles: HashMap<String, Profile>,
+	/// Add the checked text to the messages, to report diagnostics at the wrong location
+	debug_mapping: bool,
+	/// "keep" the ranges of the flagged text, "lines" splits them at line breaks into separate
+	/// diagnostics, "merge" joins the ranges on the same line, defaults to "keep"
+	locations: Locations,
 
 	/// use bundled languagetool
 	bundled: bool,
 	/// use external JAR for languagetool
 	jar_location: Option<String>,
+	/// SHA-256 of the LanguageTool release zip, it is downloaded to the cache directory on first use
+	download_jar: Option<String>,
+	/// version of the downloaded LanguageTool release, defaults to "6.4"
+	jar_version: Option<String>,
 	/// host for remote languagetool
 	host: Option<String>,
 	/// port for remote languagetool
 	port: Option<String>,
-
-	/// Size for chunk send to LanguageTool
-	chunk_size: usize,
+	/// command of the Harper language server like "harper-ls", checks only English text with Harper
+	harper: Option<String>,
+	/// username of the account on the remote server, "Add to dictionary" stores the words in its dictionary
+	username: Option<String>,
+	/// API key of the account on the remote server
+	api_key: Option<String>,
+	/// headers sent with every request to the remote server, like cookies for a reverse proxy
+	headers: HashMap<String, String>,
+	/// bearer token sent as "Authorization" header to the remote server
+	bearer_token: Option<String>,
+	/// proxy for the remote server, defaults to the proxy of HTTP_PROXY and HTTPS_PROXY
+	proxy: Option<String>,
+	/// send the text gzip compressed to the remote server, the server or a proxy in front of it must accept it
+	compress_requests: bool,
+	/// maximum requests per minute to the remote server, the public API is limited by default
+	requests_per_minute: Option<usize>,
+	/// maximum characters per minute send to the remote server
+	characters_per_minute: Option<usize>,
+	/// maximum requests to the remote server within `quota_hours`, a request exceeding it fails
+	quota_requests: Option<usize>,
+	/// maximum characters send to the remote server within `quota_hours`
+	quota_characters: Option<usize>,
+	/// window of the quota in hours, defaults to 24
+	quota_hours: Option<u64>,
+
+	/// Target size for chunks send to LanguageTool, they are only split after sentences or paragraphs
+	/// Defaults to a size suited to the backend
+	chunk_size: Option<usize>,
+	/// Maximum number of changed paragraphs of a chunk sent in one request
+	paragraphs_per_request: Option<usize>,
+	/// "paragraphs" sends only the changed paragraphs, "document" the whole text at once for
+	/// rules across paragraphs, defaults to "paragraphs"
+	#[serde(default)]
+	submission: Submission,
+	/// Maximum number of cached paragraphs, defaults to 10000
+	cache_entries: Option<usize>,
+	/// Maximum estimated memory of the cached results in bytes
+	cache_bytes: Option<usize>,
 	/// Duration to wait for additional changes before checking the file
 	/// Leave empty to only check on open and save
 	#[serde(with = "humantime_serde")]
 	on_change: Option<std::time::Duration>,
+	/// When files are checked, "save" only on open and save, "type" also after changes,
+	/// "spelling" only the spelling with `spelling_dictionaries` after changes and everything
+	/// after `on_change` without changes. Defaults to "type" with `on_change` and to "save"
+	/// without it
+	check_on: Option<CheckOn>,
 
 	/// Project Root
 	root: Option<PathBuf>,
 	/// Project Main File
 	main: Option<PathBuf>,
+	/// Project configuration with additional dictionary and disabled checks
+	config: Option<PathBuf>,
+	/// Parts of the document that are checked, replaced by the project configuration
+	/// Defaults to the ones of the profile
+	convert: Option<convert::Options>,
 }
 
-impl Default for InitOptions {
-	fn default() -> Self {
-		Self {
-			dictionary: HashMap::new(),
-			disabled_checks: HashMap::new(),
-			languages: Vec::new(),
+/// Trigger of the checks, typing is only suited to local backends or a short document.
+#[derive(
+	serde::Serialize,
+	serde::Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq
+)]
+#[serde(rename_all = "lowercase")]
+enum CheckOn {
+	Save,
+	Type,
+	/// Local spelling while typing, the backend only after a pause.
+	Spelling,
+}
 
-			bundled: false,
-			jar_location: None,
-			host: None,
-			port: None,
+/// Wait after typing without `on_change`.
+const DEFAULT_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(500);
+/// Wait after typing before the local spelling.
+const SPELLING_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(100);
+/// Pause after typing before checking with the backend for `CheckOn::Spelling` without `on_change`.
+const DEFAULT_IDLE: std::time::Duration = std::time::Duration::from_secs(2);
+/// Delay of the first retry after the backend became unavailable, doubled after every failure.
+const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+const MAX_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// Settings selecting the backend, changing them requires a new backend.
+#[derive(PartialEq)]
+struct BackendSettings {
+	bundled: bool,
+	jar_location: Option<String>,
+	/// SHA-256 and version of the downloaded release.
+	download_jar: Option<(String, Option<String>)>,
+	host: Option<String>,
+	port: Option<String>,
+	harper: Option<String>,
+	/// Username and API key of the account on the remote server.
+	login: Option<(String, String)>,
+	headers: Vec<(String, String)>,
+	proxy: Option<String>,
+	compress: bool,
+}
 
-			chunk_size: 1000,
-			on_change: None,
+/// Entries in a hashable order.
+fn sorted<K: Ord, V>(map: &HashMap<K, V>) -> Vec<(&K, &V)> {
+	let mut entries = map.iter().collect::<Vec<_>>();
+	entries.sort_by(|a, b| a.0.cmp(b.0));
+	entries
+}
 
-			root: None,
-			main: None,
+fn merge<T: PartialEq>(values: &mut Vec<T>, additional: Vec<T>) {
+	for value in additional {
+		if values.contains(&value).not() {
+			values.push(value);
+		}
+	}
+}
+
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+	std::fs::metadata(path).ok()?.modified().ok()
+}
+
+/// Languages of the backend, all languages are checked as they are if it can not list them.
+async fn language_fallback(lt: &LanguageTool) -> LanguageFallback {
+	match lt.languages().await {
+		Ok(languages) => LanguageFallback::new(&languages),
+		Err(err) => {
+			eprintln!("could not list the languages: {}", err);
+			LanguageFallback::default()
+		},
+	}
+}
+
+/// Report the configured language codes the backend does not support, instead of failing later.
+fn warn_unsupported(fallback: &LanguageFallback, options: &InitOptions) {
+	let codes = options
+		.language
+		.iter()
+		.chain(&options.languages)
+		.chain(options.language_codes.values());
+	for code in codes {
+		if fallback.supports(code).not() {
+			let used = fallback.resolve(code, &options.language_fallbacks);
+			eprintln!(
+				"language '{}' is not supported by the backend, checking with '{}'",
+				code, used
+			);
 		}
 	}
 }
@@ -73,40 +261,263 @@ fn create_language_map(codes: Vec<String>) -> HashMap<String, String> {
 }
 
 impl InitOptions {
+	/// Defaults to 10000 paragraphs, so a long session on a large project stays bounded.
+	fn cache_entries(&self) -> Option<usize> {
+		Some(self.cache_entries.unwrap_or(10_000))
+	}
+
 	async fn create_lt(&self) -> anyhow::Result<LanguageTool> {
-		let mut lt = LanguageTool::new(
-			self.bundled,
-			self.jar_location.as_ref(),
-			self.host.as_ref(),
-			self.port.as_ref(),
-		)?;
-		for (lang, dict) in &self.dictionary {
-			lt.allow_words(lang.clone(), dict).await?;
+		// The builder already checks the version of its backends.
+		let built = self.download_jar.is_some() || self.harper.is_some();
+		let mut lt = match (&self.download_jar, &self.harper) {
+			(Some(sha256), _) => {
+				let cache_dir = dirs::cache_dir()
+					.context("No cache directory.")?
+					.join("typst-languagetool");
+				let version = self.jar_version.as_deref().unwrap_or("6.4");
+				LanguageToolBuilder::download(cache_dir, version, sha256)
+					.build()
+					.await?
+			},
+			(None, Some(command)) => LanguageToolBuilder::harper(command).build().await?,
+			(None, None) => LanguageTool::new(
+				self.bundled,
+				self.jar_location.as_ref(),
+				self.host.as_ref(),
+				self.port.as_ref(),
+			)?,
+		};
+		if let Some((username, api_key)) = self.login() {
+			lt.set_login(username, api_key);
+		}
+		let headers = self.headers();
+		if headers.is_empty().not() {
+			lt.set_headers(&headers)?;
 		}
-		for (lang, checks) in &self.disabled_checks {
-			lt.disable_checks(lang.clone(), checks).await?;
+		if let Some(url) = &self.proxy {
+			lt.set_proxy(url)?;
 		}
+		lt.set_compress_requests(self.compress_requests);
+		if built.not() {
+			lt.check_version().await?;
+		}
+		self.apply(&mut lt).await?;
 		Ok(lt)
 	}
 
-	fn make_absolute(&mut self) {
-		fn make_absolute(cwd: &Path, path: &mut Option<PathBuf>) {
+	/// Apply the dictionary, disabled checks, rule options, picky level and rate limit to the backend.
+	async fn apply(&self, lt: &mut LanguageTool) -> anyhow::Result<()> {
+		let settings = cache::Settings {
+			dictionary: &self.dictionary,
+			disabled_checks: &self.disabled_checks,
+			rule_options: &self.rule_options,
+			rule_files: &self.rule_files,
+			spelling_files: &self.spelling_files,
+			picky: self.picky,
+		};
+		if let Some(report) = config::sync(lt, &settings).await?.report() {
+			eprintln!("{}", report);
+		}
+		lt.set_picky(self.picky || self.profile()?.picky).await?;
+		if self.requests_per_minute.is_some() || self.characters_per_minute.is_some() {
+			lt.set_rate_limit(RateLimit {
+				requests_per_minute: self.requests_per_minute,
+				characters_per_minute: self.characters_per_minute,
+			});
+		}
+		if self.quota_requests.is_some() || self.quota_characters.is_some() {
+			lt.set_quota(Some(Quota {
+				requests: self.quota_requests,
+				characters: self.quota_characters,
+				window: self.quota_hours.map_or(Quota::DAY, |hours| {
+					std::time::Duration::from_secs(hours * 60 * 60)
+				}),
+			}));
+		}
+		Ok(())
+	}
+
+	fn login(&self) -> Option<(&str, &str)> {
+		Some((self.username.as_deref()?, self.api_key.as_deref()?))
+	}
+
+	/// Headers for the remote server, sorted to compare them.
+	fn headers(&self) -> Vec<(String, String)> {
+		let mut headers = sorted(&self.headers)
+			.into_iter()
+			.map(|(name, value)| (name.clone(), value.clone()))
+			.collect::<Vec<_>>();
+		if let Some(token) = &self.bearer_token {
+			headers.push(("Authorization".into(), format!("Bearer {}", token)));
+		}
+		headers
+	}
+
+	fn backend(&self) -> BackendSettings {
+		BackendSettings {
+			bundled: self.bundled,
+			jar_location: self.jar_location.clone(),
+			download_jar: self
+				.download_jar
+				.clone()
+				.map(|sha256| (sha256, self.jar_version.clone())),
+			host: self.host.clone(),
+			port: self.port.clone(),
+			harper: self.harper.clone(),
+			login: self
+				.login()
+				.map(|(username, api_key)| (username.into(), api_key.into())),
+			headers: self.headers(),
+			proxy: self.proxy.clone(),
+			compress: self.compress_requests,
+		}
+	}
+
+	fn make_absolute(&mut self, base: &Path) {
+		fn make_absolute(base: &Path, path: &mut Option<PathBuf>) {
 			if let Some(path) = path {
 				if path.is_absolute() {
 					return;
 				}
-				*path = cwd.join(&path)
+				*path = base.join(&path)
+			}
+		}
+		make_absolute(base, &mut self.main);
+		make_absolute(base, &mut self.root);
+		make_absolute(base, &mut self.config);
+		for file in self.rule_files.values_mut().flatten() {
+			if file.is_relative() {
+				*file = base.join(&file);
+			}
+		}
+		for files in self.spelling_files.values_mut() {
+			files.resolve(base);
+		}
+		for file in self.spelling_dictionaries.values_mut() {
+			if file.is_relative() {
+				*file = base.join(&file);
 			}
 		}
-		let cwd = std::env::current_dir().unwrap();
-		make_absolute(&cwd, &mut self.main);
-		make_absolute(&cwd, &mut self.root);
+	}
+
+	/// Options of a root, the paths of additional workspace folders are relative to the folder.
+	/// Absolute paths into another folder are not used, the folder is the root.
+	fn resolved(&self, folder: Option<&Path>) -> Self {
+		let mut options = self.clone();
+		let Some(folder) = folder else {
+			options.make_absolute(&std::env::current_dir().unwrap());
+			options.find_project();
+			return options;
+		};
+		options.make_absolute(folder);
+		if options
+			.main
+			.as_ref()
+			.is_some_and(|main| main.starts_with(folder).not())
+		{
+			options.main = None;
+		}
+		if options
+			.root
+			.as_ref()
+			.is_some_and(|root| root.starts_with(folder).not())
+		{
+			options.root = None;
+		}
+		options.find_project();
+		options.root.get_or_insert_with(|| folder.to_owned());
+		options
+	}
+
+	/// Use the directory of `typst.toml` as root, if no root is specified.
+	fn find_project(&mut self) {
+		if self.root.is_some() {
+			return;
+		}
+		if let Some(project) = self.main.as_deref().and_then(Project::find) {
+			self.root = Some(project.root);
+		}
+	}
+
+	fn ignored(&self) -> anyhow::Result<Ignore> {
+		let mut ignore = Ignore::new(&self.ignore)?;
+		ignore.acronyms = self.ignore_acronyms;
+		ignore.digits = self.ignore_digits;
+		for path in &self.glossary {
+			ignore.add_terms(glossary::load(path)?);
+		}
+		ignore.disable(self.profile()?.disabled);
+		ignore.hide(&self.false_positives)?;
+		Ok(ignore)
+	}
+
+	/// The selected profile, an empty one without a name.
+	fn profile(&self) -> anyhow::Result<Profile> {
+		match &self.profile {
+			Some(name) => Ok(Profile::find(name, &self.profiles)?),
+			None => Ok(Profile::default()),
+		}
+	}
+
+	/// Merge the dictionary and disabled checks from the project configuration.
+	fn load_config(&mut self) -> anyhow::Result<()> {
+		let Some(path) = &self.config else {
+			return Ok(());
+		};
+		if path.exists().not() {
+			return Ok(());
+		}
+		let config = Config::load(path)?;
+		for (lang, words) in config.dictionary {
+			merge(self.dictionary.entry(lang).or_default(), words);
+		}
+		for (lang, checks) in config.disabled_checks {
+			merge(self.disabled_checks.entry(lang).or_default(), checks);
+		}
+		self.severities.extend(config.severities);
+		for (context, rules) in config.disabled_contexts {
+			merge(self.disabled_contexts.entry(context).or_default(), rules);
+		}
+		merge(&mut self.fragments, config.fragments);
+		for (lang, rule_options) in config.rule_options {
+			self.rule_options
+				.entry(lang)
+				.or_default()
+				.extend(rule_options);
+		}
+		for (lang, files) in config.rule_files {
+			merge(self.rule_files.entry(lang).or_default(), files);
+		}
+		for (lang, files) in config.spelling_files {
+			let merged = self.spelling_files.entry(lang).or_default();
+			merge(&mut merged.spelling, files.spelling);
+			merge(&mut merged.prohibit, files.prohibit);
+		}
+		if let Some(convert) = config.convert {
+			self.convert = Some(convert);
+		}
+		self.profile = self.profile.take().or(config.profile);
+		self.profiles.extend(config.profiles);
+		self.language = self.language.take().or(config.language);
+		self.language_codes.extend(config.languages);
+		for (code, fallbacks) in config.language_fallbacks {
+			merge(self.language_fallbacks.entry(code).or_default(), fallbacks);
+		}
+		self.ignore_acronyms |= config.ignore_acronyms;
+		self.ignore_digits |= config.ignore_digits;
+		self.keep_adjacent |= config.keep_adjacent;
+		merge(&mut self.ignore, config.ignore);
+		merge(&mut self.glossary, config.glossary);
+		merge(&mut self.false_positives, config.false_positives);
+		Ok(())
 	}
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
 	eprintln!("starting LSP server");
+	#[cfg(feature = "tracing")]
+	typst_languagetool::trace::print_timings()?;
 
 	let (connection, io_threads) = Connection::stdio();
 
@@ -123,6 +534,29 @@ async fn main() -> anyhow::Result<()> {
 		)),
 
 		code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+		hover_provider: Some(HoverProviderCapability::Simple(true)),
+		diagnostic_provider: Some(DiagnosticServerCapabilities::Options(DiagnosticOptions {
+			identifier: Some(diagnostics::SOURCE.into()),
+			inter_file_dependencies: true,
+			workspace_diagnostics: false,
+			work_done_progress_options: WorkDoneProgressOptions::default(),
+		})),
+		execute_command_provider: Some(ExecuteCommandOptions {
+			commands: vec![
+				ADD_WORD_COMMAND.into(),
+				DISABLE_RULE_COMMAND.into(),
+				HIDE_FALSE_POSITIVE_COMMAND.into(),
+				CHECK_WORKSPACE_COMMAND.into(),
+			],
+			work_done_progress_options: WorkDoneProgressOptions::default(),
+		}),
+		workspace: Some(WorkspaceServerCapabilities {
+			workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+				supported: Some(true),
+				change_notifications: Some(OneOf::Left(true)),
+			}),
+			file_operations: None,
+		}),
 		..Default::default()
 	};
 
@@ -136,7 +570,9 @@ async fn main() -> anyhow::Result<()> {
 			return Err(e.into());
 		},
 	};
-	let state = State::new(connection, initialization_params).await?;
+	let cancellation = Cancellation::new();
+	let connection = cancel_on_change(connection, cancellation.clone());
+	let state = State::new(connection, initialization_params, cancellation).await?;
 	state.main_loop().await?;
 	io_threads.join()?;
 
@@ -144,70 +580,396 @@ async fn main() -> anyhow::Result<()> {
 	Ok(())
 }
 
+/// Forward the messages of the client, a changed document stops the running check.
+/// Messages are only handled between the paragraphs of a check.
+fn cancel_on_change(connection: Connection, cancellation: Cancellation) -> Connection {
+	let (sender, receiver) = crossbeam_channel::unbounded();
+	let client = connection.receiver;
+	std::thread::spawn(move || {
+		for msg in client {
+			if let Message::Notification(not) = &msg {
+				if not.method
+					== <DidChangeTextDocument as lsp_types::notification::Notification>::METHOD
+				{
+					cancellation.cancel();
+				}
+			}
+			if sender.send(msg).is_err() {
+				break;
+			}
+		}
+	});
+	Connection { sender: connection.sender, receiver }
+}
+
+const ADD_WORD_COMMAND: &str = "typst-languagetool.addWord";
+const DISABLE_RULE_COMMAND: &str = "typst-languagetool.disableRule";
+const HIDE_FALSE_POSITIVE_COMMAND: &str = "typst-languagetool.hideFalsePositive";
+const CHECK_WORKSPACE_COMMAND: &str = "typst-languagetool.checkWorkspace";
+
 struct Options {
-	chunk_size: usize,
+	chunk_size: Option<usize>,
+	submission: Submission,
+	locations: Locations,
 	on_change: Option<std::time::Duration>,
+	/// Pause before the full check after the local spelling of changes.
+	on_idle: Option<std::time::Duration>,
 	language_codes: HashMap<String, String>,
+	language_fallbacks: HashMap<String, Vec<String>>,
+	language: Option<String>,
+	ignore: Ignore,
+	keep_adjacent: bool,
+	ranking: Ranking,
+	picky: bool,
+	debug_mapping: bool,
+	dictionary: HashMap<String, Vec<String>>,
+	disabled_checks: HashMap<String, Vec<String>>,
+	contexts: Contexts,
+	severities: HashMap<String, Severity>,
+	rule_options: HashMap<String, HashMap<String, Vec<RuleValue>>>,
+	rule_files: HashMap<String, Vec<PathBuf>>,
+	spelling_files: HashMap<String, SpellingFiles>,
+	/// Local dictionaries of `spelling_dictionaries` with the dictionary words.
+	spellers: HashMap<String, Speller>,
+	main: Option<PathBuf>,
+	config: Option<PathBuf>,
+	convert: convert::Options,
 }
 
-struct State {
+impl Options {
+	fn new(options: InitOptions) -> anyhow::Result<Self> {
+		let ignore = options.ignored()?;
+		let profile = options.profile()?;
+		let mut spellers = HashMap::new();
+		for (lang, path) in &options.spelling_dictionaries {
+			let mut speller = Speller::load(path)
+				.with_context(|| format!("could not read the dictionary {}", path.display()))?;
+			speller.add_words(
+				language_entries(&options.dictionary, lang)
+					.into_iter()
+					.flatten(),
+			);
+			spellers.insert(lang.clone(), speller);
+		}
+		Ok(Self {
+			language: options.language,
+			picky: options.picky || profile.picky,
+			debug_mapping: options.debug_mapping,
+			on_change: match options.check_on {
+				Some(CheckOn::Save) => None,
+				Some(CheckOn::Type) => Some(options.on_change.unwrap_or(DEFAULT_DEBOUNCE)),
+				Some(CheckOn::Spelling) => Some(SPELLING_DEBOUNCE),
+				None => options.on_change,
+			},
+			on_idle: match options.check_on {
+				Some(CheckOn::Spelling) => Some(options.on_change.unwrap_or(DEFAULT_IDLE)),
+				_ => None,
+			},
+			chunk_size: options.chunk_size,
+			submission: options.submission,
+			locations: options.locations,
+			language_codes: create_language_map(options.languages)
+				.into_iter()
+				.chain(options.language_codes)
+				.collect(),
+			language_fallbacks: options.language_fallbacks,
+			dictionary: options.dictionary,
+			disabled_checks: options.disabled_checks,
+			contexts: Contexts::new(options.disabled_contexts).with_fragments(&options.fragments),
+			severities: options.severities,
+			rule_options: options.rule_options,
+			rule_files: options.rule_files,
+			spelling_files: options.spelling_files,
+			spellers,
+			ignore,
+			keep_adjacent: options.keep_adjacent,
+			ranking: Ranking::new(options.max_replacements, options.rank_replacements),
+			main: options.main,
+			config: options.config,
+			convert: options.convert.or(profile.convert).unwrap_or_default(),
+		})
+	}
+
+	/// Settings of the root influencing the cached results.
+	fn settings(&self) -> cache::Settings<'_> {
+		cache::Settings {
+			dictionary: &self.dictionary,
+			disabled_checks: &self.disabled_checks,
+			rule_options: &self.rule_options,
+			rule_files: &self.rule_files,
+			spelling_files: &self.spelling_files,
+			picky: self.picky,
+		}
+	}
+
+	/// Apply the settings of a root to the backend, see [`config::sync`], and the picky level.
+	async fn apply(&self, lt: &mut LanguageTool) -> anyhow::Result<()> {
+		if let Some(report) = config::sync(lt, &self.settings()).await?.report() {
+			eprintln!("{}", report);
+		}
+		lt.set_picky(self.picky).await?;
+		Ok(())
+	}
+
+	/// Reset the languages of these options missing from the new settings.
+	async fn reset_missing(
+		&self,
+		lt: &mut LanguageTool,
+		dictionary: &HashMap<String, Vec<String>>,
+		disabled_checks: &HashMap<String, Vec<String>>,
+		rule_options: &HashMap<String, HashMap<String, Vec<RuleValue>>>,
+		rule_files: &HashMap<String, Vec<PathBuf>>,
+		spelling_files: &HashMap<String, SpellingFiles>,
+	) -> anyhow::Result<()> {
+		for lang in self.dictionary.keys() {
+			if dictionary.contains_key(lang).not() {
+				lt.allow_words(lang.parse()?, &[]).await?;
+			}
+		}
+		for lang in self.disabled_checks.keys() {
+			if disabled_checks.contains_key(lang).not() {
+				lt.disable_checks(lang.parse()?, &[]).await?;
+			}
+		}
+		for lang in self.rule_options.keys() {
+			if rule_options.contains_key(lang).not() {
+				lt.set_rule_options(lang.parse()?, &HashMap::new()).await?;
+			}
+		}
+		for lang in self.rule_files.keys() {
+			if rule_files.contains_key(lang).not() {
+				lt.set_rule_files(lang.parse()?, &[]).await?;
+			}
+		}
+		for lang in self.spelling_files.keys() {
+			if spelling_files.contains_key(lang).not() {
+				lt.set_spelling_files(lang.parse()?, &SpellingFiles::default())
+					.await?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Workspace folder with its own project configuration, dictionary and language settings.
+struct Root {
+	/// Folder of an additional workspace folder, the first root uses the options as given.
+	folder: Option<PathBuf>,
 	world: LtWorld,
+	/// Converted pages of the checked files, reused for unchanged pages.
+	converters: HashMap<FileId, convert::Incremental>,
+	options: Options,
+	/// Options of the client for this root, without the project configuration merged into them.
+	settings: InitOptions,
+	/// Modification time of the project configuration when it was loaded.
+	config_modified: Option<std::time::SystemTime>,
+}
+
+impl Root {
+	/// `options` are the `settings` with the project configuration.
+	fn new(
+		folder: Option<PathBuf>,
+		settings: InitOptions,
+		options: InitOptions,
+	) -> anyhow::Result<Self> {
+		let world = match (&options.main, &options.root) {
+			(Some(main), root) => LtWorld::new(main.clone(), root.clone()),
+			// Without a main file the documents are compiled on their own.
+			(None, Some(root)) => LtWorld::new(root.clone(), Some(root.clone())),
+			(None, None) => return Err(anyhow::anyhow!("main file is required")),
+		};
+		Ok(Self {
+			folder,
+			world,
+			converters: HashMap::new(),
+			config_modified: options.config.as_deref().and_then(modified),
+			options: Options::new(options)?,
+			settings,
+		})
+	}
+}
+
+const PROGRESS_TOKEN: &str = "typst-languagetool/check";
+const WORKSPACE_PROGRESS_TOKEN: &str = "typst-languagetool/checkWorkspace";
+
+/// Sent by the client when the lines shown in the editor change, the paragraphs on them are
+/// checked and published first.
+enum VisibleRange {}
+
+impl lsp_types::notification::Notification for VisibleRange {
+	type Params = VisibleRangeParams;
+	const METHOD: &'static str = "typst-languagetool/visibleRange";
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VisibleRangeParams {
+	text_document: TextDocumentIdentifier,
+	range: Range,
+}
+
+struct State {
+	/// Roots of the workspace folders, documents belong to the innermost root containing them.
+	/// Documents outside of all folders belong to the first root.
+	roots: Vec<Root>,
+	/// Root whose settings are applied to the backend.
+	active: usize,
+	/// Options of the client, used for added workspace folders.
+	client: InitOptions,
 	cache: Cache,
 	lt: LanguageTool,
+	/// Languages of the running backend.
+	fallback: LanguageFallback,
+	/// Unsupported language codes of the documents already reported.
+	warned_languages: HashSet<String>,
+	/// Documents whose language was already detected, the detection is only suggested once.
+	detected_languages: HashSet<PathBuf>,
+	/// Settings of the running backend.
+	backend: BackendSettings,
 	connection: Connection,
 	check: Option<CheckData>,
-	options: Options,
+	/// Files of a running workspace check, checked while no messages are waiting.
+	workspace: Option<WorkspaceCheck>,
+	/// Diagnostics published for open documents.
+	published: HashMap<Url, Vec<Diagnostic>>,
+	/// Revision of the published diagnostics.
+	checked: HashMap<Url, u64>,
+	/// Lines shown in the editor for the documents, see [`VisibleRange`].
+	visible: HashMap<Url, Range>,
+	/// Incremented for every change of documents or settings.
+	revision: u64,
+	/// Client requests the diagnostics instead of receiving them.
+	pull_diagnostics: bool,
+	work_done_progress: bool,
+	next_request_id: i32,
+	/// Set when a document changes, stops the running check.
+	cancellation: Cancellation,
+	/// The backend failed, the cached results are used until the next retry.
+	offline: Option<Offline>,
+	/// Client watches the files registered for `workspace/didChangeWatchedFiles`.
+	watch_files: Option<WatchFiles>,
+}
+
+/// Support of the client for watched files.
+#[derive(Debug, Clone, Copy)]
+struct WatchFiles {
+	relative_patterns: bool,
 }
 
 struct CheckData {
 	check_time: std::time::Instant,
 	url: Url,
 	path: PathBuf,
+	/// Only the local spelling of the changes, see [`CheckOn::Spelling`].
+	spelling: bool,
+}
+
+/// Backend unavailable since a failed request, see [`set_unavailable`].
+struct Offline {
+	/// Files are checked with the backend again after this time.
+	retry: std::time::Instant,
+	delay: std::time::Duration,
+}
+
+struct WorkspaceCheck {
+	paths: Vec<PathBuf>,
+	total: usize,
 }
 
 enum Action {
 	Message(Message),
 	Check(CheckData),
+	CheckWorkspace,
 }
 
 impl State {
-	pub async fn new(connection: Connection, params: Value) -> anyhow::Result<Self> {
+	pub async fn new(
+		connection: Connection,
+		params: Value,
+		cancellation: Cancellation,
+	) -> anyhow::Result<Self> {
 		let params = serde_json::from_value::<InitializeParams>(params)?;
+		let work_done_progress = params
+			.capabilities
+			.window
+			.as_ref()
+			.and_then(|window| window.work_done_progress)
+			.unwrap_or(false);
+		let pull_diagnostics = params
+			.capabilities
+			.text_document
+			.as_ref()
+			.and_then(|text_document| text_document.diagnostic.as_ref())
+			.is_some();
+		let watch_files = params
+			.capabilities
+			.workspace
+			.as_ref()
+			.and_then(|workspace| workspace.did_change_watched_files)
+			.filter(|watched| watched.dynamic_registration == Some(true))
+			.map(|watched| WatchFiles {
+				relative_patterns: watched.relative_pattern_support == Some(true),
+			});
 		let options = params.initialization_options.context("No init options")?;
 
-		let mut options = serde_ignored::deserialize::<_, _, InitOptions>(options, |path| {
+		let client = serde_ignored::deserialize::<_, _, InitOptions>(options, |path| {
 			eprintln!("unknown option: {}", path);
 		})?;
 
-		let cache = Cache::new();
-
-		options.make_absolute();
+		let settings = client.resolved(None);
+		let mut options = settings.clone();
+		options.load_config()?;
 		eprintln!("options: {:#?}", options);
 		let lt = options.create_lt().await?;
-		let Some(main) = &options.main else {
-			return Err(anyhow::anyhow!("main file is required")).unwrap();
-		};
+		let fallback = language_fallback(&lt).await;
+		warn_unsupported(&fallback, &options);
+		if options.main.is_none() {
+			return Err(anyhow::anyhow!("main file is required"));
+		}
 
-		let world = lt_world::LtWorld::new(main.clone(), options.root);
+		let mut cache = Cache::new();
+		cache.set_paragraphs_per_request(options.paragraphs_per_request);
+		cache.set_submission(options.submission);
+		cache.set_limits(options.cache_entries(), options.cache_bytes);
+		let backend = options.backend();
+		let root = Root::new(None, settings, options)?;
 
 		eprintln!("compiling document");
-		if world.compile().is_none() {
+		if root.world.compile().is_none() {
 			eprintln!("failed to compile document");
 		};
 
-		Ok(Self {
-			world,
+		let mut state = Self {
+			roots: vec![root],
+			active: 0,
+			client,
 			cache,
 			lt,
+			fallback,
+			warned_languages: HashSet::new(),
+			detected_languages: HashSet::new(),
+			backend,
 			connection,
 			check: None,
-
-			options: Options {
-				on_change: options.on_change,
-				chunk_size: options.chunk_size,
-				language_codes: create_language_map(options.languages),
-			},
-		})
+			workspace: None,
+			work_done_progress,
+			next_request_id: 0,
+
+			published: HashMap::new(),
+			checked: HashMap::new(),
+			visible: HashMap::new(),
+			revision: 0,
+			pull_diagnostics,
+			cancellation,
+			offline: None,
+			watch_files,
+		};
+		let folders = params.workspace_folders.unwrap_or_default();
+		for folder in folders {
+			state.add_folder(&folder.uri).await?;
+		}
+		state.watch_config(0)?;
+		Ok(state)
 	}
 
 	pub async fn main_loop(mut self) -> anyhow::Result<()> {
@@ -215,12 +977,29 @@ impl State {
 		loop {
 			match self.next_action()? {
 				Action::Message(msg) => self.message(msg).await?,
+				Action::Check(data) if data.spelling => {
+					self.check_spelling(&data.path, data.url).await?
+				},
 				Action::Check(data) => self.check_change(&data.path, data.url).await?,
+				Action::CheckWorkspace => self.check_workspace_file().await?,
 			}
 		}
 	}
 
 	fn next_action(&mut self) -> anyhow::Result<Action> {
+		if self.workspace.is_some() {
+			match self.connection.receiver.try_recv() {
+				Ok(msg) => return Ok(Action::Message(msg)),
+				Err(TryRecvError::Empty) => {},
+				Err(err) => return Err(err.into()),
+			}
+			if let Some(check) = &self.check {
+				if check.check_time <= std::time::Instant::now() {
+					return Ok(Action::Check(self.check.take().unwrap()));
+				}
+			}
+			return Ok(Action::CheckWorkspace);
+		}
 		if let Some(last_change) = &self.check {
 			let msg = self
 				.connection
@@ -263,6 +1042,33 @@ impl State {
 			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
 			Err(ExtractError::MethodMismatch(req)) => req,
 		};
+		let req = match cast_request::<HoverRequest>(req) {
+			Ok((id, params)) => {
+				let hover = self.hover(params);
+				send_response::<HoverRequest>(&self.connection, id, hover)?;
+				return Ok(());
+			},
+			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
+			Err(ExtractError::MethodMismatch(req)) => req,
+		};
+		let req = match cast_request::<DocumentDiagnosticRequest>(req) {
+			Ok((id, params)) => {
+				let report = self.pull_diagnostics(params).await?;
+				send_response::<DocumentDiagnosticRequest>(&self.connection, id, report)?;
+				return Ok(());
+			},
+			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
+			Err(ExtractError::MethodMismatch(req)) => req,
+		};
+		let req = match cast_request::<ExecuteCommand>(req) {
+			Ok((id, params)) => {
+				self.execute_command(params).await?;
+				send_response::<ExecuteCommand>(&self.connection, id, None)?;
+				return Ok(());
+			},
+			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
+			Err(ExtractError::MethodMismatch(req)) => req,
+		};
 		eprintln!("unknown request: {:?}", req);
 		Ok(())
 	}
@@ -272,47 +1078,509 @@ impl State {
 		params: CodeActionParams,
 	) -> anyhow::Result<Option<CodeActionResponse>> {
 		let mut action = CodeActionResponse::new();
+		let uri = &params.text_document.uri;
+		let source = uri.to_file_path().ok().and_then(|path| {
+			let world = &self.roots[self.root_index(&path)].world;
+			world.source(world.file_id(&path)).ok()
+		});
+		let suppressions = source.as_ref().map(Suppressions::new);
+
+		for diagnostic in &params.context.diagnostics {
+			if diagnostic.source.as_deref() != Some(diagnostics::SOURCE) {
+				continue;
+			}
+			let Some(data) = diagnostics::Data::from_diagnostic(diagnostic) else {
+				continue;
+			};
+
+			if let Some(word) = &data.word {
+				action.push(
+					CodeAction {
+						title: format!("Add \"{}\" to dictionary", word),
+						kind: Some(CodeActionKind::QUICKFIX),
+						diagnostics: Some(vec![diagnostic.clone()]),
+						command: Some(Command {
+							title: "Add to dictionary".into(),
+							command: ADD_WORD_COMMAND.into(),
+							arguments: Some(vec![
+								data.language.clone().into(),
+								word.clone().into(),
+								params.text_document.uri.as_str().into(),
+							]),
+						}),
+						..Default::default()
+					}
+					.into(),
+				);
+			}
+
+			action.push(
+				CodeAction {
+					title: format!("Disable rule {}", data.rule_id),
+					kind: Some(CodeActionKind::QUICKFIX),
+					diagnostics: Some(vec![diagnostic.clone()]),
+					command: Some(Command {
+						title: "Disable rule".into(),
+						command: DISABLE_RULE_COMMAND.into(),
+						arguments: Some(vec![
+							data.language.clone().into(),
+							data.rule_id.clone().into(),
+							params.text_document.uri.as_str().into(),
+						]),
+					}),
+					..Default::default()
+				}
+				.into(),
+			);
 
-		let Some(diagnostic) = params.context.diagnostics.last() else {
+			if data.sentence.trim().is_empty().not() {
+				action.push(
+					CodeAction {
+						title: format!("Hide {} in this sentence", data.rule_id),
+						kind: Some(CodeActionKind::QUICKFIX),
+						diagnostics: Some(vec![diagnostic.clone()]),
+						command: Some(Command {
+							title: "Hide false positive".into(),
+							command: HIDE_FALSE_POSITIVE_COMMAND.into(),
+							arguments: Some(vec![
+								data.rule_id.clone().into(),
+								data.sentence.clone().into(),
+								params.text_document.uri.as_str().into(),
+							]),
+						}),
+						..Default::default()
+					}
+					.into(),
+				);
+			}
+
+			if let (Some(source), Some(suppressions)) = (&source, &suppressions) {
+				let start = &diagnostic.range.start;
+				let edit = source
+					.line_column_to_byte(start.line as usize, start.character as usize)
+					.and_then(|offset| suppressions.disable(source, offset, &data.rule_id));
+				if let Some((range, new_text)) = edit {
+					let range = diagnostics::byte_range_to_range(source, range.start, range.end);
+					let edit = [(uri.clone(), vec![TextEdit { range, new_text }])]
+						.into_iter()
+						.collect();
+					action.push(
+						CodeAction {
+							title: format!("Disable rule {} for this line", data.rule_id),
+							kind: Some(CodeActionKind::QUICKFIX),
+							diagnostics: Some(vec![diagnostic.clone()]),
+							edit: Some(WorkspaceEdit {
+								changes: Some(edit),
+								..Default::default()
+							}),
+							..Default::default()
+						}
+						.into(),
+					);
+				}
+			}
+
+			for (i, value) in data.replacements.iter().enumerate() {
+				// The preview shows the replacement with the markup between the locations.
+				let preview = data.previews.get(i).cloned().flatten();
+				let title = format!("Replace with \"{}\"", preview.as_deref().unwrap_or(value));
+				let edit = [(
+					params.text_document.uri.clone(),
+					data.edits(source.as_ref(), value),
+				)]
+				.into_iter()
+				.collect();
+
+				action.push(
+					CodeAction {
+						title,
+						is_preferred: Some(i == 0),
+						kind: Some(CodeActionKind::QUICKFIX),
+						diagnostics: Some(vec![diagnostic.clone()]),
+						edit: Some(WorkspaceEdit {
+							changes: Some(edit),
+							..Default::default()
+						}),
+						command: None,
+						disabled: None,
+						data: None,
+					}
+					.into(),
+				);
+			}
+		}
+		if action.is_empty() {
 			return Ok(None);
+		}
+		Ok(Some(action))
+	}
+
+	/// Explain the rules of the published diagnostics at the position.
+	fn hover(&self, params: HoverParams) -> Option<Hover> {
+		let position = params.text_document_position_params.position;
+		let uri = params.text_document_position_params.text_document.uri;
+		let contains = |range: &lsp_types::Range| {
+			(range.start.line, range.start.character) <= (position.line, position.character)
+				&& (position.line, position.character) <= (range.end.line, range.end.character)
 		};
-		let Some(data) = &diagnostic.data else {
-			return Ok(None);
+		let hovers = self
+			.published
+			.get(&uri)?
+			.iter()
+			.filter(|diagnostic| contains(&diagnostic.range))
+			.filter_map(|diagnostic| {
+				let data = diagnostics::Data::from_diagnostic(diagnostic)?;
+				Some(data.hover(&diagnostic.message))
+			})
+			.collect::<Vec<_>>();
+		if hovers.is_empty() {
+			return None;
+		}
+		Some(Hover {
+			contents: HoverContents::Markup(MarkupContent {
+				kind: MarkupKind::Markdown,
+				value: hovers.join("\n\n---\n\n"),
+			}),
+			range: None,
+		})
+	}
+
+	async fn execute_command(&mut self, params: ExecuteCommandParams) -> anyhow::Result<()> {
+		let arguments = params
+			.arguments
+			.iter()
+			.map(|argument| argument.as_str().unwrap_or_default())
+			.collect::<Vec<_>>();
+		// The document selects the root, commands without it use the first root.
+		let root = |uri: &str| {
+			Url::parse(uri)
+				.ok()
+				.and_then(|uri| uri.to_file_path().ok())
+				.map_or(0, |path| self.root_index(&path))
 		};
+		match (params.command.as_str(), arguments.as_slice()) {
+			(ADD_WORD_COMMAND, &[lang, word]) => self.add_word(0, lang, word).await,
+			(ADD_WORD_COMMAND, &[lang, word, uri]) => self.add_word(root(uri), lang, word).await,
+			(DISABLE_RULE_COMMAND, &[lang, rule_id]) => self.disable_rule(0, lang, rule_id).await,
+			(DISABLE_RULE_COMMAND, &[lang, rule_id, uri]) => {
+				self.disable_rule(root(uri), lang, rule_id).await
+			},
+			(HIDE_FALSE_POSITIVE_COMMAND, &[rule_id, sentence, uri]) => {
+				self.hide_false_positive(root(uri), rule_id, sentence)
+			},
+			(CHECK_WORKSPACE_COMMAND, &[]) => self.check_workspace(),
+			_ => {
+				eprintln!("unknown command: {:?}", params);
+				Ok(())
+			},
+		}
+	}
+
+	async fn add_word(&mut self, index: usize, lang: &str, word: &str) -> anyhow::Result<()> {
+		// The dictionary on the server is used for all roots.
+		if self.backend.login.is_some() && self.lt.add_server_word(word).await? {
+			return self.remove_published(None, |data| data.word.as_deref() == Some(word));
+		}
+		self.activate(index).await?;
+		self.update_config(index, |path| Config::save_word(path, lang, word))?;
+
+		let options = &mut self.roots[index].options;
+		let dictionary = options.dictionary.entry(lang.into()).or_default();
+		if dictionary.iter().any(|w| w == word).not() {
+			dictionary.push(word.into());
+		}
+		self.lt.allow_words(lang.parse()?, dictionary).await?;
+
+		self.remove_published(Some(index), |data| {
+			data.language == lang && data.word.as_deref() == Some(word)
+		})
+	}
 
-		let replacements = match serde_json::from_value::<Vec<String>>(data.clone()) {
-			Ok(r) => r,
+	async fn disable_rule(
+		&mut self,
+		index: usize,
+		lang: &str,
+		rule_id: &str,
+	) -> anyhow::Result<()> {
+		self.activate(index).await?;
+		self.update_config(index, |path| {
+			Config::save_disabled_check(path, lang, rule_id)
+		})?;
+
+		let options = &mut self.roots[index].options;
+		let checks = options.disabled_checks.entry(lang.into()).or_default();
+		if checks.iter().any(|c| c == rule_id).not() {
+			checks.push(rule_id.into());
+		}
+		self.lt.disable_checks(lang.parse()?, checks).await?;
+
+		self.remove_published(Some(index), |data| {
+			data.language == lang && data.rule_id == rule_id
+		})
+	}
+
+	/// Drop the diagnostics of the rule in the sentence, the entry is added to
+	/// `false_positives` of the configuration.
+	fn hide_false_positive(
+		&mut self,
+		index: usize,
+		rule_id: &str,
+		sentence: &str,
+	) -> anyhow::Result<()> {
+		let false_positive = FalsePositive::new(rule_id, sentence);
+		self.update_config(index, |path| {
+			Config::save_false_positive(path, &false_positive)
+		})?;
+		let options = &mut self.roots[index].options;
+		options.ignore.hide(std::slice::from_ref(&false_positive))?;
+
+		let words = sentence.split_whitespace().collect::<Vec<_>>();
+		self.remove_published(Some(index), |data| {
+			data.rule_id == rule_id && data.sentence.split_whitespace().eq(words.iter().copied())
+		})
+	}
+
+	/// Root of the document, the innermost root containing it.
+	fn root_index(&self, path: &Path) -> usize {
+		self.roots
+			.iter()
+			.enumerate()
+			.filter(|(_, root)| path.starts_with(root.world.root()))
+			.max_by_key(|(_, root)| root.world.root().components().count())
+			.map_or(0, |(index, _)| index)
+	}
+
+	/// Apply the settings of the root to the backend, the backend is shared by all roots.
+	async fn activate(&mut self, index: usize) -> anyhow::Result<()> {
+		if index == self.active {
+			return Ok(());
+		}
+		let previous = &self.roots[self.active].options;
+		let options = &self.roots[index].options;
+		previous
+			.reset_missing(
+				&mut self.lt,
+				&options.dictionary,
+				&options.disabled_checks,
+				&options.rule_options,
+				&options.rule_files,
+				&options.spelling_files,
+			)
+			.await?;
+		options.apply(&mut self.lt).await?;
+		self.active = index;
+		Ok(())
+	}
+
+	/// Add a root for a workspace folder, folders inside the first root belong to it.
+	async fn add_folder(&mut self, uri: &Url) -> anyhow::Result<()> {
+		let Ok(folder) = uri.to_file_path() else {
+			return Ok(());
+		};
+		let known = self
+			.roots
+			.iter()
+			.any(|root| root.folder.as_ref() == Some(&folder));
+		if known || folder.starts_with(self.roots[0].world.root()) {
+			return Ok(());
+		}
+		eprintln!("Adding workspace folder {}", folder.display());
+		let settings = self.client.resolved(Some(&folder));
+		let mut options = settings.clone();
+		if let Err(err) = options.load_config() {
+			eprintln!("{}", err);
+		}
+		warn_unsupported(&self.fallback, &options);
+		let root = match Root::new(Some(folder), settings, options) {
+			Ok(root) => root,
 			Err(err) => {
 				eprintln!("{}", err);
-				return Ok(None);
+				return Ok(());
 			},
 		};
+		self.roots.push(root);
+		let index = self.roots.len() - 1;
+		self.watch_config(index)?;
+
+		// Open documents of the folder belonged to another root before.
+		let paths = self.published_paths(index);
+		for path in &paths {
+			let text = self
+				.roots
+				.iter_mut()
+				.find_map(|root| take_shadow_file(&mut root.world, path));
+			if let Some(text) = text {
+				self.roots[index].world.use_shadow_file(path, text);
+			}
+		}
+		self.recheck(paths).await
+	}
 
-		for (i, value) in replacements.into_iter().enumerate() {
-			let title = format!("Replace with \"{}\"", value);
-			let replace = TextEdit { range: diagnostic.range, new_text: value };
-			let edit = [(params.text_document.uri.clone(), vec![replace])]
-				.into_iter()
-				.collect();
+	/// Remove the root of a workspace folder, its documents belong to the remaining roots.
+	async fn remove_folder(&mut self, uri: &Url) -> anyhow::Result<()> {
+		let Ok(folder) = uri.to_file_path() else {
+			return Ok(());
+		};
+		let Some(index) = self
+			.roots
+			.iter()
+			.position(|root| root.folder.as_ref() == Some(&folder))
+		else {
+			return Ok(());
+		};
+		eprintln!("Removing workspace folder {}", folder.display());
+		let paths = self.published_paths(index);
+		self.activate(0).await?;
+		let mut root = self.roots.remove(index);
+		for path in &paths {
+			let Some(text) = take_shadow_file(&mut root.world, path) else {
+				continue;
+			};
+			let target = self.root_index(path);
+			let target = &mut self.roots[target];
+			if path.starts_with(target.world.root()) {
+				target.world.use_shadow_file(path, text);
+			}
+		}
+		self.revision += 1;
+		self.recheck(paths).await
+	}
 
-			action.push(
-				CodeAction {
-					title,
-					is_preferred: Some(i == 0),
-					kind: Some(CodeActionKind::QUICKFIX),
-					diagnostics: Some(params.context.diagnostics.clone()),
-					edit: Some(WorkspaceEdit {
-						changes: Some(edit),
-						..Default::default()
-					}),
-					command: None,
-					disabled: None,
-					data: None,
+	/// Paths of the documents with published diagnostics belonging to the root.
+	fn published_paths(&self, index: usize) -> Vec<PathBuf> {
+		self.published
+			.keys()
+			.filter_map(|uri| uri.to_file_path().ok())
+			.filter(|path| self.root_index(path) == index)
+			.collect()
+	}
+
+	/// Change the project configuration file, its comments and order are kept.
+	fn update_config(
+		&mut self,
+		index: usize,
+		update: impl FnOnce(&Path) -> typst_languagetool::Result<bool>,
+	) -> anyhow::Result<()> {
+		let root = &mut self.roots[index];
+		let Some(path) = &root.options.config else {
+			eprintln!("No config file, the change is only used until restart");
+			return Ok(());
+		};
+		update(path)?;
+		// The change is already applied, it is not loaded again.
+		root.config_modified = modified(path);
+		Ok(())
+	}
+
+	/// Remove the published diagnostics of the root matched by `remove`, of all roots without `index`.
+	fn remove_published(
+		&mut self,
+		index: Option<usize>,
+		remove: impl Fn(&diagnostics::Data) -> bool,
+	) -> anyhow::Result<()> {
+		self.revision += 1;
+		let published = std::mem::take(&mut self.published);
+		for (uri, mut diagnostics) in published {
+			let in_root = match (index, uri.to_file_path()) {
+				(None, _) => true,
+				(Some(index), Ok(path)) => self.root_index(&path) == index,
+				(Some(_), Err(())) => false,
+			};
+			diagnostics.retain(|diagnostic| {
+				if in_root.not() {
+					return true;
 				}
-				.into(),
-			);
+				let Some(data) = diagnostics::Data::from_diagnostic(diagnostic) else {
+					return true;
+				};
+				remove(&data).not()
+			});
+			self.publish(uri, diagnostics)?;
 		}
-		Ok(Some(action))
+		if self.pull_diagnostics {
+			self.next_request_id += 1;
+			send_request::<WorkspaceDiagnosticRefresh>(&self.connection, self.next_request_id, ())?;
+		}
+		Ok(())
+	}
+
+	/// Store the diagnostics and send them to the client if it does not pull them.
+	fn publish(&mut self, uri: Url, diagnostics: Vec<Diagnostic>) -> anyhow::Result<()> {
+		self.published.insert(uri.clone(), diagnostics.clone());
+		self.checked.insert(uri.clone(), self.revision);
+		if self.pull_diagnostics {
+			return Ok(());
+		}
+		let params = PublishDiagnosticsParams { uri, version: None, diagnostics };
+		send_notification::<PublishDiagnostics>(&self.connection, params)
+	}
+
+	/// Publish the diagnostics of a running check, they are replaced when the check is done.
+	/// The `kept` diagnostics of the previous check are added, unless they are found again.
+	fn publish_partial(
+		&self,
+		path: &Path,
+		source: &Source,
+		diagnostics: &[typst_languagetool::Diagnostic],
+		kept: &[Diagnostic],
+		severities: &HashMap<String, Severity>,
+		locations: Locations,
+	) -> anyhow::Result<()> {
+		let Ok(uri) = Url::from_file_path(path) else {
+			return Ok(());
+		};
+		if self.pull_diagnostics {
+			return Ok(());
+		}
+		let mut diagnostics = diagnostics
+			.iter()
+			.flat_map(|diagnostic| {
+				diagnostics::to_lsp(source, diagnostic.clone(), severities, locations)
+			})
+			.collect::<Vec<_>>();
+		let kept = kept
+			.iter()
+			.filter(|kept| {
+				(diagnostics.iter())
+					.any(|other| other.range == kept.range && other.code == kept.code)
+					.not()
+			})
+			.cloned()
+			.collect::<Vec<_>>();
+		diagnostics.extend(kept);
+		let params = PublishDiagnosticsParams { uri, version: None, diagnostics };
+		send_notification::<PublishDiagnostics>(&self.connection, params)
+	}
+
+	async fn pull_diagnostics(
+		&mut self,
+		params: DocumentDiagnosticParams,
+	) -> anyhow::Result<DocumentDiagnosticReportResult> {
+		let uri = params.text_document.uri;
+		let result_id = self.revision.to_string();
+		let up_to_date = self.checked.get(&uri) == Some(&self.revision);
+		if up_to_date && params.previous_result_id.as_ref() == Some(&result_id) {
+			let report = RelatedUnchangedDocumentDiagnosticReport {
+				related_documents: None,
+				unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+					result_id,
+				},
+			};
+			return Ok(DocumentDiagnosticReport::Unchanged(report).into());
+		}
+		// Documents without a file, like `untitled:` buffers, get an empty report.
+		if let (false, Ok(path)) = (up_to_date, uri.to_file_path()) {
+			self.check_change(&path, uri.clone()).await?;
+		}
+		let items = self.published.get(&uri).cloned().unwrap_or_default();
+		let report = RelatedFullDocumentDiagnosticReport {
+			related_documents: None,
+			full_document_diagnostic_report: FullDocumentDiagnosticReport {
+				result_id: Some(result_id),
+				items,
+			},
+		};
+		Ok(DocumentDiagnosticReport::Full(report).into())
 	}
 
 	pub async fn notification(&mut self, not: Notification) -> anyhow::Result<()> {
@@ -341,6 +1609,61 @@ impl State {
 			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
 			Err(ExtractError::MethodMismatch(not)) => not,
 		};
+		let not = match cast_notification::<DidChangeWatchedFiles>(not) {
+			Ok(params) => {
+				let paths = params
+					.changes
+					.iter()
+					.filter_map(|change| change.uri.to_file_path().ok())
+					.collect::<Vec<_>>();
+				for index in 0..self.roots.len() {
+					let config = self.roots[index].settings.config.as_ref();
+					if config.is_some_and(|config| paths.contains(config)) {
+						self.config_file_change(index).await?;
+					}
+				}
+				return Ok(());
+			},
+			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
+			Err(ExtractError::MethodMismatch(not)) => not,
+		};
+		let not = match cast_notification::<DidChangeWorkspaceFolders>(not) {
+			Ok(params) => {
+				for folder in &params.event.removed {
+					self.remove_folder(&folder.uri).await?;
+				}
+				for folder in &params.event.added {
+					self.add_folder(&folder.uri).await?;
+				}
+				return Ok(());
+			},
+			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
+			Err(ExtractError::MethodMismatch(not)) => not,
+		};
+		let not = match cast_notification::<VisibleRange>(not) {
+			Ok(params) => {
+				let uri = params.text_document.uri;
+				if self.visible.insert(uri.clone(), params.range) == Some(params.range) {
+					return Ok(());
+				}
+				// The newly shown lines are checked like a change, their paragraphs first.
+				let Ok(path) = uri.to_file_path() else {
+					return Ok(());
+				};
+				let root = &self.roots[self.root_index(&path)];
+				if let Some(duration) = root.options.on_change {
+					self.check = Some(CheckData {
+						check_time: std::time::Instant::now() + duration,
+						url: uri,
+						path,
+						spelling: root.options.on_idle.is_some(),
+					});
+				}
+				return Ok(());
+			},
+			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
+			Err(ExtractError::MethodMismatch(not)) => not,
+		};
 		let not = match cast_notification::<Cancel>(not) {
 			Ok(_params) => return Ok(()),
 			Err(err @ ExtractError::JsonError { .. }) => return Err(err.into()),
@@ -358,10 +1681,15 @@ impl State {
 	async fn file_save(&mut self, params: DidSaveTextDocumentParams) -> anyhow::Result<()> {
 		let path = params.text_document.uri.to_file_path().unwrap();
 		eprintln!("Save {}", path.display());
+		// Clients without watched files report no changes of the configuration.
+		if self.watch_files.is_none() {
+			self.config_file_change(self.root_index(&path)).await?;
+		}
 		self.check = Some(CheckData {
 			check_time: std::time::Instant::now(),
 			url: params.text_document.uri,
 			path,
+			spelling: false,
 		});
 		Ok(())
 	}
@@ -369,11 +1697,16 @@ impl State {
 	async fn file_open(&mut self, params: DidOpenTextDocumentParams) -> anyhow::Result<()> {
 		let path = params.text_document.uri.to_file_path().unwrap();
 		eprintln!("Open {}", path.display());
-		self.world.use_shadow_file(&path, params.text_document.text);
+		let index = self.root_index(&path);
+		self.roots[index]
+			.world
+			.use_shadow_file(&path, params.text_document.text);
+		self.revision += 1;
 		self.check = Some(CheckData {
 			check_time: std::time::Instant::now(),
 			url: params.text_document.uri,
 			path,
+			spelling: false,
 		});
 		Ok(())
 	}
@@ -381,14 +1714,26 @@ impl State {
 	async fn file_close(&mut self, params: DidCloseTextDocumentParams) -> anyhow::Result<()> {
 		let path = &params.text_document.uri.to_file_path().unwrap();
 		eprintln!("Close {}", path.display());
-		self.world.use_original_file(&path);
+		let index = self.root_index(path);
+		self.roots[index].world.use_original_file(path);
+		self.published.remove(&params.text_document.uri);
+		self.checked.remove(&params.text_document.uri);
+		self.visible.remove(&params.text_document.uri);
+		let params = PublishDiagnosticsParams {
+			uri: params.text_document.uri,
+			version: None,
+			diagnostics: Vec::new(),
+		};
+		send_notification::<PublishDiagnostics>(&self.connection, params)?;
 		Ok(())
 	}
 
 	async fn file_change(&mut self, params: DidChangeTextDocumentParams) -> anyhow::Result<()> {
 		let path = params.text_document.uri.to_file_path().unwrap();
 		eprintln!("Change {}", path.display());
-		let source = self.world.shadow_file(&path).unwrap();
+		let index = self.root_index(&path);
+		let root = &mut self.roots[index];
+		let source = root.world.shadow_file(&path).unwrap();
 
 		for change in &params.content_changes {
 			if let Some(range) = change.range {
@@ -399,18 +1744,39 @@ impl State {
 					.line_column_to_byte(range.end.line as usize, range.end.character as usize)
 					.unwrap();
 				source.edit(start..end, &change.text);
+				if let Some(published) = self.published.get_mut(&params.text_document.uri) {
+					diagnostics::carry_forward(published, range, &change.text);
+				}
 			} else {
+				if let Some(published) = self.published.get_mut(&params.text_document.uri) {
+					let (range, text) = diagnostics::replaced(source, &change.text);
+					diagnostics::carry_forward(published, range, text);
+				}
 				source.replace(&change.text);
 			}
 		}
+		self.revision += 1;
+		// The diagnostics of unchanged text keep their place until the next check.
+		if let Some(published) = self.published.get(&params.text_document.uri) {
+			if self.pull_diagnostics.not() {
+				let params = PublishDiagnosticsParams {
+					uri: params.text_document.uri.clone(),
+					version: None,
+					diagnostics: published.clone(),
+				};
+				send_notification::<PublishDiagnostics>(&self.connection, params)?;
+			}
+		}
 
-		let Some(duration) = self.options.on_change else {
+		// Typing checks only the changed paragraphs, the others are cached.
+		let Some(duration) = root.options.on_change else {
 			return Ok(());
 		};
 		self.check = Some(CheckData {
 			check_time: std::time::Instant::now() + duration,
 			url: params.text_document.uri,
 			path,
+			spelling: root.options.on_idle.is_some(),
 		});
 		Ok(())
 	}
@@ -418,21 +1784,149 @@ impl State {
 	async fn check_change(&mut self, path: &Path, url: Url) -> anyhow::Result<()> {
 		eprintln!("Checking: {}", path.display());
 
-		let diagnostics = match self.get_diagnostics(path).await {
+		self.progress_begin(PROGRESS_TOKEN, "Checking")?;
+		let diagnostics = self
+			.get_diagnostics(path, PROGRESS_TOKEN, (0, 1), false)
+			.await;
+		self.progress_end(PROGRESS_TOKEN)?;
+		let diagnostics = match diagnostics {
 			Ok(d) => d,
+			Err(err) if is_cancelled(&err) => {
+				eprintln!("Check cancelled: {}", path.display());
+				// Check the latest text once the document is not changed anymore.
+				self.check.get_or_insert(CheckData {
+					check_time: std::time::Instant::now(),
+					url,
+					path: path.to_owned(),
+					spelling: false,
+				});
+				return Ok(());
+			},
 			Err(err) => {
 				eprintln!("{:?}", err);
 				return Ok(());
 			},
 		};
+		self.cache.evict();
+		eprintln!("{:?}", self.cache.metrics());
+		// Checked again once the backend might be reachable.
+		if let Some(offline) = &self.offline {
+			self.check.get_or_insert(CheckData {
+				check_time: offline.retry,
+				url: url.clone(),
+				path: path.to_owned(),
+				spelling: false,
+			});
+		}
+
+		self.publish(url, diagnostics)
+	}
 
+	/// Publish the cached diagnostics with the local spelling of the changed paragraphs,
+	/// the file is checked with the backend after a pause of `on_change`.
+	async fn check_spelling(&mut self, path: &Path, url: Url) -> anyhow::Result<()> {
+		let index = self.root_index(path);
+		if let Some(idle) = self.roots[index].options.on_idle {
+			self.check = Some(CheckData {
+				check_time: std::time::Instant::now() + idle,
+				url: url.clone(),
+				path: path.to_owned(),
+				spelling: false,
+			});
+		}
+		// Pulled diagnostics of the revision are the ones of the full check.
+		if self.pull_diagnostics {
+			return Ok(());
+		}
+		let diagnostics = match self
+			.get_diagnostics(path, PROGRESS_TOKEN, (0, 1), true)
+			.await
+		{
+			Ok(diagnostics) => diagnostics,
+			Err(err) => {
+				eprintln!("{:?}", err);
+				return Ok(());
+			},
+		};
+		// Not marked as checked, so the next pull or save runs the full check.
+		self.published.insert(url.clone(), diagnostics.clone());
 		let params = PublishDiagnosticsParams { uri: url, version: None, diagnostics };
-		send_notification::<PublishDiagnostics>(&self.connection, params)?;
+		send_notification::<PublishDiagnostics>(&self.connection, params)
+	}
+
+	/// Queue all Typst files in the project root, they are checked while the client is idle.
+	/// Files excluded by `typst.toml`, `.gitignore` or `.ltignore` and files of downloaded packages
+	/// are skipped.
+	fn check_workspace(&mut self) -> anyhow::Result<()> {
+		let mut paths = Vec::new();
+		for (index, root) in self.roots.iter().enumerate() {
+			let mut files = Vec::new();
+			let dir = root.world.root();
+			typst_files(dir, &lt_world::Ignore::new(dir), &mut files)?;
+			let project = Project::find(dir);
+			files.retain(|path| {
+				lt_world::is_package_file(path).not()
+					&& project
+						.as_ref()
+						.is_some_and(|project| project.is_excluded(path))
+						.not() && self.root_index(path) == index
+			});
+			paths.extend(files);
+		}
+		paths.sort();
+		paths.reverse();
+		eprintln!("Checking {} files in the workspace", paths.len());
+		if self.workspace.is_none() {
+			self.progress_begin(WORKSPACE_PROGRESS_TOKEN, "Checking workspace")?;
+		}
+		self.workspace = Some(WorkspaceCheck { total: paths.len(), paths });
 		Ok(())
 	}
 
+	async fn check_workspace_file(&mut self) -> anyhow::Result<()> {
+		let Some(workspace) = &mut self.workspace else {
+			return Ok(());
+		};
+		let Some(path) = workspace.paths.pop() else {
+			self.workspace = None;
+			self.progress_end(WORKSPACE_PROGRESS_TOKEN)?;
+			if self.pull_diagnostics {
+				self.next_request_id += 1;
+				send_request::<WorkspaceDiagnosticRefresh>(
+					&self.connection,
+					self.next_request_id,
+					(),
+				)?;
+			}
+			return Ok(());
+		};
+		let files = (workspace.total - workspace.paths.len() - 1, workspace.total);
+
+		eprintln!("Checking: {}", path.display());
+		let diagnostics = self
+			.get_diagnostics(&path, WORKSPACE_PROGRESS_TOKEN, files, false)
+			.await;
+		let diagnostics = match diagnostics {
+			Ok(d) => d,
+			Err(err) if is_cancelled(&err) => {
+				if let Some(workspace) = &mut self.workspace {
+					workspace.paths.push(path);
+				}
+				return Ok(());
+			},
+			Err(err) => {
+				eprintln!("{:?}", err);
+				return Ok(());
+			},
+		};
+		let Ok(uri) = Url::from_file_path(&path) else {
+			return Ok(());
+		};
+		self.publish(uri, diagnostics)
+	}
+
 	async fn config_change(&mut self, params: DidChangeConfigurationParams) -> anyhow::Result<()> {
-		let mut options =
+		let options =
 			match serde_ignored::deserialize::<_, _, InitOptions>(params.settings, |path| {
 				eprintln!("unknown option {}", path);
 			}) {
@@ -443,95 +1937,484 @@ impl State {
 				},
 			};
 
-		options.make_absolute();
+		self.client = options;
+		for index in 0..self.roots.len() {
+			let root = &mut self.roots[index];
+			let settings = self.client.resolved(root.folder.as_deref());
+			let watched = std::mem::replace(&mut root.settings, settings.clone());
+			if settings.config != watched.config {
+				self.watch_config(index)?;
+			}
+			self.apply_settings(index, settings, true).await?;
+		}
+		Ok(())
+	}
+
+	/// Reload the project configuration after it changed on disk, like when it is edited by hand.
+	/// Only the cached results of the languages with changed settings are checked again.
+	async fn config_file_change(&mut self, index: usize) -> anyhow::Result<()> {
+		let root = &self.roots[index];
+		let modified = root.settings.config.as_deref().and_then(modified);
+		if modified == root.config_modified {
+			return Ok(());
+		}
+		eprintln!("Reloading the project configuration");
+		self.apply_settings(index, root.settings.clone(), false)
+			.await
+	}
+
+	/// Ask the client to report changes of the project configuration of the root.
+	fn watch_config(&mut self, index: usize) -> anyhow::Result<()> {
+		let config = &self.roots[index].settings.config;
+		let (Some(watch_files), Some(path)) = (self.watch_files, config) else {
+			return Ok(());
+		};
+		let glob_pattern = match (path.parent(), path.file_name()) {
+			(Some(dir), Some(name)) if watch_files.relative_patterns => {
+				let Ok(base_uri) = Url::from_directory_path(dir) else {
+					return Ok(());
+				};
+				GlobPattern::Relative(RelativePattern {
+					base_uri: OneOf::Right(base_uri),
+					pattern: name.to_string_lossy().into_owned(),
+				})
+			},
+			_ => GlobPattern::String(path.to_string_lossy().replace('\\', "/")),
+		};
+		let options = DidChangeWatchedFilesRegistrationOptions {
+			watchers: vec![FileSystemWatcher { glob_pattern, kind: None }],
+		};
+		self.next_request_id += 1;
+		let registration = Registration {
+			id: format!("typst-languagetool/config/{}", self.next_request_id),
+			method: <DidChangeWatchedFiles as lsp_types::notification::Notification>::METHOD.into(),
+			register_options: Some(serde_json::to_value(options)?),
+		};
+		send_request::<RegisterCapability>(
+			&self.connection,
+			self.next_request_id,
+			RegistrationParams { registrations: vec![registration] },
+		)
+	}
+
+	/// Use the options of the client with the project configuration for the root.
+	/// Without `clear_cache` only the results for changed settings are checked again.
+	async fn apply_settings(
+		&mut self,
+		index: usize,
+		mut options: InitOptions,
+		clear_cache: bool,
+	) -> anyhow::Result<()> {
+		self.roots[index].config_modified = options.config.as_deref().and_then(modified);
+		if let Err(err) = options.load_config() {
+			eprintln!("{}", err);
+		}
+		if let Err(err) = options.ignored() {
+			eprintln!("{}", err);
+			return Ok(());
+		}
 		eprintln!("options: {:#?}", options);
 
-		self.lt = match options.create_lt().await {
-			Ok(lt) => lt,
-			Err(err) => {
+		if options.backend() == self.backend {
+			if let Err(err) = self.update_lt(index, &options).await {
 				eprintln!("{}", err);
 				return Ok(());
-			},
-		};
+			}
+		} else {
+			self.lt = match options.create_lt().await {
+				Ok(lt) => lt,
+				Err(err) => {
+					eprintln!("{}", err);
+					return Ok(());
+				},
+			};
+			self.backend = options.backend();
+			// Results of another backend are not reused.
+			match self.lt.version().await {
+				Ok(version) => self.cache.set_version(&version),
+				Err(_) => self.cache.clear(),
+			}
+			self.active = index;
+			self.fallback = language_fallback(&self.lt).await;
+			self.warned_languages.clear();
+		}
 
-		if let Some(main) = options.main {
-			self.world.update(main, options.root);
+		self.cache
+			.set_paragraphs_per_request(options.paragraphs_per_request);
+		self.cache.set_submission(options.submission);
+		self.cache
+			.set_limits(options.cache_entries(), options.cache_bytes);
+		let root = &mut self.roots[index];
+		if let Some(main) = &options.main {
+			root.world.update(main.clone(), options.root.clone());
+		}
+		root.options = Options::new(options)?;
+		root.converters.clear();
+		if clear_cache {
+			self.cache.clear();
 		}
+		self.revision += 1;
+		self.recheck(self.published_paths(index)).await
+	}
 
-		self.options = Options {
-			on_change: options.on_change,
-			chunk_size: options.chunk_size,
-			language_codes: create_language_map(options.languages),
-		};
+	/// Apply the new settings of the root to the running backend.
+	/// Languages missing from the new settings are reset.
+	async fn update_lt(&mut self, index: usize, options: &InitOptions) -> anyhow::Result<()> {
+		self.activate(index).await?;
+		self.roots[index]
+			.options
+			.reset_missing(
+				&mut self.lt,
+				&options.dictionary,
+				&options.disabled_checks,
+				&options.rule_options,
+				&options.rule_files,
+				&options.spelling_files,
+			)
+			.await?;
+		options.apply(&mut self.lt).await
+	}
 
+	/// Check the documents again, or ask the client to pull the diagnostics again.
+	async fn recheck(&mut self, paths: Vec<PathBuf>) -> anyhow::Result<()> {
+		if paths.is_empty() {
+			return Ok(());
+		}
+		if self.pull_diagnostics {
+			self.next_request_id += 1;
+			return send_request::<WorkspaceDiagnosticRefresh>(
+				&self.connection,
+				self.next_request_id,
+				(),
+			);
+		}
+		for path in paths {
+			let Ok(uri) = Url::from_file_path(&path) else {
+				continue;
+			};
+			self.check_change(&path, uri).await?;
+		}
 		Ok(())
 	}
 
-	async fn get_diagnostics(&mut self, path: &Path) -> anyhow::Result<Vec<Diagnostic>> {
-		let Some(doc) = self.world.compile() else {
+	/// Check the file, `files` are the checked and total files reported for the progress `token`.
+	/// With `spelling` paragraphs without cached results are only checked by the local
+	/// dictionaries, nothing is sent to the backend.
+	async fn get_diagnostics(
+		&mut self,
+		path: &Path,
+		token: &str,
+		files: (usize, usize),
+		spelling: bool,
+	) -> anyhow::Result<Vec<Diagnostic>> {
+		self.cancellation.reset();
+		let index = self.root_index(path);
+		self.activate(index).await?;
+		let root = &mut self.roots[index];
+		let doc = match root.options.main {
+			Some(_) => root.world.compile(),
+			None => root.world.compile_main(path),
+		};
+		let Some(doc) = doc else {
 			eprintln!("TODO: Warning could not compile");
 			return Ok(Vec::new());
 		};
 
-		let file_id = self.world.file_id(path);
-		let paragraphs =
-			typst_languagetool::convert::document(&doc, self.options.chunk_size, file_id);
-		let mut collector = typst_languagetool::FileCollector::new(file_id, &self.world);
-		let mut next_cache = Cache::new();
+		let file_id = root.world.file_id(path);
+		let source = root.world.source(file_id)?;
+		let submission = root.options.submission;
+		let chunk_size = submission.chunk_size(
+			root.options
+				.chunk_size
+				.unwrap_or(self.lt.default_chunk_size()),
+		);
+		let options = &root.options.convert;
+		let paragraphs = root
+			.converters
+			.entry(file_id)
+			.or_insert_with(|| convert::Incremental::new(chunk_size, options.clone()))
+			.document(&doc, &source);
+		let mut paragraphs = submission
+			.chunks(paragraphs)
+			.into_iter()
+			.map(|paragraph| (false, paragraph))
+			.collect::<Vec<_>>();
+		// The paragraphs on the lines shown in the editor are checked and published first, the
+		// previous diagnostics of the other lines are kept until the rest is checked.
+		let url = Url::from_file_path(path).ok();
+		let lines = url.as_ref().and_then(|url| self.visible.get(url));
+		let mut kept = Vec::new();
+		if let Some(lines) = lines {
+			let start = source.line_to_byte(lines.start.line as usize);
+			let end = source.line_to_byte(lines.end.line as usize + 1);
+			let visible = start.unwrap_or(source.len_bytes())..end.unwrap_or(source.len_bytes());
+			for (shown, (text, mapping)) in &mut paragraphs {
+				let ranges = mapping.range_location(0..text.encode_utf16().count(), &source);
+				*shown = (ranges.iter())
+					.any(|range| range.start < visible.end && visible.start < range.end);
+			}
+			paragraphs.sort_by_key(|(shown, _)| shown.not());
+			let previous = url.as_ref().and_then(|url| self.published.get(url));
+			kept = (previous.into_iter().flatten())
+				.filter(|diagnostic| {
+					diagnostic.range.end.line < lines.start.line
+						|| lines.end.line < diagnostic.range.start.line
+				})
+				.cloned()
+				.collect();
+		}
+		let shown = paragraphs.iter().filter(|(shown, _)| *shown).count();
+		let paragraphs = paragraphs
+			.into_iter()
+			.map(|(_, paragraph)| paragraph)
+			.collect::<Vec<_>>();
+		let root = &self.roots[index];
+		// Only the cached results until the next retry of an unavailable backend.
+		let mut cached = spelling
+			|| (self.offline.as_ref())
+				.is_some_and(|offline| offline.retry > std::time::Instant::now());
+		if cached.not()
+			&& root.options.language.is_none()
+			&& self.detected_languages.insert(path.to_owned())
+		{
+			let codes = &root.options.language_codes;
+			match typst_languagetool::suggest_language(&self.lt, &paragraphs, codes).await {
+				Ok(Some(detected)) => {
+					let message = format!(
+						"The text of {} seems to be {}, set its language with `#set text(lang: \"{}\")`.",
+						path.display(),
+						detected.name,
+						detected.code.language()
+					);
+					let params = ShowMessageParams { typ: MessageType::INFO, message };
+					send_notification::<ShowMessage>(&self.connection, params)?;
+				},
+				Ok(None) => {},
+				Err(err) => eprintln!("could not detect the language: {}", err),
+			}
+		}
+		let mut collector = typst_languagetool::FileCollector::new(file_id, &root.world);
+		collector.set_debug(root.options.debug_mapping);
+		collector.set_locations(root.options.locations);
+		collector.set_contexts(root.options.contexts.clone());
+		let mut ignore = root.options.ignore.clone();
+		ignore.add_terms(glossary::terms(&source));
+		ignore.add_terms(glossary::terms(&root.world.main()));
 		let l = paragraphs.len();
+		// The rule and spelling files are read once per language.
+		let mut hashes = HashMap::new();
+		// Source ranges of the paragraphs without cached results while the backend is unavailable.
+		let mut stale = Vec::new();
 		for (idx, (text, mapping)) in paragraphs.into_iter().enumerate() {
-			let lang = self
-				.options
-				.language_codes
-				.get(mapping.short_language())
-				.map(|x| x.clone())
-				.unwrap_or(mapping.long_language());
-			let suggestions = if let Some(suggestions) = self.cache.get(&text) {
-				suggestions
-			} else {
-				eprintln!("Checking {}/{}", idx + 1, l);
-				self.lt.check_text(lang, &text).await?
+			self.cancellation.check()?;
+			let options = &root.options;
+			let code = match (mapping.file_language(), &options.language) {
+				(Some(code), _) => code.to_owned(),
+				(None, Some(language)) => language.clone(),
+				(None, None) => mapping.language_code(&options.language_codes),
 			};
-			collector.add(&suggestions, mapping);
-			next_cache.insert(text, suggestions);
+			let lang = self.fallback.resolve(&code, &options.language_fallbacks);
+			if lang != code && self.warned_languages.insert(code.clone()) {
+				eprintln!(
+					"language '{}' is not supported by the backend, checking with '{}'",
+					code, lang
+				);
+			}
+			let lang = lang.into_owned();
+			let settings = *hashes
+				.entry(lang.clone())
+				.or_insert_with(|| options.settings().hash(&lang));
+			let mut suggestions = match cached {
+				true => local_spelling(&self.cache, options, &lang, settings, &text),
+				false => match self.cache.check(&self.lt, &lang, settings, &text).await {
+					Ok(suggestions) => {
+						set_available(&self.connection, &mut self.offline)?;
+						suggestions
+					},
+					Err(err) if is_unavailable(&err) => {
+						set_unavailable(&self.connection, &mut self.offline, &err)?;
+						cached = true;
+						local_spelling(&self.cache, options, &lang, settings, &text)
+					},
+					Err(err) => return Err(err.into()),
+				},
+			};
+			if cached && spelling.not() {
+				let (_, missing) = self.cache.lookup(&lang, settings, &text);
+				for (offset, paragraph) in missing {
+					let range = offset..offset + paragraph.encode_utf16().count();
+					stale.extend(mapping.range_location(range, collector.source()));
+				}
+			}
+			ignore.retain(&text, &mut suggestions);
+			if options.keep_adjacent.not() {
+				merge_adjacent(&text, &mut suggestions);
+			}
+			let dictionary = language_entries(&options.dictionary, &lang);
+			let dictionary = dictionary
+				.into_iter()
+				.flatten()
+				.map(String::as_str)
+				.collect();
+			options.ranking.apply(&text, &mut suggestions, &dictionary);
+			let new = collector.add(&text, suggestions, mapping, &lang);
+			if spelling {
+				continue;
+			}
+			// Long files show the first diagnostics while the check continues.
+			if (new.is_empty().not() || idx + 1 == shown) && idx + 1 < l {
+				let severities = &options.severities;
+				self.publish_partial(
+					path,
+					collector.source(),
+					collector.diagnostics(),
+					&kept,
+					severities,
+					options.locations,
+				)?;
+			}
+
+			self.progress_report(
+				token,
+				typst_languagetool::Progress {
+					file: file_id,
+					files_done: files.0,
+					files_total: files.1,
+					paragraphs_done: idx + 1,
+					paragraphs_total: l,
+				},
+			)?;
 		}
-		self.cache = next_cache;
 		eprintln!("Generating diagnostics");
 
 		let (source, diagnostics) = collector.finish();
 
-		let diagnostics = diagnostics
+		let mut diagnostics = diagnostics
 			.into_iter()
-			.map(|diagnostic| {
-				let (start_line, start_column) =
-					byte_to_position(&source, diagnostic.locations[0].start);
-				let (end_line, end_column) = byte_to_position(&source, diagnostic.locations[0].end);
-
-				Diagnostic {
-					range: Range {
-						start: lsp_types::Position {
-							line: start_line as u32,
-							character: start_column as u32,
-						},
-						end: lsp_types::Position {
-							line: end_line as u32,
-							character: end_column as u32,
-						},
-					},
-					severity: Some(DiagnosticSeverity::INFORMATION),
-					code: Some(NumberOrString::String(diagnostic.rule_id)),
-					code_description: None,
-					source: None,
-					message: diagnostic.message,
-					related_information: None,
-					tags: None,
-					data: serde_json::to_value(diagnostic.replacements).ok(),
-				}
+			.flat_map(|diagnostic| {
+				let options = &root.options;
+				diagnostics::to_lsp(&source, diagnostic, &options.severities, options.locations)
 			})
-			.collect();
+			.collect::<Vec<_>>();
+		let previous = Url::from_file_path(path)
+			.ok()
+			.and_then(|url| self.published.get(&url));
+		if let Some(previous) = previous.filter(|_| stale.is_empty().not()) {
+			let ranges = stale
+				.iter()
+				.map(|range| diagnostics::byte_range_to_range(&source, range.start, range.end))
+				.collect::<Vec<_>>();
+			let kept = diagnostics::stale(previous, &ranges, &diagnostics);
+			diagnostics.extend(kept);
+		}
 
 		Ok(diagnostics)
 	}
+
+	fn progress_begin(&mut self, token: &str, title: &str) -> anyhow::Result<()> {
+		if self.work_done_progress.not() {
+			return Ok(());
+		}
+		self.next_request_id += 1;
+		send_request::<WorkDoneProgressCreate>(
+			&self.connection,
+			self.next_request_id,
+			WorkDoneProgressCreateParams {
+				token: NumberOrString::String(token.into()),
+			},
+		)?;
+		self.send_progress(
+			token,
+			WorkDoneProgress::Begin(WorkDoneProgressBegin {
+				title: title.into(),
+				cancellable: Some(false),
+				message: None,
+				percentage: Some(0),
+			}),
+		)
+	}
+
+	fn progress_report(
+		&self,
+		token: &str,
+		progress: typst_languagetool::Progress,
+	) -> anyhow::Result<()> {
+		if self.work_done_progress.not() {
+			return Ok(());
+		}
+		let mut message = format!(
+			"{}/{} paragraphs",
+			progress.paragraphs_done, progress.paragraphs_total
+		);
+		if progress.files_total > 1 {
+			message = format!(
+				"{}/{} files, {}",
+				progress.files_done + 1,
+				progress.files_total,
+				message
+			);
+		}
+		self.send_progress(
+			token,
+			WorkDoneProgress::Report(WorkDoneProgressReport {
+				cancellable: Some(false),
+				message: Some(message),
+				percentage: Some(progress.percentage()),
+			}),
+		)
+	}
+
+	fn progress_end(&self, token: &str) -> anyhow::Result<()> {
+		if self.work_done_progress.not() {
+			return Ok(());
+		}
+		self.send_progress(
+			token,
+			WorkDoneProgress::End(WorkDoneProgressEnd { message: None }),
+		)
+	}
+
+	fn send_progress(&self, token: &str, progress: WorkDoneProgress) -> anyhow::Result<()> {
+		let params = ProgressParams {
+			token: NumberOrString::String(token.into()),
+			value: ProgressParamsValue::WorkDone(progress),
+		};
+		send_notification::<Progress>(&self.connection, params)
+	}
+}
+
+/// Collect the Typst files in the directory recursively, hidden directories and paths matched by
+/// `.gitignore` or `.ltignore` are skipped.
+fn typst_files(
+	dir: &Path,
+	ignore: &lt_world::Ignore,
+	paths: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+	for entry in std::fs::read_dir(dir)? {
+		let path = entry?.path();
+		if ignore.is_ignored(&path, path.is_dir()) {
+			continue;
+		}
+		let hidden = path
+			.file_name()
+			.is_some_and(|name| name.to_string_lossy().starts_with('.'));
+		if path.is_dir() && hidden.not() {
+			typst_files(&path, &ignore.child(&path), paths)?;
+		} else if path.extension().is_some_and(|ext| ext == "typ") {
+			paths.push(path);
+		}
+	}
+	Ok(())
+}
+
+/// Text of the open document in the world, the world uses the file on disk afterwards.
+fn take_shadow_file(world: &mut LtWorld, path: &Path) -> Option<String> {
+	if path.starts_with(world.root()).not() {
+		return None;
+	}
+	let text = world.shadow_file(path)?.text().to_owned();
+	world.use_original_file(path);
+	Some(text)
 }
 
 fn cast_request<R>(req: Request) -> Result<(RequestId, R::Params), ExtractError<Request>>
@@ -550,7 +2433,6 @@ where
 	not.extract(N::METHOD)
 }
 
-#[allow(dead_code)]
 fn send_request<R>(connection: &Connection, id: i32, params: R::Params) -> anyhow::Result<()>
 where
 	R: lsp_types::request::Request,
@@ -579,29 +2461,82 @@ where
 	Ok(())
 }
 
-#[derive(Debug)]
-struct Cache {
-	cache: HashMap<String, Vec<Suggestion>>,
+/// The backend could not be reached or refused the request, the check is retried later.
+fn is_unavailable(err: &typst_languagetool::Error) -> bool {
+	matches!(
+		err,
+		typst_languagetool::Error::BackendUnavailable(_)
+			| typst_languagetool::Error::Remote(_)
+			| typst_languagetool::Error::Jvm(_)
+			| typst_languagetool::Error::RateLimited
+			| typst_languagetool::Error::QuotaExceeded(Some(_))
+			| typst_languagetool::Error::Io(_)
+	)
 }
 
-impl Cache {
-	pub fn new() -> Self {
-		Self { cache: HashMap::new() }
-	}
+/// Use the cached results after the backend failed, the first failure is shown once.
+/// Retries are delayed longer after every failure.
+fn set_unavailable(
+	connection: &Connection,
+	offline: &mut Option<Offline>,
+	err: &typst_languagetool::Error,
+) -> anyhow::Result<()> {
+	let delay = match offline {
+		Some(offline) => (offline.delay * 2).min(MAX_RETRY_DELAY),
+		None => {
+			let message = format!(
+				"LanguageTool is unavailable ({}), the cached diagnostics are shown until it responds again.",
+				err
+			);
+			let params = ShowMessageParams { typ: MessageType::WARNING, message };
+			send_notification::<ShowMessage>(connection, params)?;
+			RETRY_DELAY
+		},
+	};
+	eprintln!("backend unavailable, retrying in {:?}: {}", delay, err);
+	*offline = Some(Offline {
+		retry: std::time::Instant::now() + delay,
+		delay,
+	});
+	Ok(())
+}
 
-	pub fn get(&mut self, text: &str) -> Option<Vec<Suggestion>> {
-		self.cache.remove(text)
+fn set_available(connection: &Connection, offline: &mut Option<Offline>) -> anyhow::Result<()> {
+	if offline.take().is_some() {
+		let message = "LanguageTool responds again.".to_owned();
+		let params = ShowMessageParams { typ: MessageType::INFO, message };
+		send_notification::<ShowMessage>(connection, params)?;
 	}
+	Ok(())
+}
 
-	pub fn insert(&mut self, text: String, suggestions: Vec<Suggestion>) {
-		self.cache.insert(text, suggestions);
-	}
+fn is_cancelled(err: &anyhow::Error) -> bool {
+	matches!(
+		err.downcast_ref::<typst_languagetool::Error>(),
+		Some(typst_languagetool::Error::Cancelled)
+	)
 }
 
-fn byte_to_position(source: &Source, index: usize) -> (usize, usize) {
-	let line = source.byte_to_line(index).unwrap();
-	let start = source.line_to_byte(line).unwrap();
-	let head = source.get(start..index).unwrap();
-	let column = head.chars().count();
-	(line, column)
+/// Cached suggestions of the text with the local spelling of the paragraphs without results.
+fn local_spelling(
+	cache: &Cache,
+	options: &Options,
+	lang: &str,
+	settings: u64,
+	text: &str,
+) -> Vec<Suggestion> {
+	let (mut suggestions, missing) = cache.lookup(lang, settings, text);
+	if let Some(speller) = language_entries(&options.spellers, lang).first() {
+		let allowed = language_entries(&options.dictionary, lang)
+			.into_iter()
+			.flatten()
+			.map(String::as_str)
+			.collect();
+		for (offset, paragraph) in missing {
+			let checked = speller.check(paragraph, &allowed);
+			suggestions.extend(cache::shift(&checked, offset as isize));
+		}
+	}
+	suggestions.sort_by_key(|suggestion| suggestion.start);
+	suggestions
 }
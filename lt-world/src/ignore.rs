@@ -0,0 +1,180 @@
+use std::{
+	ops::Not,
+	path::{Path, PathBuf},
+};
+
+use crate::project::glob;
+
+/// Files with patterns of paths skipped when searching the files of a folder, like build outputs.
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".ltignore"];
+
+/// Patterns of the ignore files of a folder and its parents, with the syntax of `.gitignore`.
+#[derive(Debug, Clone)]
+pub struct Ignore {
+	/// Canonical path of the folder.
+	dir: PathBuf,
+	/// Outer folders first, the patterns of inner folders take precedence.
+	files: Vec<IgnoreFile>,
+}
+
+impl Ignore {
+	/// Ignore files of the folder and its parents up to the folder with `.git` or `typst.toml`.
+	/// Without such a parent only the ignore files of the folder are used.
+	pub fn new(dir: &Path) -> Self {
+		let canonical = canonical(dir);
+		let root = canonical
+			.ancestors()
+			.find(|dir| dir.join(".git").exists() || dir.join("typst.toml").is_file())
+			.unwrap_or(&canonical);
+		let mut dirs = canonical
+			.ancestors()
+			.take_while(|dir| dir.starts_with(root))
+			.collect::<Vec<_>>();
+		dirs.reverse();
+		let files = dirs.into_iter().flat_map(IgnoreFile::load).collect();
+		Self { dir: canonical, files }
+	}
+
+	/// With the ignore files of a subfolder.
+	pub fn child(&self, dir: &Path) -> Self {
+		let dir = canonical(dir);
+		let mut files = self.files.clone();
+		files.extend(IgnoreFile::load(&dir));
+		Self { dir, files }
+	}
+
+	pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+		let path = canonical(path);
+		self.files
+			.iter()
+			.rev()
+			.find_map(|file| file.matches(&path, is_dir))
+			.unwrap_or(false)
+	}
+
+	/// The file or a folder between it and the folder of the ignore files is ignored,
+	/// like for the changed files of a watched folder.
+	pub fn is_ignored_below(&self, path: &Path) -> bool {
+		let path = canonical(path);
+		let Ok(relative) = path.strip_prefix(&self.dir) else {
+			return false;
+		};
+		let mut ignore = self.clone();
+		let mut dir = self.dir.clone();
+		let mut components = relative.components().peekable();
+		while let Some(component) = components.next() {
+			let inner = dir.join(component);
+			let last = components.peek().is_none();
+			if ignore.is_ignored(&inner, last.not() || inner.is_dir()) {
+				return true;
+			}
+			if last.not() {
+				ignore = ignore.child(&inner);
+			}
+			dir = inner;
+		}
+		false
+	}
+}
+
+/// Patterns of one ignore file, relative to its folder.
+#[derive(Debug, Clone)]
+struct IgnoreFile {
+	dir: PathBuf,
+	patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+	glob: String,
+	/// Paths matched by a pattern starting with `!` are not ignored.
+	negated: bool,
+	/// Only folders are matched by a pattern ending with `/`.
+	dir_only: bool,
+}
+
+impl IgnoreFile {
+	fn load(dir: &Path) -> Vec<Self> {
+		IGNORE_FILES
+			.iter()
+			.filter_map(|name| std::fs::read_to_string(dir.join(name)).ok())
+			.map(|content| Self::parse(dir, &content))
+			.collect()
+	}
+
+	fn parse(dir: &Path, content: &str) -> Self {
+		let patterns = content
+			.lines()
+			.map(str::trim_end)
+			.filter(|line| line.is_empty().not() && line.starts_with('#').not())
+			.map(|line| {
+				let (negated, line) = match line.strip_prefix('!') {
+					Some(line) => (true, line),
+					None => (false, line),
+				};
+				let line = line.strip_prefix('\\').unwrap_or(line);
+				let (dir_only, line) = match line.strip_suffix('/') {
+					Some(line) => (true, line),
+					None => (false, line),
+				};
+				// Patterns without a slash match at every depth.
+				let glob = match line.strip_prefix('/') {
+					Some(line) => line.to_owned(),
+					None if line.contains('/') => line.to_owned(),
+					None => format!("**/{}", line),
+				};
+				Pattern { glob, negated, dir_only }
+			})
+			.collect();
+		Self { dir: dir.to_owned(), patterns }
+	}
+
+	/// `Some(true)` for an ignored path, `Some(false)` for a path included again by a negated
+	/// pattern and `None` if no pattern matches. The last matching pattern decides.
+	fn matches(&self, path: &Path, is_dir: bool) -> Option<bool> {
+		let relative = path.strip_prefix(&self.dir).ok()?;
+		let relative = relative.to_string_lossy().replace('\\', "/");
+		self.patterns
+			.iter()
+			.rev()
+			.filter(|pattern| pattern.dir_only.not() || is_dir)
+			.find(|pattern| glob(&pattern.glob, &relative))
+			.map(|pattern| pattern.negated.not())
+	}
+}
+
+pub(crate) fn canonical(path: &Path) -> PathBuf {
+	// The folder of a relative path in the working directory is empty.
+	let dir = if path.as_os_str().is_empty() {
+		Path::new(".")
+	} else {
+		path
+	};
+	dir.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_ignore_file() {
+		let dir = Path::new("/project");
+		let file = IgnoreFile::parse(
+			dir,
+			"# build outputs\nbuild/\n*.pdf\n!keep.pdf\n/vendor\ndocs/*.typ\n",
+		);
+		let matches = |path: &str, is_dir: bool| file.matches(&dir.join(path), is_dir);
+		assert_eq!(matches("build", true), Some(true));
+		assert_eq!(matches("build", false), None);
+		assert_eq!(matches("chapters/build", true), Some(true));
+		assert_eq!(matches("mybuild", true), None);
+		assert_eq!(matches("out/main.pdf", false), Some(true));
+		assert_eq!(matches("keep.pdf", false), Some(false));
+		assert_eq!(matches("vendor", true), Some(true));
+		assert_eq!(matches("src/vendor", true), None);
+		assert_eq!(matches("docs/intro.typ", false), Some(true));
+		assert_eq!(matches("docs/a/intro.typ", false), None);
+		assert_eq!(matches("main.typ", false), None);
+	}
+}
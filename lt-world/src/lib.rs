@@ -1,5 +1,7 @@
 mod fonts;
+mod ignore;
 mod package;
+mod project;
 
 use std::{
 	collections::HashMap,
@@ -9,6 +11,8 @@ use std::{
 use chrono::{DateTime, Datelike, FixedOffset, Local, Utc};
 use comemo::Prehashed;
 use fonts::FontManager;
+pub use ignore::{Ignore, IGNORE_FILES};
+pub use project::{is_package_file, Project};
 use typst::{
 	diag::{FileError, FileResult},
 	eval::Tracer,
@@ -91,6 +95,49 @@ impl LtWorld {
 		let mut tracer = Tracer::new();
 		typst::compile(self, &mut tracer).ok()
 	}
+
+	/// Compile another main file of the project, like for a project with several documents.
+	pub fn compile_main(&self, main: &Path) -> Option<Document> {
+		let world = MainWorld { world: self, main: self.file_id(main) };
+		let mut tracer = Tracer::new();
+		typst::compile(&world, &mut tracer).ok()
+	}
+}
+
+/// The world with another main file.
+struct MainWorld<'a> {
+	world: &'a LtWorld,
+	main: FileId,
+}
+
+impl World for MainWorld<'_> {
+	fn library(&self) -> &Prehashed<Library> {
+		self.world.library()
+	}
+
+	fn today(&self, offset: Option<i64>) -> Option<typst::foundations::Datetime> {
+		self.world.today(offset)
+	}
+
+	fn book(&self) -> &Prehashed<typst::text::FontBook> {
+		self.world.book()
+	}
+
+	fn main(&self) -> typst::syntax::Source {
+		self.world.source(self.main).unwrap()
+	}
+
+	fn source(&self, id: FileId) -> FileResult<typst::syntax::Source> {
+		self.world.source(id)
+	}
+
+	fn file(&self, id: FileId) -> FileResult<typst::foundations::Bytes> {
+		self.world.file(id)
+	}
+
+	fn font(&self, index: usize) -> Option<Font> {
+		self.world.font(index)
+	}
 }
 
 impl World for LtWorld {
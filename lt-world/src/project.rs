@@ -0,0 +1,108 @@
+use std::{
+	ops::Not,
+	path::{Path, PathBuf},
+};
+
+use crate::ignore::canonical;
+
+/// Typst project with a `typst.toml` manifest.
+#[derive(Debug, Clone)]
+pub struct Project {
+	pub root: PathBuf,
+	/// Main file declared by `package.entrypoint`.
+	pub entrypoint: Option<PathBuf>,
+	/// Patterns of `package.exclude`, relative to the root.
+	exclude: Vec<String>,
+}
+
+impl Project {
+	/// Search the manifest in the directory of `path` and its parents.
+	pub fn find(path: &Path) -> Option<Self> {
+		let start = if path.is_dir() { path } else { path.parent()? };
+		let root = start
+			.ancestors()
+			.find(|dir| dir.join("typst.toml").is_file())?;
+		let manifest = std::fs::read_to_string(root.join("typst.toml")).ok()?;
+		let manifest = manifest.parse::<toml::Table>().ok()?;
+		let package = manifest
+			.get("package")
+			.and_then(|package| package.as_table());
+		let entrypoint = package
+			.and_then(|package| package.get("entrypoint")?.as_str())
+			.map(|entrypoint| root.join(entrypoint));
+		let exclude = package
+			.and_then(|package| package.get("exclude")?.as_array())
+			.into_iter()
+			.flatten()
+			.filter_map(|pattern| pattern.as_str())
+			.map(|pattern| pattern.trim_matches('/').to_owned())
+			.collect();
+		Some(Self {
+			root: root.to_owned(),
+			entrypoint,
+			exclude,
+		})
+	}
+
+	/// Files excluded by the manifest are not part of the document.
+	pub fn is_excluded(&self, path: &Path) -> bool {
+		let (path, root) = (canonical(path), canonical(&self.root));
+		let Ok(relative) = path.strip_prefix(root) else {
+			return false;
+		};
+		let relative = relative.to_string_lossy().replace('\\', "/");
+		self.exclude.iter().any(|pattern| {
+			glob(pattern, &relative)
+				|| relative
+					.match_indices('/')
+					.any(|(index, _)| glob(pattern, &relative[..index]))
+		})
+	}
+}
+
+/// Files in the package directories of Typst are third-party code.
+pub fn is_package_file(path: &Path) -> bool {
+	[dirs::data_dir(), dirs::cache_dir()]
+		.into_iter()
+		.flatten()
+		.any(|dir| path.starts_with(dir.join("typst/packages")))
+}
+
+/// Match a glob with `*`, `**` and `?` against a path with `/` separators.
+pub(crate) fn glob(pattern: &str, path: &str) -> bool {
+	fn matches(pattern: &[u8], path: &[u8]) -> bool {
+		match pattern {
+			[] => path.is_empty(),
+			[b'*', b'*', b'/', rest @ ..] => (0..=path.len())
+				.filter(|&index| index == 0 || path[index - 1] == b'/')
+				.any(|index| matches(rest, &path[index..])),
+			[b'*', b'*', rest @ ..] => (0..=path.len()).any(|index| matches(rest, &path[index..])),
+			[b'*', rest @ ..] => (0..=path.len())
+				.take_while(|&index| index == 0 || path[index - 1] != b'/')
+				.any(|index| matches(rest, &path[index..])),
+			[b'?', rest @ ..] => {
+				path.first().is_some_and(|&c| c != b'/') && matches(rest, &path[1..])
+			},
+			[c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+		}
+	}
+	pattern.is_empty().not() && matches(pattern.as_bytes(), path.as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_glob() {
+		assert!(glob("examples", "examples"));
+		assert!(glob("*.png", "logo.png"));
+		assert!(glob("*.png", "images/logo.png").not());
+		assert!(glob("**/*.png", "images/logo.png"));
+		assert!(glob("**/*.png", "logo.png"));
+		assert!(glob("**/build", "mybuild").not());
+		assert!(glob("docs/*/main.typ", "docs/a/main.typ"));
+		assert!(glob("docs/*/main.typ", "docs/a/b/main.typ").not());
+		assert!(glob("ch?.typ", "ch1.typ"));
+	}
+}
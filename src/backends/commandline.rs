@@ -0,0 +1,288 @@
+use std::{
+	collections::{HashMap, HashSet},
+	ops::Not,
+	process::Stdio,
+	sync::RwLock,
+};
+
+use serde::Deserialize;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{
+	CheckOptions, Error, Language, LanguageCode, LanguageToolBackend, RawHook, RawResponse,
+	RuleSource, Suggestion,
+};
+
+/// Runs `java -jar languagetool-commandline.jar --json` for every check,
+/// without linking the JVM or running a server.
+#[derive(Debug)]
+pub struct LanguageToolCommandline {
+	java: String,
+	jar: String,
+	settings: RwLock<Settings>,
+}
+
+#[derive(Debug, Default)]
+struct Settings {
+	disabled_checks: HashMap<String, Vec<String>>,
+	allowed_words: HashMap<String, HashSet<String>>,
+	mother_tongue: Option<String>,
+	picky: bool,
+	raw_hook: Option<RawHook>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+	matches: Vec<Match>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Match {
+	message: String,
+	#[serde(default)]
+	sentence: String,
+	offset: usize,
+	length: usize,
+	#[serde(default)]
+	replacements: Vec<Value>,
+	rule: Rule,
+	#[serde(default, rename = "contextForSureMatch")]
+	context_for_sure_match: Option<isize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rule {
+	id: String,
+	description: String,
+	category: Category,
+	#[serde(default)]
+	urls: Vec<Value>,
+	#[serde(default, rename = "isPremium")]
+	is_premium: bool,
+	#[serde(default)]
+	tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Category {
+	name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Value {
+	value: String,
+}
+
+impl LanguageToolCommandline {
+	/// Use the `java` executable found in the `PATH`.
+	pub fn new(jar: &str) -> Self {
+		Self::with_java("java", jar)
+	}
+
+	pub fn with_java(java: &str, jar: &str) -> Self {
+		Self {
+			java: java.into(),
+			jar: jar.into(),
+			settings: RwLock::new(Settings::default()),
+		}
+	}
+
+	/// Command for a check with the settings and the options of the call.
+	fn command(&self, lang: &str, options: &CheckOptions) -> Command {
+		let settings = self.settings.read().unwrap();
+		let mut command = Command::new(&self.java);
+		command.arg("-jar").arg(&self.jar).args([
+			"--json",
+			"--encoding",
+			"utf-8",
+			"--language",
+			lang,
+		]);
+		let checks = settings
+			.disabled_checks
+			.get(lang)
+			.into_iter()
+			.flatten()
+			.chain(options.disabled_checks(lang))
+			.map(String::as_str)
+			.collect::<Vec<_>>();
+		if checks.is_empty().not() {
+			command.arg("--disable").arg(checks.join(","));
+		}
+		if let Some(mother_tongue) = options
+			.mother_tongue
+			.as_ref()
+			.or(settings.mother_tongue.as_ref())
+		{
+			command.arg("--mothertongue").arg(mother_tongue);
+		}
+		if options.picky.unwrap_or(settings.picky) {
+			command.args(["--level", "PICKY"]);
+		}
+		command
+			.arg("-")
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.kill_on_drop(true);
+		command
+	}
+
+	/// Standard output of the jar with the argument and if it succeeded.
+	async fn run(&self, arg: &str) -> crate::Result<(String, bool)> {
+		let output = Command::new(&self.java)
+			.arg("-jar")
+			.arg(&self.jar)
+			.arg(arg)
+			.stdin(Stdio::null())
+			.output()
+			.await
+			.map_err(|err| {
+				Error::BackendUnavailable(format!("Could not run '{}': {}", self.java, err))
+			})?;
+		let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+		Ok((stdout, output.status.success()))
+	}
+}
+
+impl LanguageToolBackend for LanguageToolCommandline {
+	async fn allow_words(&self, lang: LanguageCode, words: &[String]) -> crate::Result<()> {
+		let words = words.iter().cloned().collect();
+		self.settings
+			.write()
+			.unwrap()
+			.allowed_words
+			.insert(lang.into(), words);
+		Ok(())
+	}
+
+	async fn disable_checks(&self, lang: LanguageCode, checks: &[String]) -> crate::Result<()> {
+		self.settings
+			.write()
+			.unwrap()
+			.disabled_checks
+			.insert(lang.into(), checks.to_vec());
+		Ok(())
+	}
+
+	async fn set_mother_tongue(&self, mother_tongue: Option<String>) -> crate::Result<()> {
+		self.settings.write().unwrap().mother_tongue = mother_tongue;
+		Ok(())
+	}
+
+	async fn set_picky(&self, picky: bool) -> crate::Result<()> {
+		self.settings.write().unwrap().picky = picky;
+		Ok(())
+	}
+
+	async fn set_raw_hook(&self, hook: Option<RawHook>) -> crate::Result<()> {
+		self.settings.write().unwrap().raw_hook = hook;
+		Ok(())
+	}
+
+	async fn check_text(&self, lang: LanguageCode, text: &str) -> crate::Result<Vec<Suggestion>> {
+		self.check_text_with(lang, text, &CheckOptions::default())
+			.await
+	}
+
+	async fn check_text_with(
+		&self,
+		lang: LanguageCode,
+		text: &str,
+		options: &CheckOptions,
+	) -> crate::Result<Vec<Suggestion>> {
+		let mut child = self.command(&lang, options).spawn().map_err(|err| {
+			Error::BackendUnavailable(format!("Could not run '{}': {}", self.java, err))
+		})?;
+		let mut stdin = child.stdin.take().expect("stdin is piped");
+		let write = async move { stdin.write_all(text.as_bytes()).await };
+		let (written, output) = tokio::join!(write, child.wait_with_output());
+		let output = output?;
+
+		let stdout = String::from_utf8_lossy(&output.stdout);
+		let json = stdout.find('{').map(|start| &stdout[start..]);
+		let (Some(json), true) = (json, output.status.success()) else {
+			let stderr = String::from_utf8_lossy(&output.stderr);
+			if stderr.contains("language code") || stderr.contains("Unknown language") {
+				return Err(Error::InvalidLanguage(lang.into()));
+			}
+			return Err(Error::BackendUnavailable(format!(
+				"LanguageTool command line failed: {}",
+				stderr.trim()
+			)));
+		};
+		written?;
+		let response: Response = serde_json::from_str(json).map_err(|err| {
+			Error::BackendUnavailable(format!("Invalid output of the command line: {}", err))
+		})?;
+
+		let settings = self.settings.read().unwrap();
+		if let Some(hook) = &settings.raw_hook {
+			hook.call(&lang, &RawResponse::Json(json.into()));
+		}
+		let allowed = super::language_entries(&settings.allowed_words, &lang);
+		let mut suggestions = Vec::with_capacity(response.matches.len());
+		for m in response.matches {
+			let suggestion = Suggestion {
+				start: m.offset,
+				end: m.offset + m.length,
+				message: m.message,
+				sentence: m.sentence,
+				replacements: m.replacements.into_iter().map(|x| x.value).collect(),
+				rule_description: m.rule.description,
+				rule_id: m.rule.id,
+				rule_category: m.rule.category.name,
+				rule_urls: m.rule.urls.into_iter().map(|url| url.value).collect(),
+				incorrect_examples: Vec::new(),
+				correct_examples: Vec::new(),
+				rule_source: RuleSource::new(m.rule.is_premium, &m.rule.tags),
+				context_for_sure_match: m.context_for_sure_match,
+			};
+			let word = &text[suggestion.offsets(text).utf_8];
+			if allowed.iter().any(|allowed| allowed.contains(word))
+				|| options.dictionary(&lang).any(|allowed| allowed == word)
+			{
+				continue;
+			}
+			suggestions.push(suggestion);
+		}
+		Ok(suggestions)
+	}
+
+	/// Parsed from the output of `--version`, like `LanguageTool version 6.4 (2024-03-28 ...)`.
+	async fn version(&self) -> crate::Result<String> {
+		let (stdout, success) = self.run("--version").await?;
+		let version = stdout
+			.lines()
+			.find_map(|line| line.trim().strip_prefix("LanguageTool version "));
+		match (version, success) {
+			(Some(version), true) => Ok(version.trim().into()),
+			_ => Err(Error::BackendUnavailable(format!(
+				"Could not read the version of '{}'",
+				self.jar
+			))),
+		}
+	}
+
+	/// Parsed from the output of `--list`, a line like `de-CH German (Switzerland)` per language.
+	async fn languages(&self) -> crate::Result<Vec<Language>> {
+		let (stdout, success) = self.run("--list").await?;
+		if success.not() {
+			return Err(Error::BackendUnavailable(format!(
+				"Could not list the languages of '{}'",
+				self.jar
+			)));
+		}
+		let languages = stdout.lines().filter_map(|line| {
+			let (long_code, name) = line.trim().split_once(char::is_whitespace)?;
+			let code = long_code.split('-').next()?;
+			let is_code = code.chars().all(|c| c.is_ascii_lowercase());
+			is_code.then(|| Language {
+				name: name.trim().into(),
+				code: code.into(),
+				long_code: long_code.into(),
+			})
+		});
+		Ok(languages.collect())
+	}
+}
@@ -0,0 +1,149 @@
+use std::{
+	ops::Not,
+	process::Stdio,
+	time::{Duration, Instant},
+};
+
+use tokio::process::Command;
+
+use crate::{
+	remote::LanguageToolRemote, CheckOptions, DetectedLanguage, Error, Language, LanguageCode,
+	LanguageToolBackend, Quota, RateLimit, RawHook, Suggestion,
+};
+
+/// LanguageTool server in a docker container, started on creation and removed on drop.
+#[derive(Debug)]
+pub struct LanguageToolDocker {
+	remote: LanguageToolRemote,
+	container: Container,
+}
+
+#[derive(Debug)]
+struct Container {
+	id: String,
+}
+
+impl Drop for Container {
+	fn drop(&mut self) {
+		_ = std::process::Command::new("docker")
+			.args(["rm", "--force", &self.id])
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.status();
+	}
+}
+
+impl LanguageToolDocker {
+	/// Official image of the LanguageTool server.
+	pub const IMAGE: &'static str = "erikvl87/languagetool";
+	/// Port of the server inside the container.
+	const PORT: &'static str = "8010";
+	const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+	/// Start the image on a free port and wait until the server answers.
+	pub async fn start(image: &str) -> crate::Result<Self> {
+		let publish = format!("127.0.0.1::{}", Self::PORT);
+		let id = docker(&["run", "--detach", "--rm", "--publish", &publish, image]).await?;
+		let container = Container { id };
+
+		let ports = docker(&["port", &container.id, Self::PORT]).await?;
+		let port = ports
+			.lines()
+			.find_map(|line| line.rsplit_once(':'))
+			.map(|(_, port)| port.to_owned())
+			.ok_or_else(|| Error::BackendUnavailable(format!("No port in '{}'.", ports)))?;
+		let remote = LanguageToolRemote::new("http://127.0.0.1", &port)?;
+
+		let deadline = Instant::now() + Self::STARTUP_TIMEOUT;
+		while remote.ping().await.not() {
+			if Instant::now() > deadline {
+				return Err(Error::BackendUnavailable(format!(
+					"LanguageTool in '{}' did not start within {:?}.",
+					image,
+					Self::STARTUP_TIMEOUT
+				)));
+			}
+			tokio::time::sleep(Duration::from_millis(500)).await;
+		}
+		Ok(Self { remote, container })
+	}
+
+	pub fn container_id(&self) -> &str {
+		&self.container.id
+	}
+
+	/// Requests are not limited by default, the container is not shared.
+	pub fn set_rate_limit(&mut self, limit: RateLimit) {
+		self.remote.set_rate_limit(limit);
+	}
+
+	pub fn set_quota(&mut self, quota: Option<Quota>) {
+		self.remote.set_quota(quota);
+	}
+}
+
+/// Run the docker command and return its trimmed output.
+async fn docker(args: &[&str]) -> crate::Result<String> {
+	let output = Command::new("docker")
+		.args(args)
+		.stdin(Stdio::null())
+		.output()
+		.await
+		.map_err(|err| Error::BackendUnavailable(format!("Could not run 'docker': {}", err)))?;
+	if output.status.success().not() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(Error::BackendUnavailable(format!(
+			"'docker {}' failed: {}",
+			args[0],
+			stderr.trim()
+		)));
+	}
+	Ok(String::from_utf8_lossy(&output.stdout).trim().into())
+}
+
+impl LanguageToolBackend for LanguageToolDocker {
+	async fn allow_words(&self, lang: LanguageCode, words: &[String]) -> crate::Result<()> {
+		self.remote.allow_words(lang, words).await
+	}
+
+	async fn disable_checks(&self, lang: LanguageCode, checks: &[String]) -> crate::Result<()> {
+		self.remote.disable_checks(lang, checks).await
+	}
+
+	async fn set_mother_tongue(&self, mother_tongue: Option<String>) -> crate::Result<()> {
+		self.remote.set_mother_tongue(mother_tongue).await
+	}
+
+	async fn set_picky(&self, picky: bool) -> crate::Result<()> {
+		self.remote.set_picky(picky).await
+	}
+
+	async fn set_raw_hook(&self, hook: Option<RawHook>) -> crate::Result<()> {
+		self.remote.set_raw_hook(hook).await
+	}
+
+	async fn check_text(&self, lang: LanguageCode, text: &str) -> crate::Result<Vec<Suggestion>> {
+		self.remote.check_text(lang, text).await
+	}
+
+	async fn check_text_with(
+		&self,
+		lang: LanguageCode,
+		text: &str,
+		options: &CheckOptions,
+	) -> crate::Result<Vec<Suggestion>> {
+		self.remote.check_text_with(lang, text, options).await
+	}
+
+	async fn version(&self) -> crate::Result<String> {
+		self.remote.version().await
+	}
+
+	async fn languages(&self) -> crate::Result<Vec<Language>> {
+		self.remote.languages().await
+	}
+
+	async fn detect_language(&self, text: &str) -> crate::Result<Option<DetectedLanguage>> {
+		self.remote.detect_language(text).await
+	}
+}
@@ -0,0 +1,168 @@
+//! Download of a LanguageTool release zip for the JNI backend, verified with its SHA-256
+//! and extracted to a cache directory on first use.
+
+use std::{
+	fs::File,
+	ops::Not,
+	path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use zip::{result::ZipError, ZipArchive};
+
+use crate::Error;
+
+/// Release zip of LanguageTool and its SHA-256 as hex digits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JarRelease {
+	pub url: String,
+	pub sha256: String,
+}
+
+impl JarRelease {
+	/// Version used when none is given.
+	pub const VERSION: &'static str = "6.4";
+
+	/// The zip of the version from languagetool.org, like `6.4`.
+	pub fn new(version: &str, sha256: impl Into<String>) -> Self {
+		Self {
+			url: format!(
+				"https://languagetool.org/download/LanguageTool-{}.zip",
+				version
+			),
+			sha256: sha256.into(),
+		}
+	}
+
+	/// Directory of the extracted release in the cache directory.
+	fn dir(&self, cache_dir: &Path) -> PathBuf {
+		let hash = self.sha256.to_lowercase();
+		cache_dir.join(format!("LanguageTool-{}", &hash[..hash.len().min(16)]))
+	}
+}
+
+/// Class path of the release in the cache directory, the zip is downloaded
+/// and extracted if it is missing.
+pub async fn class_path(cache_dir: &Path, release: &JarRelease) -> crate::Result<String> {
+	let dir = release.dir(cache_dir);
+	if dir.is_dir().not() {
+		tokio::fs::create_dir_all(cache_dir).await?;
+		let zip = dir.with_extension("zip");
+		let hash = download(&release.url, &zip).await?;
+		if hash.eq_ignore_ascii_case(&release.sha256).not() {
+			tokio::fs::remove_file(&zip).await?;
+			return Err(Error::BackendUnavailable(format!(
+				"SHA-256 of '{}' is {}, expected {}.",
+				release.url, hash, release.sha256
+			)));
+		}
+		let target = dir.clone();
+		tokio::task::spawn_blocking(move || install(&zip, &target))
+			.await
+			.map_err(|err| Error::BackendUnavailable(err.to_string()))??;
+	}
+	jars(&dir)
+}
+
+/// Stream the download to the file, returns the SHA-256 of its bytes as hex digits.
+async fn download(url: &str, path: &Path) -> crate::Result<String> {
+	let remote = |err| Error::Remote(Box::new(err));
+	let mut res = reqwest::get(url)
+		.await
+		.and_then(|res| res.error_for_status())
+		.map_err(remote)?;
+	let mut file = tokio::fs::File::create(path).await?;
+	let mut hasher = Sha256::new();
+	while let Some(chunk) = res.chunk().await.map_err(remote)? {
+		hasher.update(&chunk);
+		file.write_all(&chunk).await?;
+	}
+	file.flush().await?;
+	Ok(hex(&hasher.finalize()))
+}
+
+/// Extract next to the directory first, so an interrupted extraction is never used.
+/// Entries with paths leaving the directory are rejected by the zip crate.
+fn install(zip: &Path, dir: &Path) -> crate::Result<()> {
+	let partial = dir.with_extension("partial");
+	if partial.exists() {
+		std::fs::remove_dir_all(&partial)?;
+	}
+	let mut archive = ZipArchive::new(File::open(zip)?).map_err(invalid_zip)?;
+	archive.extract(&partial).map_err(invalid_zip)?;
+	std::fs::rename(&partial, dir)?;
+	std::fs::remove_file(zip)?;
+	Ok(())
+}
+
+/// The directory containing `languagetool.jar`, the zip has a folder like `LanguageTool-6.4`.
+fn root(dir: &Path) -> crate::Result<PathBuf> {
+	if dir.join("languagetool.jar").is_file() {
+		return Ok(dir.into());
+	}
+	for entry in std::fs::read_dir(dir)? {
+		let path = entry?.path();
+		if path.join("languagetool.jar").is_file() {
+			return Ok(path);
+		}
+	}
+	Err(Error::BackendUnavailable(format!(
+		"No 'languagetool.jar' in '{}'.",
+		dir.display()
+	)))
+}
+
+/// `languagetool.jar`, the jars of `libs` and the directory itself for the resources.
+fn jars(dir: &Path) -> crate::Result<String> {
+	let root = root(dir)?;
+	let mut paths = vec![root.join("languagetool.jar"), root.clone()];
+	if let Ok(libs) = std::fs::read_dir(root.join("libs")) {
+		let mut libs = libs
+			.filter_map(|entry| Some(entry.ok()?.path()))
+			.filter(|path| path.extension().is_some_and(|ext| ext == "jar"))
+			.collect::<Vec<_>>();
+		libs.sort();
+		paths.extend(libs);
+	}
+	let class_path =
+		std::env::join_paths(paths).map_err(|err| Error::BackendUnavailable(err.to_string()))?;
+	class_path
+		.into_string()
+		.map_err(|path| Error::BackendUnavailable(format!("Invalid class path {:?}.", path)))
+}
+
+fn invalid_zip(err: ZipError) -> Error {
+	Error::BackendUnavailable(format!("Invalid zip: {}.", err))
+}
+
+fn hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_install() {
+		let dir = std::env::temp_dir().join(format!("typst-languagetool-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let write = |name: &str| {
+			let path = dir.join("release.zip");
+			let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+			zip.start_file(name, zip::write::SimpleFileOptions::default())
+				.unwrap();
+			std::io::Write::write_all(&mut zip, b"text").unwrap();
+			zip.finish().unwrap();
+			path
+		};
+		install(&write("a/b.txt"), &dir.join("release")).unwrap();
+		assert_eq!(
+			std::fs::read_to_string(dir.join("release/a/b.txt")).unwrap(),
+			"text"
+		);
+		assert!(install(&write("../b.txt"), &dir.join("escaped")).is_err());
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+}
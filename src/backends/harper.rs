@@ -0,0 +1,449 @@
+use std::{
+	collections::{HashMap, HashSet},
+	process::Stdio,
+	sync::RwLock,
+	time::Duration,
+};
+
+use serde_json::{json, Value};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	process::{Child, ChildStdin, ChildStdout, Command},
+	sync::Mutex,
+};
+
+use crate::{Error, Language, LanguageCode, LanguageToolBackend, RuleSource, Suggestion};
+
+/// Checks English text with the Harper grammar checker, by running `harper-ls --stdio`.
+/// Harper is much faster than LanguageTool, but only supports English.
+#[derive(Debug)]
+pub struct LanguageToolHarper {
+	command: String,
+	session: Mutex<Option<Session>>,
+	settings: RwLock<Settings>,
+}
+
+#[derive(Debug, Default)]
+struct Settings {
+	allowed_words: HashMap<String, HashSet<String>>,
+	/// Disabled Harper linters by language.
+	disabled_linters: HashMap<String, HashSet<String>>,
+}
+
+/// Harper linters for LanguageTool rules with the same purpose,
+/// other names passed to [`LanguageToolBackend::disable_checks`] are used as linter names.
+const LINTERS: &[(&str, &str)] = &[
+	("MORFOLOGIK_RULE_EN_US", "spell_check"),
+	("MORFOLOGIK_RULE_EN_GB", "spell_check"),
+	("MORFOLOGIK_RULE_EN_CA", "spell_check"),
+	("MORFOLOGIK_RULE_EN_AU", "spell_check"),
+	("MORFOLOGIK_RULE_EN_NZ", "spell_check"),
+	("MORFOLOGIK_RULE_EN_ZA", "spell_check"),
+	("UPPERCASE_SENTENCE_START", "sentence_capitalization"),
+	("ENGLISH_WORD_REPEAT_RULE", "repeated_words"),
+	("TOO_LONG_SENTENCE", "long_sentences"),
+	("EN_A_VS_AN", "an_a"),
+	("WHITESPACE_RULE", "spaces"),
+	("CONSECUTIVE_SPACES", "spaces"),
+	("EN_UNPAIRED_QUOTES", "unclosed_quotes"),
+	("EN_QUOTES", "wrong_quotes"),
+	("ORDINAL_NUMBER_SUFFIX", "correct_number_suffix"),
+	("PROFANITY", "avoid_curses"),
+];
+
+/// Longest wait for an answer of the server.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Harper linter for the LanguageTool rule id, or the id itself for other names.
+pub fn linter(check: &str) -> &str {
+	LINTERS
+		.iter()
+		.find(|(rule_id, _)| *rule_id == check)
+		.map_or(check, |(_, linter)| linter)
+}
+
+impl LanguageToolHarper {
+	/// Use the `harper-ls` executable found in the `PATH`.
+	pub fn new() -> Self {
+		Self::with_command("harper-ls")
+	}
+
+	pub fn with_command(command: &str) -> Self {
+		Self {
+			command: command.into(),
+			session: Mutex::new(None),
+			settings: RwLock::new(Settings::default()),
+		}
+	}
+
+	/// Settings of the language server with the disabled linters for the language.
+	fn configuration(&self, lang: &str) -> Value {
+		let settings = self.settings.read().unwrap();
+		let disabled = super::language_entries(&settings.disabled_linters, lang);
+		let linters = disabled
+			.into_iter()
+			.flatten()
+			.map(|linter| (linter.clone(), Value::Bool(false)))
+			.collect::<serde_json::Map<_, _>>();
+		json!({ "harper-ls": { "linters": linters } })
+	}
+}
+
+impl Default for LanguageToolHarper {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl LanguageToolBackend for LanguageToolHarper {
+	async fn allow_words(&self, lang: LanguageCode, words: &[String]) -> crate::Result<()> {
+		let words = words.iter().cloned().collect();
+		self.settings
+			.write()
+			.unwrap()
+			.allowed_words
+			.insert(lang.into(), words);
+		Ok(())
+	}
+
+	async fn disable_checks(&self, lang: LanguageCode, checks: &[String]) -> crate::Result<()> {
+		let linters = checks.iter().map(|check| linter(check).into()).collect();
+		self.settings
+			.write()
+			.unwrap()
+			.disabled_linters
+			.insert(lang.into(), linters);
+		Ok(())
+	}
+
+	/// Harper has no rules for false friends, the mother tongue is ignored.
+	async fn set_mother_tongue(&self, _mother_tongue: Option<String>) -> crate::Result<()> {
+		Ok(())
+	}
+
+	/// Harper has no picky level, all linters are enabled by default.
+	async fn set_picky(&self, _picky: bool) -> crate::Result<()> {
+		Ok(())
+	}
+
+	async fn check_text(&self, lang: LanguageCode, text: &str) -> crate::Result<Vec<Suggestion>> {
+		if lang.language() != "en" {
+			return Err(Error::InvalidLanguage(lang.into()));
+		}
+		let configuration = self.configuration(&lang);
+		let mut session = self.session.lock().await;
+		if session.is_none() {
+			*session = Some(Session::start(&self.command).await?);
+		}
+		let running = session.as_mut().expect("session is started");
+		let result = tokio::time::timeout(TIMEOUT, running.check(text, configuration)).await;
+		let diagnostics = match result {
+			Ok(Ok(diagnostics)) => diagnostics,
+			// The messages of the server are out of order, it is restarted for the next check.
+			Ok(Err(err)) => {
+				*session = None;
+				return Err(err);
+			},
+			Err(_) => {
+				*session = None;
+				return Err(Error::BackendUnavailable(format!(
+					"'{}' did not answer.",
+					self.command
+				)));
+			},
+		};
+		drop(session);
+
+		let settings = self.settings.read().unwrap();
+		let allowed = super::language_entries(&settings.allowed_words, &lang);
+		let line_starts = line_starts(text);
+		let mut suggestions = Vec::with_capacity(diagnostics.len());
+		for (diagnostic, replacements) in diagnostics {
+			let position = |key: &str| {
+				let position = &diagnostic["range"][key];
+				let line = position["line"].as_u64().unwrap_or_default() as usize;
+				let character = position["character"].as_u64().unwrap_or_default() as usize;
+				line_starts.get(line).map_or(0, |start| start + character)
+			};
+			let line = diagnostic["range"]["start"]["line"]
+				.as_u64()
+				.unwrap_or_default() as usize;
+			let message = diagnostic["message"].as_str().unwrap_or_default();
+			let rule_id = diagnostic["code"].as_str().unwrap_or("HARPER");
+			let suggestion = Suggestion {
+				start: position("start"),
+				end: position("end"),
+				message: message.into(),
+				sentence: text.lines().nth(line).unwrap_or_default().into(),
+				replacements,
+				rule_description: message.into(),
+				rule_id: rule_id.into(),
+				rule_category: "Harper".into(),
+				rule_urls: Vec::new(),
+				incorrect_examples: Vec::new(),
+				correct_examples: Vec::new(),
+				rule_source: RuleSource::Standard,
+				context_for_sure_match: None,
+			};
+			let word = text.get(suggestion.offsets(text).utf_8).unwrap_or_default();
+			if allowed.iter().any(|allowed| allowed.contains(word)) {
+				continue;
+			}
+			suggestions.push(suggestion);
+		}
+		suggestions.sort_by_key(|suggestion| suggestion.start);
+		Ok(suggestions)
+	}
+
+	/// Parsed from the output of `--version`, like `harper-ls 0.9.0`.
+	async fn version(&self) -> crate::Result<String> {
+		let output = Command::new(&self.command)
+			.arg("--version")
+			.stdin(Stdio::null())
+			.output()
+			.await
+			.map_err(|err| {
+				Error::BackendUnavailable(format!("Could not run '{}': {}", self.command, err))
+			})?;
+		let stdout = String::from_utf8_lossy(&output.stdout);
+		let version = stdout.trim();
+		Ok(version.strip_prefix("harper-ls ").unwrap_or(version).into())
+	}
+
+	/// Harper only checks English, all variants use the same dictionary.
+	async fn languages(&self) -> crate::Result<Vec<Language>> {
+		let languages = [
+			("en-US", "English (US)"),
+			("en-GB", "English (GB)"),
+			("en-AU", "English (Australian)"),
+			("en-CA", "English (Canadian)"),
+			("en-NZ", "English (New Zealand)"),
+			("en-ZA", "English (South African)"),
+		];
+		let languages = languages.into_iter().map(|(long_code, name)| Language {
+			name: name.into(),
+			code: "en".into(),
+			long_code: long_code.into(),
+		});
+		Ok(languages.collect())
+	}
+}
+
+/// UTF-16 offset of every line, the unit of the positions of the language server.
+fn line_starts(text: &str) -> Vec<usize> {
+	let mut starts = vec![0];
+	let mut offset = 0;
+	for c in text.chars() {
+		offset += c.len_utf16();
+		if c == '\n' {
+			starts.push(offset);
+		}
+	}
+	starts
+}
+
+/// Running language server, every check opens and closes a document.
+#[derive(Debug)]
+struct Session {
+	_child: Child,
+	stdin: ChildStdin,
+	stdout: BufReader<ChildStdout>,
+	next_id: u64,
+	next_document: u64,
+	/// Settings sent to the server, the current settings are answered to its requests.
+	configuration: Value,
+}
+
+impl Session {
+	async fn start(command: &str) -> crate::Result<Self> {
+		let mut child = Command::new(command)
+			.arg("--stdio")
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::null())
+			.kill_on_drop(true)
+			.spawn()
+			.map_err(|err| {
+				Error::BackendUnavailable(format!("Could not run '{}': {}", command, err))
+			})?;
+		let stdin = child.stdin.take().expect("stdin is piped");
+		let stdout = child.stdout.take().expect("stdout is piped");
+		let mut session = Self {
+			_child: child,
+			stdin,
+			stdout: BufReader::new(stdout),
+			next_id: 0,
+			next_document: 0,
+			configuration: Value::Null,
+		};
+		let capabilities = json!({
+			"textDocument": {
+				"publishDiagnostics": {},
+				"codeAction": {
+					"codeActionLiteralSupport": {
+						"codeActionKind": { "valueSet": ["quickfix"] },
+					},
+				},
+			},
+			"workspace": { "configuration": true },
+		});
+		let params = json!({ "processId": null, "rootUri": null, "capabilities": capabilities });
+		let initialize = session.request("initialize", params);
+		tokio::time::timeout(TIMEOUT, initialize)
+			.await
+			.map_err(|_| Error::BackendUnavailable(format!("'{}' did not start.", command)))??;
+		session.notify("initialized", json!({})).await?;
+		Ok(session)
+	}
+
+	/// Diagnostics of the text with the replacements of their quick fixes.
+	async fn check(
+		&mut self,
+		text: &str,
+		configuration: Value,
+	) -> crate::Result<Vec<(Value, Vec<String>)>> {
+		if configuration != self.configuration {
+			self.configuration = configuration.clone();
+			let params = json!({ "settings": configuration });
+			self.notify("workspace/didChangeConfiguration", params)
+				.await?;
+		}
+		self.next_document += 1;
+		let uri = format!("untitled:check-{}.txt", self.next_document);
+		let document = json!({ "uri": uri, "languageId": "plaintext", "version": 1, "text": text });
+		self.notify("textDocument/didOpen", json!({ "textDocument": document }))
+			.await?;
+		let diagnostics = loop {
+			let message = self.receive().await?;
+			if message["method"] == "textDocument/publishDiagnostics"
+				&& message["params"]["uri"] == uri.as_str()
+			{
+				break message["params"]["diagnostics"]
+					.as_array()
+					.cloned()
+					.unwrap_or_default();
+			}
+		};
+
+		let mut checked = Vec::with_capacity(diagnostics.len());
+		for diagnostic in diagnostics {
+			let params = json!({
+				"textDocument": { "uri": uri },
+				"range": diagnostic["range"],
+				"context": { "diagnostics": [diagnostic] },
+			});
+			let actions = self.request("textDocument/codeAction", params).await?;
+			let replacements = actions
+				.as_array()
+				.into_iter()
+				.flatten()
+				.filter_map(|action| {
+					let edits = action["edit"]["changes"][uri.as_str()].as_array()?;
+					match edits.as_slice() {
+						[edit] => Some(edit["newText"].as_str()?.to_owned()),
+						_ => None,
+					}
+				})
+				.collect();
+			checked.push((diagnostic, replacements));
+		}
+
+		let params = json!({ "textDocument": { "uri": uri } });
+		self.notify("textDocument/didClose", params).await?;
+		Ok(checked)
+	}
+
+	/// Send the request and wait for its response, other messages are skipped.
+	async fn request(&mut self, method: &str, params: Value) -> crate::Result<Value> {
+		self.next_id += 1;
+		let id = self.next_id;
+		let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+		self.send(&request).await?;
+		loop {
+			let message = self.receive().await?;
+			if message["id"] != id || message.get("method").is_some() {
+				continue;
+			}
+			if let Some(error) = message.get("error") {
+				return Err(Error::BackendUnavailable(format!(
+					"Harper failed: {}",
+					error["message"].as_str().unwrap_or_default()
+				)));
+			}
+			return Ok(message["result"].clone());
+		}
+	}
+
+	async fn notify(&mut self, method: &str, params: Value) -> crate::Result<()> {
+		let notification = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+		self.send(&notification).await
+	}
+
+	async fn send(&mut self, message: &Value) -> crate::Result<()> {
+		let content = message.to_string();
+		let header = format!("Content-Length: {}\r\n\r\n", content.len());
+		self.stdin.write_all(header.as_bytes()).await?;
+		self.stdin.write_all(content.as_bytes()).await?;
+		self.stdin.flush().await?;
+		Ok(())
+	}
+
+	/// Next message of the server, its requests are answered.
+	async fn receive(&mut self) -> crate::Result<Value> {
+		loop {
+			let message = self.read().await?;
+			let (Some(id), Some(method)) = (message.get("id"), message["method"].as_str()) else {
+				return Ok(message);
+			};
+			// Requests of the server, like for the settings or to register capabilities.
+			let result = match method {
+				"workspace/configuration" => {
+					let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+					let settings = self.configuration["harper-ls"].clone();
+					Value::Array(vec![settings; items])
+				},
+				_ => Value::Null,
+			};
+			let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+			self.send(&response).await?;
+		}
+	}
+
+	async fn read(&mut self) -> crate::Result<Value> {
+		let mut length = None;
+		loop {
+			let mut line = String::new();
+			if self.stdout.read_line(&mut line).await? == 0 {
+				return Err(Error::BackendUnavailable("Harper stopped.".into()));
+			}
+			let line = line.trim_end();
+			if line.is_empty() {
+				break;
+			}
+			if let Some(value) = line.strip_prefix("Content-Length:") {
+				length = value.trim().parse::<usize>().ok();
+			}
+		}
+		let Some(length) = length else {
+			return Err(Error::BackendUnavailable(
+				"Harper sent a message without a length.".into(),
+			));
+		};
+		let mut content = vec![0; length];
+		self.stdout.read_exact(&mut content).await?;
+		serde_json::from_slice(&content)
+			.map_err(|err| Error::BackendUnavailable(format!("Invalid message of Harper: {}", err)))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_line_starts() {
+		assert_eq!(line_starts("Ö😀\nab\n"), vec![0, 4, 7]);
+		assert_eq!(linter("EN_A_VS_AN"), "an_a");
+		assert_eq!(linter("long_sentences"), "long_sentences");
+	}
+}
@@ -1,22 +1,43 @@
 use std::{
-	collections::{hash_map::Entry, HashMap},
+	collections::HashMap,
 	ops::Not,
+	path::{Path, PathBuf},
+	sync::mpsc,
 };
 
 use jni::{
-	objects::{GlobalRef, JObject, JValue},
+	objects::{GlobalRef, JObject, JString, JValue},
 	InitArgsBuilder, JNIEnv, JavaVM,
 };
 
-use crate::{LanguageToolBackend, Suggestion};
+use crate::{
+	CheckOptions, DetectedLanguage, Error, Language, LanguageCode, LanguageToolBackend, RawHook,
+	RawResponse, Rule, RuleSource, RuleValue, SpellingFiles, Suggestion,
+};
 
+/// Handle to a worker thread owning the JVM, so the handle can be shared between threads.
 #[derive(Debug)]
 pub struct LanguageToolJNI {
+	sender: mpsc::Sender<Job>,
+}
+
+type Job = Box<dyn FnOnce(&mut Worker) + Send>;
+
+struct Worker {
 	jvm: JavaVM,
 	languages: HashMap<String, GlobalRef>,
+	allowed_words: HashMap<String, Vec<String>>,
+	disabled_checks: HashMap<String, Vec<String>>,
+	rule_options: HashMap<String, HashMap<String, Vec<RuleValue>>>,
+	rule_files: HashMap<String, Vec<PathBuf>>,
+	spelling_files: HashMap<String, SpellingFiles>,
+	mother_tongue: Option<String>,
+	interface_language: Option<String>,
+	picky: bool,
+	raw_hook: Option<RawHook>,
 }
 
-fn new_jvm(class_path: &str) -> anyhow::Result<JavaVM> {
+fn new_jvm(class_path: &str) -> crate::Result<JavaVM> {
 	let jvm_args = InitArgsBuilder::new()
 		.version(jni::JNIVersion::V8)
 		.option(format!("-Djava.class.path={}", class_path))
@@ -26,54 +47,454 @@ fn new_jvm(class_path: &str) -> anyhow::Result<JavaVM> {
 }
 
 impl LanguageToolJNI {
-	pub fn new(class_path: &str) -> anyhow::Result<Self> {
-		let jvm = new_jvm(class_path)?;
-		Ok(Self { languages: HashMap::new(), jvm })
+	pub fn new(class_path: &str) -> crate::Result<Self> {
+		Self::spawn(class_path.into())
 	}
 
-	pub fn new_bundled() -> anyhow::Result<Self> {
+	pub fn new_bundled() -> crate::Result<Self> {
 		#[cfg(feature = "bundle-jar")]
 		let path = include!(concat!(env!("OUT_DIR"), "./jar_path.rs"));
 
 		#[cfg(not(feature = "bundle-jar"))]
-		let path = Err(anyhow::anyhow!("Feature 'bundle-jar' not enabled."))?;
+		let path: &str = Err(Error::BackendUnavailable(
+			"Feature 'bundle-jar' not enabled.".into(),
+		))?;
 
-		let jvm = new_jvm(path)?;
-		Ok(Self { languages: HashMap::new(), jvm })
+		Self::spawn(path.into())
 	}
 
-	fn create_lang_tool(lang: String, env: &mut JNIEnv) -> anyhow::Result<GlobalRef> {
-		let lang_code = env.new_string(lang)?;
-		let lang = env.call_static_method(
-			"org/languagetool/Languages",
-			"getLanguageForShortCode",
-			"(Ljava/lang/String;)Lorg/languagetool/Language;",
-			&[JValue::Object(&lang_code)],
-		)?;
+	fn spawn(class_path: String) -> crate::Result<Self> {
+		let (sender, receiver) = mpsc::channel::<Job>();
+		let (started, start) = mpsc::sync_channel(1);
+		std::thread::spawn(move || {
+			let mut worker = match new_jvm(&class_path) {
+				Ok(jvm) => Worker::new(jvm),
+				Err(err) => {
+					_ = started.send(Err(err));
+					return;
+				},
+			};
+			_ = started.send(Ok(()));
+			for job in receiver {
+				job(&mut worker);
+			}
+		});
+		start.recv().map_err(|_| worker_stopped())??;
+		Ok(Self { sender })
+	}
 
-		let lang_tool = env.new_object(
-			"org/languagetool/JLanguageTool",
-			"(Lorg/languagetool/Language;)V",
-			&[lang.borrow()],
-		)?;
+	/// Run the job on the worker thread.
+	async fn run<T: Send + 'static>(
+		&self,
+		job: impl FnOnce(&mut Worker) -> crate::Result<T> + Send + 'static,
+	) -> crate::Result<T> {
+		let (tx, rx) = tokio::sync::oneshot::channel();
+		self.sender
+			.send(Box::new(move |worker| {
+				_ = tx.send(job(worker));
+			}))
+			.map_err(|_| worker_stopped())?;
+		rx.await.map_err(|_| worker_stopped())?
+	}
+}
+
+fn worker_stopped() -> Error {
+	Error::BackendUnavailable("LanguageTool worker stopped.".into())
+}
+
+impl Worker {
+	fn new(jvm: JavaVM) -> Self {
+		Self {
+			jvm,
+			languages: HashMap::new(),
+			allowed_words: HashMap::new(),
+			disabled_checks: HashMap::new(),
+			rule_options: HashMap::new(),
+			rule_files: HashMap::new(),
+			spelling_files: HashMap::new(),
+			mother_tongue: None,
+			interface_language: None,
+			picky: false,
+			raw_hook: None,
+		}
+	}
+
+	/// Create the LanguageTool instance with the settings for the language applied.
+	fn create_lang_tool(&self, lang: &str, env: &mut JNIEnv) -> crate::Result<GlobalRef> {
+		let language = Self::language(lang, env)?;
+		let rule_options = self.rule_options.get(lang);
+		let lang_tool = match (&self.mother_tongue, rule_options) {
+			(mother_tongue, Some(options)) if options.is_empty().not() => {
+				let mother_tongue = match mother_tongue {
+					Some(mother_tongue) => Self::language(mother_tongue, env)?,
+					None => JObject::null(),
+				};
+				let user_config = Self::user_config(options, env)?;
+				env.new_object(
+					"org/languagetool/JLanguageTool",
+					"(Lorg/languagetool/Language;Lorg/languagetool/Language;Lorg/languagetool/ResultCache;Lorg/languagetool/UserConfig;)V",
+					&[
+						JValue::Object(&language),
+						JValue::Object(&mother_tongue),
+						JValue::Object(&JObject::null()),
+						JValue::Object(&user_config),
+					],
+				)?
+			},
+			(Some(mother_tongue), _) => {
+				let mother_tongue = Self::language(mother_tongue, env)?;
+				env.new_object(
+					"org/languagetool/JLanguageTool",
+					"(Lorg/languagetool/Language;Lorg/languagetool/Language;)V",
+					&[JValue::Object(&language), JValue::Object(&mother_tongue)],
+				)?
+			},
+			(None, _) => env.new_object(
+				"org/languagetool/JLanguageTool",
+				"(Lorg/languagetool/Language;)V",
+				&[JValue::Object(&language)],
+			)?,
+		};
+		if let Some(interface) = &self.interface_language {
+			self.localize(&lang_tool, &language, lang, interface, env)?;
+		}
 		let lang_tool = env.new_global_ref(lang_tool)?;
 
+		let mut words = super::language_entries(&self.allowed_words, lang)
+			.into_iter()
+			.flatten()
+			.cloned()
+			.collect::<Vec<_>>();
+		let files = super::language_entries(&self.spelling_files, lang);
+		for files in &files {
+			words.extend(SpellingFiles::words(&files.spelling)?);
+		}
+		if words.is_empty().not() {
+			Self::spelling_rules(&lang_tool, "acceptPhrases", &words, env)?;
+		}
+		let mut prohibited = Vec::new();
+		for files in &files {
+			prohibited.extend(SpellingFiles::words(&files.prohibit)?);
+		}
+		if prohibited.is_empty().not() {
+			Self::spelling_rules(&lang_tool, "addProhibitedWords", &prohibited, env)?;
+		}
+		for file in super::language_entries(&self.rule_files, lang)
+			.into_iter()
+			.flatten()
+		{
+			Self::add_rules(&lang_tool, file, env)?;
+		}
+		if let Some(checks) = self.disabled_checks.get(lang) {
+			Self::disable_rules(&lang_tool, checks, env)?;
+		}
 		Ok(lang_tool)
 	}
 
+	/// Replace the built-in rules with the same rules created with the messages of the interface
+	/// language, the pattern rules have no messages of other languages and stay.
+	/// `builtinRules` is private, but JNI access is not checked.
+	fn localize(
+		&self,
+		lang_tool: &JObject,
+		language: &JObject,
+		lang: &str,
+		interface: &str,
+		env: &mut JNIEnv,
+	) -> crate::Result<()> {
+		let unsupported = |env: &mut JNIEnv, err| match err {
+			jni::errors::Error::JavaException | jni::errors::Error::FieldNotFound { .. } => {
+				_ = env.exception_clear();
+				Error::BackendUnavailable(format!(
+					"LanguageTool can not show the messages in '{}'.",
+					interface
+				))
+			},
+			err => err.into(),
+		};
+		let interface_language = Self::language(interface, env)?;
+		let messages = env
+			.call_static_method(
+				"org/languagetool/JLanguageTool",
+				"getMessageBundle",
+				"(Lorg/languagetool/Language;)Ljava/util/ResourceBundle;",
+				&[JValue::Object(&interface_language)],
+			)
+			.map_err(|err| unsupported(env, err))?
+			.l()?;
+		let mother_tongue = match &self.mother_tongue {
+			Some(mother_tongue) => Self::language(mother_tongue, env)?,
+			None => JObject::null(),
+		};
+		let user_config = match self.rule_options.get(lang) {
+			Some(options) if options.is_empty().not() => Self::user_config(options, env)?,
+			_ => JObject::null(),
+		};
+		let alternatives = env.new_object("java/util/ArrayList", "()V", &[])?;
+		let rules = env
+			.call_method(
+				language,
+				"getRelevantRules",
+				"(Ljava/util/ResourceBundle;Lorg/languagetool/UserConfig;Lorg/languagetool/Language;Ljava/util/List;)Ljava/util/List;",
+				&[
+					JValue::Object(&messages),
+					JValue::Object(&user_config),
+					JValue::Object(&mother_tongue),
+					JValue::Object(&alternatives),
+				],
+			)
+			.map_err(|err| unsupported(env, err))?
+			.l()?;
+		let mut localized = HashMap::new();
+		let list = env.get_list(&rules)?;
+		let size = list.size(env)?;
+		env.ensure_local_capacity(size + 16)?;
+		for i in 0..size {
+			let Some(rule) = list.get(env, i)? else {
+				continue;
+			};
+			localized.insert(Self::rule_id(&rule, env)?, rule);
+		}
+		let builtin = env
+			.get_field(lang_tool, "builtinRules", "Ljava/util/List;")
+			.map_err(|err| unsupported(env, err))?
+			.l()?;
+		let list = env.get_list(&builtin)?;
+		for i in 0..list.size(env)? {
+			let Some(rule) = list.get(env, i)? else {
+				continue;
+			};
+			if let Some(replacement) = localized.remove(&Self::rule_id(&rule, env)?) {
+				env.call_method(
+					&builtin,
+					"set",
+					"(ILjava/lang/Object;)Ljava/lang/Object;",
+					&[JValue::Int(i), JValue::Object(&replacement)],
+				)?;
+			}
+			env.delete_local_ref(rule)?;
+		}
+		Ok(())
+	}
+
+	fn rule_id(rule: &JObject, env: &mut JNIEnv) -> crate::Result<String> {
+		let id = env
+			.call_method(rule, "getId", "()Ljava/lang/String;", &[])?
+			.l()?;
+		let id = JString::from(id);
+		let text = env.get_string(&id)?.into();
+		env.delete_local_ref(id)?;
+		Ok(text)
+	}
+
+	/// Add the pattern rules of the XML file, the checks can disable them like built-in rules.
+	fn add_rules(lang_tool: &JObject, file: &Path, env: &mut JNIEnv) -> crate::Result<()> {
+		let path = env.new_string(file.to_string_lossy())?;
+		let rules = env
+			.call_method(
+				lang_tool,
+				"loadPatternRules",
+				"(Ljava/lang/String;)Ljava/util/List;",
+				&[JValue::Object(&path)],
+			)
+			.map_err(|err| match err {
+				jni::errors::Error::JavaException => {
+					_ = env.exception_clear();
+					Error::Config(
+						format!("Could not load the rules of '{}'", file.display()).into(),
+					)
+				},
+				err => err.into(),
+			})?
+			.l()?;
+		let list = env.get_list(&rules)?;
+		for i in 0..list.size(env)? {
+			let Some(rule) = list.get(env, i)? else {
+				continue;
+			};
+			env.call_method(
+				lang_tool,
+				"addRule",
+				"(Lorg/languagetool/rules/Rule;)V",
+				&[JValue::Object(&rule)],
+			)?;
+		}
+		Ok(())
+	}
+
+	fn language<'a>(lang: &str, env: &mut JNIEnv<'a>) -> crate::Result<JObject<'a>> {
+		let lang_code = env.new_string(lang)?;
+		let language = env
+			.call_static_method(
+				"org/languagetool/Languages",
+				"getLanguageForShortCode",
+				"(Ljava/lang/String;)Lorg/languagetool/Language;",
+				&[JValue::Object(&lang_code)],
+			)
+			.map_err(|err| match err {
+				jni::errors::Error::JavaException => {
+					_ = env.exception_clear();
+					Error::InvalidLanguage(lang.into())
+				},
+				err => err.into(),
+			})?
+			.l()?;
+		Ok(language)
+	}
+
+	/// `UserConfig` with the values of the configurable rules.
+	fn user_config<'a>(
+		options: &HashMap<String, Vec<RuleValue>>,
+		env: &mut JNIEnv<'a>,
+	) -> crate::Result<JObject<'a>> {
+		let map = env.new_object("java/util/HashMap", "()V", &[])?;
+		let map = env.get_map(&map)?;
+		for (rule_id, values) in options {
+			let array =
+				env.new_object_array(values.len() as i32, "java/lang/Object", JObject::null())?;
+			for (index, value) in values.iter().enumerate() {
+				let value = match value {
+					RuleValue::Bool(value) => env.call_static_method(
+						"java/lang/Boolean",
+						"valueOf",
+						"(Z)Ljava/lang/Boolean;",
+						&[JValue::Bool(*value as u8)],
+					)?,
+					RuleValue::Integer(value) => env.call_static_method(
+						"java/lang/Integer",
+						"valueOf",
+						"(I)Ljava/lang/Integer;",
+						&[JValue::Int(*value as i32)],
+					)?,
+					RuleValue::Text(value) => JObject::from(env.new_string(value)?).into(),
+				}
+				.l()?;
+				env.set_object_array_element(&array, index as i32, value)?;
+			}
+			let rule_id = env.new_string(rule_id)?;
+			map.put(env, &rule_id, &array)?;
+		}
+		let user_config = env.new_object(
+			"org/languagetool/UserConfig",
+			"(Ljava/util/Map;)V",
+			&[JValue::Object(map.as_ref())],
+		)?;
+		Ok(user_config)
+	}
+
+	/// Call `method` of the spelling rules with the words, like `acceptPhrases`.
+	/// `addProhibitedWords` is protected, but JNI calls are not access checked.
+	fn spelling_rules(
+		lang_tool: &JObject,
+		method: &str,
+		words: &[String],
+		env: &mut JNIEnv,
+	) -> crate::Result<()> {
+		let rules = env
+			.call_method(lang_tool, "getAllActiveRules", "()Ljava/util/List;", &[])?
+			.l()?;
+		let list = env.get_list(&rules)?;
+		let args = env.new_object("java/util/ArrayList", "()V", &[])?;
+		let args = env.get_list(&args)?;
+		for word in words {
+			let word = env.new_string(word)?;
+			args.add(env, &word)?;
+		}
+
+		for i in 0..list.size(env)? {
+			let Some(rule) = list.get(env, i)? else {
+				continue;
+			};
+			if env
+				.is_instance_of(&rule, "org/languagetool/rules/spelling/SpellingCheckRule")?
+				.not()
+			{
+				continue;
+			}
+
+			env.call_method(
+				&rule,
+				method,
+				"(Ljava/util/List;)V",
+				&[JValue::Object(args.as_ref())],
+			)?;
+		}
+		Ok(())
+	}
+
+	fn disable_rules(
+		lang_tool: &JObject,
+		checks: &[String],
+		env: &mut JNIEnv,
+	) -> crate::Result<()> {
+		let args = env.new_object("java/util/ArrayList", "()V", &[])?;
+		let args = env.get_list(&args)?;
+		for check in checks {
+			let check = env.new_string(check)?;
+			args.add(env, &check)?;
+		}
+		env.call_method(
+			lang_tool,
+			"disableRules",
+			"(Ljava/util/List;)V",
+			&[JValue::Object(args.as_ref())],
+		)?;
+		Ok(())
+	}
+
+	/// With `raw` the `toString` of the matches is collected too.
 	fn lt_request<'a>(
 		lang_tool: &JObject<'a>,
 		text: &JObject<'a>,
+		picky: bool,
+		mut raw: Option<&mut Vec<String>>,
 		env: &mut JNIEnv<'a>,
-	) -> anyhow::Result<Vec<Suggestion>> {
-		let matches = env
-			.call_method(
+	) -> crate::Result<Vec<Suggestion>> {
+		let matches = if picky {
+			let paragraph_handling = env
+				.get_static_field(
+					"org/languagetool/JLanguageTool$ParagraphHandling",
+					"NORMAL",
+					"Lorg/languagetool/JLanguageTool$ParagraphHandling;",
+				)?
+				.l()?;
+			let mode = env
+				.get_static_field(
+					"org/languagetool/JLanguageTool$Mode",
+					"ALL",
+					"Lorg/languagetool/JLanguageTool$Mode;",
+				)?
+				.l()?;
+			let level = env
+				.get_static_field(
+					"org/languagetool/JLanguageTool$Level",
+					"PICKY",
+					"Lorg/languagetool/JLanguageTool$Level;",
+				)?
+				.l()?;
+			env.call_method(
+				lang_tool,
+				"check",
+				"(Ljava/lang/String;ZLorg/languagetool/JLanguageTool$ParagraphHandling;Lorg/languagetool/rules/RuleMatchListener;Lorg/languagetool/JLanguageTool$Mode;Lorg/languagetool/JLanguageTool$Level;)Ljava/util/List;",
+				&[
+					JValue::Object(text),
+					JValue::Bool(1),
+					JValue::Object(&paragraph_handling),
+					JValue::Object(&JObject::null()),
+					JValue::Object(&mode),
+					JValue::Object(&level),
+				],
+			)?
+			.l()?
+		} else {
+			env.call_method(
 				lang_tool,
 				"check",
 				"(Ljava/lang/String;)Ljava/util/List;",
 				&[JValue::Object(text)],
 			)?
-			.l()?;
+			.l()?
+		};
 
 		let list = env.get_list(&matches)?;
 		let size = list.size(env)?;
@@ -84,6 +505,12 @@ impl LanguageToolJNI {
 			let Some(m) = list.get(env, i)? else {
 				continue;
 			};
+			if let Some(raw) = raw.as_mut() {
+				let string = env
+					.call_method(&m, "toString", "()Ljava/lang/String;", &[])?
+					.l()?;
+				raw.push(env.get_string(&string.into())?.into());
+			}
 			let start = env.call_method(&m, "getFromPos", "()I", &[])?.i()?;
 			let end = env.call_method(&m, "getToPos", "()I", &[])?.i()?;
 
@@ -91,6 +518,18 @@ impl LanguageToolJNI {
 				.call_method(&m, "getMessage", "()Ljava/lang/String;", &[])?
 				.l()?;
 			let message = env.get_string(&message.into())?.into();
+			let sentence = env
+				.call_method(
+					&m,
+					"getSentence",
+					"()Lorg/languagetool/AnalyzedSentence;",
+					&[],
+				)?
+				.l()?;
+			let sentence = env
+				.call_method(&sentence, "getText", "()Ljava/lang/String;", &[])?
+				.l()?;
+			let sentence = env.get_string(&sentence.into())?.into();
 
 			let replacements = env
 				.call_method(&m, "getSuggestedReplacements", "()Ljava/util/List;", &[])?
@@ -117,90 +556,460 @@ impl LanguageToolJNI {
 				.call_method(&rule, "getDescription", "()Ljava/lang/String;", &[])?
 				.l()?;
 			let rule_description = env.get_string(&rule_description.into())?.into();
+			let category = env
+				.call_method(
+					&rule,
+					"getCategory",
+					"()Lorg/languagetool/rules/Category;",
+					&[],
+				)?
+				.l()?;
+			let rule_category = env
+				.call_method(&category, "getName", "()Ljava/lang/String;", &[])?
+				.l()?;
+			let rule_category = env.get_string(&rule_category.into())?.into();
+			let url = env
+				.call_method(&rule, "getUrl", "()Ljava/net/URL;", &[])?
+				.l()?;
+			let mut rule_urls = Vec::new();
+			if url.is_null().not() {
+				let url = env
+					.call_method(&url, "toString", "()Ljava/lang/String;", &[])?
+					.l()?;
+				rule_urls.push(env.get_string(&url.into())?.into());
+			}
+			let incorrect_examples = Self::examples(&rule, "getIncorrectExamples", env)?;
+			let correct_examples = Self::examples(&rule, "getCorrectExamples", env)?;
+			let premium = env.call_method(&rule, "isPremium", "()Z", &[])?.z()?;
+			let tags = env
+				.call_method(&rule, "getTags", "()Ljava/util/List;", &[])?
+				.l()?;
+			let list = env.get_list(&tags)?;
+			let size = list.size(env)?;
+			let mut tags = Vec::with_capacity(size as usize);
+			for i in 0..size {
+				let Some(tag) = list.get(env, i)? else {
+					continue;
+				};
+				let tag = env
+					.call_method(&tag, "toString", "()Ljava/lang/String;", &[])?
+					.l()?;
+				tags.push(String::from(env.get_string(&tag.into())?));
+			}
+			let rule_source = RuleSource::new(premium, &tags);
+			let context_for_sure_match = env
+				.call_method(&rule, "estimateContextForSureMatch", "()I", &[])?
+				.i()?;
 
 			let suggestion = Suggestion {
 				start: start as usize,
 				end: end as usize,
 				replacements,
 				message,
+				sentence,
 				rule_id,
 				rule_description,
+				rule_category,
+				rule_urls,
+				incorrect_examples,
+				correct_examples,
+				rule_source,
+				context_for_sure_match: Some(context_for_sure_match as isize),
 			};
 			suggestions.push(suggestion);
 		}
 		Ok(suggestions)
 	}
-}
 
-impl LanguageToolBackend for LanguageToolJNI {
-	async fn check_text(&mut self, lang: String, text: &str) -> anyhow::Result<Vec<Suggestion>> {
-		let mut guard = self.jvm.attach_current_thread()?;
-		let text = guard.new_string(text)?;
-		let lang_tool = match self.languages.entry(lang.clone()) {
-			Entry::Occupied(entry) => entry.into_mut(),
-			Entry::Vacant(entry) => entry.insert(Self::create_lang_tool(lang, &mut guard)?),
-		};
-		let suggestions = Self::lt_request(lang_tool, &text, &mut guard)?;
-		Ok(suggestions)
+	/// Sentences of the example list returned by `method` of the rule.
+	fn examples<'a>(
+		rule: &JObject<'a>,
+		method: &str,
+		env: &mut JNIEnv<'a>,
+	) -> crate::Result<Vec<String>> {
+		let examples = env
+			.call_method(rule, method, "()Ljava/util/List;", &[])?
+			.l()?;
+		let list = env.get_list(&examples)?;
+		let size = list.size(env)?;
+		let mut sentences = Vec::with_capacity(size as usize);
+		for i in 0..size {
+			let Some(example) = list.get(env, i)? else {
+				continue;
+			};
+			let sentence = env
+				.call_method(&example, "getExample", "()Ljava/lang/String;", &[])?
+				.l()?;
+			sentences.push(env.get_string(&sentence.into())?.into());
+		}
+		Ok(sentences)
 	}
 
-	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
-		let mut guard = self.jvm.attach_current_thread()?;
-		let lang_tool = match self.languages.entry(lang.clone()) {
-			Entry::Occupied(entry) => entry.into_mut(),
-			Entry::Vacant(entry) => entry.insert(Self::create_lang_tool(lang, &mut guard)?),
-		};
-
-		let rules = guard
-			.call_method(lang_tool, "getAllActiveRules", "()Ljava/util/List;", &[])?
+	/// Detected with the default identifier of LanguageTool, without the fastText model.
+	fn detect_language(&self, text: &str) -> crate::Result<Option<DetectedLanguage>> {
+		let mut env = self.jvm.attach_current_thread()?;
+		let service = env
+			.get_static_field(
+				"org/languagetool/language/identifier/LanguageIdentifierService",
+				"INSTANCE",
+				"Lorg/languagetool/language/identifier/LanguageIdentifierService;",
+			)?
 			.l()?;
-		let list = guard.get_list(&rules)?;
-		let args = guard.new_object("java/util/ArrayList", "()V", &[])?;
-		let args = guard.get_list(&args)?;
-		for word in words {
-			let word = guard.new_string(word)?;
-			args.add(&mut guard, &word)?;
+		let null = JObject::null();
+		let identifier = env
+			.call_method(
+				&service,
+				"getDefaultLanguageIdentifier",
+				"(ILjava/io/File;Ljava/io/File;Ljava/io/File;)Lorg/languagetool/language/identifier/LanguageIdentifier;",
+				&[
+					JValue::Int(0),
+					JValue::Object(&null),
+					JValue::Object(&null),
+					JValue::Object(&null),
+				],
+			)?
+			.l()?;
+		let text = env.new_string(text)?;
+		let noop = env.new_object("java/util/ArrayList", "()V", &[])?;
+		let preferred = env.new_object("java/util/ArrayList", "()V", &[])?;
+		let detected = env
+			.call_method(
+				&identifier,
+				"detectLanguage",
+				"(Ljava/lang/String;Ljava/util/List;Ljava/util/List;)Lorg/languagetool/DetectedLanguage;",
+				&[
+					JValue::Object(&text),
+					JValue::Object(&noop),
+					JValue::Object(&preferred),
+				],
+			)?
+			.l()?;
+		if detected.is_null() {
+			return Ok(None);
 		}
+		let language = env
+			.call_method(
+				&detected,
+				"getDetectedLanguage",
+				"()Lorg/languagetool/Language;",
+				&[],
+			)?
+			.l()?;
+		let mut text = |method: &str| -> crate::Result<String> {
+			let value = env
+				.call_method(&language, method, "()Ljava/lang/String;", &[])?
+				.l()?;
+			Ok(env.get_string(&value.into())?.into())
+		};
+		let name = text("getName")?;
+		let Ok(code) = LanguageCode::new(&text("getShortCodeWithCountryAndVariant")?) else {
+			return Ok(None);
+		};
+		Ok(Some(DetectedLanguage { name, code }))
+	}
 
-		for i in 0..list.size(&mut guard)? {
-			let Some(rule) = list.get(&mut guard, i)? else {
+	/// All rules of a new instance without settings, so disabled checks are still listed.
+	fn rules(&self, lang: &str) -> crate::Result<Vec<Rule>> {
+		let mut env = self.jvm.attach_current_thread()?;
+		let language = Self::language(lang, &mut env)?;
+		let lang_tool = env.new_object(
+			"org/languagetool/JLanguageTool",
+			"(Lorg/languagetool/Language;)V",
+			&[JValue::Object(&language)],
+		)?;
+		let rules = env
+			.call_method(&lang_tool, "getAllRules", "()Ljava/util/List;", &[])?
+			.l()?;
+		let list = env.get_list(&rules)?;
+		let size = list.size(&mut env)?;
+		let mut res = Vec::with_capacity(size as usize);
+		for i in 0..size {
+			let Some(rule) = list.get(&mut env, i)? else {
 				continue;
 			};
-			if guard
-				.is_instance_of(&rule, "org/languagetool/rules/spelling/SpellingCheckRule")?
-				.not()
-			{
-				continue;
-			}
-
-			guard.call_method(
-				&rule,
-				"acceptPhrases",
-				"(Ljava/util/List;)V",
-				&[JValue::Object(args.as_ref())],
-			)?;
+			let mut text = |object: &JObject, method: &str| -> crate::Result<String> {
+				let value = env
+					.call_method(object, method, "()Ljava/lang/String;", &[])?
+					.l()?;
+				Ok(env.get_string(&value.into())?.into())
+			};
+			let id = text(&rule, "getId")?;
+			let description = text(&rule, "getDescription")?;
+			let category = env
+				.call_method(
+					&rule,
+					"getCategory",
+					"()Lorg/languagetool/rules/Category;",
+					&[],
+				)?
+				.l()?;
+			let category = env
+				.call_method(&category, "getName", "()Ljava/lang/String;", &[])?
+				.l()?;
+			let category = env.get_string(&category.into())?.into();
+			let default_off = env.call_method(&rule, "isDefaultOff", "()Z", &[])?.z()?;
+			res.push(Rule {
+				id,
+				description,
+				category,
+				enabled: default_off.not(),
+				incorrect_examples: Self::examples(&rule, "getIncorrectExamples", &mut env)?,
+				correct_examples: Self::examples(&rule, "getCorrectExamples", &mut env)?,
+			});
 		}
-		Ok(())
+		Ok(res)
 	}
 
-	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+	/// With `picky` the level of the check instead of [`Worker::picky`].
+	fn check_text(
+		&mut self,
+		lang: LanguageCode,
+		text: &str,
+		picky: Option<bool>,
+	) -> crate::Result<Vec<Suggestion>> {
 		let mut guard = self.jvm.attach_current_thread()?;
-		let args = guard.new_object("java/util/ArrayList", "()V", &[])?;
-		let args = guard.get_list(&args)?;
-		for check in checks {
-			let check = guard.new_string(check)?;
-			args.add(&mut guard, &check)?;
+		let text = guard.new_string(text)?;
+		let mut raw = self.raw_hook.as_ref().map(|_| Vec::new());
+		if self.languages.contains_key(lang.as_str()).not() {
+			let lang_tool = self.create_lang_tool(&lang, &mut guard)?;
+			self.languages.insert(lang.clone().into(), lang_tool);
 		}
-		let lang_tool = match self.languages.entry(lang.clone()) {
-			Entry::Occupied(entry) => entry.into_mut(),
-			Entry::Vacant(entry) => entry.insert(Self::create_lang_tool(lang, &mut guard)?),
-		};
-		guard.call_method(
-			lang_tool,
-			"disableRules",
-			"(Ljava/util/List;)V",
-			&[JValue::Object(args.as_ref())],
-		)?;
-		Ok(())
+		let lang_tool = &self.languages[lang.as_str()];
+		let picky = picky.unwrap_or(self.picky);
+		let suggestions = Self::lt_request(lang_tool, &text, picky, raw.as_mut(), &mut guard)?;
+		if let (Some(hook), Some(raw)) = (&self.raw_hook, raw) {
+			hook.call(&lang, &RawResponse::Matches(raw));
+		}
+		Ok(suggestions)
+	}
+
+	// Accepted phrases and disabled rules can not be reverted,
+	// so the LanguageTool instance is recreated with the new settings on the next check.
+
+	fn allow_words(&mut self, lang: LanguageCode, words: Vec<String>) {
+		// The words for `de` are also used for `de-DE`.
+		self.languages
+			.retain(|code, _| super::language_codes(code).all(|code| lang != code));
+		self.allowed_words.insert(lang.into(), words);
+	}
+
+	fn disable_checks(&mut self, lang: LanguageCode, checks: Vec<String>) {
+		self.languages.remove(lang.as_str());
+		self.disabled_checks.insert(lang.into(), checks);
+	}
+
+	fn set_rule_options(&mut self, lang: LanguageCode, options: HashMap<String, Vec<RuleValue>>) {
+		self.languages.remove(lang.as_str());
+		self.rule_options.insert(lang.into(), options);
+	}
+
+	fn set_rule_files(&mut self, lang: LanguageCode, files: Vec<PathBuf>) {
+		self.languages
+			.retain(|code, _| super::language_codes(code).all(|code| lang != code));
+		self.rule_files.insert(lang.into(), files);
+	}
+
+	fn set_spelling_files(&mut self, lang: LanguageCode, files: SpellingFiles) {
+		self.languages
+			.retain(|code, _| super::language_codes(code).all(|code| lang != code));
+		self.spelling_files.insert(lang.into(), files);
+	}
+
+	fn set_mother_tongue(&mut self, mother_tongue: Option<String>) {
+		self.languages.clear();
+		self.mother_tongue = mother_tongue;
+	}
+
+	fn set_interface_language(&mut self, lang: Option<String>) {
+		self.languages.clear();
+		self.interface_language = lang;
+	}
+}
+
+impl LanguageToolBackend for LanguageToolJNI {
+	async fn check_text(&self, lang: LanguageCode, text: &str) -> crate::Result<Vec<Suggestion>> {
+		let text = text.to_owned();
+		self.run(move |worker| worker.check_text(lang, &text, None))
+			.await
+	}
+
+	/// The texts are checked one by one in a single call of the worker.
+	async fn check_texts(
+		&self,
+		lang: LanguageCode,
+		texts: &[&str],
+	) -> crate::Result<Vec<Vec<Suggestion>>> {
+		let texts = texts
+			.iter()
+			.map(|&text| text.to_owned())
+			.collect::<Vec<_>>();
+		self.run(move |worker| {
+			texts
+				.iter()
+				.map(|text| worker.check_text(lang.clone(), text, None))
+				.collect()
+		})
+		.await
+	}
+
+	/// The mother tongue of the options is not supported,
+	/// the instance for a language is only created once.
+	async fn check_text_with(
+		&self,
+		lang: LanguageCode,
+		text: &str,
+		options: &CheckOptions,
+	) -> crate::Result<Vec<Suggestion>> {
+		let (checked, picky) = ((lang.clone(), text.to_owned()), options.picky);
+		let mut suggestions = self
+			.run(move |worker| worker.check_text(checked.0, &checked.1, picky))
+			.await?;
+		options.retain_allowed(&lang, text, &mut suggestions);
+		Ok(suggestions)
+	}
+
+	async fn version(&self) -> crate::Result<String> {
+		self.run(|worker| {
+			let mut env = worker.jvm.attach_current_thread()?;
+			let version = env
+				.get_static_field(
+					"org/languagetool/JLanguageTool",
+					"VERSION",
+					"Ljava/lang/String;",
+				)?
+				.l()?;
+			Ok(env.get_string(&version.into())?.into())
+		})
+		.await
+	}
+
+	async fn languages(&self) -> crate::Result<Vec<Language>> {
+		self.run(|worker| {
+			let mut env = worker.jvm.attach_current_thread()?;
+			let languages = env
+				.call_static_method(
+					"org/languagetool/Languages",
+					"get",
+					"()Ljava/util/List;",
+					&[],
+				)?
+				.l()?;
+			let list = env.get_list(&languages)?;
+			let size = list.size(&mut env)?;
+			let mut res = Vec::with_capacity(size as usize);
+			for i in 0..size {
+				let Some(language) = list.get(&mut env, i)? else {
+					continue;
+				};
+				let mut text = |method: &str| -> crate::Result<String> {
+					let value = env
+						.call_method(&language, method, "()Ljava/lang/String;", &[])?
+						.l()?;
+					Ok(env.get_string(&value.into())?.into())
+				};
+				res.push(Language {
+					name: text("getName")?,
+					code: text("getShortCode")?,
+					long_code: text("getShortCodeWithCountryAndVariant")?,
+				});
+			}
+			Ok(res)
+		})
+		.await
+	}
+
+	async fn detect_language(&self, text: &str) -> crate::Result<Option<DetectedLanguage>> {
+		let text = text.to_owned();
+		self.run(move |worker| worker.detect_language(&text)).await
+	}
+
+	async fn rules(&self, lang: LanguageCode) -> crate::Result<Vec<Rule>> {
+		self.run(move |worker| worker.rules(&lang)).await
+	}
+
+	async fn allow_words(&self, lang: LanguageCode, words: &[String]) -> crate::Result<()> {
+		let words = words.to_vec();
+		self.run(move |worker| {
+			worker.allow_words(lang, words);
+			Ok(())
+		})
+		.await
+	}
+
+	async fn disable_checks(&self, lang: LanguageCode, checks: &[String]) -> crate::Result<()> {
+		let checks = checks.to_vec();
+		self.run(move |worker| {
+			worker.disable_checks(lang, checks);
+			Ok(())
+		})
+		.await
+	}
+
+	async fn set_mother_tongue(&self, mother_tongue: Option<String>) -> crate::Result<()> {
+		self.run(move |worker| {
+			worker.set_mother_tongue(mother_tongue);
+			Ok(())
+		})
+		.await
+	}
+
+	/// The language is validated with the first check of a language.
+	async fn set_interface_language(&self, lang: Option<String>) -> crate::Result<()> {
+		self.run(move |worker| {
+			worker.set_interface_language(lang);
+			Ok(())
+		})
+		.await
+	}
+
+	async fn set_picky(&self, picky: bool) -> crate::Result<()> {
+		self.run(move |worker| {
+			worker.picky = picky;
+			Ok(())
+		})
+		.await
+	}
+
+	async fn set_raw_hook(&self, hook: Option<RawHook>) -> crate::Result<()> {
+		self.run(move |worker| {
+			worker.raw_hook = hook;
+			Ok(())
+		})
+		.await
+	}
+
+	async fn set_rule_options(
+		&self,
+		lang: LanguageCode,
+		options: &HashMap<String, Vec<RuleValue>>,
+	) -> crate::Result<()> {
+		let options = options.clone();
+		self.run(move |worker| {
+			worker.set_rule_options(lang, options);
+			Ok(())
+		})
+		.await
+	}
+
+	async fn set_rule_files(&self, lang: LanguageCode, files: &[PathBuf]) -> crate::Result<()> {
+		let files = files.to_vec();
+		self.run(move |worker| {
+			worker.set_rule_files(lang, files);
+			Ok(())
+		})
+		.await
+	}
+
+	async fn set_spelling_files(
+		&self,
+		lang: LanguageCode,
+		files: &SpellingFiles,
+	) -> crate::Result<()> {
+		let files = files.clone();
+		self.run(move |worker| {
+			worker.set_spelling_files(lang, files);
+			Ok(())
+		})
+		.await
 	}
 }
@@ -0,0 +1,246 @@
+use std::{
+	collections::{HashMap, HashSet},
+	path::PathBuf,
+	sync::Mutex,
+};
+
+use crate::{
+	DetectedLanguage, Language, LanguageCode, LanguageToolBackend, Rule, RuleSource, RuleValue,
+	SpellingFiles, Suggestion,
+};
+
+/// Backend returning canned suggestions and recording all calls,
+/// for deterministic tests without Java or a server.
+#[derive(Debug, Default)]
+pub struct MockBackend {
+	rules: Vec<(String, Suggestion)>,
+	detected: Option<DetectedLanguage>,
+	state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+	calls: Vec<Call>,
+	allowed_words: HashMap<String, HashSet<String>>,
+	disabled_checks: HashMap<String, HashSet<String>>,
+}
+
+/// Call of a [`LanguageToolBackend`] method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Call {
+	AllowWords {
+		lang: String,
+		words: Vec<String>,
+	},
+	DisableChecks {
+		lang: String,
+		checks: Vec<String>,
+	},
+	CheckText {
+		lang: String,
+		text: String,
+	},
+	DetectLanguage(String),
+	SetMotherTongue(Option<String>),
+	SetPicky(bool),
+	SetRuleOptions {
+		lang: String,
+		options: HashMap<String, Vec<RuleValue>>,
+	},
+	SetRuleFiles {
+		lang: String,
+		files: Vec<PathBuf>,
+	},
+	SetSpellingFiles {
+		lang: String,
+		files: SpellingFiles,
+	},
+}
+
+impl MockBackend {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Report every occurrence of `pattern` with the suggestion, its range is replaced.
+	/// Allowed words and disabled checks are respected.
+	pub fn respond(mut self, pattern: impl Into<String>, suggestion: Suggestion) -> Self {
+		self.rules.push((pattern.into(), suggestion));
+		self
+	}
+
+	/// Report every occurrence of `pattern` for the rule with the replacements.
+	pub fn rule(self, pattern: impl Into<String>, rule_id: &str, replacements: &[&str]) -> Self {
+		let suggestion = Suggestion {
+			start: 0,
+			end: 0,
+			message: format!("Mock rule {}.", rule_id),
+			sentence: String::new(),
+			replacements: replacements.iter().map(|&r| r.into()).collect(),
+			rule_description: String::new(),
+			rule_id: rule_id.into(),
+			rule_category: String::new(),
+			rule_urls: Vec::new(),
+			incorrect_examples: Vec::new(),
+			correct_examples: Vec::new(),
+			rule_source: RuleSource::Standard,
+			context_for_sure_match: None,
+		};
+		self.respond(pattern, suggestion)
+	}
+
+	/// Detect the language in every text that is not empty.
+	pub fn detect(mut self, name: impl Into<String>, code: LanguageCode) -> Self {
+		self.detected = Some(DetectedLanguage { name: name.into(), code });
+		self
+	}
+
+	/// All calls in order.
+	pub fn calls(&self) -> Vec<Call> {
+		self.state.lock().unwrap().calls.clone()
+	}
+
+	/// Texts passed to [`LanguageToolBackend::check_text`] in order.
+	pub fn checked_texts(&self) -> Vec<String> {
+		let state = self.state.lock().unwrap();
+		let texts = state.calls.iter().filter_map(|call| match call {
+			Call::CheckText { text, .. } => Some(text.clone()),
+			_ => None,
+		});
+		texts.collect()
+	}
+}
+
+impl LanguageToolBackend for MockBackend {
+	async fn allow_words(&self, lang: LanguageCode, words: &[String]) -> crate::Result<()> {
+		let mut state = self.state.lock().unwrap();
+		let call = Call::AllowWords {
+			lang: lang.clone().into(),
+			words: words.to_vec(),
+		};
+		state.calls.push(call);
+		state
+			.allowed_words
+			.insert(lang.into(), words.iter().cloned().collect());
+		Ok(())
+	}
+
+	async fn disable_checks(&self, lang: LanguageCode, checks: &[String]) -> crate::Result<()> {
+		let mut state = self.state.lock().unwrap();
+		let call = Call::DisableChecks {
+			lang: lang.clone().into(),
+			checks: checks.to_vec(),
+		};
+		state.calls.push(call);
+		state
+			.disabled_checks
+			.insert(lang.into(), checks.iter().cloned().collect());
+		Ok(())
+	}
+
+	async fn check_text(&self, lang: LanguageCode, text: &str) -> crate::Result<Vec<Suggestion>> {
+		let mut state = self.state.lock().unwrap();
+		let call = Call::CheckText {
+			lang: lang.clone().into(),
+			text: text.into(),
+		};
+		state.calls.push(call);
+
+		let allowed = super::language_entries(&state.allowed_words, &lang);
+		let disabled = state.disabled_checks.get(lang.as_str());
+		let mut suggestions = Vec::new();
+		for (pattern, suggestion) in &self.rules {
+			if allowed.iter().any(|allowed| allowed.contains(pattern))
+				|| disabled.is_some_and(|disabled| disabled.contains(&suggestion.rule_id))
+			{
+				continue;
+			}
+			for (index, _) in text.match_indices(pattern.as_str()) {
+				let start = text[..index].encode_utf16().count();
+				suggestions.push(Suggestion {
+					start,
+					end: start + pattern.encode_utf16().count(),
+					sentence: text.into(),
+					..suggestion.clone()
+				});
+			}
+		}
+		suggestions.sort_by_key(|suggestion| suggestion.start);
+		Ok(suggestions)
+	}
+
+	async fn version(&self) -> crate::Result<String> {
+		Ok("mock".into())
+	}
+
+	async fn languages(&self) -> crate::Result<Vec<Language>> {
+		let language = Language {
+			name: "English (US)".into(),
+			code: "en".into(),
+			long_code: "en-US".into(),
+		};
+		Ok(vec![language])
+	}
+
+	async fn detect_language(&self, text: &str) -> crate::Result<Option<DetectedLanguage>> {
+		let mut state = self.state.lock().unwrap();
+		state.calls.push(Call::DetectLanguage(text.into()));
+		if text.trim().is_empty() {
+			return Ok(None);
+		}
+		Ok(self.detected.clone())
+	}
+
+	/// The rules of the canned suggestions.
+	async fn rules(&self, _lang: LanguageCode) -> crate::Result<Vec<Rule>> {
+		let mut rules = Vec::<Rule>::new();
+		for (_, suggestion) in &self.rules {
+			if rules.iter().any(|rule| rule.id == suggestion.rule_id) {
+				continue;
+			}
+			rules.push(Rule {
+				id: suggestion.rule_id.clone(),
+				description: suggestion.rule_description.clone(),
+				category: suggestion.rule_category.clone(),
+				enabled: true,
+				incorrect_examples: suggestion.incorrect_examples.clone(),
+				correct_examples: suggestion.correct_examples.clone(),
+			});
+		}
+		Ok(rules)
+	}
+
+	async fn set_mother_tongue(&self, mother_tongue: Option<String>) -> crate::Result<()> {
+		let mut state = self.state.lock().unwrap();
+		state.calls.push(Call::SetMotherTongue(mother_tongue));
+		Ok(())
+	}
+
+	async fn set_picky(&self, picky: bool) -> crate::Result<()> {
+		let mut state = self.state.lock().unwrap();
+		state.calls.push(Call::SetPicky(picky));
+		Ok(())
+	}
+
+	async fn set_rule_options(
+		&self,
+		lang: LanguageCode,
+		options: &HashMap<String, Vec<RuleValue>>,
+	) -> crate::Result<()> {
+		let mut state = self.state.lock().unwrap();
+		let call = Call::SetRuleOptions {
+			lang: lang.into(),
+			options: options.clone(),
+		};
+		state.calls.push(call);
+		Ok(())
+	}
+
+	async fn set_rule_files(&self, lang: LanguageCode, files: &[PathBuf]) -> crate::Result<()> {
+		let mut state = self.state.lock().unwrap();
+		state
+			.calls
+			.push(Call::SetRuleFiles { lang: lang.into(), files: files.to_vec() });
+		Ok(())
+	}
+}
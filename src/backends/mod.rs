@@ -1,5 +1,330 @@
+use std::{
+	borrow::Cow,
+	collections::{HashMap, HashSet},
+	fmt,
+	ops::Not,
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
+};
+
+#[cfg(feature = "commandline")]
+pub mod commandline;
+
+#[cfg(feature = "docker")]
+pub mod docker;
+
+#[cfg(feature = "download-jar")]
+pub mod download;
+
+#[cfg(feature = "harper")]
+pub mod harper;
+
 #[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
 pub mod jni;
 
+pub mod mock;
+
 #[cfg(feature = "remote-server")]
 pub mod remote;
+
+/// Value for a configurable rule, like the maximum words of `TOO_LONG_SENTENCE`.
+#[derive(
+	Debug,
+	Clone,
+	PartialEq,
+	Eq,
+	Hash,
+	serde::Serialize,
+	serde::Deserialize
+)]
+#[serde(untagged)]
+pub enum RuleValue {
+	Bool(bool),
+	Integer(i64),
+	Text(String),
+}
+
+/// Result of a check as the backend returned it, see [`crate::LanguageToolBackend::set_raw_hook`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawResponse {
+	/// Body of the `check` response of a server, or the JSON output of the command line.
+	Json(String),
+	/// `toString` of the `RuleMatch` objects of the JNI backend.
+	Matches(Vec<String>),
+}
+
+/// Called with the language and the raw result of every check, before the suggestions are
+/// parsed from it.
+#[derive(Clone)]
+pub struct RawHook(Arc<RawFn>);
+
+type RawFn = dyn Fn(&crate::LanguageCode, &RawResponse) + Send + Sync;
+
+impl RawHook {
+	pub fn new(hook: impl Fn(&crate::LanguageCode, &RawResponse) + Send + Sync + 'static) -> Self {
+		Self(Arc::new(hook))
+	}
+
+	pub fn call(&self, lang: &crate::LanguageCode, response: &RawResponse) {
+		(self.0)(lang, response)
+	}
+}
+
+impl fmt::Debug for RawHook {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RawHook").finish_non_exhaustive()
+	}
+}
+
+/// Word lists in the format of LanguageTool's `spelling_custom.txt` and `prohibit_custom.txt`,
+/// one word or phrase per line and comments starting with `#`.
+#[derive(
+	Debug,
+	Clone,
+	Default,
+	PartialEq,
+	Eq,
+	Hash,
+	serde::Serialize,
+	serde::Deserialize
+)]
+#[serde(default)]
+pub struct SpellingFiles {
+	/// Files of accepted words.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub spelling: Vec<PathBuf>,
+	/// Files of words flagged as misspelled, even if the dictionary of the language contains them.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub prohibit: Vec<PathBuf>,
+}
+
+impl SpellingFiles {
+	pub fn is_empty(&self) -> bool {
+		self.spelling.is_empty() && self.prohibit.is_empty()
+	}
+
+	/// Make the relative paths relative to the directory instead.
+	pub fn resolve(&mut self, dir: &Path) {
+		for file in self.spelling.iter_mut().chain(&mut self.prohibit) {
+			if file.is_relative() {
+				*file = dir.join(&file);
+			}
+		}
+	}
+
+	/// Words of the files, without comments and empty lines.
+	pub fn words(files: &[PathBuf]) -> crate::Result<Vec<String>> {
+		let mut words = Vec::new();
+		for file in files {
+			let content = std::fs::read_to_string(file)?;
+			words.extend(parse_words(&content));
+		}
+		Ok(words)
+	}
+}
+
+fn parse_words(content: &str) -> impl Iterator<Item = String> + '_ {
+	content
+		.lines()
+		.map(str::trim)
+		.filter(|line| line.is_empty().not() && line.starts_with('#').not())
+		.map(String::from)
+}
+
+/// Language supported by a backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Language {
+	/// Name in English, like `German (Switzerland)`.
+	pub name: String,
+	/// Code without the region, like `de`.
+	pub code: String,
+	/// Code for the checks, like `de-CH`.
+	pub long_code: String,
+}
+
+/// Language of a text detected by a backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DetectedLanguage {
+	/// Name in English, like `German (Germany)`.
+	pub name: String,
+	/// Code for the checks, like `de-DE`.
+	pub code: crate::LanguageCode,
+}
+
+/// Supported languages of a backend, to check unsupported variants like `de-AT` with a supported one.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageFallback {
+	/// Codes with and without region, empty if the backend could not list them.
+	supported: HashSet<String>,
+}
+
+impl LanguageFallback {
+	pub fn new(languages: &[Language]) -> Self {
+		let supported = languages
+			.iter()
+			.flat_map(|language| [language.code.clone(), language.long_code.clone()])
+			.collect();
+		Self { supported }
+	}
+
+	/// All codes are supported if the backend could not list its languages.
+	pub fn supports(&self, code: &str) -> bool {
+		self.supported.is_empty() || self.supported.contains(code)
+	}
+
+	/// The code if the backend supports it, otherwise the first supported code of the chains for
+	/// the code and its more general codes, like `de-AT = ["de-DE"]`, then of the default variant
+	/// of the language and the more general codes. The code is kept if none is supported.
+	pub fn resolve<'a>(
+		&self,
+		code: &'a str,
+		chains: &HashMap<String, Vec<String>>,
+	) -> Cow<'a, str> {
+		if self.supports(code) {
+			return Cow::Borrowed(code);
+		}
+		let configured = language_entries(chains, code)
+			.into_iter()
+			.flatten()
+			.cloned();
+		let language = code.split('-').next().unwrap_or(code);
+		let default = language
+			.parse::<typst::text::Lang>()
+			.ok()
+			.map(crate::convert::default_variant);
+		let general = language_codes(code).skip(1).map(String::from);
+		configured
+			.chain(default)
+			.chain(general)
+			.find(|code| self.supported.contains(code))
+			.map_or(Cow::Borrowed(code), Cow::Owned)
+	}
+}
+
+/// Rule of a backend, like for a rule browser or to validate disabled checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rule {
+	pub id: String,
+	pub description: String,
+	pub category: String,
+	/// Enabled without configuration, rules like the picky ones are off by default.
+	pub enabled: bool,
+	/// Example sentences violating the rule, like [`crate::Suggestion::incorrect_examples`].
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub incorrect_examples: Vec<String>,
+	/// Example sentences following the rule.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub correct_examples: Vec<String>,
+}
+
+/// Budget for the requests to a remote server within a minute.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimit {
+	pub requests_per_minute: Option<usize>,
+	pub characters_per_minute: Option<usize>,
+}
+
+impl RateLimit {
+	/// Limits of the free public API at api.languagetool.org.
+	pub const PUBLIC_API: Self = Self {
+		requests_per_minute: Some(20),
+		characters_per_minute: Some(75_000),
+	};
+}
+
+/// Budget for the requests to a remote server within a longer window like a day, such as the
+/// documented limits of an account. Requests exceeding it are not sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+	pub requests: Option<usize>,
+	pub characters: Option<usize>,
+	pub window: Duration,
+}
+
+impl Quota {
+	pub const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+}
+
+impl Default for Quota {
+	fn default() -> Self {
+		Self {
+			requests: None,
+			characters: None,
+			window: Self::DAY,
+		}
+	}
+}
+
+/// The language code followed by its more general codes, like `de-DE`, `de` for `de-DE`.
+pub(crate) fn language_codes(lang: &str) -> impl Iterator<Item = &str> {
+	let bases = lang.rmatch_indices('-').map(|(index, _)| &lang[..index]);
+	std::iter::once(lang).chain(bases)
+}
+
+/// Entries for the language and its more general codes,
+/// a dictionary for `de` is also used for `de-DE` and `de-AT`.
+pub fn language_entries<'a, T>(map: &'a HashMap<String, T>, lang: &str) -> Vec<&'a T> {
+	language_codes(lang)
+		.filter_map(|code| map.get(code))
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_language_fallback() {
+		let language = |code: &str, long_code: &str| Language {
+			name: String::new(),
+			code: code.into(),
+			long_code: long_code.into(),
+		};
+		let fallback = LanguageFallback::new(&[
+			language("de", "de-DE"),
+			language("de", "de-CH"),
+			language("eo", "eo"),
+		]);
+		let chains = HashMap::from([("de-LI".into(), vec!["fr-FR".into(), "de-CH".into()])]);
+		assert_eq!(fallback.resolve("de-CH", &chains), "de-CH");
+		assert_eq!(fallback.resolve("de-AT", &chains), "de-DE");
+		assert_eq!(fallback.resolve("de-LI", &chains), "de-CH");
+		assert_eq!(fallback.resolve("eo-XX", &chains), "eo");
+		assert_eq!(fallback.resolve("fr-FR", &chains), "fr-FR");
+		assert_eq!(LanguageFallback::default().resolve("xx", &chains), "xx");
+	}
+
+	#[test]
+	fn test_language_entries() {
+		let codes = language_codes("de-DE-x-formal").collect::<Vec<_>>();
+		assert_eq!(codes, ["de-DE-x-formal", "de-DE-x", "de-DE", "de"]);
+
+		let map = HashMap::from([("de".into(), 1), ("de-DE".into(), 2), ("en".into(), 3)]);
+		assert_eq!(language_entries(&map, "de-DE"), [&2, &1]);
+		assert_eq!(language_entries(&map, "de-AT"), [&1]);
+		assert_eq!(language_entries(&map, "fr"), Vec::<&i32>::new());
+	}
+
+	#[test]
+	fn test_spelling_files() {
+		let words = parse_words("# Accepted words\ntypst\n\n  Typst-Dokument \nLanguage Tool\n");
+		assert_eq!(
+			words.collect::<Vec<_>>(),
+			["typst", "Typst-Dokument", "Language Tool"]
+		);
+		let mut files = SpellingFiles {
+			spelling: vec!["words.txt".into(), "/abs.txt".into()],
+			prohibit: Vec::new(),
+		};
+		files.resolve(Path::new("/project"));
+		assert_eq!(
+			files.spelling,
+			[PathBuf::from("/project/words.txt"), "/abs.txt".into()]
+		);
+		assert!(SpellingFiles::default().is_empty());
+	}
+}
@@ -1,68 +1,539 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+	collections::{HashMap, HashSet},
+	future::Future,
+	io::Write,
+	ops::Not,
+	sync::{Arc, RwLock},
+};
 
-use languagetool_rust::{check::Match, CheckRequest, ServerClient};
+use languagetool_rust::{
+	check::{Level, Match},
+	words::{
+		LoginArgs, WordsAddRequest, WordsAddResponse, WordsDeleteRequest, WordsDeleteResponse,
+	},
+	CheckRequest, CheckResponse, LanguagesResponse, ServerClient,
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_TYPE};
+use serde::Serialize;
 
-use crate::{LanguageToolBackend, Suggestion};
+#[cfg(not(target_arch = "wasm32"))]
+use self::limiter::Limiter;
+use crate::{
+	CheckOptions, DetectedLanguage, Error, Language, LanguageCode, LanguageToolBackend, Quota,
+	RateLimit, RawHook, RawResponse, RuleSource, Suggestion,
+};
 
 #[derive(Debug)]
 pub struct LanguageToolRemote {
-	server_client: ServerClient,
+	transport: Transport,
+	settings: RwLock<Settings>,
+	login: Option<LoginArgs>,
+	/// Send the text gzip compressed, the server or a proxy in front of it must accept it.
+	compress_requests: bool,
+	/// Headers and proxy of the HTTP client, it is created again when they change.
+	headers: HeaderMap,
+	#[cfg(not(target_arch = "wasm32"))]
+	proxy: Option<reqwest::Proxy>,
+	#[cfg(not(target_arch = "wasm32"))]
+	limiter: tokio::sync::Mutex<Limiter>,
+}
+
+#[derive(Debug, Default)]
+struct Settings {
 	disabled_categories: HashMap<String, Vec<String>>,
-	allowed_words: HashMap<String, HashSet<String>>,
+	allowed_words: HashMap<String, Arc<HashSet<String>>>,
+	mother_tongue: Option<String>,
+	picky: bool,
+	raw_hook: Option<RawHook>,
+}
+
+/// Connection to the server, over HTTP or over a Unix socket for hosts like `unix:///run/lt.sock`.
+#[derive(Debug)]
+enum Transport {
+	Http(ServerClient),
+	#[cfg(unix)]
+	Unix(unix::UnixClient),
+}
+
+impl Transport {
+	fn new(hostname: &str, port: &str) -> crate::Result<Self> {
+		#[cfg(unix)]
+		if let Some(path) = hostname.strip_prefix("unix://") {
+			return Ok(Self::Unix(unix::UnixClient::new(path.into())));
+		}
+		if hostname.starts_with("unix:") {
+			return Err(Error::BackendUnavailable(format!(
+				"Expected a socket like 'unix:///path/to/socket' and a system with Unix sockets, found '{}'.",
+				hostname
+			)));
+		}
+		Ok(Self::Http(ServerClient::new(hostname, port)))
+	}
+
+	/// With `compress` the text is sent as gzip compressed form instead of in the query.
+	async fn check(&self, req: &CheckRequest, compress: bool) -> crate::Result<CheckResponse> {
+		match self {
+			Self::Http(client) if compress => send(check_compressed(client, req)).await,
+			Self::Http(client) => Ok(send(client.check(req)).await?),
+			#[cfg(unix)]
+			Self::Unix(client) => client.post("check", req, compress).await,
+		}
+	}
+
+	/// Body of the check response, for [`RawResponse::Json`].
+	async fn check_raw(&self, req: &CheckRequest, compress: bool) -> crate::Result<Vec<u8>> {
+		match self {
+			Self::Http(client) => send(post_check(client, req, compress)).await,
+			#[cfg(unix)]
+			Self::Unix(client) => client.post_raw("check", req, compress).await,
+		}
+	}
+
+	async fn languages(&self) -> crate::Result<LanguagesResponse> {
+		match self {
+			Self::Http(client) => Ok(send(client.languages()).await?),
+			#[cfg(unix)]
+			Self::Unix(client) => client.get("languages").await,
+		}
+	}
+
+	async fn words_add(&self, req: &WordsAddRequest) -> crate::Result<WordsAddResponse> {
+		match self {
+			Self::Http(client) => Ok(send(client.words_add(req)).await?),
+			#[cfg(unix)]
+			Self::Unix(client) => client.post("words/add", req, false).await,
+		}
+	}
+
+	async fn words_delete(&self, req: &WordsDeleteRequest) -> crate::Result<WordsDeleteResponse> {
+		match self {
+			Self::Http(client) => Ok(send(client.words_delete(req)).await?),
+			#[cfg(unix)]
+			Self::Unix(client) => client.post("words/delete", req, false).await,
+		}
+	}
 }
 
 impl LanguageToolRemote {
-	pub fn new(hostname: &str, port: &str) -> anyhow::Result<Self> {
-		let server_client = ServerClient::new(hostname, port);
+	/// A `hostname` like `unix:///run/lt.sock` connects to a server listening on the Unix socket,
+	/// the `port` is not used then.
+	pub fn new(hostname: &str, port: &str) -> crate::Result<Self> {
+		let transport = Transport::new(hostname, port)?;
+		#[cfg(not(target_arch = "wasm32"))]
+		let limit = if hostname.contains("api.languagetool.org") {
+			RateLimit::PUBLIC_API
+		} else {
+			RateLimit::default()
+		};
 		Ok(Self {
-			server_client,
-			disabled_categories: HashMap::new(),
-			allowed_words: HashMap::new(),
+			transport,
+			settings: RwLock::new(Settings::default()),
+			login: None,
+			compress_requests: false,
+			headers: HeaderMap::new(),
+			#[cfg(not(target_arch = "wasm32"))]
+			proxy: None,
+			#[cfg(not(target_arch = "wasm32"))]
+			limiter: tokio::sync::Mutex::new(Limiter::new(limit)),
 		})
 	}
+
+	/// Check if the server answers.
+	#[cfg(feature = "docker")]
+	pub(crate) async fn ping(&self) -> bool {
+		match &self.transport {
+			Transport::Http(client) => client.ping().await.is_ok(),
+			#[cfg(unix)]
+			Transport::Unix(client) => client.get::<LanguagesResponse>("languages").await.is_ok(),
+		}
+	}
+
+	/// Account of a Premium or self-hosted server with user accounts,
+	/// its dictionary is used for all checks.
+	pub fn set_login(&mut self, username: &str, api_key: &str) {
+		let mut login = LoginArgs::default();
+		login.username = username.into();
+		login.api_key = api_key.into();
+		self.login = Some(login);
+	}
+
+	/// Headers sent with every request, like `Authorization` for a server behind an
+	/// authenticating reverse proxy. Fails with [`Error::Config`] for an invalid name or value.
+	pub fn set_headers(&mut self, headers: &[(String, String)]) -> crate::Result<()> {
+		let mut map = HeaderMap::new();
+		for (name, value) in headers {
+			let name = HeaderName::from_bytes(name.as_bytes())
+				.map_err(|err| Error::Config(Box::new(err)))?;
+			let mut value =
+				HeaderValue::from_str(value).map_err(|err| Error::Config(Box::new(err)))?;
+			// Values like tokens are not shown in debug output.
+			value.set_sensitive(true);
+			map.append(name, value);
+		}
+		self.headers = map;
+		#[cfg(unix)]
+		if let Transport::Unix(client) = &mut self.transport {
+			client.headers = headers.to_vec();
+		}
+		self.update_client()
+	}
+
+	/// Send the requests through the proxy, like `http://proxy.example.com:3128`.
+	/// Without it the proxy of `HTTP_PROXY` and `HTTPS_PROXY` is used, hosts in `NO_PROXY` are
+	/// always reached directly. Fails with [`Error::Config`] for an invalid URL.
+	/// Not used for Unix sockets.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn set_proxy(&mut self, url: &str) -> crate::Result<()> {
+		let proxy = reqwest::Proxy::all(url).map_err(|err| Error::Config(Box::new(err)))?;
+		self.proxy = Some(proxy.no_proxy(reqwest::NoProxy::from_env()));
+		self.update_client()
+	}
+
+	/// Send the text of the checks gzip compressed with `Content-Encoding: gzip`,
+	/// the server or a reverse proxy in front of it must decompress it.
+	/// Responses are always accepted compressed.
+	pub fn set_compress_requests(&mut self, compress: bool) {
+		self.compress_requests = compress;
+	}
+
+	fn update_client(&mut self) -> crate::Result<()> {
+		// Without Unix sockets there is only the HTTP client.
+		#[cfg(unix)]
+		let Transport::Http(client) = &mut self.transport
+		else {
+			return Ok(());
+		};
+		#[cfg(not(unix))]
+		let Transport::Http(client) = &mut self.transport;
+		let builder = reqwest::Client::builder().default_headers(self.headers.clone());
+		#[cfg(not(target_arch = "wasm32"))]
+		let builder = match &self.proxy {
+			Some(proxy) => builder.proxy(proxy.clone()),
+			None => builder,
+		};
+		client.client = builder
+			.build()
+			.map_err(|err| Error::Remote(Box::new(err)))?;
+		Ok(())
+	}
+
+	fn login(&self) -> crate::Result<LoginArgs> {
+		self.login.clone().ok_or_else(|| {
+			Error::BackendUnavailable(
+				"The server dictionary requires a username and API key.".into(),
+			)
+		})
+	}
+
+	/// Add the word to the dictionary of the account, shared by all languages.
+	pub async fn add_word(&self, word: &str) -> crate::Result<()> {
+		let mut req = WordsAddRequest::default();
+		req.word = word.into();
+		req.login = self.login()?;
+		self.transport.words_add(&req).await?;
+		Ok(())
+	}
+
+	/// Remove the word from the dictionary of the account.
+	pub async fn delete_word(&self, word: &str) -> crate::Result<()> {
+		let mut req = WordsDeleteRequest::default();
+		req.word = word.into();
+		req.login = self.login()?;
+		self.transport.words_delete(&req).await?;
+		Ok(())
+	}
+
+	/// Delay requests to stay within the limit, the public API is limited by default.
+	/// Not supported on wasm, where the server answers with [`crate::Error::RateLimited`].
+	#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+	pub fn set_rate_limit(&mut self, limit: RateLimit) {
+		#[cfg(not(target_arch = "wasm32"))]
+		{
+			self.limiter.get_mut().limit = limit;
+		}
+	}
+
+	/// Fail with [`crate::Error::QuotaExceeded`] instead of sending a request exceeding the quota,
+	/// the requests sent before are counted. Not supported on wasm.
+	#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+	pub fn set_quota(&mut self, quota: Option<Quota>) {
+		#[cfg(not(target_arch = "wasm32"))]
+		{
+			self.limiter.get_mut().quota = quota;
+		}
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod limiter {
+	use std::{
+		collections::VecDeque,
+		ops::Not,
+		time::{Duration, Instant},
+	};
+
+	use crate::{Error, Quota, RateLimit};
+
+	/// Sliding window over the requests of the last minute, or of the window of the quota.
+	#[derive(Debug)]
+	pub struct Limiter {
+		pub limit: RateLimit,
+		pub quota: Option<Quota>,
+		requests: VecDeque<(Instant, usize)>,
+	}
+
+	impl Limiter {
+		const WINDOW: Duration = Duration::from_secs(60);
+
+		pub fn new(limit: RateLimit) -> Self {
+			Self {
+				limit,
+				quota: None,
+				requests: VecDeque::new(),
+			}
+		}
+
+		/// Wait until a request with `characters` fits into the budget.
+		/// Requests larger than the whole budget are sent once the window is empty.
+		/// Fails without waiting if the request would exceed the quota.
+		pub async fn acquire(&mut self, characters: usize) -> crate::Result<()> {
+			let window = self
+				.quota
+				.map_or(Self::WINDOW, |quota| quota.window.max(Self::WINDOW));
+			loop {
+				let now = Instant::now();
+				while let Some(&(time, _)) = self.requests.front() {
+					if now.duration_since(time) < window {
+						break;
+					}
+					self.requests.pop_front();
+				}
+				if let Some(quota) = self.quota {
+					self.check_quota(quota, characters, now)?;
+				}
+				let start = self
+					.requests
+					.partition_point(|&(time, _)| now.duration_since(time) >= Self::WINDOW);
+				let minute = self.requests.range(start..);
+				let requests_full = self
+					.limit
+					.requests_per_minute
+					.is_some_and(|max| minute.len() >= max);
+				let used = minute.map(|&(_, c)| c).sum::<usize>();
+				let characters_full = self
+					.limit
+					.characters_per_minute
+					.is_some_and(|max| used > 0 && used + characters > max);
+				let Some(&(oldest, _)) = self.requests.get(start) else {
+					break;
+				};
+				if requests_full.not() && characters_full.not() {
+					break;
+				}
+				tokio::time::sleep_until((oldest + Self::WINDOW).into()).await;
+			}
+			self.requests.push_back((Instant::now(), characters));
+			Ok(())
+		}
+
+		/// Fails with the time until the oldest requests left the window of the quota so the
+		/// request fits.
+		fn check_quota(&self, quota: Quota, characters: usize, now: Instant) -> crate::Result<()> {
+			if quota.characters.is_some_and(|max| characters > max) || quota.requests == Some(0) {
+				return Err(Error::QuotaExceeded(None));
+			}
+			let in_window = self
+				.requests
+				.iter()
+				.filter(|&&(time, _)| now.duration_since(time) < quota.window);
+			let mut requests = in_window.clone().count();
+			let mut used = in_window.clone().map(|&(_, c)| c).sum::<usize>();
+			let fits = |requests: usize, used: usize| {
+				quota.requests.is_none_or(|max| requests < max)
+					&& quota.characters.is_none_or(|max| used + characters <= max)
+			};
+			if fits(requests, used) {
+				return Ok(());
+			}
+			for &(time, c) in in_window {
+				requests -= 1;
+				used -= c;
+				if fits(requests, used) {
+					let wait = (time + quota.window).saturating_duration_since(now);
+					return Err(Error::QuotaExceeded(Some(wait)));
+				}
+			}
+			Err(Error::QuotaExceeded(None))
+		}
+	}
+
+	#[cfg(test)]
+	mod test {
+		use super::*;
+
+		#[test]
+		fn test_quota() {
+			let now = Instant::now();
+			let quota = Quota {
+				requests: Some(3),
+				characters: Some(100),
+				window: Duration::from_secs(3600),
+			};
+			let mut limiter = Limiter::new(RateLimit::default());
+			limiter
+				.requests
+				.push_back((now - Duration::from_secs(3000), 60));
+			limiter
+				.requests
+				.push_back((now - Duration::from_secs(60), 30));
+			let wait = |limiter: &Limiter, characters: usize| match limiter
+				.check_quota(quota, characters, now)
+			{
+				Ok(()) => Ok(()),
+				Err(Error::QuotaExceeded(wait)) => Err(wait),
+				Err(err) => panic!("{}", err),
+			};
+			assert_eq!(wait(&limiter, 10), Ok(()));
+			assert_eq!(wait(&limiter, 20), Err(Some(Duration::from_secs(600))));
+			assert_eq!(wait(&limiter, 101), Err(None));
+
+			limiter.requests.push_back((now, 0));
+			assert_eq!(wait(&limiter, 0), Err(Some(Duration::from_secs(600))));
+		}
+	}
 }
 
 impl LanguageToolBackend for LanguageToolRemote {
-	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
-		self.allowed_words
-			.insert(lang, words.iter().map(|x| x.clone()).collect());
+	async fn allow_words(&self, lang: LanguageCode, words: &[String]) -> crate::Result<()> {
+		let words = Arc::new(words.iter().cloned().collect());
+		self.settings
+			.write()
+			.unwrap()
+			.allowed_words
+			.insert(lang.into(), words);
 		Ok(())
 	}
 
-	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
-		self.disabled_categories
-			.insert(lang, checks.iter().map(|x| x.clone()).collect());
+	async fn disable_checks(&self, lang: LanguageCode, checks: &[String]) -> crate::Result<()> {
+		self.settings
+			.write()
+			.unwrap()
+			.disabled_categories
+			.insert(lang.into(), checks.to_vec());
+		Ok(())
+	}
+
+	async fn set_mother_tongue(&self, mother_tongue: Option<String>) -> crate::Result<()> {
+		self.settings.write().unwrap().mother_tongue = mother_tongue;
+		Ok(())
+	}
+
+	async fn set_picky(&self, picky: bool) -> crate::Result<()> {
+		self.settings.write().unwrap().picky = picky;
+		Ok(())
+	}
+
+	async fn set_raw_hook(&self, hook: Option<RawHook>) -> crate::Result<()> {
+		self.settings.write().unwrap().raw_hook = hook;
 		Ok(())
 	}
 
 	async fn check_text(
-		&mut self,
-		lang: String,
+		&self,
+		lang: LanguageCode,
+		text: &str,
+	) -> crate::Result<Vec<crate::Suggestion>> {
+		self.check_text_with(lang, text, &CheckOptions::default())
+			.await
+	}
+
+	async fn check_text_with(
+		&self,
+		lang: LanguageCode,
 		text: &str,
-	) -> anyhow::Result<Vec<crate::Suggestion>> {
-		let disabled_rules = self.disabled_categories.get(&lang).map(|x| x.clone());
-		let allowed = self.allowed_words.get(&lang);
+		options: &CheckOptions,
+	) -> crate::Result<Vec<crate::Suggestion>> {
+		let (mut disabled_rules, mut allowed, mother_tongue, picky, hook) = {
+			let settings = self.settings.read().unwrap();
+			(
+				settings.disabled_categories.get(lang.as_str()).cloned(),
+				super::language_entries(&settings.allowed_words, &lang)
+					.into_iter()
+					.cloned()
+					.collect::<Vec<_>>(),
+				options
+					.mother_tongue
+					.clone()
+					.or_else(|| settings.mother_tongue.clone()),
+				options.picky.unwrap_or(settings.picky),
+				settings.raw_hook.clone(),
+			)
+		};
+		let disabled = options.disabled_checks(&lang).cloned().collect::<Vec<_>>();
+		if disabled.is_empty().not() {
+			disabled_rules.get_or_insert_with(Vec::new).extend(disabled);
+		}
+		let words = options.dictionary(&lang).cloned().collect::<HashSet<_>>();
+		if words.is_empty().not() {
+			allowed.push(Arc::new(words));
+		}
 
 		let mut req = CheckRequest::default()
 			.with_text(String::from(text))
-			.with_language(lang);
+			.with_language(lang.as_str().into());
+		if let Some(login) = &self.login {
+			req.username = Some(login.username.clone());
+			req.api_key = Some(login.api_key.clone());
+		}
 		req.disabled_rules = disabled_rules;
+		req.mother_tongue = mother_tongue;
+		if picky {
+			req.level = Level::Picky;
+		}
 
-		let response = self.server_client.check(&req).await?;
+		#[cfg(not(target_arch = "wasm32"))]
+		self.limiter
+			.lock()
+			.await
+			.acquire(text.chars().count())
+			.await?;
+		let body = self
+			.transport
+			.check_raw(&req, self.compress_requests)
+			.await?;
+		if let Some(hook) = &hook {
+			hook.call(
+				&lang,
+				&RawResponse::Json(String::from_utf8_lossy(&body).into()),
+			);
+		}
+		let response = serde_json::from_slice::<Response>(&body)
+			.map_err(|err| Error::Remote(Box::new(err)))?;
 
 		let mut suggestions = Vec::with_capacity(response.matches.len());
-		for m in response.matches {
-			if let Some(allowed) = allowed {
-				if filter_match(&m, allowed) {
-					continue;
-				}
+		for RemoteMatch { m, extras } in response.matches {
+			if allowed.iter().any(|allowed| filter_match(&m, allowed)) {
+				continue;
 			}
 			let suggestion = Suggestion {
 				start: m.offset,
 				end: m.offset + m.length,
 				message: m.message,
+				sentence: m.sentence,
 				rule_description: m.rule.description,
 				rule_id: m.rule.id,
+				rule_category: m.rule.category.name,
+				rule_urls: m
+					.rule
+					.urls
+					.unwrap_or_default()
+					.into_iter()
+					.map(|url| url.value)
+					.collect(),
+				incorrect_examples: Vec::new(),
+				correct_examples: Vec::new(),
+				rule_source: RuleSource::new(extras.rule.is_premium, &extras.rule.tags),
+				context_for_sure_match: extras.context_for_sure_match,
 				replacements: m.replacements.into_iter().map(|x| x.value).collect(),
 			};
 			suggestions.push(suggestion);
@@ -70,6 +541,378 @@ impl LanguageToolBackend for LanguageToolRemote {
 
 		Ok(suggestions)
 	}
+
+	async fn version(&self) -> crate::Result<String> {
+		let req = CheckRequest::default()
+			.with_text(String::from("."))
+			.with_language(String::from("en-US"));
+		let response = self.transport.check(&req, false).await?;
+		Ok(response.software.version)
+	}
+
+	/// Detected by a check with the language `auto`, without the settings of the languages.
+	async fn detect_language(&self, text: &str) -> crate::Result<Option<DetectedLanguage>> {
+		let mut req = CheckRequest::default()
+			.with_text(String::from(text))
+			.with_language(String::from("auto"));
+		if let Some(login) = &self.login {
+			req.username = Some(login.username.clone());
+			req.api_key = Some(login.api_key.clone());
+		}
+		#[cfg(not(target_arch = "wasm32"))]
+		self.limiter
+			.lock()
+			.await
+			.acquire(text.chars().count())
+			.await?;
+		let response = self.transport.check(&req, self.compress_requests).await?;
+		let detected = response.language.detected_language;
+		let Ok(code) = LanguageCode::new(&detected.code) else {
+			return Ok(None);
+		};
+		Ok(Some(DetectedLanguage { name: detected.name, code }))
+	}
+
+	async fn languages(&self) -> crate::Result<Vec<Language>> {
+		let response = self.transport.languages().await?;
+		let languages = response.into_iter().map(|language| Language {
+			name: language.name,
+			code: language.code,
+			long_code: language.long_code,
+		});
+		Ok(languages.collect())
+	}
+}
+
+/// Like [`ServerClient::check`], but with the request as gzip compressed form.
+async fn check_compressed(
+	client: &ServerClient,
+	req: &CheckRequest,
+) -> crate::Result<CheckResponse> {
+	let body = post_check(client, req, true).await?;
+	serde_json::from_slice(&body).map_err(|err| Error::Remote(Box::new(err)))
+}
+
+/// Body of the response to the request as form, gzip compressed with `compress`.
+async fn post_check(
+	client: &ServerClient,
+	req: &CheckRequest,
+	compress: bool,
+) -> crate::Result<Vec<u8>> {
+	let form = form(req)?.into_bytes();
+	let request = client
+		.client
+		.post(format!("{}/check", client.api))
+		.header(CONTENT_TYPE, "application/x-www-form-urlencoded");
+	let request = match compress {
+		true => request.header(CONTENT_ENCODING, "gzip").body(gzip(&form)?),
+		false => request.body(form),
+	};
+	let response = request
+		.send()
+		.await
+		.map_err(|err| Error::Remote(Box::new(err)))?;
+	let status = response.status();
+	let body = response
+		.bytes()
+		.await
+		.map_err(|err| Error::Remote(Box::new(err)))?;
+	if status.is_success().not() {
+		let message = String::from_utf8_lossy(&body).into_owned();
+		return Err(languagetool_rust::error::Error::InvalidRequest(message).into());
+	}
+	Ok(body.into())
+}
+
+fn form(req: &impl Serialize) -> crate::Result<String> {
+	serde_urlencoded::to_string(req).map_err(|err| Error::Remote(Box::new(err)))
+}
+
+fn gzip(data: &[u8]) -> crate::Result<Vec<u8>> {
+	let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+	encoder.write_all(data)?;
+	Ok(encoder.finish()?)
+}
+
+#[cfg(unix)]
+mod unix {
+	use std::{io::Read, path::PathBuf, sync::Mutex};
+
+	use futures_util::future::poll_fn;
+	use hyper::{body::Body, client::conn::SendRequest, header, Method, Request};
+	use serde::{de::DeserializeOwned, Serialize};
+	use tokio::net::UnixStream;
+
+	use crate::Error;
+
+	/// HTTP/1.1 client for a server listening on a Unix socket, idle connections are kept alive.
+	#[derive(Debug)]
+	pub struct UnixClient {
+		path: PathBuf,
+		pub headers: Vec<(String, String)>,
+		idle: Mutex<Vec<SendRequest<Body>>>,
+	}
+
+	impl UnixClient {
+		pub fn new(path: PathBuf) -> Self {
+			Self {
+				path,
+				headers: Vec::new(),
+				idle: Mutex::new(Vec::new()),
+			}
+		}
+
+		pub async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> crate::Result<T> {
+			let body = self
+				.request(Method::GET, endpoint, Vec::new(), false)
+				.await?;
+			serde_json::from_slice(&body).map_err(|err| Error::Remote(Box::new(err)))
+		}
+
+		/// The request is sent as form, like the query of the HTTP client.
+		/// With `compress` the form is gzip compressed.
+		pub async fn post<T: DeserializeOwned>(
+			&self,
+			endpoint: &str,
+			form: &impl Serialize,
+			compress: bool,
+		) -> crate::Result<T> {
+			let body = self.post_raw(endpoint, form, compress).await?;
+			serde_json::from_slice(&body).map_err(|err| Error::Remote(Box::new(err)))
+		}
+
+		/// Body of the response to the form, like [`UnixClient::post`].
+		pub async fn post_raw(
+			&self,
+			endpoint: &str,
+			form: &impl Serialize,
+			compress: bool,
+		) -> crate::Result<Vec<u8>> {
+			let form = super::form(form)?.into_bytes();
+			let body = if compress { super::gzip(&form)? } else { form };
+			self.request(Method::POST, endpoint, body, compress).await
+		}
+
+		/// Body of a successful response, a gzip compressed body is decoded.
+		async fn request(
+			&self,
+			method: Method,
+			endpoint: &str,
+			body: Vec<u8>,
+			compressed: bool,
+		) -> crate::Result<Vec<u8>> {
+			let mut request = Request::builder()
+				.uri(format!("/v2/{}", endpoint))
+				.header(header::HOST, "localhost")
+				.header(header::ACCEPT, "application/json")
+				.header(header::ACCEPT_ENCODING, "gzip");
+			if method == Method::POST {
+				request = request.header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+			}
+			if compressed {
+				request = request.header(header::CONTENT_ENCODING, "gzip");
+			}
+			for (name, value) in &self.headers {
+				request = request.header(name, value);
+			}
+			let request = request
+				.method(method)
+				.body(Body::from(body))
+				.map_err(|err| Error::Config(Box::new(err)))?;
+
+			let mut sender = self.connection().await?;
+			let response = sender.send_request(request).await.map_err(remote)?;
+			let status = response.status();
+			let gzip = response
+				.headers()
+				.get(header::CONTENT_ENCODING)
+				.is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"gzip"));
+			let body = hyper::body::to_bytes(response.into_body())
+				.await
+				.map_err(remote)?;
+			self.idle.lock().unwrap().push(sender);
+
+			let body = if gzip {
+				let mut decoded = Vec::new();
+				flate2::read::GzDecoder::new(&body[..])
+					.read_to_end(&mut decoded)
+					.map_err(remote)?;
+				decoded
+			} else {
+				body.to_vec()
+			};
+			if status.is_success() {
+				return Ok(body);
+			}
+			// Same errors as for the HTTP client, like for unknown languages.
+			let message = String::from_utf8_lossy(&body).into_owned();
+			Err(languagetool_rust::error::Error::InvalidRequest(message).into())
+		}
+
+		/// An idle connection the server did not close, or a new one.
+		async fn connection(&self) -> crate::Result<SendRequest<Body>> {
+			while let Some(mut sender) = self.take_idle() {
+				if poll_fn(|cx| sender.poll_ready(cx)).await.is_ok() {
+					return Ok(sender);
+				}
+			}
+			let stream = UnixStream::connect(&self.path).await?;
+			let (sender, connection) = hyper::client::conn::handshake(stream)
+				.await
+				.map_err(remote)?;
+			tokio::spawn(async move {
+				let _ = connection.await;
+			});
+			Ok(sender)
+		}
+
+		fn take_idle(&self) -> Option<SendRequest<Body>> {
+			self.idle.lock().unwrap().pop()
+		}
+	}
+
+	fn remote(err: impl std::error::Error + Send + Sync + 'static) -> Error {
+		Error::Remote(Box::new(err))
+	}
+
+	#[cfg(test)]
+	mod test {
+		use tokio::{
+			io::{AsyncReadExt, AsyncWriteExt},
+			net::UnixListener,
+		};
+
+		use super::*;
+
+		/// Answer every connection with the responses, one after the other.
+		fn serve(responses: Vec<Vec<u8>>) -> UnixClient {
+			let path = std::env::temp_dir().join(format!(
+				"typst-languagetool-{}-{}.sock",
+				std::process::id(),
+				responses.len()
+			));
+			let _ = std::fs::remove_file(&path);
+			let listener = UnixListener::bind(&path).unwrap();
+			tokio::spawn(async move {
+				let (mut stream, _) = listener.accept().await.unwrap();
+				for response in responses {
+					let mut request = [0; 1024];
+					let _ = stream.read(&mut request).await.unwrap();
+					stream.write_all(&response).await.unwrap();
+				}
+			});
+			UnixClient::new(path)
+		}
+
+		#[tokio::test]
+		async fn test_responses() {
+			let body = super::super::gzip(b"[]").unwrap();
+			let head = format!(
+				"HTTP/1.1 200 OK\r\nContent-Encoding: GZIP\r\nContent-Length: {}\r\n\r\n",
+				body.len()
+			);
+			let gzip = [head.into_bytes(), body].concat();
+			let client = serve(vec![
+				b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n[]".to_vec(),
+				b"HTTP/1.1 400 Bad Request\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2;x=y\r\nde\r\n0\r\n\r\n".to_vec(),
+				gzip,
+				b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n[]".to_vec(),
+			]);
+			// The connection is kept alive between the responses.
+			assert_eq!(client.get::<Vec<u8>>("languages").await.unwrap(), b"");
+			let err = client.get::<Vec<u8>>("languages").await.unwrap_err();
+			assert!(err.to_string().contains("abcde"));
+			assert_eq!(client.get::<Vec<u8>>("languages").await.unwrap(), b"");
+			// The body ends before its length.
+			assert!(client.get::<Vec<u8>>("languages").await.is_err());
+		}
+
+		#[tokio::test]
+		async fn test_invalid_response() {
+			let client = serve(vec![b"HTTP/1.1 abc\r\n\r\n".to_vec()]);
+			assert!(client.get::<Vec<u8>>("languages").await.is_err());
+		}
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn send<F: Future + Send>(future: F) -> F {
+	future
+}
+
+#[cfg(target_arch = "wasm32")]
+fn send<F: Future>(future: F) -> wasm::AssumeSend<F> {
+	wasm::AssumeSend(future)
+}
+
+#[cfg(target_arch = "wasm32")]
+mod wasm {
+	use std::{
+		future::Future,
+		pin::Pin,
+		task::{Context, Poll},
+	};
+
+	/// The fetch future of the browser is not `Send`,
+	/// but without wasm threads it never leaves the current thread.
+	pub struct AssumeSend<F>(pub F);
+
+	#[cfg(not(target_feature = "atomics"))]
+	unsafe impl<F> Send for AssumeSend<F> {}
+
+	impl<F: Future> Future for AssumeSend<F> {
+		type Output = F::Output;
+
+		fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+			// SAFETY: the inner future is never moved out of the pinned wrapper.
+			unsafe { self.map_unchecked_mut(|this| &mut this.0) }.poll(cx)
+		}
+	}
+}
+
+/// Matches of a check response.
+#[derive(Debug, serde::Deserialize)]
+struct Response {
+	matches: Vec<RemoteMatch>,
+}
+
+/// Match of `languagetool-rust` with the fields it does not parse.
+#[derive(Debug)]
+struct RemoteMatch {
+	m: Match,
+	extras: MatchExtras,
+}
+
+// `rule` has fields of both, so the match is read as a value for each of them.
+impl<'de> serde::Deserialize<'de> for RemoteMatch {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		use serde::de::Error;
+
+		let value = serde_json::Value::deserialize(deserializer)?;
+		let extras = MatchExtras::deserialize(&value).map_err(D::Error::custom)?;
+		let m = Match::deserialize(value).map_err(D::Error::custom)?;
+		Ok(Self { m, extras })
+	}
+}
+
+/// Fields of a match that `languagetool-rust` does not parse, missing ones keep their
+/// defaults.
+#[derive(Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MatchExtras {
+	#[serde(default)]
+	context_for_sure_match: Option<isize>,
+	#[serde(default)]
+	rule: RuleExtras,
+}
+
+#[derive(Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleExtras {
+	#[serde(default)]
+	is_premium: bool,
+	#[serde(default)]
+	tags: Vec<String>,
 }
 
 fn filter_match(m: &Match, allowed: &HashSet<String>) -> bool {
@@ -86,3 +929,47 @@ fn filter_match(m: &Match, allowed: &HashSet<String>) -> bool {
 	let text = &m.context.text[start..end];
 	allowed.contains(text)
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_response() {
+		let rule = r#""id": "TYPO", "description": "Typo", "issueType": "misspelling",
+			"category": {"id": "TYPOS", "name": "Typos"}"#;
+		let context = r#""context": {"text": "teh", "offset": 0, "length": 3}"#;
+		let body = format!(
+			r#"{{"matches": [
+				{{"message": "Typo", "shortMessage": "", "offset": 0, "length": 3,
+					"replacements": [{{"value": "the"}}], "sentence": "teh", {context},
+					"contextForSureMatch": 2,
+					"rule": {{{rule}, "isPremium": true, "tags": ["picky"]}}}},
+				{{"message": "Typo", "shortMessage": "", "offset": 4, "length": 3,
+					"replacements": [], "sentence": "teh", {context}, "rule": {{{rule}}}}}
+			]}}"#
+		);
+		let response = serde_json::from_str::<Response>(&body).unwrap();
+		let extras = (response.matches.iter())
+			.map(|m| &m.extras)
+			.collect::<Vec<_>>();
+		assert_eq!(
+			extras,
+			[
+				&MatchExtras {
+					context_for_sure_match: Some(2),
+					rule: RuleExtras {
+						is_premium: true,
+						tags: vec!["picky".into()]
+					},
+				},
+				&MatchExtras::default(),
+			]
+		);
+		assert_eq!(response.matches[1].m.offset, 4);
+		assert_eq!(response.matches[0].m.replacements[0].value, "the");
+
+		let body = body.replace(r#""isPremium": true"#, r#""isPremium": "yes""#);
+		assert!(serde_json::from_str::<Response>(&body).is_err());
+	}
+}
@@ -0,0 +1,128 @@
+use std::{collections::HashMap, future::Future, path::PathBuf};
+
+use tokio::runtime::Runtime;
+
+use crate::{
+	CheckOptions, LanguageCode, LanguageToolBackend, LanguageToolBuilder, RawHook, RuleValue,
+	SpellingFiles, Suggestion,
+};
+
+/// Blocking wrapper around [`crate::LanguageTool`], for programs without an async runtime.
+/// The calls run on an internal single threaded runtime, so they must not be used
+/// from within another async runtime.
+#[derive(Debug)]
+pub struct LanguageTool {
+	inner: crate::LanguageTool,
+	runtime: Runtime,
+}
+
+impl LanguageTool {
+	pub fn new(inner: crate::LanguageTool) -> crate::Result<Self> {
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()?;
+		Ok(Self { inner, runtime })
+	}
+
+	pub fn build(builder: LanguageToolBuilder) -> crate::Result<Self> {
+		let runtime = tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()?;
+		let inner = runtime.block_on(builder.build())?;
+		Ok(Self { inner, runtime })
+	}
+
+	pub fn allow_words(&self, lang: LanguageCode, words: &[String]) -> crate::Result<()> {
+		self.block_on(self.inner.allow_words(lang, words))
+	}
+
+	pub fn disable_checks(&self, lang: LanguageCode, checks: &[String]) -> crate::Result<()> {
+		self.block_on(self.inner.disable_checks(lang, checks))
+	}
+
+	pub fn check_text(&self, lang: LanguageCode, text: &str) -> crate::Result<Vec<Suggestion>> {
+		self.block_on(self.inner.check_text(lang, text))
+	}
+
+	pub fn check_text_with(
+		&self,
+		lang: LanguageCode,
+		text: &str,
+		options: &CheckOptions,
+	) -> crate::Result<Vec<Suggestion>> {
+		self.block_on(self.inner.check_text_with(lang, text, options))
+	}
+
+	pub fn check_texts(
+		&self,
+		lang: LanguageCode,
+		texts: &[&str],
+	) -> crate::Result<Vec<Vec<Suggestion>>> {
+		self.block_on(self.inner.check_texts(lang, texts))
+	}
+
+	pub fn version(&self) -> crate::Result<String> {
+		self.block_on(self.inner.version())
+	}
+
+	pub fn languages(&self) -> crate::Result<Vec<crate::Language>> {
+		self.block_on(self.inner.languages())
+	}
+
+	pub fn detect_language(&self, text: &str) -> crate::Result<Option<crate::DetectedLanguage>> {
+		self.block_on(self.inner.detect_language(text))
+	}
+
+	pub fn rules(&self, lang: LanguageCode) -> crate::Result<Vec<crate::Rule>> {
+		self.block_on(self.inner.rules(lang))
+	}
+
+	pub fn set_mother_tongue(&self, mother_tongue: Option<String>) -> crate::Result<()> {
+		self.block_on(self.inner.set_mother_tongue(mother_tongue))
+	}
+
+	pub fn set_interface_language(&self, lang: Option<String>) -> crate::Result<()> {
+		self.block_on(self.inner.set_interface_language(lang))
+	}
+
+	pub fn set_picky(&self, picky: bool) -> crate::Result<()> {
+		self.block_on(self.inner.set_picky(picky))
+	}
+
+	pub fn set_rule_options(
+		&self,
+		lang: LanguageCode,
+		options: &HashMap<String, Vec<RuleValue>>,
+	) -> crate::Result<()> {
+		self.block_on(self.inner.set_rule_options(lang, options))
+	}
+
+	pub fn set_rule_files(&self, lang: LanguageCode, files: &[PathBuf]) -> crate::Result<()> {
+		self.block_on(self.inner.set_rule_files(lang, files))
+	}
+
+	pub fn set_spelling_files(
+		&self,
+		lang: LanguageCode,
+		files: &SpellingFiles,
+	) -> crate::Result<()> {
+		self.block_on(self.inner.set_spelling_files(lang, files))
+	}
+
+	pub fn set_raw_hook(&self, hook: Option<RawHook>) -> crate::Result<()> {
+		self.block_on(self.inner.set_raw_hook(hook))
+	}
+
+	/// Run other async functions using the backend, like [`crate::cache::Cache::check`].
+	pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+		self.runtime.block_on(future)
+	}
+
+	pub fn inner(&self) -> &crate::LanguageTool {
+		&self.inner
+	}
+
+	pub fn into_inner(self) -> crate::LanguageTool {
+		self.inner
+	}
+}
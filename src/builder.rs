@@ -0,0 +1,349 @@
+use std::{collections::HashMap, ops::Not};
+
+use crate::{LanguageTool, LanguageToolBackend, Quota, RateLimit, RuleValue, SpellingFiles};
+
+#[derive(Debug, Clone)]
+enum Backend {
+	Bundled,
+	Jar(String),
+	/// Cache directory, version and SHA-256 of the release.
+	#[cfg_attr(not(feature = "download-jar"), allow(dead_code))]
+	Download(std::path::PathBuf, String, String),
+	Remote(String),
+	Commandline(String),
+	Docker(String),
+	Harper(String),
+}
+
+/// Configure and create a [`LanguageTool`] backend.
+#[derive(Debug, Clone)]
+pub struct LanguageToolBuilder {
+	backend: Backend,
+	picky: bool,
+	mother_tongue: Option<String>,
+	interface_language: Option<String>,
+	dictionary: HashMap<String, Vec<String>>,
+	disabled_checks: HashMap<String, Vec<String>>,
+	rule_options: HashMap<String, HashMap<String, Vec<RuleValue>>>,
+	rule_files: HashMap<String, Vec<std::path::PathBuf>>,
+	spelling_files: HashMap<String, SpellingFiles>,
+	rate_limit: Option<RateLimit>,
+	quota: Option<Quota>,
+	login: Option<(String, String)>,
+	headers: Vec<(String, String)>,
+	proxy: Option<String>,
+	compress_requests: bool,
+}
+
+impl LanguageToolBuilder {
+	fn new(backend: Backend) -> Self {
+		Self {
+			backend,
+			picky: false,
+			mother_tongue: None,
+			interface_language: None,
+			dictionary: HashMap::new(),
+			disabled_checks: HashMap::new(),
+			rule_options: HashMap::new(),
+			rule_files: HashMap::new(),
+			spelling_files: HashMap::new(),
+			rate_limit: None,
+			quota: None,
+			login: None,
+			headers: Vec::new(),
+			proxy: None,
+			compress_requests: false,
+		}
+	}
+
+	/// Use the jar bundled at compile time, requires the feature `bundle-jar`.
+	pub fn bundled() -> Self {
+		Self::new(Backend::Bundled)
+	}
+
+	/// Use an external LanguageTool jar, requires the feature `extern-jar`.
+	pub fn jar(path: impl Into<String>) -> Self {
+		Self::new(Backend::Jar(path.into()))
+	}
+
+	/// Download the release zip of LanguageTool to the cache directory on first use and use
+	/// its jars, requires the feature `download-jar`. The zip must have the SHA-256, see
+	/// [`crate::download::JarRelease`].
+	pub fn download(
+		cache_dir: impl Into<std::path::PathBuf>,
+		version: impl Into<String>,
+		sha256: impl Into<String>,
+	) -> Self {
+		Self::new(Backend::Download(
+			cache_dir.into(),
+			version.into(),
+			sha256.into(),
+		))
+	}
+
+	/// Use a LanguageTool server like `http://localhost:8081`,
+	/// requires the feature `remote-server`.
+	pub fn remote(url: impl Into<String>) -> Self {
+		Self::new(Backend::Remote(url.into()))
+	}
+
+	/// Run the LanguageTool command line jar for every check,
+	/// requires the feature `commandline`.
+	pub fn commandline(path: impl Into<String>) -> Self {
+		Self::new(Backend::Commandline(path.into()))
+	}
+
+	/// Start the image with a LanguageTool server like [`crate::docker::LanguageToolDocker::IMAGE`],
+	/// requires the feature `docker`.
+	pub fn docker(image: impl Into<String>) -> Self {
+		Self::new(Backend::Docker(image.into()))
+	}
+
+	/// Check English text with the Harper language server like `harper-ls`,
+	/// requires the feature `harper`.
+	pub fn harper(command: impl Into<String>) -> Self {
+		Self::new(Backend::Harper(command.into()))
+	}
+
+	/// Also report the stricter rules of the picky level.
+	pub fn picky(mut self, picky: bool) -> Self {
+		self.picky = picky;
+		self
+	}
+
+	/// Native language of the author, enables rules for false friends.
+	pub fn mother_tongue(mut self, lang: impl Into<String>) -> Self {
+		self.mother_tongue = Some(lang.into());
+		self
+	}
+
+	/// Language of the messages of the built-in rules, see
+	/// [`LanguageToolBackend::set_interface_language`].
+	pub fn interface_language(mut self, lang: impl Into<String>) -> Self {
+		self.interface_language = Some(lang.into());
+		self
+	}
+
+	/// Add allowed words for the language.
+	pub fn dictionary(
+		mut self,
+		lang: impl Into<String>,
+		words: impl IntoIterator<Item = impl Into<String>>,
+	) -> Self {
+		let dictionary = self.dictionary.entry(lang.into()).or_default();
+		dictionary.extend(words.into_iter().map(Into::into));
+		self
+	}
+
+	/// Add rules to ignore for the language.
+	pub fn disabled_checks(
+		mut self,
+		lang: impl Into<String>,
+		checks: impl IntoIterator<Item = impl Into<String>>,
+	) -> Self {
+		let disabled = self.disabled_checks.entry(lang.into()).or_default();
+		disabled.extend(checks.into_iter().map(Into::into));
+		self
+	}
+
+	/// Set the values of a configurable rule for the language, only used by the JNI backend.
+	pub fn rule_options(
+		mut self,
+		lang: impl Into<String>,
+		rule_id: impl Into<String>,
+		values: Vec<RuleValue>,
+	) -> Self {
+		let options = self.rule_options.entry(lang.into()).or_default();
+		options.insert(rule_id.into(), values);
+		self
+	}
+
+	/// Add rule XML files for the language, like house style rules,
+	/// only used by the JNI backend.
+	pub fn rule_files(
+		mut self,
+		lang: impl Into<String>,
+		files: impl IntoIterator<Item = impl Into<std::path::PathBuf>>,
+	) -> Self {
+		let rule_files = self.rule_files.entry(lang.into()).or_default();
+		rule_files.extend(files.into_iter().map(Into::into));
+		self
+	}
+
+	/// Files of accepted and prohibited words for the language, only used by the JNI backend.
+	pub fn spelling_files(mut self, lang: impl Into<String>, files: SpellingFiles) -> Self {
+		self.spelling_files.insert(lang.into(), files);
+		self
+	}
+
+	/// Limit the requests to a remote server, the public API is limited by default.
+	pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+		self.rate_limit = Some(limit);
+		self
+	}
+
+	/// Fail with [`crate::Error::QuotaExceeded`] instead of sending requests exceeding the quota.
+	pub fn quota(mut self, quota: Quota) -> Self {
+		self.quota = Some(quota);
+		self
+	}
+
+	/// Account on the remote server, its dictionary is used for all checks.
+	pub fn login(mut self, username: impl Into<String>, api_key: impl Into<String>) -> Self {
+		self.login = Some((username.into(), api_key.into()));
+		self
+	}
+
+	/// Header sent with every request to the remote server, like a cookie for a reverse proxy.
+	pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+		self.headers.push((name.into(), value.into()));
+		self
+	}
+
+	/// Send `Authorization: Bearer <token>` with every request to the remote server.
+	pub fn bearer_token(self, token: impl AsRef<str>) -> Self {
+		let value = format!("Bearer {}", token.as_ref());
+		self.header("Authorization", value)
+	}
+
+	/// Proxy for the requests to the remote server, like `http://proxy.example.com:3128`.
+	/// Without it the proxy of `HTTP_PROXY` and `HTTPS_PROXY` is used.
+	pub fn proxy(mut self, url: impl Into<String>) -> Self {
+		self.proxy = Some(url.into());
+		self
+	}
+
+	/// Send the text gzip compressed to the remote server, it or a proxy in front of it must
+	/// accept `Content-Encoding: gzip`. Responses are compressed whenever the server supports it.
+	pub fn compress_requests(mut self, compress: bool) -> Self {
+		self.compress_requests = compress;
+		self
+	}
+
+	pub async fn build(self) -> crate::Result<LanguageTool> {
+		let mut lt = match &self.backend {
+			#[cfg(feature = "docker")]
+			Backend::Docker(image) => LanguageTool::Docker(Box::new(
+				crate::docker::LanguageToolDocker::start(image).await?,
+			)),
+			#[cfg(feature = "download-jar")]
+			Backend::Download(cache_dir, version, sha256) => {
+				let release = crate::download::JarRelease::new(version, sha256);
+				let class_path = crate::download::class_path(cache_dir, &release).await?;
+				LanguageTool::JNI(crate::jni::LanguageToolJNI::new(&class_path)?)
+			},
+			_ => self.backend()?,
+		};
+		if let Some(limit) = self.rate_limit {
+			lt.set_rate_limit(limit);
+		}
+		lt.set_quota(self.quota);
+		if let Some((username, api_key)) = &self.login {
+			lt.set_login(username, api_key);
+		}
+		if self.headers.is_empty().not() {
+			lt.set_headers(&self.headers)?;
+		}
+		if let Some(url) = &self.proxy {
+			lt.set_proxy(url)?;
+		}
+		lt.set_compress_requests(self.compress_requests);
+		lt.check_version().await?;
+		for (lang, words) in self.dictionary {
+			lt.allow_words(lang.parse()?, &words).await?;
+		}
+		for (lang, checks) in self.disabled_checks {
+			lt.disable_checks(lang.parse()?, &checks).await?;
+		}
+		for (lang, options) in self.rule_options {
+			lt.set_rule_options(lang.parse()?, &options).await?;
+		}
+		for (lang, files) in self.rule_files {
+			lt.set_rule_files(lang.parse()?, &files).await?;
+		}
+		for (lang, files) in self.spelling_files {
+			lt.set_spelling_files(lang.parse()?, &files).await?;
+		}
+		if self.mother_tongue.is_some() {
+			lt.set_mother_tongue(self.mother_tongue).await?;
+		}
+		if self.interface_language.is_some() {
+			lt.set_interface_language(self.interface_language).await?;
+		}
+		if self.picky {
+			lt.set_picky(true).await?;
+		}
+		Ok(lt)
+	}
+
+	/// Create the backend without applying the settings.
+	pub(crate) fn backend(&self) -> crate::Result<LanguageTool> {
+		let lt = match &self.backend {
+			#[cfg(feature = "bundle-jar")]
+			Backend::Bundled => LanguageTool::JNI(crate::jni::LanguageToolJNI::new_bundled()?),
+			#[cfg(not(feature = "bundle-jar"))]
+			Backend::Bundled => Err(crate::Error::BackendUnavailable(
+				"Feature 'bundle-jar' is disabled.".into(),
+			))?,
+
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Backend::Jar(path) => LanguageTool::JNI(crate::jni::LanguageToolJNI::new(path)?),
+			#[cfg(not(any(feature = "bundle-jar", feature = "extern-jar")))]
+			Backend::Jar(path) => Err(crate::Error::BackendUnavailable(format!(
+				"Features 'bundle-jar' and 'extern-jar' are disabled, can not use '{}'.",
+				path
+			)))?,
+
+			#[cfg(feature = "download-jar")]
+			Backend::Download(..) => Err(crate::Error::BackendUnavailable(
+				"The release is only downloaded by 'build'.".into(),
+			))?,
+			#[cfg(not(feature = "download-jar"))]
+			Backend::Download(_, version, _) => Err(crate::Error::BackendUnavailable(format!(
+				"Feature 'download-jar' is disabled, can not download LanguageTool {}.",
+				version
+			)))?,
+
+			#[cfg(feature = "remote-server")]
+			Backend::Remote(url) => {
+				LanguageTool::Remote(Box::new(crate::remote::LanguageToolRemote::new(url, "")?))
+			},
+			#[cfg(not(feature = "remote-server"))]
+			Backend::Remote(url) => Err(crate::Error::BackendUnavailable(format!(
+				"Feature 'remote-server' is disabled, can not use '{}'.",
+				url
+			)))?,
+
+			#[cfg(feature = "commandline")]
+			Backend::Commandline(path) => {
+				LanguageTool::Commandline(crate::commandline::LanguageToolCommandline::new(path))
+			},
+			#[cfg(not(feature = "commandline"))]
+			Backend::Commandline(path) => Err(crate::Error::BackendUnavailable(format!(
+				"Feature 'commandline' is disabled, can not use '{}'.",
+				path
+			)))?,
+
+			#[cfg(feature = "docker")]
+			Backend::Docker(_) => Err(crate::Error::BackendUnavailable(
+				"The docker container is only started by 'build'.".into(),
+			))?,
+			#[cfg(not(feature = "docker"))]
+			Backend::Docker(image) => Err(crate::Error::BackendUnavailable(format!(
+				"Feature 'docker' is disabled, can not use '{}'.",
+				image
+			)))?,
+
+			#[cfg(feature = "harper")]
+			Backend::Harper(command) => LanguageTool::Harper(Box::new(
+				crate::harper::LanguageToolHarper::with_command(command),
+			)),
+			#[cfg(not(feature = "harper"))]
+			Backend::Harper(command) => Err(crate::Error::BackendUnavailable(format!(
+				"Feature 'harper' is disabled, can not use '{}'.",
+				command
+			)))?,
+		};
+		Ok(lt)
+	}
+}
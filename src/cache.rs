@@ -0,0 +1,669 @@
+use std::{
+	collections::HashMap,
+	hash::{DefaultHasher, Hash, Hasher},
+	ops::Not,
+	path::PathBuf,
+	time::Duration,
+};
+
+#[cfg(feature = "disk-cache")]
+use std::path::Path;
+
+use crate::{
+	convert, language_entries, Error, LanguageToolBackend, RuleValue, SpellingFiles, Submission,
+	Suggestion,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+	lang: String,
+	settings: u64,
+	text: String,
+}
+
+#[derive(Debug)]
+struct Entry {
+	/// Suggestions relative to the start of the paragraph.
+	suggestions: Vec<Suggestion>,
+	used: bool,
+	/// Tick of the last use, the least recently used entries are removed first.
+	last_used: u64,
+	/// Estimated memory of the entry and its key in bytes.
+	size: usize,
+}
+
+impl Entry {
+	fn new(key: &Key, suggestions: Vec<Suggestion>, tick: u64) -> Self {
+		let strings = |suggestion: &Suggestion| {
+			let examples = suggestion
+				.incorrect_examples
+				.iter()
+				.chain(&suggestion.correct_examples)
+				.chain(&suggestion.replacements)
+				.chain(&suggestion.rule_urls);
+			let fields = [
+				&suggestion.message,
+				&suggestion.sentence,
+				&suggestion.rule_description,
+				&suggestion.rule_id,
+				&suggestion.rule_category,
+			];
+			examples.chain(fields).map(String::len).sum::<usize>()
+		};
+		let size = std::mem::size_of::<(Key, Entry)>()
+			+ key.lang.len()
+			+ key.text.len()
+			+ suggestions
+				.iter()
+				.map(|suggestion| std::mem::size_of::<Suggestion>() + strings(suggestion))
+				.sum::<usize>();
+		Self {
+			suggestions,
+			used: true,
+			last_used: tick,
+			size,
+		}
+	}
+}
+
+/// Check results for paragraphs, keyed by language, settings and text.
+/// The suggestions are stored relative to the paragraph, so they stay valid when edits move it.
+#[derive(Debug, Default)]
+pub struct Cache {
+	entries: HashMap<Key, Entry>,
+	metrics: Metrics,
+	/// Maximum number of paragraphs joined in one request, all of a chunk without a limit.
+	paragraphs_per_request: Option<usize>,
+	/// Incremented for every check, orders the entries by their last use.
+	tick: u64,
+	/// Estimated memory of all entries in bytes.
+	bytes: usize,
+	max_entries: Option<usize>,
+	max_bytes: Option<usize>,
+	/// LanguageTool version of the results, see [`Cache::set_version`].
+	version: Option<String>,
+	/// Whole documents are cached as one entry instead of by paragraph.
+	submission: Submission,
+}
+
+/// Paragraphs of a text without cached results, see [`Cache::prepare`].
+#[derive(Debug)]
+pub struct Pending {
+	lang: String,
+	suggestions: Vec<Suggestion>,
+	batches: Vec<Batch>,
+}
+
+/// UTF-16 offsets and keys of the paragraphs sent in one request.
+type Batch = Vec<(usize, Key)>;
+
+impl Pending {
+	/// Send the batches to the backend one after the other until one fails,
+	/// the cache is not borrowed while waiting for the backend.
+	pub async fn send(self, lt: &impl LanguageToolBackend) -> Sent {
+		let mut sent = Sent {
+			suggestions: self.suggestions,
+			checked: Vec::new(),
+			failed: None,
+			latency: Duration::ZERO,
+		};
+		for batch in self.batches {
+			let texts = batch
+				.iter()
+				.map(|(_, key)| key.text.as_str())
+				.collect::<Vec<_>>();
+			#[cfg(not(target_arch = "wasm32"))]
+			let start = std::time::Instant::now();
+			let result = match self.lang.parse() {
+				Ok(lang) => lt.check_texts(lang, &texts).await,
+				Err(err) => Err(err),
+			};
+			#[cfg(not(target_arch = "wasm32"))]
+			{
+				sent.latency += start.elapsed();
+			}
+			match result {
+				Ok(checked) => sent.checked.push((batch, checked)),
+				Err(err) => {
+					sent.failed = Some((batch, err));
+					break;
+				},
+			}
+		}
+		sent
+	}
+}
+
+/// Results of the backend for a [`Pending`] text, cached with [`Cache::finish`].
+#[derive(Debug)]
+pub struct Sent {
+	suggestions: Vec<Suggestion>,
+	checked: Vec<(Batch, Vec<Vec<Suggestion>>)>,
+	failed: Option<(Batch, Error)>,
+	latency: Duration,
+}
+
+/// Usage of the backend by a [`Cache`], since it was created or [`Cache::reset_metrics`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metrics {
+	/// Requests sent to the backend.
+	pub requests: usize,
+	/// Failed requests, including the rate limited ones.
+	pub failures: usize,
+	/// Requests rejected with [`Error::RateLimited`].
+	pub rate_limited: usize,
+	/// Requests not sent because of [`Error::QuotaExceeded`], also counted as failures.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub quota_exceeded: usize,
+	/// Characters of the paragraphs sent to the backend, without the separators of joined paragraphs.
+	pub chars: usize,
+	/// Paragraphs with cached results.
+	pub hits: usize,
+	/// Paragraphs sent to the backend.
+	pub misses: usize,
+	/// Total duration of the requests, not measured on wasm.
+	pub latency: Duration,
+}
+
+impl Cache {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Send at most `paragraphs` paragraphs in one request, fewer for a faster first result.
+	pub fn set_paragraphs_per_request(&mut self, paragraphs: Option<usize>) {
+		self.paragraphs_per_request = paragraphs.map(|paragraphs| paragraphs.max(1));
+	}
+
+	/// With [`Submission::Document`] the checked texts are cached and sent as a whole,
+	/// so rules see the text around the paragraphs.
+	pub fn set_submission(&mut self, submission: Submission) {
+		self.submission = submission;
+	}
+
+	/// Keep at most `entries` paragraphs or `bytes` of estimated memory,
+	/// the least recently used paragraphs are removed first. Both are unlimited by default.
+	pub fn set_limits(&mut self, entries: Option<usize>, bytes: Option<usize>) {
+		self.max_entries = entries;
+		self.max_bytes = bytes;
+		self.shrink();
+	}
+
+	/// Results are only reused for the same LanguageTool version, the results of another version
+	/// are dropped, like after switching the backend.
+	pub fn set_version(&mut self, version: &str) {
+		if self.version.as_deref() != Some(version) {
+			self.clear();
+			self.version = Some(version.into());
+		}
+	}
+
+	/// Check the text, only paragraphs without cached results are sent to the backend,
+	/// joined in a single request or batches of [`Cache::set_paragraphs_per_request`].
+	/// `settings` is the [`settings_hash`] of everything influencing the results.
+	pub async fn check(
+		&mut self,
+		lt: &impl LanguageToolBackend,
+		lang: &str,
+		settings: u64,
+		text: &str,
+	) -> crate::Result<Vec<Suggestion>> {
+		let sent = self.prepare(lang, settings, text).send(lt).await;
+		self.finish(sent)
+	}
+
+	/// The cached suggestions of the text and the batches of paragraphs without results, split
+	/// from [`Cache::check`] so several texts can be sent at the same time, see [`Pending::send`].
+	pub fn prepare(&mut self, lang: &str, settings: u64, text: &str) -> Pending {
+		self.tick += 1;
+		let mut suggestions = Vec::new();
+		let mut changed = Vec::new();
+		let paragraphs = match self.submission {
+			Submission::Paragraphs => convert::paragraphs(text),
+			Submission::Document if text.trim().is_empty() => Vec::new(),
+			Submission::Document => vec![(0, text)],
+		};
+		for (offset, paragraph) in paragraphs {
+			let key = Key {
+				lang: lang.into(),
+				settings,
+				text: paragraph.into(),
+			};
+			match self.entries.get_mut(&key) {
+				Some(entry) => {
+					self.metrics.hits += 1;
+					entry.used = true;
+					entry.last_used = self.tick;
+					suggestions.extend(shift(&entry.suggestions, offset as isize));
+				},
+				None => changed.push((offset, key)),
+			}
+		}
+		let batch = self.paragraphs_per_request.unwrap_or(changed.len()).max(1);
+		let mut changed = changed.into_iter().peekable();
+		let mut batches = Vec::new();
+		while changed.peek().is_some() {
+			batches.push(changed.by_ref().take(batch).collect());
+		}
+		Pending { lang: lang.into(), suggestions, batches }
+	}
+
+	/// Cache the results of [`Pending::send`], the suggestions of the text or the first error.
+	pub fn finish(&mut self, sent: Sent) -> crate::Result<Vec<Suggestion>> {
+		let Sent {
+			mut suggestions,
+			checked,
+			failed,
+			latency,
+		} = sent;
+		self.metrics.latency += latency;
+		for (batch, checked) in checked {
+			self.count(&batch);
+			for ((offset, key), checked) in batch.into_iter().zip(checked) {
+				suggestions.extend(shift(&checked, offset as isize));
+				let entry = Entry::new(&key, checked, self.tick);
+				self.bytes += entry.size;
+				if let Some(old) = self.entries.insert(key, entry) {
+					self.bytes -= old.size;
+				}
+			}
+		}
+		if let Some((batch, err)) = failed {
+			self.count(&batch);
+			self.metrics.failures += 1;
+			match err {
+				Error::RateLimited => self.metrics.rate_limited += 1,
+				Error::QuotaExceeded(_) => self.metrics.quota_exceeded += 1,
+				_ => {},
+			}
+			return Err(err);
+		}
+		self.shrink();
+		suggestions.sort_by_key(|suggestion| suggestion.start);
+		Ok(suggestions)
+	}
+
+	/// Count the request of the batch in the metrics.
+	fn count(&mut self, batch: &[(usize, Key)]) {
+		self.metrics.requests += 1;
+		self.metrics.misses += batch.len();
+		self.metrics.chars += batch
+			.iter()
+			.map(|(_, key)| key.text.chars().count())
+			.sum::<usize>();
+	}
+
+	/// The cached suggestions of the text and the UTF-16 offsets and paragraphs without results,
+	/// without sending anything or counting hits, like for a fast check while typing.
+	pub fn lookup<'a>(
+		&self,
+		lang: &str,
+		settings: u64,
+		text: &'a str,
+	) -> (Vec<Suggestion>, Vec<(usize, &'a str)>) {
+		let mut suggestions = Vec::new();
+		let mut missing = Vec::new();
+		let paragraphs = match self.submission {
+			Submission::Paragraphs => convert::paragraphs(text),
+			Submission::Document if text.trim().is_empty() => Vec::new(),
+			Submission::Document => vec![(0, text)],
+		};
+		for (offset, paragraph) in paragraphs {
+			let key = Key {
+				lang: lang.into(),
+				settings,
+				text: paragraph.into(),
+			};
+			match self.entries.get(&key) {
+				Some(entry) => suggestions.extend(shift(&entry.suggestions, offset as isize)),
+				None => missing.push((offset, paragraph)),
+			}
+		}
+		(suggestions, missing)
+	}
+
+	/// Remove the entries not used since the last call.
+	pub fn evict(&mut self) {
+		let bytes = &mut self.bytes;
+		self.entries.retain(|_, entry| {
+			if entry.used.not() {
+				*bytes -= entry.size;
+			}
+			entry.used
+		});
+		for entry in self.entries.values_mut() {
+			entry.used = false;
+		}
+	}
+
+	/// Remove the least recently used entries until the limits are met.
+	fn shrink(&mut self) {
+		let over = |entries: usize, bytes: usize| {
+			self.max_entries.is_some_and(|max| entries > max)
+				|| self.max_bytes.is_some_and(|max| bytes > max)
+		};
+		if over(self.entries.len(), self.bytes).not() {
+			return;
+		}
+		let mut order = self
+			.entries
+			.iter()
+			.map(|(key, entry)| (entry.last_used, key.clone()))
+			.collect::<Vec<_>>();
+		order.sort_by_key(|(last_used, _)| *last_used);
+		let mut entries = self.entries.len();
+		for (_, key) in order {
+			if over(entries, self.bytes).not() {
+				break;
+			}
+			if let Some(entry) = self.entries.remove(&key) {
+				self.bytes -= entry.size;
+				entries -= 1;
+			}
+		}
+	}
+
+	/// Load the results saved by [`Cache::save`] in the directory for the LanguageTool `version`.
+	/// Results of another version or format and unreadable files are ignored.
+	/// The loaded results count as unused for [`Cache::evict`].
+	#[cfg(feature = "disk-cache")]
+	pub fn load(&mut self, dir: &Path, version: &str) -> crate::Result<()> {
+		self.set_version(version);
+		let content = match std::fs::read_to_string(dir.join(DISK_FILE)) {
+			Ok(content) => content,
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+			Err(err) => return Err(err.into()),
+		};
+		let Ok(file) = serde_json::from_str::<DiskFile>(&content) else {
+			return Ok(());
+		};
+		if file.format != DISK_FORMAT || file.version != version {
+			return Ok(());
+		}
+		for saved in file.entries {
+			let key = Key {
+				lang: saved.lang,
+				settings: saved.settings,
+				text: saved.text,
+			};
+			let mut entry = Entry::new(&key, saved.suggestions, 0);
+			entry.used = false;
+			self.bytes += entry.size;
+			if let Some(old) = self.entries.insert(key, entry) {
+				self.bytes -= old.size;
+			}
+		}
+		self.shrink();
+		Ok(())
+	}
+
+	/// Save the results in the directory, replacing the previous file.
+	/// Call [`Cache::evict`] before to drop the results of removed paragraphs
+	/// and of changed dictionaries or disabled checks, their settings hash is not used anymore.
+	#[cfg(feature = "disk-cache")]
+	pub fn save(&self, dir: &Path, version: &str) -> crate::Result<()> {
+		let entries = self.entries.iter().map(|(key, entry)| DiskEntry {
+			lang: key.lang.clone(),
+			settings: key.settings,
+			text: key.text.clone(),
+			suggestions: entry.suggestions.clone(),
+		});
+		let file = DiskFile {
+			format: DISK_FORMAT,
+			version: version.into(),
+			entries: entries.collect(),
+		};
+		let content = serde_json::to_string(&file)
+			.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+		std::fs::create_dir_all(dir)?;
+		crate::config::write_atomic(&dir.join(DISK_FILE), &content)
+	}
+
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.bytes = 0;
+	}
+
+	/// Estimated memory of the cached results in bytes.
+	pub fn bytes(&self) -> usize {
+		self.bytes
+	}
+
+	pub fn metrics(&self) -> &Metrics {
+		&self.metrics
+	}
+
+	pub fn reset_metrics(&mut self) {
+		self.metrics = Metrics::default();
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+}
+
+/// File in the directory of [`Cache::save`].
+#[cfg(feature = "disk-cache")]
+const DISK_FILE: &str = "typst-languagetool-cache.json";
+
+/// Incremented when the saved entries or suggestions change, older files are ignored.
+#[cfg(feature = "disk-cache")]
+const DISK_FORMAT: u32 = 1;
+
+#[cfg(feature = "disk-cache")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct DiskFile {
+	format: u32,
+	/// LanguageTool version of the results.
+	version: String,
+	entries: Vec<DiskEntry>,
+}
+
+#[cfg(feature = "disk-cache")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct DiskEntry {
+	lang: String,
+	settings: u64,
+	text: String,
+	suggestions: Vec<Suggestion>,
+}
+
+/// Hash of the settings influencing the results, like the dictionary and disabled checks.
+/// Stable for a build, the results saved by another build might not be found.
+pub fn settings_hash(settings: &impl Hash) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	settings.hash(&mut hasher);
+	hasher.finish()
+}
+
+/// Settings of the backend influencing the results for a language, besides the text and the
+/// LanguageTool version. The converter options only change the text.
+#[derive(Debug, Clone, Copy)]
+pub struct Settings<'a> {
+	pub dictionary: &'a HashMap<String, Vec<String>>,
+	pub disabled_checks: &'a HashMap<String, Vec<String>>,
+	pub rule_options: &'a HashMap<String, HashMap<String, Vec<RuleValue>>>,
+	pub rule_files: &'a HashMap<String, Vec<PathBuf>>,
+	pub spelling_files: &'a HashMap<String, SpellingFiles>,
+	pub picky: bool,
+}
+
+impl Settings<'_> {
+	/// [`settings_hash`] of the settings for the language, with the contents of the rule and
+	/// spelling files, so the results of edited files are not reused.
+	pub fn hash(&self, lang: &str) -> u64 {
+		let rule_options = self.rule_options.get(lang).map(|options| {
+			let mut options = options.iter().collect::<Vec<_>>();
+			options.sort_by(|a, b| a.0.cmp(b.0));
+			options
+		});
+		let rule_files = language_entries(self.rule_files, lang);
+		let spelling_files = language_entries(self.spelling_files, lang);
+		let contents = (rule_files.iter().copied().flatten())
+			.chain(
+				(spelling_files.iter())
+					.flat_map(|files| files.spelling.iter().chain(&files.prohibit)),
+			)
+			.map(|path| std::fs::read(path).ok())
+			.collect::<Vec<_>>();
+		settings_hash(&(
+			language_entries(self.dictionary, lang),
+			self.disabled_checks.get(lang),
+			rule_options,
+			rule_files,
+			spelling_files,
+			contents,
+			self.picky,
+		))
+	}
+}
+
+/// Move the suggestions by `offset` UTF-16 code units.
+pub fn shift(suggestions: &[Suggestion], offset: isize) -> impl Iterator<Item = Suggestion> + '_ {
+	suggestions.iter().map(move |suggestion| Suggestion {
+		start: suggestion.start.saturating_add_signed(offset),
+		end: suggestion.end.saturating_add_signed(offset),
+		..suggestion.clone()
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::mock::MockBackend;
+
+	#[tokio::test]
+	async fn test_cache() {
+		let lt = MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let mut cache = Cache::new();
+		let text = "One teh.\n\nTwo teh.";
+		let suggestions = cache.check(&lt, "en-US", 0, text).await.unwrap();
+		let starts = suggestions.iter().map(|s| s.start).collect::<Vec<_>>();
+		assert_eq!(starts, vec![4, 14]);
+
+		let text = "Zero.\n\nOne teh.\n\nTwo teh.";
+		let suggestions = cache.check(&lt, "en-US", 0, text).await.unwrap();
+		let starts = suggestions.iter().map(|s| s.start).collect::<Vec<_>>();
+		assert_eq!(starts, vec![11, 21]);
+		assert_eq!(lt.checked_texts(), vec!["One teh.\n\nTwo teh.", "Zero."]);
+		let metrics = cache.metrics();
+		assert_eq!(
+			(
+				metrics.requests,
+				metrics.hits,
+				metrics.misses,
+				metrics.chars
+			),
+			(2, 2, 3, 21)
+		);
+
+		cache.evict();
+		cache.check(&lt, "en-US", 0, "Two teh.").await.unwrap();
+		cache.evict();
+		assert_eq!(cache.len(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_submission() {
+		let lt = MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let mut cache = Cache::new();
+		cache.set_submission(Submission::Document);
+		let text = "One teh.\n\nTwo teh.";
+		let suggestions = cache.check(&lt, "en-US", 0, text).await.unwrap();
+		assert_eq!(suggestions.len(), 2);
+		cache.check(&lt, "en-US", 0, text).await.unwrap();
+		cache
+			.check(&lt, "en-US", 0, "Zero.\n\nOne teh.")
+			.await
+			.unwrap();
+		assert_eq!(lt.checked_texts(), vec![text, "Zero.\n\nOne teh."]);
+	}
+
+	#[tokio::test]
+	async fn test_limits() {
+		let lt = MockBackend::new();
+		let mut cache = Cache::new();
+		cache.set_limits(Some(2), None);
+		for text in ["One.", "Two.", "One.", "Three."] {
+			cache.check(&lt, "en-US", 0, text).await.unwrap();
+		}
+		assert_eq!(cache.len(), 2);
+		cache.check(&lt, "en-US", 0, "One.").await.unwrap();
+		assert_eq!(lt.checked_texts(), vec!["One.", "Two.", "Three."]);
+
+		cache.set_limits(None, Some(cache.bytes() / 2));
+		assert_eq!(cache.len(), 1);
+		cache.clear();
+		assert_eq!(cache.bytes(), 0);
+	}
+
+	#[cfg(feature = "disk-cache")]
+	#[tokio::test]
+	async fn test_disk_cache() {
+		let dir = std::env::temp_dir().join(format!("typst-lt-cache-{}", std::process::id()));
+		let lt = MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let mut cache = Cache::new();
+		cache.check(&lt, "en-US", 0, "One teh.").await.unwrap();
+		cache.save(&dir, "6.4").unwrap();
+
+		let mut loaded = Cache::new();
+		loaded.load(&dir, "6.4").unwrap();
+		let suggestions = loaded.check(&lt, "en-US", 0, "One teh.").await.unwrap();
+		assert_eq!(suggestions.len(), 1);
+		assert_eq!(lt.checked_texts(), vec!["One teh."]);
+
+		let mut other = Cache::new();
+		other.load(&dir, "6.5").unwrap();
+		assert!(other.is_empty());
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_settings() {
+		let lt = MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let mut cache = Cache::new();
+		cache.set_version("6.4");
+		cache.check(&lt, "en-US", 0, "One teh.").await.unwrap();
+		cache.set_version("6.4");
+		assert_eq!(cache.len(), 1);
+		cache.set_version("6.5");
+		assert!(cache.is_empty());
+
+		let path = std::env::temp_dir().join(format!("typst-lt-rules-{}.xml", std::process::id()));
+		let (empty, options) = (HashMap::new(), HashMap::new());
+		let rule_files = HashMap::from([("en".to_string(), vec![path.clone()])]);
+		let settings = Settings {
+			dictionary: &empty,
+			disabled_checks: &empty,
+			rule_options: &options,
+			rule_files: &rule_files,
+			spelling_files: &HashMap::new(),
+			picky: false,
+		};
+		std::fs::write(&path, "<rules/>").unwrap();
+		let before = settings.hash("en-US");
+		assert_eq!(before, settings.hash("en-US"));
+		assert_ne!(before, settings.hash("de-DE"));
+		std::fs::write(&path, "<rules><rule/></rules>").unwrap();
+		assert_ne!(before, settings.hash("en-US"));
+		assert_ne!(before, Settings { picky: true, ..settings }.hash("en-US"));
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_paragraphs_per_request() {
+		let lt = MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let mut cache = Cache::new();
+		cache.set_paragraphs_per_request(Some(2));
+		let text = "One teh.\n\nTwo.\n\nThree teh.";
+		let suggestions = cache.check(&lt, "en-US", 0, text).await.unwrap();
+		let starts = suggestions.iter().map(|s| s.start).collect::<Vec<_>>();
+		assert_eq!(starts, vec![4, 22]);
+		assert_eq!(lt.checked_texts(), vec!["One teh.\n\nTwo.", "Three teh."]);
+	}
+}
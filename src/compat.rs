@@ -0,0 +1,65 @@
+//! Parts of the Typst API that change between releases, only typst 0.11 is supported.
+//! The converters reach the syntax tree and the smart quotes through here, so the other
+//! modules do not depend on them.
+
+use typst::{
+	eval::Tracer,
+	foundations::Smart,
+	layout::{Frame, FrameItem, Point},
+	model::Document,
+	text::{Lang, Region, SmartQuoteDict, SmartQuotes, TextItem},
+	World,
+};
+
+/// The syntax tree walked by the converters, its node kinds and typed nodes change between
+/// releases.
+pub use typst::syntax::{ast, FileId, LinkedNode, Source, Span, SyntaxKind, SyntaxNode};
+pub use typst::text::SmartQuoter;
+
+/// Compile the document, the errors are joined into one message.
+pub fn compile(world: &dyn World) -> Result<Document, String> {
+	let _span = crate::trace::span!("compile").entered();
+	let mut tracer = Tracer::new();
+	typst::compile(world, &mut tracer).map_err(|errors| {
+		let messages = errors.iter().map(|error| error.message.as_str());
+		messages.collect::<Vec<_>>().join(", ")
+	})
+}
+
+/// Hash of a page, unchanged pages are not converted again.
+pub fn hash(frame: &Frame) -> u128 {
+	typst::util::hash128(frame)
+}
+
+/// Frame items relevant for the text.
+pub enum Item<'a> {
+	Group(&'a Frame),
+	Text(&'a TextItem),
+	Other,
+}
+
+pub fn items(frame: &Frame) -> impl Iterator<Item = (Point, Item<'_>)> {
+	frame.items().map(|(pos, item)| {
+		let item = match item {
+			FrameItem::Group(group) => Item::Group(&group.frame),
+			FrameItem::Text(text) => Item::Text(text),
+			FrameItem::Meta(..) | FrameItem::Shape(..) | FrameItem::Image(..) => Item::Other,
+		};
+		(*pos, item)
+	})
+}
+
+/// The quote of the language for a straight quote like in the document,
+/// the arguments of the smart quotes changed between releases.
+pub fn smart_quote(
+	quoter: &mut SmartQuoter,
+	lang: Lang,
+	region: Option<Region>,
+	alternative: bool,
+	double: bool,
+	peeked: Option<char>,
+) -> &'static str {
+	static AUTO: Smart<SmartQuoteDict> = Smart::Auto;
+	let quotes = SmartQuotes::new(&AUTO, lang, region, alternative);
+	quoter.quote(&quotes, double, peeked)
+}
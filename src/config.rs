@@ -0,0 +1,942 @@
+use std::{
+	collections::HashMap,
+	ops::Not,
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use toml_edit::{value, Array, ArrayOfTables, DocumentMut, Item, Table, TableLike};
+
+use crate::{
+	cache, convert, glossary, ignore::Ignore, Diagnostic, LanguageCode, LanguageToolBackend,
+	RuleSource, RuleValue, SpellingFiles,
+};
+
+/// Prefix of the environment variables read by [`Config::merge_env`].
+pub const ENV_PREFIX: &str = "TYPST_LT_";
+
+/// Project configuration, stored as TOML.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+	/// Additional allowed words for each language, the words for `de` are also used for `de-DE`.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub dictionary: HashMap<String, Vec<String>>,
+	/// LanguageTool rules to ignore for each language (WHITESPACE_RULE, ...).
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub disabled_checks: HashMap<String, Vec<String>>,
+	/// Rules or categories disabled in parts of the document, like `heading = ["Capitalization"]`,
+	/// see [`crate::suppress::Contexts::names`].
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub disabled_contexts: HashMap<String, Vec<String>>,
+	/// Parts of the document whose text is often not a complete sentence, like `figure.caption`,
+	/// the [`crate::suppress::FRAGMENT_RULES`] are disabled in them.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub fragments: Vec<String>,
+	/// Values of configurable rules for each language, like `TOO_LONG_SENTENCE = [40]`.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub rule_options: HashMap<String, HashMap<String, Vec<RuleValue>>>,
+	/// Rule XML files for each language, like house style rules, relative to the file.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub rule_files: HashMap<String, Vec<PathBuf>>,
+	/// Files of accepted and prohibited words for each language, relative to the file.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub spelling_files: HashMap<String, SpellingFiles>,
+	/// Language code used for all text instead of the language set in the document, also for
+	/// plain text and Markdown files, like `de-DE`.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub language: Option<String>,
+	/// LanguageTool codes for the languages of the document, like `en = "en-US"` or `pt-BR = "pt-BR"`.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub languages: HashMap<String, String>,
+	/// Codes checked instead of a code the backend does not support, like `de-AT = ["de-DE"]`.
+	/// The default variant of the language and the code without region are tried afterwards.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub language_fallbacks: HashMap<String, Vec<String>>,
+	/// Regexes for tokens like ticket ids or file paths, diagnostics inside their matches are dropped.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub ignore: Vec<String>,
+	/// Glossary files whose terms are allowed in all languages, relative to the file, see
+	/// [`glossary::load`].
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub glossary: Vec<PathBuf>,
+	/// Diagnostics of a rule in sentences matching a regex, dropped in all languages,
+	/// like the hidden false positives of LTeX.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub false_positives: Vec<FalsePositive>,
+	/// Drop spelling diagnostics of uppercase words like `RISC-V`.
+	#[serde(skip_serializing_if = "Not::not")]
+	pub ignore_acronyms: bool,
+	/// Drop spelling diagnostics of words containing digits like `GPT4`.
+	#[serde(skip_serializing_if = "Not::not")]
+	pub ignore_digits: bool,
+	/// Keep the diagnostics of a rule for adjacent tokens apart instead of merging them.
+	#[serde(skip_serializing_if = "Not::not")]
+	pub keep_adjacent: bool,
+	/// Severity of the diagnostics by rule id or category, like `"Possible Typo" = "error"`.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub severities: HashMap<String, Severity>,
+	/// Parts of the document that are checked, the frontend defaults are used when missing.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub convert: Option<convert::Options>,
+	/// Name of the selected profile, one of [`Config::profiles`] or a built-in one,
+	/// see [`Profile::builtin`].
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub profile: Option<String>,
+	/// Profiles defined by the user, they replace built-in profiles of the same name.
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub profiles: HashMap<String, Profile>,
+}
+
+/// A diagnostic of the rule in a sentence matching the regex is a false positive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FalsePositive {
+	pub rule: String,
+	/// Regex for the sentence of the diagnostic, like `^Also see Fig\\. 2\\.$`.
+	pub sentence: String,
+}
+
+impl FalsePositive {
+	/// The false positive of the rule in exactly this sentence, the regex allows any whitespace
+	/// between the words like a line break.
+	pub fn new(rule: &str, sentence: &str) -> Self {
+		let words = sentence
+			.split_whitespace()
+			.map(regex::escape)
+			.collect::<Vec<_>>();
+		Self {
+			rule: rule.into(),
+			sentence: format!("^{}$", words.join(r"\s+")),
+		}
+	}
+
+	/// The false positive of the rule of the diagnostic in its sentence.
+	pub fn of(diagnostic: &Diagnostic) -> Self {
+		Self::new(&diagnostic.rule_id, &diagnostic.sentence)
+	}
+}
+
+/// Rules, picky level and converter options for a style of writing, selected by name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+	/// Rule ids or categories, ignoring the case, whose diagnostics are dropped in all languages.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub disabled: Vec<String>,
+	/// Also report the stricter rules of the picky level.
+	#[serde(skip_serializing_if = "Not::not")]
+	pub picky: bool,
+	/// Parts of the document that are checked, unless the configuration sets them.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub convert: Option<convert::Options>,
+}
+
+impl Profile {
+	pub const BUILTIN: [&'static str; 3] = ["academic", "technical", "casual"];
+
+	/// Built-in profiles:
+	/// - `academic` reports the picky rules and skips equations.
+	/// - `technical` drops typography rules and the start and bracket rules tripped by code,
+	///   it skips equations and tables.
+	/// - `casual` drops the style, plain English, colloquialism and typography rules.
+	pub fn builtin(name: &str) -> Option<Self> {
+		let disabled = |rules: &[&str]| rules.iter().map(|&rule| rule.to_owned()).collect();
+		let profile = match name {
+			"academic" => Self {
+				disabled: Vec::new(),
+				picky: true,
+				convert: Some(convert::Options {
+					math: false,
+					..convert::Options::default()
+				}),
+			},
+			"technical" => Self {
+				disabled: disabled(&[
+					"Typography",
+					"UPPERCASE_SENTENCE_START",
+					"EN_UNPAIRED_BRACKETS",
+					"COMMA_PARENTHESIS_WHITESPACE",
+				]),
+				picky: false,
+				convert: Some(convert::Options {
+					math: false,
+					tables: false,
+					..convert::Options::default()
+				}),
+			},
+			"casual" => Self {
+				disabled: disabled(&["Style", "Plain English", "Colloquialisms", "Typography"]),
+				picky: false,
+				convert: None,
+			},
+			_ => return None,
+		};
+		Some(profile)
+	}
+
+	/// The profile of the user or the built-in one, fails with [`crate::Error::Config`] for
+	/// unknown names.
+	pub fn find(name: &str, profiles: &HashMap<String, Profile>) -> crate::Result<Self> {
+		if let Some(profile) = profiles.get(name) {
+			return Ok(profile.clone());
+		}
+		Self::builtin(name).ok_or_else(|| {
+			let mut names = profiles.keys().map(String::as_str).collect::<Vec<_>>();
+			names.extend(Self::BUILTIN);
+			names.sort();
+			names.dedup();
+			let message = format!(
+				"unknown profile '{}', expected one of {}",
+				name,
+				names.join(", ")
+			);
+			crate::Error::Config(message.into())
+		})
+	}
+}
+
+/// Severity of a diagnostic, shown by the frontends and used for the exit code of the CLI.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	Default,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	Hash,
+	Serialize,
+	Deserialize
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+	Hint,
+	#[default]
+	Info,
+	Warning,
+	Error,
+}
+
+impl Severity {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Hint => "hint",
+			Self::Info => "info",
+			Self::Warning => "warning",
+			Self::Error => "error",
+		}
+	}
+
+	/// Severity of the diagnostic by its rule id, otherwise by its category ignoring the case.
+	/// Diagnostics of other rules are [`Severity::Info`], the ones of picky rules
+	/// [`Severity::Hint`], since they are stylistic suggestions rather than errors.
+	pub fn of(severities: &HashMap<String, Severity>, diagnostic: &Diagnostic) -> Self {
+		if let Some(severity) = severities.get(&diagnostic.rule_id) {
+			return *severity;
+		}
+		let severity = severities
+			.iter()
+			.find(|(category, _)| category.eq_ignore_ascii_case(&diagnostic.rule_category))
+			.map(|(_, severity)| *severity);
+		match (severity, diagnostic.rule_source) {
+			(Some(severity), _) => severity,
+			(None, RuleSource::Picky) => Severity::Hint,
+			(None, _) => Severity::default(),
+		}
+	}
+}
+
+impl std::str::FromStr for Severity {
+	type Err = String;
+
+	fn from_str(value: &str) -> Result<Self, Self::Err> {
+		match value {
+			"hint" => Ok(Self::Hint),
+			"info" => Ok(Self::Info),
+			"warning" => Ok(Self::Warning),
+			"error" => Ok(Self::Error),
+			_ => Err(format!(
+				"Unknown severity `{}`, expected hint, info, warning or error.",
+				value
+			)),
+		}
+	}
+}
+
+impl Config {
+	/// The diagnostics dropped by [`Config::ignore`], the toggles for words, the terms of the
+	/// [`Config::glossary`] files and the [`Config::false_positives`].
+	/// The disabled rules of the [`Config::profile`] too.
+	pub fn ignored(&self) -> crate::Result<Ignore> {
+		let mut ignore = Ignore::new(&self.ignore)?;
+		ignore.acronyms = self.ignore_acronyms;
+		ignore.digits = self.ignore_digits;
+		for path in &self.glossary {
+			ignore.add_terms(glossary::load(path)?);
+		}
+		ignore.disable(self.profile()?.disabled);
+		ignore.hide(&self.false_positives)?;
+		Ok(ignore)
+	}
+
+	/// The selected profile, an empty one without a name, see [`Profile::find`].
+	pub fn profile(&self) -> crate::Result<Profile> {
+		match &self.profile {
+			Some(name) => Profile::find(name, &self.profiles),
+			None => Ok(Profile::default()),
+		}
+	}
+
+	/// The converter options of the configuration, otherwise the ones of the profile.
+	pub fn convert_options(&self) -> convert::Options {
+		let profile = || self.profile().ok()?.convert;
+		self.convert.clone().or_else(profile).unwrap_or_default()
+	}
+
+	/// The paths of [`Config::rule_files`], [`Config::spelling_files`] and [`Config::glossary`]
+	/// are resolved against the directory of the file.
+	pub fn load(path: &Path) -> crate::Result<Self> {
+		let content = std::fs::read_to_string(path)?;
+		let mut config: Self = toml::from_str(&content)?;
+		let dir = path.parent().unwrap_or(Path::new(""));
+		for files in config.rule_files.values_mut() {
+			for file in files {
+				*file = dir.join(&file);
+			}
+		}
+		for files in config.spelling_files.values_mut() {
+			files.resolve(dir);
+		}
+		for file in &mut config.glossary {
+			*file = dir.join(&file);
+		}
+		Ok(config)
+	}
+
+	/// Merge the environment variables starting with [`ENV_PREFIX`], see [`Config::merge_vars`].
+	/// Variables that are not valid unicode are skipped.
+	pub fn merge_env(&mut self) {
+		let vars = std::env::vars_os().filter_map(|(name, value)| {
+			Some((name.into_string().ok()?, value.into_string().ok()?))
+		});
+		self.merge_vars(vars);
+	}
+
+	/// Settings of variables like in CI, they take precedence over the file.
+	/// - `TYPST_LT_LANG=en-GB,pt-BR=pt-PT` replaces the codes of `[languages]`, a code without
+	///   `=` is used for its language.
+	/// - `TYPST_LT_DICTIONARY_<LANG>=word,word` and `TYPST_LT_DISABLED_CHECKS_<LANG>=RULE,RULE`
+	///   add to `[dictionary]` and `[disabled_checks]`, like `DE_CH` for `de-CH`.
+	/// - `TYPST_LT_PROFILE=technical` selects the profile.
+	pub fn merge_vars(&mut self, vars: impl IntoIterator<Item = (String, String)>) {
+		for (name, value) in vars {
+			let Some(name) = name.strip_prefix(ENV_PREFIX) else {
+				continue;
+			};
+			let values = value
+				.split(',')
+				.map(str::trim)
+				.filter(|value| value.is_empty().not());
+			if name == "LANG" {
+				for value in values {
+					let (language, code) = match value.split_once('=') {
+						Some((language, code)) => (language.trim(), code.trim()),
+						None => (value.split('-').next().unwrap_or(value), value),
+					};
+					self.languages.insert(language.into(), code.into());
+				}
+			} else if name == "PROFILE" {
+				self.profile = Some(value.trim().to_owned()).filter(|name| name.is_empty().not());
+			} else if let Some(lang) = name.strip_prefix("DICTIONARY_") {
+				let lang = env_language(lang);
+				for word in values {
+					self.allow_word(&lang, word);
+				}
+			} else if let Some(lang) = name.strip_prefix("DISABLED_CHECKS_") {
+				let lang = env_language(lang);
+				for rule_id in values {
+					self.disable_check(&lang, rule_id);
+				}
+			}
+		}
+	}
+
+	/// Write the whole configuration, the comments, formatting and order of the settings already
+	/// in the file are kept, like [`Config::save_word`] does. Settings missing from the
+	/// configuration are removed. The paths resolved by [`Config::load`] are written relative to
+	/// the file again.
+	pub fn save(&self, path: &Path) -> crate::Result<()> {
+		let mut document = read_document(path)?;
+		let dir = path.parent().unwrap_or(Path::new(""));
+		let mut config = self.clone();
+		let files = config.rule_files.values_mut().flatten();
+		let spelling = config
+			.spelling_files
+			.values_mut()
+			.flat_map(|files| files.spelling.iter_mut().chain(&mut files.prohibit));
+		for file in files.chain(spelling).chain(&mut config.glossary) {
+			if let Ok(relative) = file.strip_prefix(dir) {
+				*file = relative.to_owned();
+			}
+		}
+		let mut saved = toml::to_string(&config)?
+			.parse::<DocumentMut>()
+			.map_err(|err| crate::Error::Config(Box::new(err)))?;
+		// Added tables come after the tables of the file.
+		let after = last_position(document.as_table()).map_or(0, |last| last + 1);
+		shift_positions(saved.as_table_mut(), after);
+		update(document.as_table_mut(), saved.as_table());
+		write_atomic(path, &document.to_string())
+	}
+
+	/// Add the word to the dictionary in the file, keeping its comments and order.
+	/// The file is created if missing, returns `false` if the word was already present.
+	pub fn save_word(path: &Path, lang: &str, word: &str) -> crate::Result<bool> {
+		append(path, "dictionary", lang, word)
+	}
+
+	/// Add the rule to the disabled checks in the file, like [`Config::save_word`].
+	pub fn save_disabled_check(path: &Path, lang: &str, rule_id: &str) -> crate::Result<bool> {
+		append(path, "disabled_checks", lang, rule_id)
+	}
+
+	/// Add the false positive to the file, like [`Config::save_word`].
+	pub fn save_false_positive(path: &Path, false_positive: &FalsePositive) -> crate::Result<bool> {
+		let mut document = read_document(path)?;
+		let invalid = || crate::Error::Config("'false_positives' is not an array of tables".into());
+		let tables = document
+			.entry("false_positives")
+			.or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()))
+			.as_array_of_tables_mut()
+			.ok_or_else(invalid)?;
+		let known = tables.iter().any(|table| {
+			table.get("rule").and_then(Item::as_str) == Some(&false_positive.rule)
+				&& table.get("sentence").and_then(Item::as_str) == Some(&false_positive.sentence)
+		});
+		if known {
+			return Ok(false);
+		}
+		let mut table = Table::new();
+		table.insert("rule", value(&false_positive.rule));
+		table.insert("sentence", value(&false_positive.sentence));
+		tables.push(table);
+		write_atomic(path, &document.to_string())?;
+		Ok(true)
+	}
+
+	/// Add the word to the dictionary, returns `false` if it was already present.
+	pub fn allow_word(&mut self, lang: &str, word: &str) -> bool {
+		insert_unique(self.dictionary.entry(lang.into()).or_default(), word)
+	}
+
+	/// Add the rule to the disabled checks, returns `false` if it was already present.
+	pub fn disable_check(&mut self, lang: &str, rule_id: &str) -> bool {
+		insert_unique(
+			self.disabled_checks.entry(lang.into()).or_default(),
+			rule_id,
+		)
+	}
+
+	/// Add the false positive, returns `false` if it was already present.
+	pub fn hide_false_positive(&mut self, false_positive: FalsePositive) -> bool {
+		if self.false_positives.contains(&false_positive) {
+			return false;
+		}
+		self.false_positives.push(false_positive);
+		true
+	}
+
+	/// Settings of the configuration influencing the cached results, the picky level is the one
+	/// of the profile.
+	pub fn settings(&self) -> cache::Settings<'_> {
+		cache::Settings {
+			dictionary: &self.dictionary,
+			disabled_checks: &self.disabled_checks,
+			rule_options: &self.rule_options,
+			rule_files: &self.rule_files,
+			spelling_files: &self.spelling_files,
+			picky: self.profile().is_ok_and(|profile| profile.picky),
+		}
+	}
+
+	/// Apply the dictionary, disabled checks, rule options, rule files and spelling files
+	/// to the backend, see [`sync`], and the picky level of a picky profile.
+	pub async fn apply(&self, lt: &impl LanguageToolBackend) -> crate::Result<Synced> {
+		if self.profile()?.picky {
+			lt.set_picky(true).await?;
+		}
+		sync(lt, &self.settings()).await
+	}
+
+	/// Replace the `previous` configuration applied to the backend,
+	/// the languages missing from this configuration are reset.
+	pub async fn apply_changes(
+		&self,
+		previous: &Self,
+		lt: &impl LanguageToolBackend,
+	) -> crate::Result<Synced> {
+		if previous.profile()?.picky && self.profile()?.picky.not() {
+			lt.set_picky(false).await?;
+		}
+		for lang in previous.dictionary.keys() {
+			if self.dictionary.contains_key(lang).not() {
+				lt.allow_words(lang.parse()?, &[]).await?;
+			}
+		}
+		for lang in previous.disabled_checks.keys() {
+			if self.disabled_checks.contains_key(lang).not() {
+				lt.disable_checks(lang.parse()?, &[]).await?;
+			}
+		}
+		for lang in previous.rule_options.keys() {
+			if self.rule_options.contains_key(lang).not() {
+				lt.set_rule_options(lang.parse()?, &HashMap::new()).await?;
+			}
+		}
+		for lang in previous.rule_files.keys() {
+			if self.rule_files.contains_key(lang).not() {
+				lt.set_rule_files(lang.parse()?, &[]).await?;
+			}
+		}
+		for lang in previous.spelling_files.keys() {
+			if self.spelling_files.contains_key(lang).not() {
+				lt.set_spelling_files(lang.parse()?, &SpellingFiles::default())
+					.await?;
+			}
+		}
+		self.apply(lt).await
+	}
+}
+
+/// Languages whose settings were sent to the backend, see [`sync`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Synced {
+	/// Codes of the languages whose settings were sent, sorted.
+	pub languages: Vec<String>,
+	/// Language code and rule id of the disabled checks that are no rule of the backend.
+	/// Only the backends listing their rules find them, see [`LanguageToolBackend::rules`].
+	pub unknown_rules: Vec<(String, String)>,
+	/// Codes of the languages with disabled checks that were not verified, because the
+	/// backend lists no rules for them.
+	pub unverified: Vec<String>,
+}
+
+impl Synced {
+	/// A line for each unknown rule and language with unverified checks, `None` if there
+	/// are none.
+	pub fn report(&self) -> Option<String> {
+		let lines = (self.unknown_rules.iter())
+			.map(|(lang, id)| format!("Disabled check '{}' is not a rule for '{}'.", id, lang))
+			.chain(self.unverified.iter().map(|lang| {
+				format!(
+					"Disabled checks for '{}' are not verified, the backend does not list its rules.",
+					lang
+				)
+			}))
+			.collect::<Vec<_>>();
+		if lines.is_empty() {
+			return None;
+		}
+		Some(lines.join("\n"))
+	}
+}
+
+/// Send all settings of a language to the backend together, see
+/// [`LanguageToolBackend::apply_settings`], one language after the other, and check that
+/// the disabled checks are rules of the backend.
+/// An unknown language code fails before anything is sent.
+pub async fn sync(
+	lt: &impl LanguageToolBackend,
+	settings: &cache::Settings<'_>,
+) -> crate::Result<Synced> {
+	let mut languages = settings
+		.dictionary
+		.keys()
+		.chain(settings.disabled_checks.keys())
+		.chain(settings.rule_options.keys())
+		.chain(settings.rule_files.keys())
+		.chain(settings.spelling_files.keys())
+		.collect::<Vec<_>>();
+	languages.sort();
+	languages.dedup();
+	let codes = languages
+		.iter()
+		.map(|lang| lang.parse())
+		.collect::<crate::Result<Vec<LanguageCode>>>()?;
+	let mut synced = Synced::default();
+	for (lang, code) in languages.into_iter().zip(codes) {
+		lt.apply_settings(lang, settings).await?;
+		if let Some(checks) = settings.disabled_checks.get(lang) {
+			let rules = lt.rules(code).await.unwrap_or_default();
+			if rules.is_empty() {
+				synced.unverified.push(lang.clone());
+			} else {
+				let unknown = checks
+					.iter()
+					.filter(|check| rules.iter().all(|rule| rule.id != **check));
+				synced
+					.unknown_rules
+					.extend(unknown.map(|check| (lang.clone(), check.clone())));
+			}
+		}
+		synced.languages.push(lang.clone());
+	}
+	Ok(synced)
+}
+
+/// Language code of a variable name, like `de-CH` for `DE_CH`.
+fn env_language(name: &str) -> String {
+	let mut parts = name.split('_');
+	let language = parts.next().unwrap_or_default().to_lowercase();
+	std::iter::once(language)
+		.chain(parts.map(String::from))
+		.collect::<Vec<_>>()
+		.join("-")
+}
+
+/// Append the value to the array `table.key` of the file.
+fn append(path: &Path, table: &str, key: &str, value: &str) -> crate::Result<bool> {
+	let mut document = read_document(path)?;
+	let invalid =
+		|name: &str| crate::Error::Config(format!("'{}' is not a table or array", name).into());
+	let table = document
+		.entry(table)
+		.or_insert_with(|| Item::Table(Table::new()))
+		.as_table_like_mut()
+		.ok_or_else(|| invalid(table))?;
+	let values = table
+		.entry(key)
+		.or_insert(Item::Value(Array::new().into()))
+		.as_array_mut()
+		.ok_or_else(|| invalid(key))?;
+	if values.iter().any(|v| v.as_str() == Some(value)) {
+		return Ok(false);
+	}
+	values.push(value);
+	write_atomic(path, &document.to_string())?;
+	Ok(true)
+}
+
+/// Replace the items of the `table` by the `saved` ones, the unchanged items keep their
+/// comments and formatting.
+fn update(table: &mut dyn TableLike, saved: &dyn TableLike) {
+	let removed = (table.iter())
+		.map(|(key, _)| key.to_owned())
+		.filter(|key| saved.contains_key(key).not())
+		.collect::<Vec<_>>();
+	for key in removed {
+		table.remove(&key);
+	}
+	for (key, item) in saved.iter() {
+		let Some(old) = table.get_mut(key) else {
+			table.insert(key, item.clone());
+			continue;
+		};
+		match (old, item) {
+			(Item::Value(old), Item::Value(new)) => {
+				if plain(old).is_none() || plain(old) != plain(new) {
+					let decor = old.decor().clone();
+					*old = new.clone();
+					*old.decor_mut() = decor;
+				}
+			},
+			(Item::ArrayOfTables(old), Item::ArrayOfTables(new)) if old.len() == new.len() => {
+				for (old, new) in old.iter_mut().zip(new.iter()) {
+					update(old, new);
+				}
+			},
+			(old, new) => match (old.as_table_like_mut(), new.as_table_like()) {
+				(Some(old), Some(new)) => update(old, new),
+				_ => *old = new.clone(),
+			},
+		}
+	}
+}
+
+/// The value without its comments and formatting.
+fn plain(value: &toml_edit::Value) -> Option<toml::Value> {
+	let mut table = toml::from_str::<toml::Table>(&format!("value = {}", value)).ok()?;
+	table.remove("value")
+}
+
+/// Last position of the tables in the document.
+fn last_position(table: &Table) -> Option<usize> {
+	let nested = table.iter().filter_map(|(_, item)| match item {
+		Item::Table(table) => last_position(table),
+		Item::ArrayOfTables(tables) => tables.iter().filter_map(last_position).max(),
+		_ => None,
+	});
+	nested.chain(table.position()).max()
+}
+
+/// Move the tables of the document by `offset` positions.
+fn shift_positions(table: &mut Table, offset: usize) {
+	if let Some(position) = table.position() {
+		table.set_position(position + offset);
+	}
+	for (_, item) in table.iter_mut() {
+		match item {
+			Item::Table(table) => shift_positions(table, offset),
+			Item::ArrayOfTables(tables) => {
+				for table in tables.iter_mut() {
+					shift_positions(table, offset);
+				}
+			},
+			_ => {},
+		}
+	}
+}
+
+/// The document of the file, an empty one if it is missing.
+fn read_document(path: &Path) -> crate::Result<DocumentMut> {
+	let content = match std::fs::read_to_string(path) {
+		Ok(content) => content,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+		Err(err) => return Err(err.into()),
+	};
+	content
+		.parse::<DocumentMut>()
+		.map_err(|err| crate::Error::Config(Box::new(err)))
+}
+
+/// Write to a temporary file next to `path` and rename it, readers never see a partial file.
+pub(crate) fn write_atomic(path: &Path, content: &str) -> crate::Result<()> {
+	let mut temporary = path.as_os_str().to_owned();
+	temporary.push(".tmp");
+	std::fs::write(&temporary, content)?;
+	std::fs::rename(&temporary, path)?;
+	Ok(())
+}
+
+fn insert_unique(values: &mut Vec<String>, value: &str) -> bool {
+	if values.iter().any(|v| v == value) {
+		return false;
+	}
+	values.push(value.into());
+	true
+}
+
+#[cfg(test)]
+mod test {
+	use std::ops::Not;
+
+	use super::*;
+
+	#[test]
+	fn test_save_word() {
+		let path = std::env::temp_dir().join(format!("typst-lt-{}.toml", std::process::id()));
+		let content = "# Words of the project\n[dictionary]\nen = [\"typst\"] # lowercase\n[rule_files]\nen = [\"rules/style.xml\"]\n";
+		std::fs::write(&path, content).unwrap();
+
+		assert!(Config::save_word(&path, "en", "LanguageTool").unwrap());
+		assert!(Config::save_word(&path, "en", "typst").unwrap().not());
+		assert!(Config::save_disabled_check(&path, "en", "WHITESPACE_RULE").unwrap());
+		let false_positive = FalsePositive {
+			rule: "COMMA_PARENTHESIS_WHITESPACE".into(),
+			sentence: "^See \\(.*\\)\\.$".into(),
+		};
+		assert!(Config::save_false_positive(&path, &false_positive).unwrap());
+		assert!(Config::save_false_positive(&path, &false_positive)
+			.unwrap()
+			.not());
+		let saved = std::fs::read_to_string(&path).unwrap();
+		assert!(saved.starts_with("# Words of the project\n"));
+		assert!(saved.contains("# lowercase"));
+
+		let config = Config::load(&path).unwrap();
+		assert_eq!(config.dictionary["en"], vec!["typst", "LanguageTool"]);
+		assert_eq!(config.disabled_checks["en"], vec!["WHITESPACE_RULE"]);
+		assert_eq!(config.false_positives, [false_positive]);
+		let dir = path.parent().unwrap();
+		assert_eq!(config.rule_files["en"], vec![dir.join("rules/style.xml")]);
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn test_save() {
+		let path = std::env::temp_dir().join(format!("typst-lt-save-{}.toml", std::process::id()));
+		let content = "keep_adjacent = true\nglossary = [\"terms.txt\"]\n\n# Words of the project\n[dictionary]\nen = [\"typst\"] # lowercase\nde = [\n\t\"Typst\",\n]\n\n[severities]\nTYPO = \"error\"\n";
+		std::fs::write(&path, content).unwrap();
+
+		let mut config = Config::load(&path).unwrap();
+		config
+			.dictionary
+			.get_mut("en")
+			.unwrap()
+			.push("LanguageTool".into());
+		config.severities.clear();
+		config.keep_adjacent = false;
+		config.ignore_acronyms = true;
+		config.save(&path).unwrap();
+		let saved = std::fs::read_to_string(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!(
+			saved,
+			"glossary = [\"terms.txt\"]\nignore_acronyms = true\n\n# Words of the project\n[dictionary]\nen = [\"typst\", \"LanguageTool\"] # lowercase\nde = [\n\t\"Typst\",\n]\n"
+		);
+	}
+
+	#[test]
+	fn test_merge_vars() {
+		let mut config = Config::default();
+		config.languages.insert("en".into(), "en-US".into());
+		config
+			.dictionary
+			.insert("de-CH".into(), vec!["Typst".into()]);
+		config.merge_vars([
+			("TYPST_LT_LANG".into(), "en-GB, pt-BR=pt-PT".into()),
+			("TYPST_LT_DICTIONARY_DE_CH".into(), "Typst,Grüezi,".into()),
+			(
+				"TYPST_LT_DISABLED_CHECKS_EN".into(),
+				"WHITESPACE_RULE".into(),
+			),
+			("LANG".into(), "C".into()),
+		]);
+		assert_eq!(config.languages["en"], "en-GB");
+		assert_eq!(config.languages["pt-BR"], "pt-PT");
+		assert_eq!(config.dictionary["de-CH"], vec!["Typst", "Grüezi"]);
+		assert_eq!(config.disabled_checks["en"], vec!["WHITESPACE_RULE"]);
+		assert_eq!(config.languages.len(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_severities() {
+		let lt = crate::mock::MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let options = crate::CheckOptions::default();
+		let mut diagnostic = crate::check_source(&lt, "A teh.", &options).await.unwrap()[0].clone();
+		let config: Config =
+			toml::from_str("[severities]\nTYPO = \"error\"\n\"possible typo\" = \"hint\"\n")
+				.unwrap();
+		assert_eq!(
+			Severity::of(&config.severities, &diagnostic),
+			Severity::Error
+		);
+		diagnostic.rule_id = "OTHER".into();
+		assert_eq!(
+			Severity::of(&config.severities, &diagnostic),
+			Severity::Info
+		);
+		diagnostic.rule_source = RuleSource::Picky;
+		assert_eq!(
+			Severity::of(&config.severities, &diagnostic),
+			Severity::Hint
+		);
+		diagnostic.rule_category = "Possible Typo".into();
+		assert_eq!(
+			Severity::of(&config.severities, &diagnostic),
+			Severity::Hint
+		);
+		assert!(Severity::Warning > Severity::Info);
+		assert!("fatal".parse::<Severity>().is_err());
+	}
+
+	#[test]
+	fn test_profile() {
+		let mut config: Config = toml::from_str(
+			"profile = \"thesis\"\n[profiles.thesis]\ndisabled = [\"typography\", \"TYPO\"]\n",
+		)
+		.unwrap();
+		let profile = config.profile().unwrap();
+		assert_eq!(profile.disabled, ["typography", "TYPO"]);
+		assert!(profile.picky.not());
+
+		let suggestion = |rule_id: &str, rule_category: &str| crate::Suggestion {
+			rule_id: rule_id.into(),
+			rule_category: rule_category.into(),
+			..crate::Suggestion::test(0, 1)
+		};
+		let mut suggestions = vec![
+			suggestion("EN_QUOTES", "Typography"),
+			suggestion("TYPO", "Possible Typo"),
+			suggestion("OTHER", "Grammar"),
+		];
+		config.ignored().unwrap().retain("A b", &mut suggestions);
+		assert_eq!(suggestions.len(), 1);
+		assert_eq!(suggestions[0].rule_id, "OTHER");
+
+		config.profile = Some("academic".into());
+		assert!(config.settings().picky);
+		assert!(config.convert_options().math.not());
+		config.convert = Some(convert::Options::default());
+		assert!(config.convert_options().math);
+		config.profile = Some("formal".into());
+		assert!(config.profile().is_err());
+	}
+
+	#[tokio::test]
+	async fn test_apply_changes() {
+		use crate::mock::{Call, MockBackend};
+
+		let lt = MockBackend::new();
+		let mut previous = Config::default();
+		previous
+			.dictionary
+			.insert("de".into(), vec!["Typst".into()]);
+		previous
+			.dictionary
+			.insert("en".into(), vec!["typst".into()]);
+		previous
+			.rule_files
+			.insert("en".into(), vec!["style.xml".into()]);
+		let mut config = Config::default();
+		config
+			.dictionary
+			.insert("en".into(), vec!["LanguageTool".into()]);
+		config.apply_changes(&previous, &lt).await.unwrap();
+		let calls = lt.calls();
+		assert_eq!(
+			calls,
+			vec![
+				Call::AllowWords { lang: "de".into(), words: Vec::new() },
+				Call::SetRuleFiles { lang: "en".into(), files: Vec::new() },
+				Call::AllowWords {
+					lang: "en".into(),
+					words: vec!["LanguageTool".into()]
+				},
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_sync() {
+		use crate::mock::{Call, MockBackend};
+
+		let lt = MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let mut config = Config::default();
+		config
+			.disabled_checks
+			.insert("en".into(), vec!["TYPO".into(), "NO_RULE".into()]);
+		config.dictionary.insert("en".into(), vec!["typst".into()]);
+		config.dictionary.insert("de".into(), vec!["Typst".into()]);
+		let synced = config.apply(&lt).await.unwrap();
+		assert_eq!(synced.languages, vec!["de", "en"]);
+		assert_eq!(
+			synced.unknown_rules,
+			vec![("en".to_owned(), "NO_RULE".to_owned())]
+		);
+		assert_eq!(
+			lt.calls(),
+			vec![
+				Call::AllowWords {
+					lang: "de".into(),
+					words: vec!["Typst".into()]
+				},
+				Call::AllowWords {
+					lang: "en".into(),
+					words: vec!["typst".into()]
+				},
+				Call::DisableChecks {
+					lang: "en".into(),
+					checks: vec!["TYPO".into(), "NO_RULE".into()]
+				},
+			]
+		);
+
+		assert!(synced.unverified.is_empty());
+
+		let synced = config.apply(&MockBackend::new()).await.unwrap();
+		assert!(synced.unknown_rules.is_empty());
+		assert_eq!(synced.unverified, vec!["en"]);
+	}
+}
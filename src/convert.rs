@@ -1,23 +1,107 @@
-use std::ops::Range;
+use std::{
+	collections::HashMap,
+	ops::{Not, Range, RangeInclusive},
+	sync::OnceLock,
+};
+
+use regex::Regex;
 
+use serde::{Deserialize, Serialize};
 use typst::{
-	layout::{Abs, Em, Point},
+	layout::{Abs, Em, Frame, Point},
 	model::Document,
-	syntax::{FileId, Source, Span, SyntaxKind},
-	text::{Lang, TextItem},
+	text::{Lang, Region, TextItem},
+};
+use unicode_normalization::{
+	char::{canonical_combining_class, compose},
+	UnicodeNormalization,
+};
+
+use crate::{
+	compat::{self, ast, FileId, Item, LinkedNode, Source, Span, SyntaxKind, SyntaxNode},
+	trace, Suggestion,
 };
 
-use crate::Suggestion;
+pub mod markdown;
+pub mod markup;
+pub mod plain;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Mapping {
-	chars: Vec<(Span, Range<u16>)>,
+	chars: Chars,
 	language: Lang,
+	/// Region set together with the language, like `set text(lang: "de", region: "CH")`.
+	region: Option<Region>,
+	/// Only spelling diagnostics are kept, like for the names of [`Options::labels`].
+	spelling: bool,
+	/// Code of the [`FILE_LANGUAGE`] comment of the file, for text without a language.
+	code: Option<String>,
+}
+
+/// Source location for every UTF-16 code unit of the converted text.
+#[derive(Debug, Clone)]
+enum Chars {
+	/// Glyph spans from the compiled document.
+	Spans(Vec<(Span, Range<u16>)>),
+	/// Byte ranges in the source text, empty for inserted text.
+	Bytes(Vec<Range<usize>>),
 }
 
 impl Mapping {
 	pub fn location(&self, suggestion: &Suggestion, source: &Source) -> Vec<Range<usize>> {
-		let chars = &self.chars[suggestion.start..suggestion.end];
+		self.units(suggestion.start..suggestion.end, source)
+	}
+
+	/// Source locations of a UTF-16 range of the converted text, like of a paragraph.
+	pub fn range_location(&self, range: Range<usize>, source: &Source) -> Vec<Range<usize>> {
+		self.units(range, source)
+	}
+
+	/// Pieces of the converted text with the source they come from, as UTF-16 ranges in the text
+	/// and byte ranges in the source. Inserted text like paragraph breaks has no source.
+	pub fn pieces(&self, source: &Source) -> Vec<(Range<usize>, Option<Range<usize>>)> {
+		let len = match &self.chars {
+			Chars::Spans(chars) => chars.len(),
+			Chars::Bytes(bytes) => bytes.len(),
+		};
+		let mut pieces = Vec::<(Range<usize>, Option<Range<usize>>)>::new();
+		for index in 0..len {
+			let location = self.units(index..index + 1, source).first().cloned();
+			match (pieces.last_mut(), location) {
+				// Escapes, links and composed chars map several code units to the same source.
+				(Some((text, last)), location) if *last == location => text.end = index + 1,
+				(Some((text, Some(last))), Some(range)) if last.end == range.start => {
+					text.end = index + 1;
+					last.end = range.end;
+				},
+				(_, location) => pieces.push((index..index + 1, location)),
+			}
+		}
+		pieces
+	}
+
+	/// Source locations of the UTF-16 code units of the text.
+	fn units(&self, range: Range<usize>, source: &Source) -> Vec<Range<usize>> {
+		match &self.chars {
+			Chars::Spans(chars) => Self::span_location(&chars[range], source),
+			Chars::Bytes(bytes) => Self::byte_location(&bytes[range], source),
+		}
+	}
+
+	/// Text of the chunk comes from one of the byte ranges in the source.
+	pub fn overlaps(&self, source: &Source, ranges: &[Range<usize>]) -> bool {
+		let locations = match &self.chars {
+			Chars::Spans(chars) => Self::span_location(chars, source),
+			Chars::Bytes(bytes) => Self::byte_location(bytes, source),
+		};
+		locations.iter().any(|location| {
+			ranges
+				.iter()
+				.any(|range| location.start < range.end && range.start < location.end)
+		})
+	}
+
+	fn span_location(chars: &[(Span, Range<u16>)], source: &Source) -> Vec<Range<usize>> {
 		let mut locations = Vec::<Range<usize>>::new();
 		for (span, range) in chars.iter().cloned() {
 			let Some(id) = span.id() else {
@@ -33,13 +117,21 @@ impl Mapping {
 				let start = node.range().start;
 				let range = (start + range.start as usize)..(start + range.end as usize);
 				match locations.last_mut() {
-					Some(last_range) if last_range.end == range.start => last_range.end = range.end,
+					Some(last_range) if joined(source, last_range, &range) => {
+						last_range.end = range.end
+					},
+					// Chars composed by normalization share the range.
+					Some(last_range) if *last_range == range => {},
 					_ => locations.push(range),
 				}
 			} else {
+				// Escapes and shorthands like `\u{65}` or `--` inside of words.
 				let range = node.range();
 				match locations.last_mut() {
 					Some(last_range) if *last_range == range => {},
+					Some(last_range) if joined(source, last_range, &range) => {
+						last_range.end = range.end
+					},
 					_ => locations.push(range),
 				}
 			}
@@ -48,105 +140,1706 @@ impl Mapping {
 		locations
 	}
 
+	fn byte_location(bytes: &[Range<usize>], source: &Source) -> Vec<Range<usize>> {
+		let mut locations = Vec::<Range<usize>>::new();
+		for range in bytes.iter().filter(|range| range.is_empty().not()) {
+			match locations.last_mut() {
+				Some(last_range) if joined(source, last_range, range) => last_range.end = range.end,
+				Some(last_range) if last_range.end >= range.end => {},
+				_ => locations.push(range.clone()),
+			}
+		}
+		locations
+	}
+
+	/// Normalize the text to NFC, composed chars map to the source of the chars they replace.
+	fn normalize(&mut self, text: &mut String) {
+		if unicode_normalization::is_nfc(text) {
+			return;
+		}
+		match &mut self.chars {
+			Chars::Spans(chars) => (*text, *chars) = normalized(text, chars, merge_spans),
+			Chars::Bytes(bytes) => (*text, *bytes) = normalized(text, bytes, merge_bytes),
+		}
+	}
+
+	/// Replace the URLs and email addresses with placeholders, their code units map to the source
+	/// of the whole link.
+	fn placeholders(&mut self, text: &mut String) {
+		let links = links()
+			.find_iter(text)
+			.map(|link| link.range())
+			.collect::<Vec<_>>();
+		if links.is_empty() {
+			return;
+		}
+		match &mut self.chars {
+			Chars::Spans(chars) => (*text, *chars) = replaced(text, chars, &links, merge_spans),
+			Chars::Bytes(bytes) => (*text, *bytes) = replaced(text, bytes, &links, merge_bytes),
+		}
+	}
+
+	/// Split the mapping at the UTF-16 offset `at`, returns the mapping of the tail.
+	fn split_off(&mut self, at: usize) -> Self {
+		let chars = match &mut self.chars {
+			Chars::Spans(chars) => Chars::Spans(chars.split_off(at)),
+			Chars::Bytes(bytes) => Chars::Bytes(bytes.split_off(at)),
+		};
+		Self {
+			chars,
+			language: self.language,
+			region: self.region,
+			spelling: self.spelling,
+			code: self.code.clone(),
+		}
+	}
+
+	/// The next chunk can be joined to this one, it has the same language and kind of mapping.
+	fn joins(&self, other: &Self) -> bool {
+		let kinds = matches!(
+			(&self.chars, &other.chars),
+			(Chars::Spans(_), Chars::Spans(_)) | (Chars::Bytes(_), Chars::Bytes(_))
+		);
+		kinds
+			&& self.language == other.language
+			&& self.region == other.region
+			&& self.spelling == other.spelling
+			&& self.code == other.code
+	}
+
+	/// Append the mapping of the next chunk after `separator` code units without a source.
+	fn append(&mut self, separator: usize, other: Self) {
+		match (&mut self.chars, other.chars) {
+			(Chars::Spans(chars), Chars::Spans(other)) => {
+				chars.extend(std::iter::repeat_n((Span::detached(), 0..0), separator));
+				chars.extend(other);
+			},
+			(Chars::Bytes(bytes), Chars::Bytes(other)) => {
+				bytes.extend(std::iter::repeat_n(0..0, separator));
+				bytes.extend(other);
+			},
+			_ => {},
+		}
+	}
+
+	/// Only diagnostics of spelling rules apply to the text, see [`Options::labels`].
+	pub fn is_spelling_only(&self) -> bool {
+		self.spelling
+	}
+
+	/// Language of text without `#set text(lang: ..)`, English without a region.
+	pub fn has_default_language(&self) -> bool {
+		self.language == Lang::ENGLISH && self.region.is_none()
+	}
+
 	pub fn short_language(&self) -> &str {
 		self.language.as_str()
 	}
 
+	/// Code of a [`FILE_LANGUAGE`] comment, it replaces a language configured for all files.
+	pub fn file_language(&self) -> Option<&str> {
+		self.code.as_deref()
+	}
+
+	/// LanguageTool code for the language, `overrides` replace the built-in codes.
+	/// Their keys are languages with an optional region like `de` or `de-CH`.
+	/// The code of a [`FILE_LANGUAGE`] comment is used as is.
+	pub fn language_code(&self, overrides: &HashMap<String, String>) -> String {
+		if let Some(code) = &self.code {
+			return code.clone();
+		}
+		let lang = self.language.as_str();
+		let region = self
+			.region
+			.and_then(|region| overrides.get(&format!("{}-{}", lang, region.as_str())));
+		match region.or_else(|| overrides.get(lang)) {
+			Some(code) => code.clone(),
+			None => self.long_language(),
+		}
+	}
+
 	// https://languagetool.org/http-api/swagger-ui/#!/default/get_languages
-	// defaults to european region codes (maybe).
+	// defaults to european region codes (maybe), unless the document sets a region
+	// with a variant in LanguageTool.
 	// todo: default to highest population.
 	pub fn long_language(&self) -> String {
-		match self.language {
-			Lang::FRENCH => "fr-FR".into(),
-			Lang::SWEDISH => "sv-SE".into(),
-			Lang::ITALIAN => "it-IT".into(),
-			Lang::SPANISH => "es-ES".into(),
-			Lang::DUTCH => "nl-NL".into(),
-			Lang::CHINESE => "zh-CN".into(),
-			Lang::UKRAINIAN => "uk-UA".into(),
-			Lang::SLOVENIAN => "sl-SI".into(),
-			Lang::RUSSIAN => "ru-RU".into(),
-			Lang::ROMANIAN => "ro-RO".into(),
-			Lang::POLISH => "pl-PL".into(),
-			Lang::JAPANESE => "ja-JP".into(),
-			Lang::GREEK => "el-GR".into(),
-			Lang::DANISH => "da-DK".into(),
-			Lang::CATALAN => "ca-ES".into(),
-			Lang::PORTUGUESE => "pt-PT".into(),
-			Lang::ENGLISH => "en-GB".into(),
-			Lang::GERMAN => "de-DE".into(),
-			lang @ _ => lang.as_str().into(),
+		if let Some(region) = self.region {
+			let code = format!("{}-{}", self.language.as_str(), region.as_str());
+			if VARIANTS.contains(&code.as_str()) {
+				return code;
+			}
 		}
+		default_variant(self.language)
 	}
 }
 
-const LINE_SPACING: Em = Em::new(0.65);
+/// LanguageTool code for a language without a region, like `de-DE` for `de`.
+pub(crate) fn default_variant(language: Lang) -> String {
+	match language {
+		Lang::FRENCH => "fr-FR".into(),
+		Lang::SWEDISH => "sv-SE".into(),
+		Lang::ITALIAN => "it-IT".into(),
+		Lang::SPANISH => "es-ES".into(),
+		Lang::DUTCH => "nl-NL".into(),
+		Lang::CHINESE => "zh-CN".into(),
+		Lang::UKRAINIAN => "uk-UA".into(),
+		Lang::SLOVENIAN => "sl-SI".into(),
+		Lang::RUSSIAN => "ru-RU".into(),
+		Lang::ROMANIAN => "ro-RO".into(),
+		Lang::POLISH => "pl-PL".into(),
+		Lang::JAPANESE => "ja-JP".into(),
+		Lang::GREEK => "el-GR".into(),
+		Lang::DANISH => "da-DK".into(),
+		Lang::CATALAN => "ca-ES".into(),
+		Lang::PORTUGUESE => "pt-PT".into(),
+		Lang::ENGLISH => "en-GB".into(),
+		Lang::GERMAN => "de-DE".into(),
+		lang => lang.as_str().into(),
+	}
+}
+
+/// Discretionary hyphen, removed from the text so the word is checked as a whole.
+const SOFT_HYPHEN: char = '\u{ad}';
+
+/// If the ranges are adjacent or only separated by the markup of a discretionary hyphen,
+/// like `-?` in `hy-?phen`, so the location covers the whole word.
+fn joined(source: &Source, first: &Range<usize>, second: &Range<usize>) -> bool {
+	if first.end == second.start {
+		return true;
+	}
+	let Some(between) = source.text().get(first.end..second.start) else {
+		return false;
+	};
+	let escape = between
+		.strip_prefix("\\u{")
+		.and_then(|code| code.strip_suffix('}'))
+		.and_then(|code| u32::from_str_radix(code, 16).ok());
+	matches!(between, "-?" | "\u{ad}") || escape == Some(SOFT_HYPHEN as u32)
+}
+
+/// Keep the chunks with text from the byte ranges of the source, like the lines of a section.
+pub fn retain_ranges(
+	chunks: &mut Vec<(String, Mapping)>,
+	source: &Source,
+	ranges: &[Range<usize>],
+) {
+	chunks.retain(|(_, mapping)| mapping.overlaps(source, ranges));
+}
+
+/// Prefix of a comment on the first line setting the language of the file, like
+/// `// lt-lang: fr-FR`, for files without `set text(lang: ..)`.
+pub const FILE_LANGUAGE: &str = "lt-lang:";
+
+/// Language code of the [`FILE_LANGUAGE`] comment on the first line of the source.
+pub fn file_language(source: &Source) -> Option<&str> {
+	let line = source.text().lines().next()?.trim();
+	let code = line
+		.strip_prefix("//")?
+		.trim()
+		.strip_prefix(FILE_LANGUAGE)?
+		.trim();
+	code.is_empty().not().then_some(code)
+}
+
+/// Check the chunks of text without a language set by `set text(..)` with the language of the
+/// [`FILE_LANGUAGE`] comment of the source.
+fn apply_file_language(chunks: &mut [(String, Mapping)], source: &Source) {
+	let Some(code) = file_language(source) else {
+		return;
+	};
+	let (lang, region) = code.split_once('-').unwrap_or((code, ""));
+	let Ok(language) = lang.to_ascii_lowercase().parse::<Lang>() else {
+		return;
+	};
+	for (_, mapping) in chunks {
+		if mapping.has_default_language() {
+			mapping.language = language;
+			mapping.region = region.get(..2).and_then(|region| region.parse().ok());
+			mapping.code = Some(code.into());
+		}
+	}
+}
+
+/// Join adjacent chunks of the same language with empty lines, like the pages of a document and
+/// the parts checked separately, to check all text of a language at once.
+pub fn join(chunks: Vec<(String, Mapping)>) -> Vec<(String, Mapping)> {
+	let mut res = Vec::<(String, Mapping)>::new();
+	for (text, mapping) in chunks {
+		match res.last_mut() {
+			Some((last_text, last)) if last.joins(&mapping) => {
+				*last_text += "\n\n";
+				*last_text += &text;
+				last.append(2, mapping);
+			},
+			_ => res.push((text, mapping)),
+		}
+	}
+	res
+}
+
+/// Normalize the text of the chunks to NFC, see [`Options::normalize`].
+pub fn normalize(chunks: &mut [(String, Mapping)]) {
+	for (text, mapping) in chunks {
+		mapping.normalize(text);
+	}
+}
+
+/// Replace the URLs and email addresses in the text of the chunks with placeholders,
+/// so they neither break sentences nor are flagged as misspelled, see [`Options::links`].
+pub fn placeholders(chunks: &mut [(String, Mapping)]) {
+	for (text, mapping) in chunks {
+		mapping.placeholders(text);
+	}
+}
+
+/// Checked instead of the URLs and email addresses, the backends know them as links.
+const URL_PLACEHOLDER: &str = "https://example.com";
+const EMAIL_PLACEHOLDER: &str = "mail@example.com";
+
+/// URLs like `https://typst.app` or `www.typst.app` and email addresses, without the
+/// punctuation after them.
+fn links() -> &'static Regex {
+	static LINKS: OnceLock<Regex> = OnceLock::new();
+	LINKS.get_or_init(|| {
+		let url = r#"(?:(?:https?|ftp)://|www\.)[^\s<>"]*[^\s<>".,;:!?'")\]]"#;
+		let email = r"[\w.%+-]+@[\w-]+(?:\.[\w-]+)+";
+		Regex::new(&format!("{}|{}", url, email)).unwrap()
+	})
+}
+
+/// Replace the byte ranges of the links with placeholders,
+/// the UTF-16 code units of a placeholder map to the merged `units` of its link.
+fn replaced<T: Clone>(
+	text: &str,
+	units: &[T],
+	links: &[Range<usize>],
+	merge: impl Fn(&[T]) -> T,
+) -> (String, Vec<T>) {
+	let mut res = (
+		String::with_capacity(text.len()),
+		Vec::with_capacity(units.len()),
+	);
+	let (mut start, mut unit) = (0, 0);
+	for link in links {
+		let before = text[start..link.start].encode_utf16().count();
+		res.0 += &text[start..link.start];
+		res.1.extend_from_slice(&units[unit..unit + before]);
+		unit += before;
+		let link_text = &text[link.clone()];
+		let link_units = link_text.encode_utf16().count();
+		let placeholder = if link_text.contains('@') && link_text.contains("://").not() {
+			EMAIL_PLACEHOLDER
+		} else {
+			URL_PLACEHOLDER
+		};
+		let merged = merge(&units[unit..unit + link_units]);
+		res.0 += placeholder;
+		res.1
+			.extend(placeholder.encode_utf16().map(|_| merged.clone()));
+		unit += link_units;
+		start = link.end;
+	}
+	res.0 += &text[start..];
+	res.1.extend_from_slice(&units[unit..]);
+	res
+}
+
+/// Glyph spans of merged code units, the range covers all of them if they share the span.
+fn merge_spans(chars: &[(Span, Range<u16>)]) -> (Span, Range<u16>) {
+	let (span, range) = chars[0].clone();
+	if chars.iter().all(|(other, _)| *other == span) {
+		let end = chars.iter().map(|(_, range)| range.end).max();
+		(span, range.start..end.unwrap_or(range.end))
+	} else {
+		(span, range)
+	}
+}
+
+/// Byte range covering the merged code units, inserted text has empty ranges.
+fn merge_bytes(bytes: &[Range<usize>]) -> Range<usize> {
+	let mut ranges = bytes.iter().filter(|range| range.is_empty().not());
+	match ranges.next() {
+		Some(first) => ranges.fold(first.clone(), |range, other| {
+			range.start.min(other.start)..range.end.max(other.end)
+		}),
+		None => bytes[0].clone(),
+	}
+}
+
+/// Normalize every sequence of a starter and the chars composed with it,
+/// the UTF-16 code units of a changed sequence map to the merged `units`.
+fn normalized<T: Clone>(text: &str, units: &[T], merge: impl Fn(&[T]) -> T) -> (String, Vec<T>) {
+	let mut res = (
+		String::with_capacity(text.len()),
+		Vec::with_capacity(units.len()),
+	);
+	let mut push = |sequence: &str, units: &[T]| {
+		let composed = sequence.nfc().collect::<String>();
+		if composed == sequence {
+			res.1.extend_from_slice(units);
+		} else {
+			let unit = merge(units);
+			res.1.extend(composed.encode_utf16().map(|_| unit.clone()));
+		}
+		res.0 += &composed;
+	};
+	let (mut start, mut unit_start, mut unit) = (0, 0, 0);
+	// The starter composed with the following chars, like a Hangul syllable from its jamo.
+	let mut starter = None;
+	for (index, c) in text.char_indices() {
+		match starter.and_then(|starter| compose(starter, c)) {
+			Some(composed) => starter = Some(composed),
+			None if starter.is_some() && canonical_combining_class(c) != 0 => {},
+			None => {
+				if index > start {
+					push(&text[start..index], &units[unit_start..unit]);
+					(start, unit_start) = (index, unit);
+				}
+				starter = Some(c);
+			},
+		}
+		unit += c.len_utf16();
+	}
+	push(&text[start..], &units[unit_start..]);
+	res
+}
+
+/// Regional variants of LanguageTool, other regions use the default variant of the language.
+const VARIANTS: &[&str] = &[
+	"en-US", "en-GB", "en-AU", "en-CA", "en-NZ", "en-ZA", "de-DE", "de-AT", "de-CH", "pt-PT",
+	"pt-BR", "pt-AO", "pt-MZ", "fr-FR", "fr-CA", "fr-BE", "fr-CH", "nl-NL", "nl-BE", "es-ES",
+	"es-AR", "ca-ES",
+];
+
+/// Builds chunks from text, where every char maps to its byte range in the source.
+/// Discretionary hyphens are removed.
+struct ByteConverter {
+	text: String,
+	bytes: Vec<Range<usize>>,
+	language: Lang,
+	chunk_size: usize,
+	/// Size of the buffers of a chunk, reserved when the first text of the chunk is added.
+	capacity: usize,
+	/// Arguments of the footnotes in converted markup, converted after the text.
+	footnotes: Vec<Span>,
+	/// Smart quotes of converted markup.
+	quotes: markup::Quotes,
+	/// Byte ranges of the names of labels and references, converted after the footnotes.
+	labels: Vec<Range<usize>>,
+	/// The current chunk is checked only for spelling.
+	spelling: bool,
+	/// Text of a part converted in parallel that is separated when it is joined,
+	/// see [`ByteConverter::part`].
+	head: Option<Head>,
+	res: Vec<(String, Mapping)>,
+}
 
-pub fn document(doc: &Document, chunk_size: usize, file_id: FileId) -> Vec<(String, Mapping)> {
+impl ByteConverter {
+	/// The buffers are sized for chunks of the source text with `len` bytes.
+	fn new(chunk_size: usize, language: Lang, len: usize) -> Self {
+		Self {
+			text: String::new(),
+			bytes: Vec::new(),
+			language,
+			chunk_size,
+			capacity: len.min(chunk_size.saturating_mul(2)),
+			footnotes: Vec::new(),
+			quotes: markup::Quotes::new(language),
+			labels: Vec::new(),
+			spelling: false,
+			head: None,
+			res: Vec::new(),
+		}
+	}
+
+	/// Converter of a part of the markup starting after an empty line. Where the chunks are
+	/// separated depends on the length of the chunk before the part, so the text is kept until
+	/// a paragraph longer than a chunk and separated when the part is joined,
+	/// see [`ByteConverter::join`].
+	fn part(chunk_size: usize, language: Lang, len: usize) -> Self {
+		Self {
+			head: Some(Head::default()),
+			..Self::new(chunk_size, language, len)
+		}
+	}
+
+	/// Append a converted part, like it was converted after the text of this converter.
+	fn join(&mut self, mut part: Self) {
+		let Some(mut head) = part.head.take() else {
+			return;
+		};
+		if head.ended.not() {
+			head.text = std::mem::take(&mut part.text);
+			head.bytes = std::mem::take(&mut part.bytes);
+		}
+		let mut start = (0, 0);
+		for &end in &head.breaks {
+			self.text += &head.text[start.0..end.0];
+			self.bytes.extend_from_slice(&head.bytes[start.1..end.1]);
+			self.parbreak();
+			start = (end.0 + 2, end.1 + 2);
+		}
+		self.text += &head.text[start.0..];
+		self.bytes.extend_from_slice(&head.bytes[start.1..]);
+		if head.ended {
+			self.parbreak();
+			self.res.append(&mut part.res);
+			self.text = part.text;
+			self.bytes = part.bytes;
+		}
+		self.footnotes.append(&mut part.footnotes);
+		self.labels.append(&mut part.labels);
+		self.quotes = part.quotes;
+	}
+
+	fn reserve(&mut self) {
+		if self.bytes.capacity() == 0 {
+			self.text.reserve(self.capacity);
+			self.bytes.reserve(self.capacity);
+		}
+	}
+
+	/// Add source text starting at the byte `offset`.
+	fn push(&mut self, text: &str, offset: usize) {
+		self.reserve();
+		for (index, c) in text.char_indices() {
+			if c == SOFT_HYPHEN {
+				continue;
+			}
+			let range = (offset + index)..(offset + index + c.len_utf8());
+			for _ in 0..c.len_utf16() {
+				self.bytes.push(range.clone());
+			}
+			self.text.push(c);
+		}
+	}
+
+	/// Add text replacing the source bytes in `range`.
+	fn replace(&mut self, text: &str, range: Range<usize>) {
+		self.reserve();
+		for c in text.chars().filter(|&c| c != SOFT_HYPHEN) {
+			for _ in 0..c.len_utf16() {
+				self.bytes.push(range.clone());
+			}
+			self.text.push(c);
+		}
+	}
+
+	/// Add text without a location in the source.
+	fn insert(&mut self, text: &str) {
+		self.reserve();
+		for _ in text.encode_utf16() {
+			self.bytes.push(0..0);
+		}
+		self.text += text;
+	}
+
+	fn parbreak(&mut self) {
+		if self.text.trim().is_empty() || self.text.ends_with("\n\n") {
+			return;
+		}
+		if let Some(head) = self.head.as_mut().filter(|head| head.ended.not()) {
+			// Only a paragraph longer than a chunk is always separated from the text before it.
+			let start = head.breaks.last().map_or(0, |&(_, bytes)| bytes + 2);
+			if self.bytes.len() - start > self.chunk_size {
+				head.text = std::mem::take(&mut self.text);
+				head.bytes = std::mem::take(&mut self.bytes);
+				head.ended = true;
+			} else {
+				head.breaks.push((self.text.len(), self.bytes.len()));
+				self.insert("\n\n");
+			}
+			return;
+		}
+		if self.bytes.len() > self.chunk_size {
+			self.seperate();
+			return;
+		}
+		self.insert("\n\n");
+	}
+
+	fn seperate(&mut self) {
+		while self.text.ends_with("\n\n") {
+			self.text.truncate(self.text.len() - 2);
+			self.bytes.truncate(self.bytes.len() - 2);
+		}
+		let text = std::mem::take(&mut self.text);
+		let bytes = std::mem::take(&mut self.bytes);
+		if text.trim().is_empty() {
+			return;
+		}
+		let mapping = Mapping {
+			chars: Chars::Bytes(bytes),
+			language: self.language,
+			region: None,
+			spelling: self.spelling,
+			code: None,
+		};
+		self.res
+			.extend(split_sentences(text, mapping, self.chunk_size));
+	}
+
+	fn finish(mut self) -> Vec<(String, Mapping)> {
+		self.seperate();
+		self.res
+	}
+}
+
+/// Text of a part up to the first paragraph longer than a chunk, see [`ByteConverter::part`].
+#[derive(Debug, Default)]
+struct Head {
+	text: String,
+	bytes: Vec<Range<usize>>,
+	/// Offsets in the text and bytes of the inserted empty lines.
+	breaks: Vec<(usize, usize)>,
+	/// The text was separated after its last paragraph.
+	ended: bool,
+}
+
+/// Split converted text into paragraphs at empty lines.
+/// Returns the non-empty paragraphs with their offset in UTF-16 code units,
+/// the unit used for the positions of suggestions.
+pub fn paragraphs(text: &str) -> Vec<(usize, &str)> {
 	let mut res = Vec::new();
+	let mut offset = 0;
+	for paragraph in text.split("\n\n") {
+		if paragraph.trim().is_empty().not() {
+			res.push((offset, paragraph));
+		}
+		offset += paragraph.encode_utf16().count() + 2;
+	}
+	res
+}
+
+/// Split chunks longer than `chunk_size` UTF-16 code units, only after the end of a sentence
+/// or paragraph. Chunks without such a boundary are kept in one piece.
+fn split_sentences(
+	text: String,
+	mut mapping: Mapping,
+	chunk_size: usize,
+) -> Vec<(String, Mapping)> {
+	let len = text.encode_utf16().count();
+	let _span = trace::span!("split", len = len, chunk_size = chunk_size).entered();
+	if len <= chunk_size {
+		return vec![(text, mapping)];
+	}
+
+	// (UTF-16 offset, byte offset) of the first char after a boundary
+	let mut boundaries = Vec::new();
+	let mut offset = 0;
+	let mut sentence_end = false;
+	let mut whitespace = false;
+	for (index, c) in text.char_indices() {
+		if c.is_whitespace() {
+			whitespace |= sentence_end || c == '\n';
+		} else {
+			if whitespace {
+				boundaries.push((offset, index));
+			}
+			whitespace = false;
+			sentence_end = matches!(c, '.' | '!' | '?' | '…')
+				|| (sentence_end && matches!(c, '"' | '\'' | ')' | '”' | '’' | '»'));
+		}
+		offset += c.len_utf16();
+	}
+
+	let mut cuts = Vec::new();
+	let mut start = (0, 0);
+	while len - start.0 > chunk_size {
+		let limit = start.0 + chunk_size;
+		let later = boundaries.iter().filter(|&&(offset, _)| offset > start.0);
+		let cut = later
+			.clone()
+			.take_while(|&&(offset, _)| offset <= limit)
+			.last()
+			.or_else(|| later.clone().next());
+		let Some(&cut) = cut else {
+			break;
+		};
+		cuts.push(cut);
+		start = cut;
+	}
+
+	let mut res = Vec::with_capacity(cuts.len() + 1);
+	let mut text = text;
+	for &(offset, index) in cuts.iter().rev() {
+		let tail_text = text.split_off(index);
+		let tail_mapping = mapping.split_off(offset);
+		res.push((tail_text, tail_mapping));
+	}
+	res.push((text, mapping));
+	res.reverse();
+	res
+}
+
+/// Parts of the document that are checked, set in the project configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Options {
+	/// Check the entries listed by `bibliography`.
+	pub bibliography: bool,
+	/// Check the text of citations like `@key` or `cite(<key>)`.
+	pub citations: bool,
+	/// Check figure captions, separated from the surrounding text.
+	pub captions: bool,
+	/// Check the `alt` text of images.
+	pub alt_text: bool,
+	/// Check the `attribution` of quotes, separated from the quote.
+	pub attributions: bool,
+	/// Check the title, author and keywords set with `set document(..)`.
+	pub metadata: bool,
+	/// Check headings, both `= Heading` and `heading[..]`.
+	pub headings: bool,
+	/// Check the items of lists, enumerations and term lists.
+	pub lists: bool,
+	/// Check the cells of `table(..)` and `grid(..)`.
+	pub tables: bool,
+	/// Check the content of `footnote[..]`, separated from the surrounding text.
+	pub footnotes: bool,
+	/// Check the text of equations in compiled documents, equations are never converted from markup.
+	pub math: bool,
+	/// Check the text of comments as separate paragraphs.
+	pub comments: bool,
+	/// Check string literals in code as separate paragraphs, like `#"text"` or `#box("text")`.
+	/// Strings of `set` rules, imports, named arguments and paths are not checked.
+	pub code_strings: bool,
+	/// Check content that is never shown, like `hide[..]` and the branches of `if false`.
+	pub hidden: bool,
+	/// Check the words of labels and references like `<sec-introduction>` as separate paragraphs,
+	/// only for spelling. A prefix up to a `:` like in `<fig:plot>` is not checked.
+	pub labels: bool,
+	/// Check the markup of function bodies and show rules once as separate paragraphs, like
+	/// `[Chapter #it.body]`. Otherwise only their text shown in the document is checked.
+	pub templates: bool,
+	/// Functions whose arguments are not checked, like `canvas` for drawings.
+	pub skip: Vec<String>,
+	/// Functions whose arguments are checked as separate paragraphs, even if they are not shown.
+	pub check: Vec<String>,
+	/// Functions producing placeholder text like `lorem`, which is always one. Their
+	/// generated text is not checked.
+	pub placeholders: Vec<String>,
+	/// Functions to skip or check for popular packages, added to `skip` and `check`.
+	pub presets: Vec<Preset>,
+	/// Normalize the text to NFC, decomposed chars like `e` with a combining accent are flagged otherwise.
+	pub normalize: bool,
+	/// Check URLs and email addresses as text, otherwise they are replaced with placeholders.
+	/// The text of `link(..)[..]` is always checked.
+	pub links: bool,
+}
+
+impl Options {
+	/// The arguments of calls to the function are not checked.
+	pub fn skips(&self, function: &str) -> bool {
+		let element = match function {
+			"heading" => self.headings.not(),
+			"list" | "enum" | "terms" => self.lists.not(),
+			"table" | "grid" => self.tables.not(),
+			"footnote" => self.footnotes.not(),
+			_ => false,
+		};
+		element
+			|| self.is_placeholder(function)
+			|| self.skip.iter().any(|skip| skip == function)
+			|| self
+				.presets
+				.iter()
+				.any(|preset| preset.skip().contains(&function))
+	}
+
+	/// Calls of the function produce placeholder text, see [`Options::placeholders`].
+	pub fn is_placeholder(&self, function: &str) -> bool {
+		function == "lorem"
+			|| self
+				.placeholders
+				.iter()
+				.any(|placeholder| placeholder == function)
+	}
+
+	/// The arguments of calls to the function are checked as separate paragraphs.
+	pub fn checks(&self, function: &str) -> bool {
+		self.check.iter().any(|check| check == function)
+			|| self
+				.presets
+				.iter()
+				.any(|preset| preset.check().contains(&function))
+	}
+}
+
+/// Functions of a package that are skipped or checked, selected with [`Options::presets`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+	/// Speaker notes of `pdfpc.speaker-note(..)`.
+	Polylux,
+	/// Speaker notes of `speaker-note[..]`.
+	Touying,
+	/// Labels of drawings in `canvas(..)`.
+	Cetz,
+	/// Language names and settings of `codly(..)`.
+	Codly,
+	/// Abbreviations of the glossary entries in `print-glossary(..)` and the keys of `gls(..)`.
+	Glossarium,
+}
+
+impl Preset {
+	/// Functions whose arguments are not checked.
+	pub fn skip(self) -> &'static [&'static str] {
+		match self {
+			Self::Polylux | Self::Touying => &[],
+			Self::Cetz => &["canvas"],
+			Self::Codly => &["codly"],
+			Self::Glossarium => &["print-glossary", "gls", "glspl", "Gls", "Glspl"],
+		}
+	}
+
+	/// Functions whose arguments are checked as separate paragraphs.
+	pub fn check(self) -> &'static [&'static str] {
+		match self {
+			Self::Polylux | Self::Touying => &["speaker-note"],
+			Self::Cetz | Self::Codly | Self::Glossarium => &[],
+		}
+	}
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			bibliography: false,
+			citations: false,
+			captions: true,
+			alt_text: true,
+			attributions: true,
+			metadata: true,
+			headings: true,
+			lists: true,
+			tables: true,
+			footnotes: true,
+			math: true,
+			comments: false,
+			code_strings: false,
+			hidden: false,
+			labels: false,
+			templates: false,
+			skip: Vec::new(),
+			check: Vec::new(),
+			placeholders: Vec::new(),
+			presets: Vec::new(),
+			normalize: false,
+			links: false,
+		}
+	}
+}
+
+/// Why a part of the source is not checked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", content = "function", rename_all = "kebab-case")]
+pub enum Reason {
+	Citation,
+	Bibliography,
+	Heading,
+	List,
+	Math,
+	Comment,
+	Caption,
+	Attribution,
+	AltText,
+	Metadata,
+	/// Content that is never shown, like `hide[..]`.
+	Hidden,
+	/// Raw text of Markdown, like code blocks and html tags.
+	Raw,
+	/// Text of a placeholder function, see [`Options::placeholders`].
+	Placeholder(String),
+	/// Arguments of a call of the function, see [`Options::skips`].
+	Function(String),
+	/// Text not checked before the time budget of the run was used up.
+	TimeBudget,
+}
+
+/// Byte range of the source that is not checked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Skipped {
+	pub range: Range<usize>,
+	#[serde(flatten)]
+	pub reason: Reason,
+}
+
+/// Parts of the source that are not checked with the options, sorted by their start.
+/// Parts checked as separate paragraphs instead, like footnotes, are not included.
+pub fn skipped(source: &Source, options: &Options) -> Vec<Skipped> {
+	let mut skipped = Regions::new(source, options).reasons;
+	skipped.sort_by_key(|skipped| skipped.range.start);
+	skipped
+}
+
+/// Span numbers of the syntax nodes in the source treated specially by the options.
+#[derive(Debug)]
+struct Regions {
+	file_id: FileId,
+	/// Length of the source text in bytes, the buffers of the chunks are sized with it.
+	len: usize,
+	/// Excluded from checking.
+	skipped: Vec<RangeInclusive<u64>>,
+	/// Captions and the figures themselves for supplements like "Figure 1:".
+	captions: Vec<RangeInclusive<u64>>,
+	/// Byte ranges of string literals checked as separate paragraphs, like `alt` texts.
+	strings: Vec<Range<usize>>,
+	/// Byte ranges of the text of comments, without their markers.
+	comments: Vec<Range<usize>>,
+	/// Content that is never shown, checked as separate paragraphs.
+	/// Also the arguments of the functions of [`Options::check`].
+	hidden: Vec<Span>,
+	/// Byte ranges of the names of labels and references, see [`Options::labels`].
+	labels: Vec<Range<usize>>,
+	/// Language and region after every `set text(..)` with one of them, the scope is ignored.
+	languages: Vec<(u64, Lang, Option<Region>)>,
+	/// Options of the regions, also used for the hidden content.
+	options: Options,
+	/// Byte ranges of the skipped nodes, comments and hidden content that are not checked.
+	reasons: Vec<Skipped>,
+}
+
+impl Regions {
+	fn new(source: &Source, options: &Options) -> Self {
+		let mut regions = Self {
+			file_id: source.id(),
+			len: source.text().len(),
+			skipped: Vec::new(),
+			captions: Vec::new(),
+			strings: Vec::new(),
+			comments: Vec::new(),
+			hidden: Vec::new(),
+			labels: Vec::new(),
+			languages: Vec::new(),
+			options: options.clone(),
+			reasons: Vec::new(),
+		};
+		regions.visit(&LinkedNode::new(source.root()), options);
+		regions
+	}
+
+	fn visit(&mut self, node: &LinkedNode, options: &Options) {
+		if options.labels {
+			self.labels.extend(label_name(node));
+		}
+		let skipped = match node.kind() {
+			SyntaxKind::Ref => options.citations.not(),
+			SyntaxKind::Heading => options.headings.not(),
+			SyntaxKind::ListItem | SyntaxKind::EnumItem | SyntaxKind::TermItem => {
+				options.lists.not()
+			},
+			SyntaxKind::Equation => options.math.not(),
+			SyntaxKind::LineComment | SyntaxKind::BlockComment => {
+				if options.comments {
+					let comment = comment(node).map(|(offset, text)| offset..offset + text.len());
+					self.comments.extend(comment);
+				} else {
+					self.skip(node, Reason::Comment);
+				}
+				false
+			},
+			// Also skipped in the document, so shown strings are not checked twice.
+			SyntaxKind::Str if options.code_strings && code_string(node) => {
+				self.strings.push(node.range());
+				true
+			},
+			SyntaxKind::FuncCall => match callee(node) {
+				Some("bibliography") => options.bibliography.not(),
+				Some("cite") => options.citations.not(),
+				Some("figure") => {
+					let number = node.span().number();
+					self.captions.push(number..=number);
+					false
+				},
+				Some(function) if options.skips(function) => true,
+				// Checked on their own, so they neither split the sentence of their marker
+				// nor are checked between the paragraphs of the page.
+				Some("footnote") => {
+					self.check_call(node);
+					true
+				},
+				Some(function) if options.checks(function) => {
+					self.check_call(node);
+					true
+				},
+				_ => false,
+			},
+			// Checked on their own, not at every use.
+			SyntaxKind::Closure | SyntaxKind::ShowRule if options.templates => {
+				match template(node) {
+					Some(body) => {
+						self.hidden.push(body.span());
+						true
+					},
+					None => false,
+				}
+			},
+			SyntaxKind::SetRule if callee(node) == Some("text") => {
+				self.set_text(node);
+				false
+			},
+			SyntaxKind::Named => match named(node) {
+				Some(("figure", "caption")) if options.captions => {
+					self.captions.push(numbers(node));
+					false
+				},
+				Some(("figure", "caption")) => true,
+				Some(("quote", "attribution")) => options.attributions.not(),
+				Some(("image", "alt")) => {
+					if options.alt_text {
+						self.strings(node);
+					}
+					true
+				},
+				Some(("document", "title" | "author" | "keywords")) => {
+					if options.metadata {
+						self.strings(node);
+					}
+					true
+				},
+				_ => false,
+			},
+			_ => false,
+		};
+		if skipped {
+			self.skipped.push(numbers(node));
+			if let Some(reason) = reason(node, options) {
+				self.skip(node, reason);
+			}
+			return;
+		}
+		if options.hidden {
+			self.hidden.extend(hidden(node));
+		} else {
+			let spans = hidden(node);
+			for child in node
+				.children()
+				.filter(|child| spans.contains(&child.span()))
+			{
+				self.skip(&child, Reason::Hidden);
+			}
+		}
+		for child in node.children() {
+			self.visit(&child, options);
+		}
+	}
+
+	fn skip(&mut self, node: &LinkedNode, reason: Reason) {
+		let range = node.range();
+		self.reasons.push(Skipped { range, reason });
+	}
 
-	for page in &doc.pages {
-		let mut converter = Converter::new(chunk_size, Lang::ENGLISH);
-		converter.frame(&page.frame, Point::zero(), &mut res, file_id);
-		if converter.contains_file {
-			res.push((converter.text, converter.mapping));
+	/// Track the language and region of `set text(lang: .., region: ..)`.
+	fn set_text(&mut self, node: &LinkedNode) {
+		let Some(rule) = node.cast::<ast::SetRule>() else {
+			return;
+		};
+		let (mut lang, mut region) = match self.languages.last() {
+			Some(&(_, lang, region)) => (lang, region),
+			None => (Lang::ENGLISH, None),
+		};
+		let mut changed = false;
+		for arg in rule.args().items() {
+			let ast::Arg::Named(named) = arg else {
+				continue;
+			};
+			let value = match named.expr() {
+				ast::Expr::Str(value) => Some(value.get()),
+				_ => None,
+			};
+			match named.name().get().as_str() {
+				"lang" => {
+					let Some(value) = value.and_then(|value| value.parse().ok()) else {
+						continue;
+					};
+					if value != lang {
+						region = None;
+					}
+					lang = value;
+				},
+				"region" => region = value.and_then(|value| value.parse().ok()),
+				_ => continue,
+			}
+			changed = true;
 		}
+		if changed {
+			self.languages.push((node.span().number(), lang, region));
+		}
+	}
+
+	/// Region for the language at the span, `None` if it does not change the region.
+	fn region(&self, span: Span, lang: Lang) -> Option<Option<Region>> {
+		if self.in_file(span).not() {
+			return None;
+		}
+		let number = span.number();
+		let index = self.languages.partition_point(|&(n, _, _)| n <= number);
+		let region = match index.checked_sub(1).map(|index| self.languages[index]) {
+			Some((_, set_lang, region)) if set_lang == lang => region,
+			_ => None,
+		};
+		Some(region)
+	}
+
+	/// Add the string literals of the named argument, also inside of arrays.
+	fn strings(&mut self, named: &LinkedNode) {
+		let Some(value) = named.children().last() else {
+			return;
+		};
+		let strings = match value.kind() {
+			SyntaxKind::Str => vec![value],
+			SyntaxKind::Array => value.children().collect(),
+			_ => Vec::new(),
+		};
+		let strings = strings.into_iter().filter(|s| s.kind() == SyntaxKind::Str);
+		self.strings.extend(strings.map(|s| s.range()));
+	}
+
+	/// Add the string literals and content of the arguments, skipped in the document.
+	fn check_call(&mut self, call: &LinkedNode) {
+		let Some(args) = call
+			.children()
+			.find(|child| child.kind() == SyntaxKind::Args)
+		else {
+			return;
+		};
+		for arg in args.children() {
+			match arg.kind() {
+				SyntaxKind::Str => self.strings.push(arg.range()),
+				SyntaxKind::Named => self.strings(&arg),
+				_ => {},
+			}
+		}
+		self.hidden.push(args.span());
+	}
+
+	fn in_file(&self, span: Span) -> bool {
+		span.id() == Some(self.file_id)
+	}
+
+	fn skipped(&self, span: Span) -> bool {
+		self.in_file(span) && contains(&self.skipped, span)
+	}
+
+	/// If the span is part of a caption, `None` for spans outside of the file.
+	fn caption(&self, span: Span) -> Option<bool> {
+		self.in_file(span).then(|| contains(&self.captions, span))
+	}
+
+	/// The strings as chunks, with the escape sequences resolved, the comments
+	/// and the hidden content.
+	fn extra_chunks(
+		&self,
+		source: &Source,
+		chunk_size: usize,
+		language: Lang,
+	) -> Vec<(String, Mapping)> {
+		let mut converter = ByteConverter::new(chunk_size, language, source.text().len());
+		for range in &self.strings {
+			// without the quotes
+			let range = (range.start + 1)..(range.end - 1);
+			string(&mut converter, &source.text()[range.clone()], range.start);
+			converter.parbreak();
+		}
+		for range in &self.comments {
+			converter.push(&source.text()[range.clone()], range.start);
+			converter.parbreak();
+		}
+		for &span in &self.hidden {
+			if let Some(node) = source.find(span) {
+				markup::markup(&mut converter, &node, &self.options);
+				converter.parbreak();
+			}
+		}
+		markup::footnotes(&mut converter, source, &self.options);
+		converter.labels.clone_from(&self.labels);
+		labels(&mut converter, source);
+		converter.finish()
+	}
+}
+
+/// Why the skipped node is not checked, `None` if it is checked as separate paragraph.
+fn reason(node: &LinkedNode, options: &Options) -> Option<Reason> {
+	let reason = match node.kind() {
+		SyntaxKind::Ref => Reason::Citation,
+		SyntaxKind::Heading => Reason::Heading,
+		SyntaxKind::ListItem | SyntaxKind::EnumItem | SyntaxKind::TermItem => Reason::List,
+		SyntaxKind::Equation => Reason::Math,
+		SyntaxKind::FuncCall => match callee(node)? {
+			"bibliography" => Reason::Bibliography,
+			"cite" => Reason::Citation,
+			function if options.is_placeholder(function) => Reason::Placeholder(function.into()),
+			function if options.skips(function) => Reason::Function(function.into()),
+			_ => return None,
+		},
+		SyntaxKind::Named => match named(node)? {
+			("figure", "caption") => Reason::Caption,
+			("quote", "attribution") => Reason::Attribution,
+			("image", "alt") if options.alt_text.not() => Reason::AltText,
+			("document", _) if options.metadata.not() => Reason::Metadata,
+			_ => return None,
+		},
+		_ => return None,
+	};
+	Some(reason)
+}
+
+fn contains(numbers: &[RangeInclusive<u64>], span: Span) -> bool {
+	numbers
+		.iter()
+		.any(|numbers| numbers.contains(&span.number()))
+}
+
+/// Span numbers of the node and its descendants, they form an interval.
+fn numbers(node: &SyntaxNode) -> RangeInclusive<u64> {
+	let mut numbers = node.span().number()..=node.span().number();
+	let mut stack = vec![node];
+	while let Some(node) = stack.pop() {
+		let number = node.span().number();
+		numbers = *numbers.start().min(&number)..=*numbers.end().max(&number);
+		stack.extend(node.children());
+	}
+	numbers
+}
+
+/// Body of a function or the content replacing the elements of a show rule, checked with
+/// [`Options::templates`]. Show rules with a function are handled by the function.
+fn template<'a>(node: &LinkedNode<'a>) -> Option<LinkedNode<'a>> {
+	let last = node
+		.children()
+		.filter(|child| child.kind().is_trivia().not())
+		.last()?;
+	match node.kind() {
+		SyntaxKind::Closure => Some(last),
+		SyntaxKind::ShowRule if last.kind() == SyntaxKind::ContentBlock => Some(last),
+		_ => None,
+	}
+}
+
+/// Name of the called or set function, the last field for calls like `module.name(..)`.
+fn callee(node: &SyntaxNode) -> Option<&str> {
+	let callee = match node.cast::<ast::FuncCall>() {
+		Some(call) => call.callee(),
+		None => node.cast::<ast::SetRule>()?.target(),
+	};
+	match callee {
+		ast::Expr::Ident(ident) => Some(ident.get().as_str()),
+		ast::Expr::FieldAccess(access) => Some(access.field().get().as_str()),
+		_ => None,
+	}
+}
+
+/// If the call is a quote shown as a block, like `quote(block: true)[..]`.
+fn block_quote(node: &LinkedNode) -> bool {
+	let Some(call) = node.cast::<ast::FuncCall>() else {
+		return false;
+	};
+	call.args().items().any(|arg| match arg {
+		ast::Arg::Named(named) => {
+			named.name().get() == "block" && matches!(named.expr(), ast::Expr::Bool(b) if b.get())
+		},
+		_ => false,
+	})
+}
+
+/// Children of the node that are never shown: the arguments of `hide(..)`
+/// and the branch of `if true` or `if false` that is not taken.
+fn hidden(node: &LinkedNode) -> Vec<Span> {
+	let mut children = node.children().filter(|child| {
+		let kind = child.kind();
+		kind.is_trivia().not() && kind != SyntaxKind::If && kind != SyntaxKind::Else
+	});
+	match node.kind() {
+		SyntaxKind::FuncCall if callee(node) == Some("hide") => children
+			.filter(|child| child.kind() == SyntaxKind::Args)
+			.map(|child| child.span())
+			.collect(),
+		SyntaxKind::Conditional => {
+			let Some(condition) = children.next() else {
+				return Vec::new();
+			};
+			let Some(ast::Expr::Bool(condition)) = condition.cast() else {
+				return Vec::new();
+			};
+			// The body of the `if` and of the `else`.
+			let skip = if condition.get() { 1 } else { 0 };
+			children
+				.nth(skip)
+				.map(|child| child.span())
+				.into_iter()
+				.collect()
+		},
+		_ => Vec::new(),
+	}
+}
+
+/// Names of the function and the argument for named arguments like `image(alt: ..)`
+/// or `set document(title: ..)`.
+fn named<'a>(node: &'a LinkedNode) -> Option<(&'a str, &'a str)> {
+	let name = node.cast::<ast::Named>()?.name().get().as_str();
+	let call = node.parent()?.parent()?;
+	Some((callee(call.get())?, name))
+}
+
+/// Functions whose string arguments are names, paths or patterns, not text.
+const NAME_FUNCTIONS: &[&str] = &[
+	"image",
+	"bibliography",
+	"read",
+	"json",
+	"csv",
+	"yaml",
+	"toml",
+	"xml",
+	"cbor",
+	"plugin",
+	"label",
+	"link",
+	"numbering",
+	"counter",
+	"state",
+	"regex",
+	"datetime",
+	"lorem",
+];
+
+/// If the string literal is text checked with [`Options::code_strings`].
+fn code_string(node: &LinkedNode) -> bool {
+	let Some(parent) = node.parent() else {
+		return true;
+	};
+	if matches!(
+		parent.kind(),
+		SyntaxKind::Named | SyntaxKind::Keyed | SyntaxKind::Binary
+	) {
+		return false;
+	}
+	let mut ancestor = Some(parent);
+	while let Some(node) = ancestor {
+		match node.kind() {
+			SyntaxKind::SetRule
+			| SyntaxKind::ShowRule
+			| SyntaxKind::ModuleImport
+			| SyntaxKind::ModuleInclude
+			| SyntaxKind::Equation => return false,
+			SyntaxKind::FuncCall if callee(node).is_some_and(|f| NAME_FUNCTIONS.contains(&f)) => {
+				return false
+			},
+			SyntaxKind::ContentBlock | SyntaxKind::Markup => return true,
+			_ => {},
+		}
+		ancestor = node.parent();
+	}
+	true
+}
+
+/// Byte offset and text of a comment without its markers, `None` for the comments
+/// disabling rules like `// lt-disable-next-line` and setting the language of the file.
+fn comment<'a>(node: &'a LinkedNode) -> Option<(usize, &'a str)> {
+	let text = node.text().as_str();
+	let (offset, text) = match node.kind() {
+		SyntaxKind::LineComment => (2, text.strip_prefix("//")?),
+		SyntaxKind::BlockComment => {
+			let text = text.strip_prefix("/*")?;
+			(2, text.strip_suffix("*/").unwrap_or(text))
+		},
+		_ => return None,
+	};
+	let trimmed = text.trim();
+	if trimmed.is_empty()
+		|| trimmed.starts_with(crate::suppress::DISABLE_NEXT_LINE)
+		|| trimmed.starts_with(FILE_LANGUAGE)
+	{
+		return None;
+	}
+	let offset = offset + text.len() - text.trim_start().len();
+	Some((node.offset() + offset, trimmed))
+}
+
+/// Byte range of the name of a label like `<sec-introduction>` or a reference like
+/// `@sec-introduction`.
+fn label_name(node: &LinkedNode) -> Option<Range<usize>> {
+	let range = match node.kind() {
+		SyntaxKind::Label => node.range(),
+		SyntaxKind::Ref => node
+			.children()
+			.find(|child| child.kind() == SyntaxKind::RefMarker)?
+			.range(),
+		_ => return None,
+	};
+	match node.kind() {
+		SyntaxKind::Label => Some(range.start + 1..range.end - 1),
+		_ => Some(range.start + 1..range.end),
+	}
+}
+
+/// The words of the collected labels and references as a chunk checked only for spelling,
+/// see [`Options::labels`]. Other chars separate the words.
+fn labels(converter: &mut ByteConverter, source: &Source) {
+	if converter.labels.is_empty() {
+		return;
+	}
+	converter.seperate();
+	converter.spelling = true;
+	for range in std::mem::take(&mut converter.labels) {
+		let name = &source.text()[range.clone()];
+		let start = name.rfind(':').map_or(0, |colon| colon + 1);
+		for (index, c) in name.char_indices().skip_while(|&(index, _)| index < start) {
+			let offset = range.start + index;
+			if c.is_alphanumeric() {
+				converter.push(&name[index..index + c.len_utf8()], offset);
+			} else if converter.text.ends_with([' ', '\n']).not() {
+				converter.replace(" ", offset..offset + c.len_utf8());
+			}
+		}
+		converter.parbreak();
+	}
+	converter.seperate();
+	converter.spelling = false;
+}
+
+/// Add the content of a string literal, escape sequences map to their source bytes.
+fn string(converter: &mut ByteConverter, text: &str, offset: usize) {
+	let mut buffer = [0; 4];
+	let mut chars = text.char_indices().peekable();
+	while let Some((index, c)) = chars.next() {
+		if c != '\\' {
+			converter.push(&text[index..index + c.len_utf8()], offset + index);
+			continue;
+		}
+		let Some((_, escaped)) = chars.next() else {
+			break;
+		};
+		let mut end = index + 2;
+		let replacement = match escaped {
+			'n' | 't' => Some(' '),
+			'r' => None,
+			'u' => {
+				for (i, c) in chars.by_ref() {
+					end = i + 1;
+					if c == '}' {
+						break;
+					}
+				}
+				let code = text[index + 2..end]
+					.trim_start_matches('{')
+					.trim_end_matches('}');
+				u32::from_str_radix(code, 16).ok().and_then(char::from_u32)
+			},
+			c => Some(c),
+		};
+		let replacement = replacement.map_or("", |c| c.encode_utf8(&mut buffer));
+		converter.replace(replacement, (offset + index)..(offset + end));
+	}
+}
+
+const LINE_SPACING: Em = Em::new(0.65);
+
+/// If the document contains text of the file, like for a file included by the main file.
+pub fn includes(doc: &Document, file_id: FileId) -> bool {
+	fn contains(frame: &Frame, file_id: FileId) -> bool {
+		compat::items(frame).any(|(_, item)| match item {
+			Item::Group(frame) => contains(frame, file_id),
+			Item::Text(text) => text
+				.glyphs
+				.iter()
+				.any(|glyph| glyph.span.0.id() == Some(file_id)),
+			Item::Other => false,
+		})
+	}
+	doc.pages.iter().any(|page| contains(&page.frame, file_id))
+}
+
+/// Convert the text of the `source` file in the document, the pages of sources larger than
+/// [`markup::PARALLEL_BYTES`] are converted on several threads.
+pub fn document(
+	doc: &Document,
+	chunk_size: usize,
+	source: &Source,
+	options: &Options,
+) -> Vec<(String, Mapping)> {
+	let frames = doc.pages.iter().map(|page| &page.frame).collect::<Vec<_>>();
+	convert_frames(&frames, chunk_size, source, options)
+}
+
+/// Like [`document`], but only the pages with text from the byte ranges of the source are
+/// converted, like the pages of some lines in a large document. The chunks are kept like with
+/// [`retain_ranges`].
+pub fn document_ranges(
+	doc: &Document,
+	chunk_size: usize,
+	source: &Source,
+	options: &Options,
+	ranges: &[Range<usize>],
+) -> Vec<(String, Mapping)> {
+	let mut numbers = Vec::new();
+	for range in ranges {
+		range_numbers(&LinkedNode::new(source.root()), range, &mut numbers);
+	}
+	let frames = doc
+		.pages
+		.iter()
+		.map(|page| &page.frame)
+		.filter(|frame| shows(frame, source.id(), &numbers))
+		.collect::<Vec<_>>();
+	let mut res = convert_frames(&frames, chunk_size, source, options);
+	retain_ranges(&mut res, source, ranges);
+	res
+}
+
+/// Span numbers of the nodes overlapping the byte range, the numbers of a node inside the range
+/// and its descendants are one interval.
+fn range_numbers(
+	node: &LinkedNode,
+	range: &Range<usize>,
+	intervals: &mut Vec<RangeInclusive<u64>>,
+) {
+	let (start, end) = (node.offset(), node.offset() + node.len());
+	if start >= range.end || end <= range.start {
+		return;
+	}
+	if range.start <= start && end <= range.end {
+		intervals.push(numbers(node.get()));
+		return;
+	}
+	let number = node.span().number();
+	intervals.push(number..=number);
+	for child in node.children() {
+		range_numbers(&child, range, intervals);
+	}
+}
+
+/// If the frame has text of the nodes with the span numbers in the file.
+fn shows(frame: &Frame, file_id: FileId, numbers: &[RangeInclusive<u64>]) -> bool {
+	compat::items(frame).any(|(_, item)| match item {
+		Item::Group(frame) => shows(frame, file_id, numbers),
+		Item::Text(text) => text
+			.glyphs
+			.iter()
+			.any(|glyph| glyph.span.0.id() == Some(file_id) && contains(numbers, glyph.span.0)),
+		Item::Other => false,
+	})
+}
+
+fn convert_frames(
+	frames: &[&Frame],
+	chunk_size: usize,
+	source: &Source,
+	options: &Options,
+) -> Vec<(String, Mapping)> {
+	let _span = trace::span!("convert", pages = frames.len()).entered();
+	let regions = Regions::new(source, options);
+	let mut res = Vec::new();
+	for converted in page_frames(frames, chunk_size, &regions) {
+		res.extend(converted);
+	}
+	let language = res
+		.first()
+		.map_or(Lang::ENGLISH, |(_, mapping)| mapping.language);
+	res.extend(regions.extra_chunks(source, chunk_size, language));
+	apply_file_language(&mut res, source);
+	if options.links.not() {
+		placeholders(&mut res);
+	}
+	if options.normalize {
+		normalize(&mut res);
+	}
+	res
+}
+
+/// Convert the pages, for sources larger than [`markup::PARALLEL_BYTES`] on several threads.
+/// Every page is converted on its own, so the results are the same.
+fn page_frames(
+	frames: &[&Frame],
+	chunk_size: usize,
+	regions: &Regions,
+) -> Vec<Vec<(String, Mapping)>> {
+	let threads = std::thread::available_parallelism().map_or(1, usize::from);
+	if regions.len <= markup::PARALLEL_BYTES || threads == 1 || frames.len() < 2 {
+		return frames
+			.iter()
+			.map(|frame| page_frame(frame, chunk_size, regions))
+			.collect();
+	}
+	let per_thread = frames.len().div_ceil(threads);
+	std::thread::scope(|scope| {
+		let handles = frames
+			.chunks(per_thread)
+			.map(|frames| {
+				scope.spawn(move || {
+					frames
+						.iter()
+						.map(|frame| page_frame(frame, chunk_size, regions))
+						.collect::<Vec<_>>()
+				})
+			})
+			.collect::<Vec<_>>();
+		handles
+			.into_iter()
+			.flat_map(|handle| {
+				handle
+					.join()
+					.unwrap_or_else(|err| std::panic::resume_unwind(err))
+			})
+			.collect()
+	})
+}
+
+fn page_frame(frame: &Frame, chunk_size: usize, regions: &Regions) -> Vec<(String, Mapping)> {
+	let mut res = Vec::new();
+	let mut converter = Converter::new(chunk_size, Lang::ENGLISH, regions);
+	converter.frame(frame, Point::zero(), &mut res);
+	if converter.contains_file {
+		let (text, mapping) = converter.take();
+		res.extend(split_sentences(text, mapping, chunk_size));
 	}
 	res
 }
 
-struct Converter {
+/// Converts documents like [`document`], but reuses the results for unchanged pages.
+/// Pages are converted independently, so only the pages touched by an edit are converted again.
+#[derive(Debug)]
+pub struct Incremental {
+	chunk_size: usize,
+	options: Options,
+	/// Converted pages by the hash of their frame.
+	pages: HashMap<u128, Vec<(String, Mapping)>>,
+}
+
+impl Incremental {
+	pub fn new(chunk_size: usize, options: Options) -> Self {
+		Self {
+			chunk_size,
+			options,
+			pages: HashMap::new(),
+		}
+	}
+
+	/// Convert the text of the `source` file in the document.
+	pub fn document(&mut self, doc: &Document, source: &Source) -> Vec<(String, Mapping)> {
+		let _span = trace::span!("convert", pages = doc.pages.len()).entered();
+		let regions = Regions::new(source, &self.options);
+		let hashes = doc
+			.pages
+			.iter()
+			.map(|page| compat::hash(&page.frame))
+			.collect::<Vec<_>>();
+		// The changed pages are converted together, on several threads for large sources.
+		let mut changed = HashMap::new();
+		for (page, hash) in doc.pages.iter().zip(&hashes) {
+			if self.pages.contains_key(hash).not() {
+				changed.entry(*hash).or_insert(&page.frame);
+			}
+		}
+		let (changed, frames): (Vec<_>, Vec<_>) = changed.into_iter().unzip();
+		let converted = page_frames(&frames, self.chunk_size, &regions);
+		let mut converted = changed
+			.into_iter()
+			.zip(converted)
+			.collect::<HashMap<_, _>>();
+		let mut pages = HashMap::with_capacity(doc.pages.len());
+		let mut res = Vec::new();
+		for hash in hashes {
+			let converted = self
+				.pages
+				.remove(&hash)
+				.or_else(|| converted.remove(&hash))
+				.or_else(|| pages.get(&hash).cloned())
+				.unwrap_or_default();
+			res.extend(converted.iter().cloned());
+			pages.insert(hash, converted);
+		}
+		self.pages = pages;
+		let language = res
+			.first()
+			.map_or(Lang::ENGLISH, |(_, mapping)| mapping.language);
+		res.extend(regions.extra_chunks(source, self.chunk_size, language));
+		apply_file_language(&mut res, source);
+		if self.options.links.not() {
+			placeholders(&mut res);
+		}
+		if self.options.normalize {
+			normalize(&mut res);
+		}
+		res
+	}
+}
+
+struct Converter<'a> {
 	text: String,
-	mapping: Mapping,
+	chars: Vec<(Span, Range<u16>)>,
+	language: Lang,
+	region: Option<Region>,
 	x: Abs,
 	y: Abs,
 	span: (Span, u16),
 	chunk_size: usize,
 	contains_file: bool,
+	regions: &'a Regions,
+	in_caption: bool,
 }
 
-impl Converter {
-	fn new(chunk_size: usize, language: Lang) -> Self {
+impl<'a> Converter<'a> {
+	fn new(chunk_size: usize, language: Lang, regions: &'a Regions) -> Self {
 		Self {
 			text: String::new(),
-			mapping: Mapping { chars: Vec::new(), language },
+			chars: Vec::new(),
+			language,
+			region: None,
 			x: Abs::zero(),
 			y: Abs::zero(),
 			span: (Span::detached(), 0),
 			contains_file: false,
 			chunk_size,
+			regions,
+			in_caption: false,
+		}
+	}
+
+	fn reserve(&mut self) {
+		if self.chars.capacity() == 0 {
+			let capacity = self.regions.len.min(self.chunk_size.saturating_mul(2));
+			self.text.reserve(capacity);
+			self.chars.reserve(capacity);
 		}
 	}
 
 	fn insert_space(&mut self) {
 		self.text += " ";
-		self.mapping.chars.push((Span::detached(), 0..0));
+		self.chars.push((Span::detached(), 0..0));
 	}
 
 	fn seperate(&mut self, res: &mut Vec<(String, Mapping)>) {
-		let language = self.mapping.language;
+		let (language, region) = (self.language, self.region);
 		if self.contains_file {
-			let text = std::mem::take(&mut self.text);
-			let mapping = std::mem::replace(
-				&mut self.mapping,
-				Mapping {
-					chars: Vec::new(),
-					language: Lang::ENGLISH,
-				},
-			);
-			res.push((text, mapping));
+			let (text, mapping) = self.take();
+			res.extend(split_sentences(text, mapping, self.chunk_size));
 		}
-		*self = Converter::new(self.chunk_size, language);
+		*self = Converter::new(self.chunk_size, language, self.regions);
+		self.region = region;
+	}
+
+	fn take(&mut self) -> (String, Mapping) {
+		let text = std::mem::take(&mut self.text);
+		let chars = std::mem::take(&mut self.chars);
+		let mapping = Mapping {
+			chars: Chars::Spans(chars),
+			language: self.language,
+			region: self.region,
+			spelling: false,
+			code: None,
+		};
+		(text, mapping)
 	}
 
 	fn insert_parbreak(&mut self, res: &mut Vec<(String, Mapping)>) {
-		if self.mapping.chars.len() > self.chunk_size {
+		if self.chars.len() > self.chunk_size {
 			self.seperate(res);
 			return;
 		}
 		self.text += "\n\n";
-		self.mapping.chars.push((Span::detached(), 0..0));
-		self.mapping.chars.push((Span::detached(), 0..0));
+		self.chars.push((Span::detached(), 0..0));
+		self.chars.push((Span::detached(), 0..0));
 	}
 
 	fn whitespace(&mut self, text: &TextItem, pos: Point, res: &mut Vec<(String, Mapping)>) {
@@ -166,56 +1859,625 @@ impl Converter {
 		self.insert_space();
 	}
 
-	fn frame(
-		&mut self,
-		frame: &typst::layout::Frame,
-		pos: Point,
-		res: &mut Vec<(String, Mapping)>,
-		file_id: FileId,
-	) {
-		for &(p, ref item) in frame.items() {
-			self.item(p + pos, item, res, file_id);
+	fn frame(&mut self, frame: &Frame, pos: Point, res: &mut Vec<(String, Mapping)>) {
+		for (p, item) in compat::items(frame) {
+			self.item(p + pos, item, res);
 		}
 	}
 
-	fn item(
-		&mut self,
-		pos: Point,
-		item: &typst::layout::FrameItem,
-		res: &mut Vec<(String, Mapping)>,
-		file_id: FileId,
-	) {
-		use typst::introspection::Meta as M;
-		use typst::layout::FrameItem as I;
+	fn item(&mut self, pos: Point, item: Item, res: &mut Vec<(String, Mapping)>) {
 		match item {
-			I::Group(g) => self.frame(&g.frame, pos, res, file_id),
-			I::Text(t) => {
-				if self.mapping.language != t.lang {
+			Item::Group(frame) => self.frame(frame, pos, res),
+			Item::Text(t) if t.glyphs.iter().all(|g| self.regions.skipped(g.span.0)) => {},
+			Item::Text(t) => {
+				let region = t
+					.glyphs
+					.first()
+					.and_then(|g| self.regions.region(g.span.0, t.lang))
+					.unwrap_or(if self.language == t.lang {
+						self.region
+					} else {
+						None
+					});
+				if self.language != t.lang || self.region != region {
 					self.seperate(res);
 				}
-				self.mapping.language = t.lang;
+				self.language = t.lang;
+				self.region = region;
 
 				self.whitespace(t, pos, res);
 				self.x = pos.x + t.width();
 				self.y = pos.y;
-				self.text += t.text.as_str();
-
-				let mut iter = t.glyphs.iter();
-				for _ in t.text.encode_utf16() {
-					let g = iter.next();
-					let m = g
-						.map(|g| (g.span.0, g.span.1..(g.span.1 + g.range.len() as u16)))
-						.unwrap_or((Span::detached(), 0..0));
-					if let Some(id) = m.0.id() {
-						self.span = (m.0, m.1.end);
-						if id == file_id {
-							self.contains_file = true;
+
+				self.reserve();
+				let mut glyph = 0;
+				for (index, c) in t.text.char_indices() {
+					let first = char_location(t, &mut glyph, index, c);
+					let span = first.0;
+					if self.regions.skipped(span) || c == SOFT_HYPHEN || footnote_marker(span, c) {
+						continue;
+					}
+					match self.regions.caption(span) {
+						Some(caption) if caption != self.in_caption => {
+							self.insert_parbreak(res);
+							self.in_caption = caption;
+						},
+						_ => {},
+					}
+					self.text.push(c);
+					// a char has at most two UTF-16 code units
+					let second = (c.len_utf16() == 2).then(|| first.clone());
+					for m in std::iter::once(first).chain(second) {
+						if let Some(id) = m.0.id() {
+							self.span = (m.0, m.1.end);
+							if id == self.regions.file_id {
+								self.contains_file = true;
+							}
 						}
+						self.chars.push(m);
 					}
-					self.mapping.chars.push(m);
 				}
 			},
-			I::Meta(M::Link(..) | M::Elem(..) | M::Hide, _) | I::Shape(..) | I::Image(..) => {},
+			Item::Other => {},
 		}
 	}
 }
+
+/// Generated footnote markers like `¹` are not part of the text.
+fn footnote_marker(span: Span, c: char) -> bool {
+	span.is_detached() && matches!(c, '\u{b9}' | '\u{b2}' | '\u{b3}' | '\u{2070}'..='\u{2079}')
+}
+
+/// Span and bytes in the span of the char at the byte `index` of the text. The glyph of a ligature,
+/// an emoji or an escape like `\u{1F600}` covers several bytes and chars of the text.
+/// `glyph` is the index of the glyph of the previous char.
+fn char_location(text: &TextItem, glyph: &mut usize, index: usize, c: char) -> (Span, Range<u16>) {
+	let covers = |g: &typst::text::Glyph| g.range().contains(&index);
+	let found = text.glyphs[*glyph..]
+		.iter()
+		.position(covers)
+		.map(|offset| *glyph + offset)
+		.or_else(|| text.glyphs.iter().position(covers));
+	let Some(found) = found else {
+		return (Span::detached(), 0..0);
+	};
+	*glyph = found;
+	let g = &text.glyphs[found];
+	let start = g.span.1 + (index - g.range().start) as u16;
+	(g.span.0, start..(start + c.len_utf8() as u16))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Location in the source of the first occurrence of the word in the converted text.
+	fn location(
+		converted: &str,
+		mapping: &Mapping,
+		source: &Source,
+		word: &str,
+	) -> Vec<Range<usize>> {
+		let start = converted.find(word).unwrap();
+		let utf_16 = |index: usize| converted[..index].encode_utf16().count();
+		let suggestion = Suggestion::test(utf_16(start), utf_16(start + word.len()));
+		mapping.location(&suggestion, source)
+	}
+
+	#[test]
+	fn test_paragraphs() {
+		let text = "Öne\n\ntwo\n\n\n\nthree";
+		assert_eq!(
+			paragraphs(text),
+			vec![(0, "Öne"), (5, "two"), (12, "three")]
+		);
+	}
+
+	#[test]
+	fn test_split_sentences() {
+		let text = "One two. Three (four.) Five";
+		let mut converter = ByteConverter::new(12, Lang::ENGLISH, 0);
+		converter.push(text, 0);
+		let res = converter.finish();
+		let chunks = res
+			.iter()
+			.map(|(text, _)| text.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(chunks, vec!["One two. ", "Three (four.) ", "Five"]);
+
+		let (_, mapping) = &res[2];
+		let Chars::Bytes(bytes) = &mapping.chars else {
+			panic!("expected byte mapping");
+		};
+		assert_eq!(bytes[0], 23..24);
+	}
+
+	#[test]
+	fn test_regions() {
+		let text = "See @key.\n#bibliography(\"refs.bib\")\n#figure(image(\"a.png\", alt: \"A \\\"cat\\\"\"), caption: [Cat])";
+		let source = Source::detached(text);
+		let regions = Regions::new(&source, &Options::default());
+		let mut stack = vec![source.root()];
+		let (mut skipped, mut captions) = (Vec::new(), Vec::new());
+		while let Some(node) = stack.pop() {
+			if node.children().len() == 0 && regions.skipped(node.span()) {
+				skipped.push(node.text().to_string());
+			}
+			if node.children().len() == 0 && regions.caption(node.span()) == Some(true) {
+				captions.push(node.text().to_string());
+			}
+			stack.extend(node.children());
+		}
+		skipped.sort();
+		captions.sort();
+		assert_eq!(
+			skipped,
+			vec![
+				" ",
+				"\"A \\\"cat\\\"\"",
+				"\"refs.bib\"",
+				"(",
+				")",
+				":",
+				"@key",
+				"alt",
+				"bibliography"
+			]
+		);
+		assert_eq!(captions, vec![" ", ":", "Cat", "[", "]", "caption"]);
+
+		let res = regions.extra_chunks(&source, 1000, Lang::ENGLISH);
+		assert_eq!(res.len(), 1);
+		assert_eq!(res[0].0, "A \"cat\"");
+
+		let options = Options {
+			bibliography: true,
+			citations: true,
+			..Options::default()
+		};
+		assert_eq!(Regions::new(&source, &options).skipped.len(), 1);
+
+		let text = "#set document(title: \"Titel\", author: (\"A\", \"B\"))";
+		let source = Source::detached(text);
+		let regions = Regions::new(&source, &Options::default());
+		let res = regions.extra_chunks(&source, 1000, Lang::ENGLISH);
+		assert_eq!(res[0].0, "Titel\n\nA\n\nB");
+	}
+
+	#[test]
+	fn test_scope() {
+		let text = "= Title\n- Item\n#footnote[Note] #table[Cell]\n// Comment\n// lt-disable-next-line\n#box(\"Code\") #image(\"a.png\") $x$";
+		let source = Source::detached(text);
+		let convert = |options: &Options| {
+			let chunks = markup::document(&source, 1000, Lang::ENGLISH, options);
+			chunks.into_iter().map(|(text, _)| text).collect::<String>()
+		};
+		assert_eq!(
+			convert(&Options::default()),
+			"Title\n\nItem\n\nCell \n\nNote"
+		);
+		let options = Options {
+			headings: false,
+			lists: false,
+			tables: false,
+			footnotes: false,
+			comments: true,
+			code_strings: true,
+			..Options::default()
+		};
+		assert_eq!(convert(&options), "Comment\n\nCode");
+
+		let options = Options { math: false, ..options };
+		let regions = Regions::new(&source, &options);
+		assert_eq!(regions.skipped.len(), 6);
+		let res = regions.extra_chunks(&source, 1000, Lang::ENGLISH);
+		assert_eq!(res[0].0, "Code\n\nComment");
+	}
+
+	#[test]
+	fn test_footnotes() {
+		let text = "The text#footnote[A note#footnote[Nested.].] continues.\n\nNext.";
+		let source = Source::detached(text);
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &Options::default());
+		let (converted, mapping) = &chunks[0];
+		assert_eq!(
+			converted,
+			"The text continues.\n\nNext.\n\nA note.\n\nNested."
+		);
+		let start = converted.find("note").unwrap();
+		let suggestion = Suggestion::test(start, start + 4);
+		assert_eq!(mapping.location(&suggestion, &source), vec![20..24]);
+
+		let regions = Regions::new(&source, &Options::default());
+		let res = regions.extra_chunks(&source, 1000, Lang::ENGLISH);
+		assert_eq!(res[0].0, "A note.\n\nNested.");
+	}
+
+	#[test]
+	fn test_quotes() {
+		let text = "Before\n#quote(block: true, attribution: [Someone])[The quote.]\nafter #quote[inline] text.";
+		let source = Source::detached(text);
+		let convert = |options: &Options| {
+			let chunks = markup::document(&source, 1000, Lang::ENGLISH, options);
+			chunks.into_iter().map(|(text, _)| text).collect::<String>()
+		};
+		assert_eq!(
+			convert(&Options::default()),
+			"Before \n\nSomeone\n\nThe quote.\n\nafter inline text."
+		);
+		let options = Options {
+			attributions: false,
+			..Options::default()
+		};
+		assert_eq!(
+			convert(&options),
+			"Before \n\nThe quote.\n\nafter inline text."
+		);
+		assert_eq!(Regions::new(&source, &options).skipped.len(), 1);
+	}
+
+	#[test]
+	fn test_smart_quotes() {
+		let text = concat!(
+			"\"Hi,\" it's me.\n",
+			"#[#set smartquote(enabled: false)\n\"Straight\"]\n",
+			"#set text(lang: \"de\")\n",
+			"\"Hallo\"",
+		);
+		let source = Source::detached(text);
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &Options::default());
+		let converted = chunks.into_iter().map(|(text, _)| text).collect::<String>();
+		assert_eq!(converted, "“Hi,” it’s me. \"Straight\" „Hallo“");
+	}
+
+	#[test]
+	fn test_set_text() {
+		let text =
+			"Hello\n#set text(lang: \"de\", region: \"ch\")\nGrüezi\n#set text(lang: \"en\")\nHi";
+		let source = Source::detached(text);
+		let regions = Regions::new(&source, &Options::default());
+		let span = |word: &str| {
+			let offset = text.find(word).unwrap();
+			LinkedNode::new(source.root())
+				.leaf_at(offset + 1)
+				.unwrap()
+				.span()
+		};
+		assert_eq!(regions.region(span("Hello"), Lang::ENGLISH), Some(None));
+		let region = regions.region(span("Grüezi"), Lang::GERMAN);
+		assert_eq!(
+			region.flatten().map(|r| r.as_str().to_owned()),
+			Some("CH".into())
+		);
+		assert_eq!(regions.region(span("Hi"), Lang::ENGLISH), Some(None));
+		assert_eq!(regions.region(Span::detached(), Lang::ENGLISH), None);
+	}
+
+	#[test]
+	fn test_hidden() {
+		let text = "Shown #hide[Secret] #if false [Draft] else [Final]";
+		let source = Source::detached(text);
+		let check = |hidden| {
+			let options = Options { hidden, ..Options::default() };
+			let chunks = markup::document(&source, 1000, Lang::ENGLISH, &options);
+			chunks.into_iter().map(|(text, _)| text).collect::<String>()
+		};
+		assert_eq!(check(false), "Shown Final");
+		assert_eq!(check(true), "Shown \n\nSecret\n\nDraft\n\nFinal");
+	}
+
+	#[test]
+	fn test_templates() {
+		let text = "#show heading: it => [Chapter #it.body]\n#let note(body) = [Note #body]\nText #note[this].";
+		let source = Source::detached(text);
+		let check = |templates| {
+			let options = Options { templates, ..Options::default() };
+			let chunks = markup::document(&source, 1000, Lang::ENGLISH, &options);
+			chunks.into_iter().map(|(text, _)| text).collect::<String>()
+		};
+		assert_eq!(check(false), "Chapter Note Text this.");
+		assert_eq!(check(true), "Chapter \n\nNote \n\nText this.");
+	}
+
+	#[test]
+	fn test_labels() {
+		let text = "= Introduction <sec-introducton>\nSee @sec-introducton and @fig:plot_data.";
+		let source = Source::detached(text);
+		let options = Options { labels: true, ..Options::default() };
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &options);
+		assert_eq!(chunks.len(), 2);
+		assert_eq!(chunks[0].0, "Introduction\n\nSee and .");
+		assert!(chunks[0].1.is_spelling_only().not());
+		let (text, mapping) = &chunks[1];
+		assert_eq!(text, "sec introducton\n\nsec introducton\n\nplot data");
+		assert!(mapping.is_spelling_only());
+		let suggestion = Suggestion::test(4, 15);
+		assert_eq!(mapping.location(&suggestion, &source), vec![20..31]);
+
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &Options::default());
+		assert_eq!(chunks.len(), 1);
+	}
+
+	#[test]
+	fn test_skipped() {
+		let text =
+			"= Title\nSee @key $x$ // Note\n#hide[Secret] #canvas[Drawing] #footnote[Checked]\n#lorem(5) #todo[Later]";
+		let source = Source::detached(text);
+		let options = Options {
+			headings: false,
+			math: false,
+			skip: vec!["canvas".into()],
+			placeholders: vec!["todo".into()],
+			..Options::default()
+		};
+		let skipped = skipped(&source, &options)
+			.into_iter()
+			.map(|skipped| (&text[skipped.range], skipped.reason))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			skipped,
+			[
+				("= Title", Reason::Heading),
+				("@key", Reason::Citation),
+				("$x$", Reason::Math),
+				("// Note", Reason::Comment),
+				("[Secret]", Reason::Hidden),
+				("canvas[Drawing]", Reason::Function("canvas".into())),
+				("lorem(5)", Reason::Placeholder("lorem".into())),
+				("todo[Later]", Reason::Placeholder("todo".into())),
+			]
+		);
+	}
+
+	#[test]
+	fn test_retain_ranges() {
+		let source = Source::detached("First part.\n\nSecond part.\n\nThird part.");
+		let mut chunks = markup::document(&source, 1, Lang::ENGLISH, &Options::default());
+		assert_eq!(chunks.len(), 3);
+		let second = source.text().find("Second").unwrap();
+		let third = source.text().find("Third").unwrap();
+		retain_ranges(
+			&mut chunks,
+			&source,
+			&[second..second + 1, third..third + 1],
+		);
+		let texts = chunks
+			.iter()
+			.map(|(text, _)| text.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(texts, ["Second part.", "Third part."]);
+	}
+
+	#[test]
+	fn test_string() {
+		let mut converter = ByteConverter::new(1000, Lang::ENGLISH, 0);
+		string(&mut converter, r"A\tb\u{e9}\r\\c", 1);
+		let (text, mapping) = converter.finish().remove(0);
+		assert_eq!(text, "A b\u{e9}\\c");
+		let Chars::Bytes(bytes) = mapping.chars else {
+			panic!("byte mapping expected");
+		};
+		assert_eq!(bytes, [1..2, 2..4, 4..5, 5..11, 13..15, 15..16]);
+	}
+
+	#[test]
+	fn test_soft_hyphen() {
+		let text = "A hy-?phen~and long\\u{ad}word.\\\nEnd";
+		let source = Source::detached(text);
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &Options::default());
+		let (converted, mapping) = &chunks[0];
+		assert_eq!(converted, "A hyphen\u{a0}and longword. End");
+		assert_eq!(location(converted, mapping, &source, "hyphen"), vec![2..10]);
+		assert_eq!(
+			location(converted, mapping, &source, "longword"),
+			vec![15..29]
+		);
+	}
+
+	#[test]
+	fn test_escapes() {
+		let text = r"A \# \* \$ \u{1F600}t\u{65}h.";
+		let source = Source::detached(text);
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &Options::default());
+		let (converted, mapping) = &chunks[0];
+		assert_eq!(converted, "A # * $ \u{1F600}teh.");
+		assert_eq!(location(converted, mapping, &source, "#"), vec![2..4]);
+		assert_eq!(
+			location(converted, mapping, &source, "\u{1F600}"),
+			vec![11..20]
+		);
+		assert_eq!(location(converted, mapping, &source, "teh"), vec![20..28]);
+	}
+
+	#[test]
+	fn test_pieces() {
+		let text = "A *bold* \\# word.\n\nNext.";
+		let source = Source::detached(text);
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &Options::default());
+		let (converted, mapping) = &chunks[0];
+		let pieces = mapping
+			.pieces(&source)
+			.into_iter()
+			.map(|(range, location)| {
+				let piece =
+					String::from_utf16(&converted.encode_utf16().collect::<Vec<_>>()[range]);
+				(piece.unwrap(), location.map(|location| &text[location]))
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			pieces,
+			[
+				("A ".into(), Some("A ")),
+				("bold".into(), Some("bold")),
+				(" # word.".into(), Some(" \\# word.")),
+				("\n\n".into(), None),
+				("Next.".into(), Some("Next.")),
+			]
+		);
+	}
+
+	#[test]
+	fn test_join() {
+		let text = "One *teh*.\n\nTwo teh.";
+		let source = Source::detached(text);
+		let chunks = markup::document(&source, 1, Lang::ENGLISH, &Options::default());
+		assert_eq!(chunks.len(), 2);
+		let chunks = join(chunks);
+		assert_eq!(chunks.len(), 1);
+		assert_eq!(chunks[0].0, "One teh.\n\nTwo teh.");
+		let locations = chunks[0]
+			.1
+			.pieces(&source)
+			.into_iter()
+			.filter_map(|(_, location)| location)
+			.map(|location| &text[location])
+			.collect::<Vec<_>>();
+		assert_eq!(locations, ["One ", "teh", ".", "Two teh."]);
+
+		let mut chunks = plain::document("One.", 1, Lang::ENGLISH);
+		chunks.extend(plain::document("Zwei.", 1, Lang::GERMAN));
+		chunks.extend(plain::document("Drei.", 1, Lang::GERMAN));
+		let texts = join(chunks)
+			.into_iter()
+			.map(|(text, _)| text)
+			.collect::<Vec<_>>();
+		assert_eq!(texts, ["One. ", "Zwei. \n\nDrei. "]);
+	}
+
+	#[test]
+	fn test_links() {
+		let text = "See https://typst.app/docs, #link(\"https://typst.app\")[the docs] or www.typst.app.\nWrite to mail\\@typst.app.";
+		let source = Source::detached(text);
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &Options::default());
+		let (converted, mapping) = &chunks[0];
+		assert_eq!(
+			converted,
+			"See https://example.com, the docs or https://example.com. Write to mail@example.com."
+		);
+		assert_eq!(
+			location(converted, mapping, &source, "https://example.com"),
+			vec![4..26]
+		);
+		assert_eq!(
+			location(converted, mapping, &source, "or https://example.com"),
+			vec![65..81]
+		);
+		assert_eq!(
+			location(converted, mapping, &source, "mail@example.com"),
+			vec![92..107]
+		);
+
+		let options = Options { links: true, ..Options::default() };
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &options);
+		assert!(chunks[0]
+			.0
+			.starts_with("See https://typst.app/docs, the docs"));
+	}
+
+	#[test]
+	fn test_normalize() {
+		let text = "Cafe\u{301} and \u{1100}\u{1161}\u{11a8} nai\u{308}ve";
+		let source = Source::detached(text);
+		let options = Options { normalize: true, ..Options::default() };
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &options);
+		let (converted, mapping) = &chunks[0];
+		assert_eq!(converted, "Caf\u{e9} and \u{ac01} na\u{ef}ve");
+		assert_eq!(
+			location(converted, mapping, &source, "Caf\u{e9}"),
+			vec![0..6]
+		);
+		assert_eq!(
+			location(converted, mapping, &source, "\u{ac01}"),
+			vec![11..20]
+		);
+		assert_eq!(
+			location(converted, mapping, &source, "na\u{ef}ve"),
+			vec![21..28]
+		);
+	}
+
+	#[test]
+	fn test_file_language() {
+		let source = Source::detached("// lt-lang: fr-CA\nBonjour. // Commentaire");
+		assert_eq!(file_language(&source), Some("fr-CA"));
+		let options = Options { comments: true, ..Options::default() };
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &options);
+		assert_eq!(chunks[0].0, "Bonjour. \n\nCommentaire");
+		let mapping = &chunks[0].1;
+		assert_eq!(mapping.short_language(), "fr");
+		assert_eq!(mapping.language_code(&HashMap::new()), "fr-CA");
+		assert!(mapping.has_default_language().not());
+
+		let chunks = markup::document(&source, 1000, Lang::GERMAN, &options);
+		assert_eq!(chunks[0].1.file_language(), None);
+		let source = Source::detached("Text\n// lt-lang: fr-CA\n");
+		assert_eq!(file_language(&source), None);
+	}
+
+	#[test]
+	fn test_parallel() {
+		let text = "= Title\n\nOne \"quoted\" sentence.\n\n#set smartquote(enabled: false)\n\n\
+			Two \"quotes\" #footnote[A note.] here.\n\nThree sentences.\n\n- An item\n- Another item\n\n\
+			Four. <label>\n\nFive.";
+		let source = Source::detached(text);
+		let options = Options { labels: true, ..Options::default() };
+		for chunk_size in [1, 30, 1000] {
+			let expected = markup::convert(&source, chunk_size, Lang::ENGLISH, &options, 1);
+			let chunks = markup::convert(&source, chunk_size, Lang::ENGLISH, &options, 4);
+			assert_eq!(chunks.len(), expected.len());
+			for ((text, mapping), (expected, expected_mapping)) in chunks.iter().zip(&expected) {
+				assert_eq!(text, expected);
+				let range = 0..text.encode_utf16().count();
+				assert_eq!(
+					mapping.range_location(range.clone(), &source),
+					expected_mapping.range_location(range, &source)
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn test_language_code() {
+		let mapping = |language, region: Option<&str>| Mapping {
+			chars: Chars::Bytes(Vec::new()),
+			language,
+			region: region.and_then(|region| region.parse().ok()),
+			spelling: false,
+			code: None,
+		};
+		let overrides = HashMap::from([("en".to_owned(), "en-US".to_owned())]);
+		assert_eq!(
+			mapping(Lang::ENGLISH, None).language_code(&overrides),
+			"en-US"
+		);
+		assert_eq!(
+			mapping(Lang::GERMAN, Some("CH")).language_code(&overrides),
+			"de-CH"
+		);
+		assert_eq!(
+			mapping(Lang::GERMAN, Some("LI")).language_code(&overrides),
+			"de-DE"
+		);
+		let overrides = HashMap::from([("pt-BR".to_owned(), "pt-PT".to_owned())]);
+		let code = mapping(Lang::PORTUGUESE, Some("BR")).language_code(&overrides);
+		assert_eq!(code, "pt-PT");
+	}
+
+	#[test]
+	fn test_presets() {
+		let text = "Slide #canvas({ content((0, 0))[Label] }) #speaker-note[Note] #pdfpc.speaker-note(\"Said\")";
+		let source = Source::detached(text);
+		let options = Options {
+			presets: vec![Preset::Cetz, Preset::Touying],
+			..Options::default()
+		};
+		let chunks = markup::document(&source, 1000, Lang::ENGLISH, &options);
+		let text = chunks.into_iter().map(|(text, _)| text).collect::<String>();
+		assert_eq!(text, "Slide \n\nNote");
+
+		let regions = Regions::new(&source, &options);
+		assert_eq!(regions.skipped.len(), 3);
+		let res = regions.extra_chunks(&source, 1000, Lang::ENGLISH);
+		assert_eq!(res[0].0, "Said\n\nNote");
+	}
+}
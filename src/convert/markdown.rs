@@ -0,0 +1,202 @@
+use std::ops::{Not, Range};
+
+use typst::text::Lang;
+
+use super::{ByteConverter, Mapping, Reason, Skipped};
+
+/// Convert a minimal subset of Markdown.
+/// Code blocks, inline code, link targets and html tags are skipped,
+/// headings, list items and block quotes are checked without their markers.
+pub fn document(text: &str, chunk_size: usize, language: Lang) -> Vec<(String, Mapping)> {
+	let mut converter = ByteConverter::new(chunk_size, language, text.len());
+	let mut offset = 0;
+	let mut fence = None::<&str>;
+	for line in text.split_inclusive('\n') {
+		let line_offset = offset;
+		offset += line.len();
+		let content = line.trim_end_matches(['\n', '\r']);
+		let trimmed = content.trim_start();
+
+		if let Some(marker) = fence {
+			if trimmed.starts_with(marker) {
+				fence = None;
+			}
+			continue;
+		}
+		if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+			converter.parbreak();
+			fence = Some(marker);
+			continue;
+		}
+		if trimmed.is_empty() {
+			converter.parbreak();
+			continue;
+		}
+
+		let (marker, block) = block_marker(trimmed);
+		if block {
+			converter.parbreak();
+		}
+		let start = content.len() - trimmed.len() + marker;
+		inline(&mut converter, &content[start..], line_offset + start);
+		if block && trimmed.starts_with('#') {
+			converter.parbreak();
+		} else {
+			converter.replace(" ", (line_offset + content.len())..offset);
+		}
+	}
+	converter.finish()
+}
+
+/// Code blocks, inline code and html tags of the text, they are not checked.
+pub fn skipped(text: &str) -> Vec<Skipped> {
+	let mut skipped = Vec::new();
+	let mut offset = 0;
+	let mut fence = None::<(&str, usize)>;
+	for line in text.split_inclusive('\n') {
+		let line_offset = offset;
+		offset += line.len();
+		let trimmed = line.trim_start();
+		if let Some((marker, start)) = fence {
+			if trimmed.starts_with(marker) {
+				let range = start..line_offset + line.trim_end_matches(['\n', '\r']).len();
+				skipped.push(Skipped { range, reason: Reason::Raw });
+				fence = None;
+			}
+			continue;
+		}
+		if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+			fence = Some((marker, line_offset + line.len() - trimmed.len()));
+			continue;
+		}
+		let ticks = line
+			.match_indices('`')
+			.map(|(index, _)| line_offset + index);
+		let ticks = ticks.collect::<Vec<_>>();
+		let code = ticks
+			.chunks_exact(2)
+			.map(|pair| pair[0]..pair[1] + 1)
+			.collect::<Vec<_>>();
+		let mut tags: Vec<Range<usize>> = Vec::new();
+		for (index, _) in line.match_indices('<') {
+			let start = line_offset + index;
+			let in_code = code.iter().any(|code| code.contains(&start));
+			let after_tag = tags.last().is_some_and(|tag| tag.contains(&start));
+			if let (Some(len), false, false) = (tag(&line[index..]), in_code, after_tag) {
+				tags.push(start..start + len);
+			}
+		}
+		let mut ranges = code.into_iter().chain(tags).collect::<Vec<_>>();
+		ranges.sort_by_key(|range| range.start);
+		for range in ranges {
+			skipped.push(Skipped { range, reason: Reason::Raw });
+		}
+	}
+	if let Some((_, start)) = fence {
+		skipped.push(Skipped {
+			range: start..text.len(),
+			reason: Reason::Raw,
+		});
+	}
+	skipped
+}
+
+/// Length of the block marker and if the line starts a new block.
+fn block_marker(line: &str) -> (usize, bool) {
+	let hashes = line.len() - line.trim_start_matches('#').len();
+	if (1..=6).contains(&hashes) && line[hashes..].starts_with(' ') {
+		return (hashes + 1, true);
+	}
+	for marker in ["- ", "* ", "+ ", "> "] {
+		if line.starts_with(marker) {
+			return (marker.len(), true);
+		}
+	}
+	let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+	if digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") ")) {
+		return (digits + 2, true);
+	}
+	(0, false)
+}
+
+fn inline(converter: &mut ByteConverter, text: &str, offset: usize) {
+	let mut chars = text.char_indices().peekable();
+	while let Some((index, c)) = chars.next() {
+		match c {
+			'`' => {
+				// skip inline code
+				for (_, c) in chars.by_ref() {
+					if c == '`' {
+						break;
+					}
+				}
+			},
+			'<' => match tag(&text[index..]) {
+				// skip html tags, autolinks and comments
+				Some(len) => while chars.next_if(|&(next, _)| next < index + len).is_some() {},
+				None => converter.push("<", offset + index),
+			},
+			']' if matches!(chars.peek(), Some((_, '('))) => {
+				// skip link targets
+				for (_, c) in chars.by_ref() {
+					if c == ')' {
+						break;
+					}
+				}
+			},
+			'*' | '[' | ']' => {},
+			'!' if matches!(chars.peek(), Some((_, '['))) => {},
+			'_' if is_word(text[..index].chars().last())
+				&& is_word(text[index + 1..].chars().next()) =>
+			{
+				converter.push("_", offset + index)
+			},
+			'_' => {},
+			_ => converter.push(&text[index..index + c.len_utf8()], offset + index),
+		}
+	}
+}
+
+/// Length of the html tag, autolink or comment at the start of the text, like `<br>` or
+/// `<https://typst.app>`. A `<` in prose like `a < b` starts none.
+fn tag(text: &str) -> Option<usize> {
+	let rest = text.strip_prefix('<')?;
+	let first = rest.chars().next()?;
+	if (first.is_ascii_alphabetic() || first == '/' || first == '!').not() {
+		return None;
+	}
+	rest.find('>').map(|end| end + 2)
+}
+
+fn is_word(c: Option<char>) -> bool {
+	c.is_some_and(char::is_alphanumeric)
+}
+
+#[cfg(test)]
+mod test {
+	use crate::compat::Source;
+
+	use super::*;
+	use crate::Suggestion;
+
+	#[test]
+	fn test_markdown() {
+		let text = "# Title\n\nSome *bold* [link](http://x.y) `code`\ntext <br/> if a < b.\n\n```\nskip\n```\n";
+		let res = document(text, 1000, Lang::ENGLISH);
+		assert_eq!(res.len(), 1);
+		let (converted, mapping) = &res[0];
+		assert_eq!(converted, "Title\n\nSome bold link  text  if a < b. ");
+
+		let start = converted.find("link").unwrap();
+		let suggestion = Suggestion::test(start, start + 4);
+		let location = mapping.location(&suggestion, &Source::detached(text));
+		assert_eq!(location, vec![22..26]);
+		assert_eq!(&text[22..26], "link");
+
+		let skipped = skipped(text)
+			.into_iter()
+			.map(|skipped| &text[skipped.range])
+			.collect::<Vec<_>>();
+		assert_eq!(skipped, ["`code`", "<br/>", "```\nskip\n```"]);
+	}
+}
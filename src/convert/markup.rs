@@ -0,0 +1,394 @@
+use std::ops::Not;
+
+use typst::text::{Lang, Region};
+
+use super::{ByteConverter, Mapping, Options};
+use crate::compat::{self, ast, LinkedNode, SmartQuoter, Source, SyntaxKind};
+
+/// Convert the markup of a Typst source without compiling it.
+/// Code, math and raw text are skipped, except for content blocks like in `#emph[...]`
+/// and the strings of [`Options::code_strings`].
+/// Content that is never shown is only converted with [`Options::hidden`],
+/// the arguments of [`Options::skip`] are never converted.
+/// Sources larger than [`PARALLEL_BYTES`] are converted in segments on several threads.
+pub fn document(
+	source: &Source,
+	chunk_size: usize,
+	language: Lang,
+	options: &Options,
+) -> Vec<(String, Mapping)> {
+	let threads = std::thread::available_parallelism().map_or(1, usize::from);
+	let segments = match source.text().len() > PARALLEL_BYTES {
+		true => threads,
+		false => 1,
+	};
+	convert(source, chunk_size, language, options, segments)
+}
+
+/// Size of a source in bytes from which it is converted in parallel, see [`document`] and
+/// [`super::document`].
+pub const PARALLEL_BYTES: usize = 1 << 20;
+
+pub(super) fn convert(
+	source: &Source,
+	chunk_size: usize,
+	language: Lang,
+	options: &Options,
+	segments: usize,
+) -> Vec<(String, Mapping)> {
+	let mut converter = ByteConverter::new(chunk_size, language, source.text().len());
+	let root = LinkedNode::new(source.root());
+	if segments > 1 {
+		parallel(&mut converter, &root, options, segments);
+	} else {
+		markup(&mut converter, &root, options);
+	}
+	footnotes(&mut converter, source, options);
+	super::labels(&mut converter, source);
+	let mut res = converter.finish();
+	super::apply_file_language(&mut res, source);
+	if options.links.not() {
+		super::placeholders(&mut res);
+	}
+	if options.normalize {
+		super::normalize(&mut res);
+	}
+	res
+}
+
+pub(super) fn markup(converter: &mut ByteConverter, node: &LinkedNode, options: &Options) {
+	let range = node.range();
+	match node.kind() {
+		SyntaxKind::Text => converter.push(node.text(), range.start),
+		SyntaxKind::Space => {
+			if node.text().matches('\n').count() >= 2 {
+				converter.parbreak();
+			} else if converter.text.is_empty().not()
+				&& converter.text.ends_with(char::is_whitespace).not()
+			{
+				converter.replace(" ", range);
+			}
+		},
+		SyntaxKind::Linebreak => converter.replace(" ", range),
+		SyntaxKind::Parbreak => converter.parbreak(),
+		SyntaxKind::SmartQuote => quote(converter, node),
+		SyntaxKind::Link => converter.push(node.text(), range.start),
+		SyntaxKind::Escape => {
+			if let Some(escape) = node.cast::<ast::Escape>() {
+				converter.replace(escape.get().encode_utf8(&mut [0; 4]), range);
+			}
+		},
+		SyntaxKind::Shorthand => {
+			if let Some(shorthand) = node.cast::<ast::Shorthand>() {
+				converter.replace(shorthand.get().encode_utf8(&mut [0; 4]), range);
+			}
+		},
+		SyntaxKind::Heading if options.headings.not() => {},
+		SyntaxKind::ListItem | SyntaxKind::EnumItem | SyntaxKind::TermItem
+			if options.lists.not() => {},
+		SyntaxKind::Heading
+		| SyntaxKind::ListItem
+		| SyntaxKind::EnumItem
+		| SyntaxKind::TermItem => {
+			converter.parbreak();
+			children(converter, node, options);
+			converter.parbreak();
+		},
+		// Set rules apply until the end of the markup containing them.
+		SyntaxKind::Markup => {
+			let style = converter.quotes.style;
+			children(converter, node, options);
+			converter.quotes.style = style;
+		},
+		SyntaxKind::Strong | SyntaxKind::Emph | SyntaxKind::ContentBlock => {
+			children(converter, node, options)
+		},
+		SyntaxKind::Label | SyntaxKind::Ref if options.labels => {
+			converter.labels.extend(super::label_name(node))
+		},
+		SyntaxKind::Raw | SyntaxKind::Equation | SyntaxKind::Label | SyntaxKind::Ref => {},
+		SyntaxKind::LineComment | SyntaxKind::BlockComment if options.comments => {
+			comment(converter, node)
+		},
+		kind if kind.is_trivia() => {},
+		// Code and markers, only content blocks contain markup.
+		_ => code(converter, node, options),
+	}
+}
+
+/// Convert the top-level markup in about `segments` parts of the same size on their own threads.
+/// The parts end at empty lines and start with the quote style of the set rules before them,
+/// they are joined like they were converted one after another.
+fn parallel(converter: &mut ByteConverter, root: &LinkedNode, options: &Options, segments: usize) {
+	let target = root.len() / segments.max(1) + 1;
+	let mut parts = Vec::new();
+	let initial = converter.quotes.style;
+	let mut style = initial;
+	let (mut first, mut start, mut len) = (0, style, 0);
+	for (index, child) in root.children().enumerate() {
+		len += child.len();
+		if child.kind() == SyntaxKind::SetRule {
+			style.set(&child);
+		}
+		if child.kind() == SyntaxKind::Parbreak && len >= target {
+			parts.push((start, first..index + 1, len));
+			(first, start, len) = (index + 1, style, 0);
+		}
+	}
+	if first < root.children().len() {
+		parts.push((start, first..root.children().len(), len));
+	}
+	let (chunk_size, language) = (converter.chunk_size, converter.language);
+	let node = root.get();
+	let converted = std::thread::scope(|scope| {
+		let handles = parts
+			.into_iter()
+			.map(|(style, children, len)| {
+				scope.spawn(move || {
+					let mut part = ByteConverter::part(chunk_size, language, len);
+					part.quotes.style = style;
+					let root = LinkedNode::new(node);
+					for child in root.children().skip(children.start).take(children.len()) {
+						markup(&mut part, &child, options);
+					}
+					part
+				})
+			})
+			.collect::<Vec<_>>();
+		handles
+			.into_iter()
+			.map(|handle| {
+				handle
+					.join()
+					.unwrap_or_else(|err| std::panic::resume_unwind(err))
+			})
+			.collect::<Vec<_>>()
+	});
+	for part in converted {
+		converter.join(part);
+	}
+	converter.quotes.style = initial;
+}
+
+fn children(converter: &mut ByteConverter, node: &LinkedNode, options: &Options) {
+	for child in node.children() {
+		markup(converter, &child, options);
+	}
+}
+
+fn code(converter: &mut ByteConverter, node: &LinkedNode, options: &Options) {
+	if node.kind() == SyntaxKind::CodeBlock {
+		let style = converter.quotes.style;
+		for child in node.children() {
+			code(converter, &child, options);
+		}
+		converter.quotes.style = style;
+		return;
+	}
+	if node.kind() == SyntaxKind::SetRule {
+		converter.quotes.style.set(node);
+	}
+	let function = match node.kind() {
+		SyntaxKind::FuncCall => super::callee(node),
+		_ => None,
+	};
+	match node.kind() {
+		SyntaxKind::ContentBlock => markup(converter, node, options),
+		SyntaxKind::LineComment | SyntaxKind::BlockComment if options.comments => {
+			comment(converter, node)
+		},
+		SyntaxKind::Str if options.code_strings && super::code_string(node) => {
+			let range = node.range();
+			converter.parbreak();
+			super::string(converter, &node.text()[1..range.len() - 1], range.start + 1);
+			converter.parbreak();
+		},
+		SyntaxKind::Str | SyntaxKind::Raw | SyntaxKind::Equation => {},
+		SyntaxKind::Named if super::named(node) == Some(("quote", "attribution")) => {
+			let value = node.children().last();
+			if let Some(value) = value.filter(|_| options.attributions) {
+				paragraph(converter, &value, options);
+			}
+		},
+		_ if function.is_some_and(|function| options.skips(function)) => {},
+		SyntaxKind::Closure | SyntaxKind::ShowRule if options.templates => {
+			match super::template(node) {
+				Some(body) => paragraph(converter, &body, options),
+				None => {
+					for child in node.children() {
+						code(converter, &child, options);
+					}
+				},
+			}
+		},
+		SyntaxKind::FuncCall if function == Some("footnote") => {
+			let args = node
+				.children()
+				.filter(|child| child.kind() == SyntaxKind::Args);
+			converter.footnotes.extend(args.map(|args| args.span()));
+		},
+		// Like in a compiled document, block quotes are paragraphs of their own.
+		SyntaxKind::FuncCall if function == Some("quote") && super::block_quote(node) => {
+			converter.parbreak();
+			for child in node.children() {
+				code(converter, &child, options);
+			}
+			converter.parbreak();
+		},
+		_ if function.is_some_and(|function| options.checks(function)) => {
+			let args = node
+				.children()
+				.filter(|child| child.kind() == SyntaxKind::Args);
+			for args in args {
+				paragraph(converter, &args, options);
+			}
+		},
+		_ => {
+			let skipped = super::hidden(node);
+			for child in node.children() {
+				if skipped.contains(&child.span()).not() {
+					code(converter, &child, options);
+				} else if options.hidden {
+					paragraph(converter, &child, options);
+				}
+			}
+		},
+	}
+}
+
+/// Substitution of the smart quotes like in the document, following `set smartquote(..)` and
+/// the language of `set text(..)`. Without compiling, conditions of the set rules are ignored.
+#[derive(Debug, Clone)]
+pub(super) struct Quotes {
+	style: QuoteStyle,
+	quoter: SmartQuoter,
+	/// Length of the converted text after the last quote.
+	end: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct QuoteStyle {
+	enabled: bool,
+	alternative: bool,
+	lang: Lang,
+	region: Option<Region>,
+}
+
+impl Quotes {
+	pub(super) fn new(lang: Lang) -> Self {
+		let style = QuoteStyle {
+			enabled: true,
+			alternative: false,
+			lang,
+			region: None,
+		};
+		Self {
+			style,
+			quoter: SmartQuoter::new(),
+			end: None,
+		}
+	}
+}
+
+impl QuoteStyle {
+	/// Apply the arguments of `set smartquote(..)` and `set text(..)`.
+	fn set(&mut self, node: &LinkedNode) {
+		let Some(rule) = node.cast::<ast::SetRule>() else {
+			return;
+		};
+		let function = super::callee(node);
+		for arg in rule.args().items() {
+			let ast::Arg::Named(named) = arg else {
+				continue;
+			};
+			let value = named.expr();
+			match (function, named.name().get().as_str(), value) {
+				(Some("smartquote"), "enabled", ast::Expr::Bool(value)) => {
+					self.enabled = value.get()
+				},
+				(Some("smartquote"), "alternative", ast::Expr::Bool(value)) => {
+					self.alternative = value.get()
+				},
+				(Some("text"), "lang", ast::Expr::Str(value)) => {
+					if let Ok(lang) = value.get().parse() {
+						if lang != self.lang {
+							self.region = None;
+						}
+						self.lang = lang;
+					}
+				},
+				(Some("text"), "region", ast::Expr::Str(value)) => {
+					self.region = value.get().parse().ok()
+				},
+				_ => {},
+			}
+		}
+	}
+}
+
+/// A straight quote of the markup as the quote shown in the document, unless smart quotes are
+/// disabled.
+fn quote(converter: &mut ByteConverter, node: &LinkedNode) {
+	let quotes = &mut converter.quotes;
+	let style = quotes.style;
+	if style.enabled.not() {
+		converter.push(node.text(), node.range().start);
+		return;
+	}
+	let last = converter.text.chars().last().unwrap_or(' ');
+	quotes
+		.quoter
+		.last(last, quotes.end == Some(converter.text.len()));
+	let double = node.text() == "\"";
+	let quote = compat::smart_quote(
+		&mut quotes.quoter,
+		style.lang,
+		style.region,
+		style.alternative,
+		double,
+		peeked(node),
+	);
+	converter.replace(quote, node.range());
+	converter.quotes.end = Some(converter.text.len());
+}
+
+/// First char after the node, to tell closing quotes from apostrophes.
+fn peeked(node: &LinkedNode) -> Option<char> {
+	let parent = node.parent()?.get();
+	let mut next = parent.children().nth(node.index() + 1)?;
+	loop {
+		if next.children().len() == 0 {
+			return next.text().chars().next();
+		}
+		next = next.children().next()?;
+	}
+}
+
+/// Convert the footnotes after the text, so they don't split the sentences of their markers.
+/// Footnotes inside of footnotes follow the other footnotes.
+pub(super) fn footnotes(converter: &mut ByteConverter, source: &Source, options: &Options) {
+	let mut index = 0;
+	while let Some(&span) = converter.footnotes.get(index) {
+		index += 1;
+		if let Some(node) = source.find(span) {
+			paragraph(converter, &node, options);
+		}
+	}
+	converter.footnotes.clear();
+}
+
+/// Like for a compiled document, hidden content and checked arguments are a paragraph of their own.
+fn paragraph(converter: &mut ByteConverter, node: &LinkedNode, options: &Options) {
+	converter.parbreak();
+	code(converter, node, options);
+	converter.parbreak();
+}
+
+/// The text of a comment as a paragraph of its own, see [`Options::comments`].
+fn comment(converter: &mut ByteConverter, node: &LinkedNode) {
+	if let Some((offset, text)) = super::comment(node) {
+		converter.parbreak();
+		converter.push(text, offset);
+		converter.parbreak();
+	}
+}
@@ -0,0 +1,20 @@
+use typst::text::Lang;
+
+use super::{ByteConverter, Mapping};
+
+/// Convert text without markup, paragraphs are seperated by empty lines.
+pub fn document(text: &str, chunk_size: usize, language: Lang) -> Vec<(String, Mapping)> {
+	let mut converter = ByteConverter::new(chunk_size, language, text.len());
+	let mut offset = 0;
+	for line in text.split_inclusive('\n') {
+		let content = line.trim_end_matches(['\n', '\r']);
+		if content.trim().is_empty() {
+			converter.parbreak();
+		} else {
+			converter.push(content, offset);
+			converter.replace(" ", (offset + content.len())..(offset + line.len()));
+		}
+		offset += line.len();
+	}
+	converter.finish()
+}
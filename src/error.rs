@@ -0,0 +1,152 @@
+use std::fmt;
+
+type Source = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors of the library, grouped by how a frontend might react to them.
+#[derive(Debug)]
+pub enum Error {
+	/// The backend is disabled at compile time, the options are invalid or it stopped.
+	BackendUnavailable(String),
+	/// The server rejected the request because of too many requests, retry later.
+	RateLimited,
+	/// The request would exceed the [`crate::Quota`] and was not sent. The time until it fits,
+	/// `None` if it never fits.
+	QuotaExceeded(Option<std::time::Duration>),
+	/// The release of LanguageTool is older than [`crate::MINIMUM_VERSION`].
+	UnsupportedVersion(String),
+	/// The backend does not support the language code.
+	InvalidLanguage(String),
+	/// The text could not be converted for checking.
+	Conversion(String),
+	/// The Java virtual machine could not be started or a call failed.
+	Jvm(Source),
+	/// The request to the remote server failed.
+	Remote(Source),
+	/// The project configuration could not be read or written.
+	Config(Source),
+	/// The check was stopped with [`crate::Cancellation::cancel`].
+	Cancelled,
+	Io(std::io::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::BackendUnavailable(message) => write!(f, "backend unavailable: {}", message),
+			Self::RateLimited => write!(f, "rate limited by the server, retry later"),
+			Self::QuotaExceeded(Some(wait)) => write!(
+				f,
+				"quota would be exceeded, retry in {} seconds",
+				wait.as_secs().max(1)
+			),
+			Self::QuotaExceeded(None) => write!(f, "request is larger than the quota"),
+			Self::UnsupportedVersion(version) => write!(
+				f,
+				"LanguageTool {} is not supported, at least {}.{} is required",
+				version,
+				crate::MINIMUM_VERSION.0,
+				crate::MINIMUM_VERSION.1
+			),
+			Self::InvalidLanguage(lang) => write!(f, "language '{}' is not supported", lang),
+			Self::Conversion(message) => write!(f, "conversion failed: {}", message),
+			Self::Jvm(err) => write!(f, "JVM error: {}", err),
+			Self::Remote(err) => write!(f, "remote server error: {}", err),
+			Self::Config(err) => write!(f, "invalid configuration: {}", err),
+			Self::Cancelled => write!(f, "check cancelled"),
+			Self::Io(err) => write!(f, "{}", err),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Jvm(err) | Self::Remote(err) | Self::Config(err) => Some(err.as_ref()),
+			Self::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(err: std::io::Error) -> Self {
+		Self::Io(err)
+	}
+}
+
+impl From<toml::de::Error> for Error {
+	fn from(err: toml::de::Error) -> Self {
+		Self::Config(Box::new(err))
+	}
+}
+
+impl From<toml::ser::Error> for Error {
+	fn from(err: toml::ser::Error) -> Self {
+		Self::Config(Box::new(err))
+	}
+}
+
+#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+impl From<jni::errors::Error> for Error {
+	fn from(err: jni::errors::Error) -> Self {
+		Self::Jvm(Box::new(err))
+	}
+}
+
+#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+impl From<jni::errors::StartJvmError> for Error {
+	fn from(err: jni::errors::StartJvmError) -> Self {
+		Self::Jvm(Box::new(err))
+	}
+}
+
+#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+impl From<jni::JvmError> for Error {
+	fn from(err: jni::JvmError) -> Self {
+		Self::Jvm(Box::new(err))
+	}
+}
+
+#[cfg(feature = "remote-server")]
+impl From<languagetool_rust::error::Error> for Error {
+	fn from(err: languagetool_rust::error::Error) -> Self {
+		use languagetool_rust::error::Error as E;
+		if let E::InvalidRequest(message) = &err {
+			let message = message.to_lowercase();
+			if message.contains("too many requests") || message.contains("rate limit") {
+				return Self::RateLimited;
+			}
+			if let Some(lang) = invalid_language(&message) {
+				return Self::InvalidLanguage(lang);
+			}
+		}
+		Self::Remote(Box::new(err))
+	}
+}
+
+/// Language from a server response like `'xx' is not a language code known to LanguageTool`.
+#[cfg(feature = "remote-server")]
+fn invalid_language(message: &str) -> Option<String> {
+	use std::ops::Not;
+
+	if message.contains("not a language code").not() {
+		return None;
+	}
+	let (_, rest) = message.split_once('\'')?;
+	let (lang, _) = rest.split_once('\'')?;
+	Some(lang.into())
+}
+
+#[cfg(all(test, feature = "remote-server"))]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_invalid_language() {
+		let message = "error: 'xx' is not a language code known to languagetool.";
+		assert_eq!(invalid_language(message), Some("xx".into()));
+		assert_eq!(invalid_language("error: 'xx' is invalid"), None);
+	}
+}
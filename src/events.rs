@@ -0,0 +1,138 @@
+//! Events of a running check, so frontends can show its progress and status without polling,
+//! see [`crate::CheckOptions::events`].
+
+use std::{
+	collections::HashSet,
+	fmt,
+	ops::Not,
+	sync::{mpsc, Arc},
+};
+
+use typst::syntax::FileId;
+
+use crate::{convert::Mapping, CheckOptions, Diagnostic};
+
+/// Progress of a check, passed to the listener of [`Events`].
+#[derive(Debug, Clone)]
+pub enum Event {
+	/// The check of the files started.
+	CheckStarted { files: usize },
+	/// The text of the file was converted into the chunks sent to the backend.
+	FileConverted { file: FileId, chunks: usize },
+	/// The chunk `n` of the `total` chunks of the file was checked, counted from 1.
+	ChunkChecked {
+		file: FileId,
+		n: usize,
+		total: usize,
+	},
+	/// All chunks of the file were checked.
+	DiagnosticsReady {
+		file: FileId,
+		diagnostics: Vec<Diagnostic>,
+	},
+	/// A problem that does not stop the check, like a language the backend does not support.
+	BackendWarning { message: String },
+	/// The check of all files finished.
+	CheckFinished { files: usize, diagnostics: usize },
+}
+
+type Listener = dyn Fn(Event) + Send + Sync;
+
+/// Listener of the events, nothing is emitted without one.
+#[derive(Clone, Default)]
+pub struct Events(Option<Arc<Listener>>);
+
+impl fmt::Debug for Events {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Events").field(&self.0.is_some()).finish()
+	}
+}
+
+impl Events {
+	/// The listener is called on the task running the check, it should return quickly.
+	pub fn new(listener: impl Fn(Event) + Send + Sync + 'static) -> Self {
+		Self(Some(Arc::new(listener)))
+	}
+
+	/// Events sent to the receiver, they are dropped once it is gone.
+	pub fn channel() -> (Self, mpsc::Receiver<Event>) {
+		let (tx, rx) = mpsc::channel();
+		let events = Self::new(move |event| {
+			let _ = tx.send(event);
+		});
+		(events, rx)
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		self.0.is_some()
+	}
+
+	/// The event is only built with a listener, like for cloning the diagnostics.
+	pub fn emit(&self, event: impl FnOnce() -> Event) {
+		if let Some(listener) = &self.0 {
+			listener(event());
+		}
+	}
+
+	/// Warn once per language code of the chunks the backend does not support.
+	pub(crate) fn unsupported_languages(
+		&self,
+		chunks: &[(String, Mapping)],
+		options: &CheckOptions,
+	) {
+		if self.is_enabled().not() {
+			return;
+		}
+		let mut warned = HashSet::new();
+		for (_, mapping) in chunks {
+			let code = options.chunk_code(mapping);
+			let lang = options.fallback.resolve(&code, &options.language_fallbacks);
+			if lang != code && warned.insert(code.clone()) {
+				let message = format!(
+					"Language '{}' is not supported by the backend, checking with '{}'.",
+					code, lang
+				);
+				self.emit(|| Event::BackendWarning { message });
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::mock::MockBackend;
+
+	#[tokio::test]
+	async fn test_events() {
+		let lt = MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let (events, rx) = Events::channel();
+		let options = CheckOptions {
+			chunk_size: 1,
+			events,
+			..CheckOptions::default()
+		};
+		let diagnostics = crate::check_source(&lt, "One teh.\n\nTwo teh.", &options)
+			.await
+			.unwrap();
+		let events = rx.try_iter().collect::<Vec<_>>();
+		assert!(matches!(events[0], Event::CheckStarted { files: 1 }));
+		assert!(matches!(events[1], Event::FileConverted { chunks: 2, .. }));
+		assert!(matches!(
+			events[2],
+			Event::ChunkChecked { n: 1, total: 2, .. }
+		));
+		assert!(matches!(
+			events[3],
+			Event::ChunkChecked { n: 2, total: 2, .. }
+		));
+		assert!(
+			matches!(&events[4], Event::DiagnosticsReady { diagnostics: ready, .. } if ready.len() == diagnostics.len())
+		);
+		assert!(matches!(
+			events[5],
+			Event::CheckFinished { files: 1, diagnostics: 2 }
+		));
+		assert_eq!(events.len(), 6);
+	}
+}
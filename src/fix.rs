@@ -0,0 +1,322 @@
+use std::ops::{Not, Range};
+
+use typst::syntax::parse;
+
+use crate::Diagnostic;
+
+/// A single replacement of a byte range in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+	pub range: Range<usize>,
+	pub replacement: String,
+}
+
+impl Fix {
+	/// Fix for the diagnostic with the replacement at `index`.
+	/// Diagnostics spanning multiple locations are interrupted by markup and are not fixed.
+	pub fn new(diagnostic: &Diagnostic, index: usize) -> Option<Self> {
+		let [range] = diagnostic.locations.as_slice() else {
+			return None;
+		};
+		let replacement = diagnostic.replacements.get(index)?;
+		Some(Self {
+			range: range.clone(),
+			replacement: replacement.clone(),
+		})
+	}
+}
+
+/// Fixes for the diagnostic in `text` with the replacement at `index`, only the words that differ
+/// from the flagged text are replaced, see [`minimal_edits`]. Diagnostics changing words
+/// interrupted by markup are not fixed.
+pub fn fixes(text: &str, diagnostic: &Diagnostic, index: usize) -> Option<Vec<Fix>> {
+	let replacement = diagnostic.replacements.get(index)?;
+	minimal_edits(text, &diagnostic.locations, replacement)
+		.or_else(|| Fix::new(diagnostic, index).map(|fix| vec![fix]))
+}
+
+/// Source text from the first to the last location of the diagnostic in `text` with the
+/// replacement at `index` applied, like `the* word` for `teh` and `wrd` in `*teh* wrd`. The markup
+/// between the locations is kept, `None` if the diagnostic can not be fixed, see [`fixes`].
+pub fn preview(text: &str, diagnostic: &Diagnostic, index: usize) -> Option<String> {
+	let start = diagnostic.locations.iter().map(|range| range.start).min()?;
+	let end = diagnostic.locations.iter().map(|range| range.end).max()?;
+	let fixes = fixes(text, diagnostic, index)?
+		.into_iter()
+		.map(|fix| {
+			let inside = start <= fix.range.start && fix.range.end <= end;
+			inside.then(|| Fix {
+				range: fix.range.start - start..fix.range.end - start,
+				replacement: fix.replacement,
+			})
+		})
+		.collect::<Option<Vec<_>>>()?;
+	Some(apply_fixes(text.get(start..end)?, &fixes).text)
+}
+
+/// Select fixes for every diagnostic in `text`, `select` returns the index of the replacement to
+/// use.
+pub fn select_fixes(
+	text: &str,
+	diagnostics: &[Diagnostic],
+	mut select: impl FnMut(&Diagnostic) -> Option<usize>,
+) -> Vec<Fix> {
+	diagnostics
+		.iter()
+		.filter_map(|diagnostic| fixes(text, diagnostic, select(diagnostic)?))
+		.flatten()
+		.collect()
+}
+
+/// Limit of compared token pairs, longer replacements replace their whole location.
+const MAX_COMPARED: usize = 100_000;
+
+/// Edits replacing the flagged text of the locations in `text` with `replacement`, only the
+/// words that differ are replaced, so markup between the locations like emphasis or references
+/// is kept. `None` if a changed part of the flagged text is interrupted by markup.
+pub fn minimal_edits(
+	text: &str,
+	locations: &[Range<usize>],
+	replacement: &str,
+) -> Option<Vec<Fix>> {
+	let pieces = locations
+		.iter()
+		.map(|range| text.get(range.clone()))
+		.collect::<Option<Vec<_>>>()?;
+	let flagged = pieces.concat();
+	let old = tokens(&flagged);
+	let new = tokens(replacement);
+	if old.len() * new.len() > MAX_COMPARED {
+		return None;
+	}
+
+	// Longest common subsequence of the tokens, `lengths[i][j]` for `old[i..]` and `new[j..]`.
+	let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+	for i in (0..old.len()).rev() {
+		for j in (0..new.len()).rev() {
+			lengths[i][j] = if flagged[old[i].clone()] == replacement[new[j].clone()] {
+				lengths[i + 1][j + 1] + 1
+			} else {
+				lengths[i + 1][j].max(lengths[i][j + 1])
+			};
+		}
+	}
+
+	// Changed parts as byte ranges in the flagged text and the replacement.
+	let mut changes = Vec::<(Range<usize>, Range<usize>)>::new();
+	let (mut i, mut j) = (0, 0);
+	let mut change: Option<(Range<usize>, Range<usize>)> = None;
+	while i < old.len() || j < new.len() {
+		let (old_at, new_at) = (
+			position(&old, i, flagged.len()),
+			position(&new, j, replacement.len()),
+		);
+		if i < old.len() && j < new.len() && flagged[old[i].clone()] == replacement[new[j].clone()]
+		{
+			changes.extend(change.take());
+			i += 1;
+			j += 1;
+			continue;
+		}
+		let (old_range, new_range) = change.get_or_insert((old_at..old_at, new_at..new_at));
+		if j < new.len() && (i == old.len() || lengths[i][j + 1] >= lengths[i + 1][j]) {
+			new_range.end = new[j].end;
+			j += 1;
+		} else {
+			old_range.end = old[i].end;
+			i += 1;
+		}
+	}
+	changes.extend(change);
+
+	changes
+		.into_iter()
+		.map(|(old_range, new_range)| {
+			Some(Fix {
+				range: source_range(locations, &pieces, old_range)?,
+				replacement: replacement[new_range].into(),
+			})
+		})
+		.collect()
+}
+
+/// Words, runs of whitespace and single other chars as byte ranges.
+fn tokens(text: &str) -> Vec<Range<usize>> {
+	let class = |c: char| {
+		if c.is_alphanumeric() || c == '\'' || c == '’' {
+			1
+		} else if c.is_whitespace() {
+			2
+		} else {
+			0
+		}
+	};
+	let mut tokens = Vec::<Range<usize>>::new();
+	let mut previous = None;
+	for (index, c) in text.char_indices() {
+		let end = index + c.len_utf8();
+		match tokens.last_mut() {
+			Some(last) if previous == Some(class(c)) && class(c) != 0 => last.end = end,
+			_ => tokens.push(index..end),
+		}
+		previous = Some(class(c));
+	}
+	tokens
+}
+
+/// Byte offset of the token at `index`, the length of the text after the last token.
+fn position(tokens: &[Range<usize>], index: usize, len: usize) -> usize {
+	tokens.get(index).map_or(len, |token| token.start)
+}
+
+/// Range in the source of a range of the flagged text, `None` if it spans several locations.
+/// Insertions between two locations are placed at the end of the first one.
+fn source_range(
+	locations: &[Range<usize>],
+	pieces: &[&str],
+	range: Range<usize>,
+) -> Option<Range<usize>> {
+	let mut offset = 0;
+	for (location, piece) in locations.iter().zip(pieces) {
+		let end = offset + piece.len();
+		let inside = if range.is_empty() {
+			range.start <= end
+		} else {
+			range.start < end
+		};
+		if inside {
+			if range.end > end {
+				return None;
+			}
+			let start = location.start + range.start - offset;
+			return Some(start..start + range.len());
+		}
+		offset = end;
+	}
+	None
+}
+
+/// Result of applying fixes to a text.
+#[derive(Debug)]
+pub struct Fixed {
+	pub text: String,
+	pub applied: Vec<Fix>,
+	/// Fixes overlapping an earlier fix.
+	pub skipped: Vec<Fix>,
+	/// Fixes with new syntax errors in the fixed text, see [`apply_typst_fixes`].
+	pub broken: Vec<Fix>,
+}
+
+/// Apply the fixes to the text, fixes overlapping an earlier fix are skipped.
+/// Bytes outside the fixed ranges are preserved exactly.
+pub fn apply_fixes(text: &str, fixes: &[Fix]) -> Fixed {
+	let mut sorted = fixes.to_vec();
+	sorted.sort_by_key(|fix| (fix.range.start, fix.range.end));
+
+	let mut result = String::with_capacity(text.len());
+	let mut applied = Vec::new();
+	let mut skipped = Vec::new();
+	let mut position = 0;
+	for fix in sorted {
+		let valid = fix.range.end <= text.len()
+			&& text.is_char_boundary(fix.range.start)
+			&& text.is_char_boundary(fix.range.end);
+		if fix.range.start < position || valid.not() {
+			skipped.push(fix);
+			continue;
+		}
+		result += &text[position..fix.range.start];
+		result += &fix.replacement;
+		position = fix.range.end;
+		applied.push(fix);
+	}
+	result += &text[position..];
+
+	Fixed {
+		text: result,
+		applied,
+		skipped,
+		broken: Vec::new(),
+	}
+}
+
+/// Apply the fixes to Typst markup like [`apply_fixes`], but fixes adding syntax errors are not
+/// applied, like a replacement with an unclosed `*`. Without new errors all fixes are applied at
+/// once, otherwise every fix is checked on its own.
+pub fn apply_typst_fixes(text: &str, fixes: &[Fix]) -> Fixed {
+	let errors = |text: &str| parse(text).errors().len();
+	let original = errors(text);
+	let fixed = apply_fixes(text, fixes);
+	if errors(&fixed.text) <= original {
+		return fixed;
+	}
+	let (valid, mut broken) = fixed.applied.into_iter().partition::<Vec<_>, _>(|fix| {
+		errors(&apply_fixes(text, std::slice::from_ref(fix)).text) <= original
+	});
+	let mut result = apply_fixes(text, &valid);
+	// Fixes only breaking the text together are all left out.
+	if errors(&result.text) > original {
+		broken.extend(result.applied);
+		result = apply_fixes(text, &[]);
+	}
+	Fixed {
+		text: result.text,
+		applied: result.applied,
+		skipped: fixed.skipped,
+		broken,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn fix(range: Range<usize>, replacement: &str) -> Fix {
+		Fix { range, replacement: replacement.into() }
+	}
+
+	#[test]
+	fn test_apply() {
+		let fixed = apply_fixes(
+			"Thsi is  a tset.",
+			&[fix(11..15, "test"), fix(0..4, "This"), fix(7..9, " ")],
+		);
+		assert_eq!(fixed.text, "This is a test.");
+		assert_eq!(fixed.applied.len(), 3);
+	}
+
+	#[test]
+	fn test_minimal_edits() {
+		let text = "Use teh quick fox.";
+		let edits = minimal_edits(text, std::slice::from_ref(&(4..13)), "the quick");
+		assert_eq!(edits, Some(vec![fix(4..7, "the")]));
+
+		// The emphasis between the locations is kept.
+		let text = "I has *many* apple.";
+		let locations = [0..6, 7..11, 12..18];
+		let edits = minimal_edits(text, &locations, "I have many apples").unwrap();
+		assert_eq!(edits, vec![fix(2..5, "have"), fix(13..18, "apples")]);
+		assert_eq!(apply_fixes(text, &edits).text, "I have *many* apples.");
+
+		let edits = minimal_edits(text, &locations, "I has many");
+		assert_eq!(edits, Some(vec![fix(12..18, "")]));
+		let edits = minimal_edits(text, &locations, "many apple");
+		assert_eq!(edits, Some(vec![fix(0..6, "")]));
+		assert_eq!(minimal_edits(text, &locations, "I have it"), None);
+	}
+
+	#[test]
+	fn test_overlap() {
+		let fixed = apply_fixes("abc def", &[fix(0..3, "x"), fix(2..5, "y")]);
+		assert_eq!(fixed.text, "x def");
+		assert_eq!(fixed.skipped, vec![fix(2..5, "y")]);
+	}
+
+	#[test]
+	fn test_typst_fixes() {
+		let fixed = apply_typst_fixes("A tset and *bold*.", &[fix(2..6, "test"), fix(11..12, "")]);
+		assert_eq!(fixed.text, "A test and *bold*.");
+		assert_eq!(fixed.applied, vec![fix(2..6, "test")]);
+		assert_eq!(fixed.broken, vec![fix(11..12, "")]);
+	}
+}
@@ -0,0 +1,157 @@
+//! Terms of a glossary, like defined abbreviations, whose spelling diagnostics are dropped.
+//! They are read from glossary files or from the entries of the glossarium and acrostiche
+//! packages in the document.
+
+use std::{ops::Not, path::Path};
+
+use typst::syntax::{ast, Source, SyntaxNode};
+
+/// Fields of glossarium entries containing a term.
+const FIELDS: [&str; 4] = ["short", "plural", "long", "longplural"];
+
+/// Terms of a glossary file. Typst files are searched for entries like [`terms`], other files
+/// have one term per line, a description after `:` and lines starting with `#` are skipped.
+pub fn load(path: &Path) -> crate::Result<Vec<String>> {
+	let content = std::fs::read_to_string(path)?;
+	if path.extension().is_some_and(|extension| extension == "typ") {
+		return Ok(terms(&Source::detached(content)));
+	}
+	let terms = content
+		.lines()
+		.map(|line| line.split(':').next().unwrap_or_default().trim())
+		.filter(|line| line.is_empty().not() && line.starts_with('#').not())
+		.map(str::to_owned)
+		.collect();
+	Ok(terms)
+}
+
+/// Terms defined in the source, the `short`, `plural`, `long` and `longplural` fields of
+/// glossarium entries like `(key: "ku", short: "KU")` and the acronyms and their long forms
+/// of acrostiche's `init-acronyms(("NN": "Neural Network"))`.
+pub fn terms(source: &Source) -> Vec<String> {
+	let mut terms = Vec::new();
+	let mut stack = vec![source.root()];
+	while let Some(node) = stack.pop() {
+		if let Some(dict) = node.cast::<ast::Dict>() {
+			entry(dict, &mut terms);
+		}
+		if is_acronyms(node) {
+			let acronyms = node
+				.cast::<ast::FuncCall>()
+				.into_iter()
+				.flat_map(|call| call.args().items())
+				.filter_map(|arg| match arg {
+					ast::Arg::Pos(ast::Expr::Dict(dict)) => Some(dict),
+					_ => None,
+				});
+			for dict in acronyms {
+				acronym(dict, &mut terms);
+			}
+		}
+		stack.extend(node.children());
+	}
+	terms
+}
+
+/// The terms of a dictionary with a `key` and a `short` field.
+fn entry(dict: ast::Dict, terms: &mut Vec<String>) {
+	let fields = dict
+		.items()
+		.filter_map(|item| match item {
+			ast::DictItem::Named(named) => Some((named.name().get().clone(), named.expr())),
+			ast::DictItem::Keyed(keyed) => Some((string(keyed.key())?.into(), keyed.expr())),
+			ast::DictItem::Spread(_) => None,
+		})
+		.collect::<Vec<_>>();
+	let has = |name: &str| fields.iter().any(|(field, _)| field == name);
+	if has("key").not() || has("short").not() {
+		return;
+	}
+	for (field, expr) in fields {
+		if FIELDS.contains(&field.as_str()) {
+			strings(expr, terms);
+		}
+	}
+}
+
+/// The keys and long forms of a dictionary of acronyms.
+fn acronym(dict: ast::Dict, terms: &mut Vec<String>) {
+	for item in dict.items() {
+		let (key, expr) = match item {
+			ast::DictItem::Named(named) => (named.name().get().to_string(), named.expr()),
+			ast::DictItem::Keyed(keyed) => match string(keyed.key()) {
+				Some(key) => (key, keyed.expr()),
+				None => continue,
+			},
+			ast::DictItem::Spread(_) => continue,
+		};
+		terms.push(key);
+		strings(expr, terms);
+	}
+}
+
+/// The string or the strings of an array.
+fn strings(expr: ast::Expr, terms: &mut Vec<String>) {
+	match expr {
+		ast::Expr::Array(array) => {
+			for item in array.items() {
+				if let ast::ArrayItem::Pos(expr) = item {
+					terms.extend(string(expr));
+				}
+			}
+		},
+		expr => terms.extend(string(expr)),
+	}
+}
+
+fn string(expr: ast::Expr) -> Option<String> {
+	match expr {
+		ast::Expr::Str(value) => Some(value.get().to_string()),
+		_ => None,
+	}
+}
+
+/// If the node is a call of `init-acronyms`, also as field like `acrostiche.init-acronyms`.
+fn is_acronyms(node: &SyntaxNode) -> bool {
+	let Some(call) = node.cast::<ast::FuncCall>() else {
+		return false;
+	};
+	match call.callee() {
+		ast::Expr::Ident(ident) => ident.get() == "init-acronyms",
+		ast::Expr::FieldAccess(access) => access.field().get() == "init-acronyms",
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_terms() {
+		let source = Source::detached(concat!(
+			"#let entries = (\n",
+			"  (key: \"ku\", short: \"KU Leuven\", long: \"Katholieke Universiteit Leuven\"),\n",
+			"  (key: \"gpu\", short: \"GPU\", plural: \"GPUs\", desc: [A processor]),\n",
+			")\n",
+			"#init-acronyms((\"NN\": (\"Neural Network\", \"Neural Networks\"), \"CNN\": \"Conv\"))\n",
+			"#let config = (short: \"no key\")\n",
+		));
+		let mut terms = terms(&source);
+		terms.sort();
+		assert_eq!(
+			terms,
+			[
+				"CNN",
+				"Conv",
+				"GPU",
+				"GPUs",
+				"KU Leuven",
+				"Katholieke Universiteit Leuven",
+				"NN",
+				"Neural Network",
+				"Neural Networks"
+			]
+		);
+	}
+}
@@ -0,0 +1,204 @@
+//! Patterns for technical tokens like version strings and ticket ids,
+//! the diagnostics inside their matches are dropped.
+//! Spelling diagnostics of acronyms, words with digits and glossary terms can be dropped too,
+//! like all diagnostics of the rules disabled by a profile.
+
+use std::{
+	collections::HashSet,
+	ops::{Not, Range},
+};
+
+use regex::Regex;
+
+use crate::{config::FalsePositive, Suggestion};
+
+/// Tokens whose diagnostics are dropped, like matches of `[A-Z]+-[0-9]+` for ticket ids.
+#[derive(Debug, Clone, Default)]
+pub struct Ignore {
+	patterns: Vec<Regex>,
+	/// Drop spelling diagnostics of uppercase words like `RISC-V`.
+	pub acronyms: bool,
+	/// Drop spelling diagnostics of words containing digits like `GPT4` or `IPv6`.
+	pub digits: bool,
+	/// Words of glossary terms, see [`Ignore::add_terms`].
+	terms: HashSet<String>,
+	/// Rule ids or categories of the dropped diagnostics, see [`Ignore::disable`].
+	disabled: Vec<String>,
+	/// Rule ids with the regexes of their false positive sentences, see [`Ignore::hide`].
+	false_positives: Vec<(String, Regex)>,
+}
+
+impl Ignore {
+	/// Fails with [`crate::Error::Config`] for an invalid regex.
+	pub fn new(patterns: &[String]) -> crate::Result<Self> {
+		let patterns = patterns
+			.iter()
+			.map(|pattern| Regex::new(pattern))
+			.collect::<Result<_, _>>()
+			.map_err(|err| crate::Error::Config(Box::new(err)))?;
+		Ok(Self { patterns, ..Self::default() })
+	}
+
+	/// Drop the spelling diagnostics of the words of the terms, also with a plural `s`
+	/// like `GPUs` for `GPU`.
+	pub fn add_terms<T: AsRef<str>>(&mut self, terms: impl IntoIterator<Item = T>) {
+		for term in terms {
+			let words = term
+				.as_ref()
+				.split_whitespace()
+				.map(|word| word.trim_matches(|c: char| c.is_alphanumeric().not()))
+				.filter(|word| word.is_empty().not());
+			self.terms.extend(words.map(str::to_owned));
+		}
+	}
+
+	/// Drop the diagnostics of the rules in all languages, by rule id or by category ignoring
+	/// the case, like `"Typography"`.
+	pub fn disable<T: Into<String>>(&mut self, rules: impl IntoIterator<Item = T>) {
+		self.disabled.extend(rules.into_iter().map(Into::into));
+	}
+
+	/// Drop the diagnostics of the rules in sentences matching their regex, the sentences are
+	/// matched without the surrounding whitespace.
+	/// Fails with [`crate::Error::Config`] for an invalid regex.
+	pub fn hide(&mut self, false_positives: &[FalsePositive]) -> crate::Result<()> {
+		for false_positive in false_positives {
+			let sentence = Regex::new(&false_positive.sentence)
+				.map_err(|err| crate::Error::Config(Box::new(err)))?;
+			self.false_positives
+				.push((false_positive.rule.clone(), sentence));
+		}
+		Ok(())
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.patterns.is_empty()
+			&& self.acronyms.not()
+			&& self.digits.not()
+			&& self.terms.is_empty()
+			&& self.disabled.is_empty()
+			&& self.false_positives.is_empty()
+	}
+
+	/// Remove the suggestions overlapping a match, `text` is the text passed to the backend.
+	pub fn retain(&self, text: &str, suggestions: &mut Vec<Suggestion>) {
+		if self.is_empty() || suggestions.is_empty() {
+			return;
+		}
+		let matches = self
+			.patterns
+			.iter()
+			.flat_map(|pattern| pattern.find_iter(text))
+			.map(|found| found.range())
+			.collect::<Vec<_>>();
+		suggestions.retain(|suggestion| {
+			if self.disables(suggestion) {
+				return false;
+			}
+			let range = suggestion.offsets(text).utf_8;
+			if suggestion.is_spelling() && self.ignores_word(word(text, range.clone())) {
+				return false;
+			}
+			matches
+				.iter()
+				.all(|found| found.end <= range.start || range.end <= found.start)
+		});
+	}
+
+	fn disables(&self, suggestion: &Suggestion) -> bool {
+		self.disabled.iter().any(|rule| {
+			*rule == suggestion.rule_id || rule.eq_ignore_ascii_case(&suggestion.rule_category)
+		}) || self.false_positives.iter().any(|(rule, sentence)| {
+			*rule == suggestion.rule_id && sentence.is_match(suggestion.sentence.trim())
+		})
+	}
+
+	fn ignores_word(&self, word: &str) -> bool {
+		let mut letters = word.chars().filter(|c| c.is_alphabetic()).peekable();
+		let acronym = letters.peek().is_some() && letters.all(char::is_uppercase);
+		(self.acronyms && acronym && word.chars().count() > 1)
+			|| (self.digits && word.chars().any(|c| c.is_numeric()))
+			|| self.terms.contains(word)
+			|| word
+				.strip_suffix('s')
+				.is_some_and(|word| self.terms.contains(word))
+	}
+}
+
+/// The flagged text extended to the whole word, including joined parts like in `RISC-V`.
+fn word(text: &str, range: Range<usize>) -> &str {
+	let part = |c: char| c.is_alphanumeric() || c == '-';
+	let start = text[..range.start]
+		.rfind(|c: char| part(c).not())
+		.map_or(0, |index| {
+			index + text[index..].chars().next().map_or(0, char::len_utf8)
+		});
+	let end = text[range.end..]
+		.find(|c: char| part(c).not())
+		.map_or(text.len(), |index| range.end + index);
+	text[start..end].trim_matches('-')
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_retain() {
+		let text = "See ABC-123 in ßrc/main.rs for the fxi.";
+		let suggestion = |word: &str| {
+			let start = text.find(word).unwrap();
+			Suggestion::test(
+				text[..start].encode_utf16().count(),
+				text[..start + word.len()].encode_utf16().count(),
+			)
+		};
+		let mut suggestions = vec![suggestion("ABC"), suggestion("ßrc"), suggestion("fxi")];
+		let patterns = [r"[A-Z]+-[0-9]+".to_owned(), r"\S+/\S+\.rs".to_owned()];
+		Ignore::new(&patterns)
+			.unwrap()
+			.retain(text, &mut suggestions);
+		assert_eq!(suggestions.len(), 1);
+		assert_eq!(suggestions[0].start, 35);
+		assert!(Ignore::new(&["(".to_owned()]).is_err());
+
+		let mut ignore = Ignore::default();
+		let false_positive =
+			FalsePositive::new("TYPO", "See ABC-123 in\n ßrc/main.rs for the fxi.");
+		assert_eq!(
+			false_positive.sentence,
+			r"^See\s+ABC\-123\s+in\s+ßrc/main\.rs\s+for\s+the\s+fxi\.$"
+		);
+		ignore.hide(&[false_positive]).unwrap();
+		let mut suggestions = vec![suggestion("fxi"), suggestion("fxi")];
+		suggestions[0].rule_id = "TYPO".into();
+		suggestions[1].rule_id = "OTHER".into();
+		for suggestion in &mut suggestions {
+			suggestion.sentence = format!("{} ", text);
+		}
+		ignore.retain(text, &mut suggestions);
+		assert_eq!(suggestions.len(), 1);
+		assert_eq!(suggestions[0].rule_id, "OTHER");
+	}
+
+	#[test]
+	fn test_words() {
+		let ignore = Ignore {
+			acronyms: true,
+			digits: true,
+			..Ignore::default()
+		};
+		assert!(ignore.ignores_word(word("A RISC-V core", 2..6)));
+		assert!(ignore.ignores_word(word("The GPT4 model", 4..7)));
+		assert!(ignore.ignores_word(word("Use IPv6.", 4..8)));
+		assert!(ignore.ignores_word(word("A teh word", 2..5)).not());
+		assert!(ignore.ignores_word(word("I am", 0..1)).not());
+
+		let mut ignore = Ignore::default();
+		ignore.add_terms(["KU Leuven", "(GPU)"]);
+		assert!(ignore.ignores_word(word("The KU Leuven", 4..6)));
+		assert!(ignore.ignores_word(word("The KU Leuven", 7..13)));
+		assert!(ignore.ignores_word(word("Two GPUs", 4..8)));
+		assert!(ignore.ignores_word(word("A teh word", 2..5)).not());
+	}
+}
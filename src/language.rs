@@ -0,0 +1,169 @@
+use std::{
+	borrow::Cow,
+	fmt,
+	ops::{Deref, Not},
+	str::FromStr,
+};
+
+use crate::Error;
+
+/// Language code like `en-US`, `de` or `ca-ES-valencia`, normalized to a lowercase language,
+/// an uppercase region and lowercase variants joined by `-`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(try_from = "String", into = "String")
+)]
+pub struct LanguageCode(Cow<'static, str>);
+
+impl LanguageCode {
+	pub const EN_US: Self = Self(Cow::Borrowed("en-US"));
+	pub const EN_GB: Self = Self(Cow::Borrowed("en-GB"));
+	pub const DE_DE: Self = Self(Cow::Borrowed("de-DE"));
+	pub const DE_CH: Self = Self(Cow::Borrowed("de-CH"));
+	pub const FR_FR: Self = Self(Cow::Borrowed("fr-FR"));
+	pub const ES_ES: Self = Self(Cow::Borrowed("es-ES"));
+	pub const IT_IT: Self = Self(Cow::Borrowed("it-IT"));
+	pub const NL_NL: Self = Self(Cow::Borrowed("nl-NL"));
+	pub const PT_PT: Self = Self(Cow::Borrowed("pt-PT"));
+	pub const PT_BR: Self = Self(Cow::Borrowed("pt-BR"));
+
+	/// Normalize the code, `_` separates the parts like `-`, like `EN_us` for `en-US`.
+	/// Codes without a language of two or three letters, with an invalid region or with
+	/// empty parts are invalid.
+	pub fn new(code: &str) -> crate::Result<Self> {
+		let invalid = || Error::InvalidLanguage(code.into());
+		let mut parts = code.trim().split(['-', '_']);
+		let language = parts.next().unwrap_or_default();
+		if (2..=3).contains(&language.len()).not() || is_alphabetic(language).not() {
+			return Err(invalid());
+		}
+		let mut normalized = language.to_ascii_lowercase();
+		for (index, part) in parts.enumerate() {
+			let valid = match part.len() {
+				// Regions have two letters or three digits, like `ES` or `419`.
+				2 if index == 0 => is_alphabetic(part),
+				3 if index == 0 => part.bytes().all(|b| b.is_ascii_digit()),
+				1..=8 => part.bytes().all(|b| b.is_ascii_alphanumeric()),
+				_ => false,
+			};
+			if valid.not() {
+				return Err(invalid());
+			}
+			normalized.push('-');
+			if index == 0 && part.len() == 2 {
+				normalized += &part.to_ascii_uppercase();
+			} else {
+				normalized += &part.to_ascii_lowercase();
+			}
+		}
+		Ok(Self(Cow::Owned(normalized)))
+	}
+
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+
+	/// Language without region and variants, like `en` for `en-US`.
+	pub fn language(&self) -> &str {
+		self.0.split('-').next().unwrap_or_default()
+	}
+
+	/// Region of the code, like `US` for `en-US`.
+	pub fn region(&self) -> Option<&str> {
+		self.0.split('-').nth(1).filter(|region| {
+			region
+				.bytes()
+				.all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+		})
+	}
+}
+
+fn is_alphabetic(part: &str) -> bool {
+	part.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+impl Deref for LanguageCode {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		&self.0
+	}
+}
+
+impl AsRef<str> for LanguageCode {
+	fn as_ref(&self) -> &str {
+		&self.0
+	}
+}
+
+impl fmt::Display for LanguageCode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+impl FromStr for LanguageCode {
+	type Err = Error;
+
+	fn from_str(code: &str) -> crate::Result<Self> {
+		Self::new(code)
+	}
+}
+
+impl TryFrom<String> for LanguageCode {
+	type Error = Error;
+
+	fn try_from(code: String) -> crate::Result<Self> {
+		Self::new(&code)
+	}
+}
+
+impl From<LanguageCode> for String {
+	fn from(code: LanguageCode) -> Self {
+		code.0.into_owned()
+	}
+}
+
+impl PartialEq<str> for LanguageCode {
+	fn eq(&self, other: &str) -> bool {
+		self.0 == other
+	}
+}
+
+impl PartialEq<&str> for LanguageCode {
+	fn eq(&self, other: &&str) -> bool {
+		self.0 == *other
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_language_code() {
+		let code = |code: &str| LanguageCode::new(code).map(String::from).ok();
+		assert_eq!(code("en-US").as_deref(), Some("en-US"));
+		assert_eq!(code("EN_us").as_deref(), Some("en-US"));
+		assert_eq!(code(" de ").as_deref(), Some("de"));
+		assert_eq!(code("es-419").as_deref(), Some("es-419"));
+		assert_eq!(code("ca-ES-Valencia").as_deref(), Some("ca-ES-valencia"));
+		assert_eq!(
+			code("de-DE-x-simple-language").as_deref(),
+			Some("de-DE-x-simple-language")
+		);
+		assert_eq!(code(""), None);
+		assert_eq!(code("english"), None);
+		assert_eq!(code("en-"), None);
+		assert_eq!(code("en-U$"), None);
+
+		let code = LanguageCode::new("pt_br").unwrap();
+		assert_eq!(code, LanguageCode::PT_BR);
+		assert_eq!(code.language(), "pt");
+		assert_eq!(code.region(), Some("BR"));
+		assert_eq!(LanguageCode::new("de").unwrap().region(), None);
+		assert_eq!(LanguageCode::EN_US, "en-US");
+	}
+}
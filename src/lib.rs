@@ -1,20 +1,249 @@
 mod backends;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
+pub mod cache;
+mod compat;
+pub mod config;
 pub mod convert;
+mod error;
+pub mod events;
+pub mod fix;
+pub mod glossary;
+pub mod ignore;
+mod language;
+pub mod pipeline;
+pub mod rank;
+pub mod readability;
+pub mod session;
+pub mod spelling;
+pub mod suppress;
+pub mod trace;
 
-use std::ops::{Not, Range};
+use std::{
+	collections::{HashMap, HashSet},
+	future::Future,
+	ops::{Not, Range},
+	path::PathBuf,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
 
 pub use backends::*;
+pub use builder::LanguageToolBuilder;
 use convert::Mapping;
+pub use error::{Error, Result};
+use events::{Event, Events};
+use futures_util::{stream, Stream, StreamExt};
+pub use language::LanguageCode;
+use suppress::{Contexts, Suppressions};
 use typst::{
-	syntax::{FileId, Source},
+	syntax::{is_newline, FileId, Source},
+	text::Lang,
 	World,
 };
 
-#[allow(async_fn_in_trait)]
-pub trait LanguageToolBackend {
-	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()>;
-	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()>;
-	async fn check_text(&mut self, lang: String, text: &str) -> anyhow::Result<Vec<Suggestion>>;
+/// Backends are shared between threads, all methods only take `&self`.
+pub trait LanguageToolBackend: Send + Sync {
+	fn allow_words(
+		&self,
+		lang: LanguageCode,
+		words: &[String],
+	) -> impl Future<Output = crate::Result<()>> + Send;
+	fn disable_checks(
+		&self,
+		lang: LanguageCode,
+		checks: &[String],
+	) -> impl Future<Output = crate::Result<()>> + Send;
+	fn check_text(
+		&self,
+		lang: LanguageCode,
+		text: &str,
+	) -> impl Future<Output = crate::Result<Vec<Suggestion>>> + Send;
+	/// Check with the dictionary, disabled checks, picky level and mother tongue of the options
+	/// added to the settings of the backend, without changing them for other checks.
+	/// By default the suggestions for the words and disabled rules are removed after the check,
+	/// the level and mother tongue are only supported by the LanguageTool backends.
+	fn check_text_with(
+		&self,
+		lang: LanguageCode,
+		text: &str,
+		options: &CheckOptions,
+	) -> impl Future<Output = crate::Result<Vec<Suggestion>>> + Send {
+		async move {
+			let mut suggestions = self.check_text(lang.clone(), text).await?;
+			options.retain_allowed(&lang, text, &mut suggestions);
+			Ok(suggestions)
+		}
+	}
+	/// Check several texts of the language, the suggestions of every text are relative to it.
+	/// By default the texts are joined with empty lines and checked together,
+	/// backends without a cost per request check them one by one.
+	fn check_texts(
+		&self,
+		lang: LanguageCode,
+		texts: &[&str],
+	) -> impl Future<Output = crate::Result<Vec<Vec<Suggestion>>>> + Send {
+		async move {
+			let (joined, offsets) = join_texts(texts);
+			let suggestions = self.check_text(lang, &joined).await?;
+			Ok(split_suggestions(suggestions, &offsets))
+		}
+	}
+	/// Release of LanguageTool, results of different releases are not cached together.
+	fn version(&self) -> impl Future<Output = crate::Result<String>> + Send;
+	/// Languages supported by the backend, to validate language codes before the first check.
+	fn languages(&self) -> impl Future<Output = crate::Result<Vec<Language>>> + Send;
+	/// Language of the text, `None` if the backend can not detect languages.
+	/// The servers and the JNI backend detect them, the command line and Harper do not.
+	fn detect_language(
+		&self,
+		_text: &str,
+	) -> impl Future<Output = crate::Result<Option<DetectedLanguage>>> + Send {
+		async { Ok(None) }
+	}
+	/// Rules of the backend for the language, empty if the backend can not list them.
+	/// Only the JNI backend lists them, the servers and the command line have no API for it.
+	fn rules(&self, _lang: LanguageCode) -> impl Future<Output = crate::Result<Vec<Rule>>> + Send {
+		async { Ok(Vec::new()) }
+	}
+	/// Native language of the author, enables rules for false friends.
+	fn set_mother_tongue(
+		&self,
+		mother_tongue: Option<String>,
+	) -> impl Future<Output = crate::Result<()>> + Send;
+	/// Language of the messages of LanguageTool's built-in rules, independent of the checked
+	/// language, like `de` for German explanations of English rules. Only the JNI backend
+	/// supports it, the other backends fail with [`Error::BackendUnavailable`] for a language.
+	fn set_interface_language(
+		&self,
+		lang: Option<String>,
+	) -> impl Future<Output = crate::Result<()>> + Send {
+		async move {
+			match lang {
+				Some(lang) => Err(Error::BackendUnavailable(format!(
+					"The messages can not be shown in '{}', only the JAR backends support an interface language.",
+					lang
+				))),
+				None => Ok(()),
+			}
+		}
+	}
+	/// Also report the stricter rules of the picky level.
+	fn set_picky(&self, picky: bool) -> impl Future<Output = crate::Result<()>> + Send;
+	/// Values for configurable rules of the language by rule id, replacing the previous values.
+	/// Only the JNI backend supports them, the other backends ignore them.
+	fn set_rule_options(
+		&self,
+		_lang: LanguageCode,
+		_options: &HashMap<String, Vec<RuleValue>>,
+	) -> impl Future<Output = crate::Result<()>> + Send {
+		async { Ok(()) }
+	}
+	/// Rule XML files of the language in the format of LanguageTool's `grammar.xml`,
+	/// like house style rules, replacing the previous files.
+	/// Only the JNI backend supports them, the other backends ignore them.
+	fn set_rule_files(
+		&self,
+		_lang: LanguageCode,
+		_files: &[PathBuf],
+	) -> impl Future<Output = crate::Result<()>> + Send {
+		async { Ok(()) }
+	}
+	/// Word lists of accepted and prohibited words of the language, replacing the previous files.
+	/// Only the JNI backend supports them, the other backends ignore them.
+	fn set_spelling_files(
+		&self,
+		_lang: LanguageCode,
+		_files: &SpellingFiles,
+	) -> impl Future<Output = crate::Result<()>> + Send {
+		async { Ok(()) }
+	}
+	/// Send all settings of the language together, replacing the previous ones. The default
+	/// sends them one after the other, a backend can override it to apply them at once.
+	fn apply_settings(
+		&self,
+		lang: &str,
+		settings: &cache::Settings<'_>,
+	) -> impl Future<Output = crate::Result<()>> + Send {
+		async move {
+			let code = lang.parse::<LanguageCode>()?;
+			if let Some(words) = settings.dictionary.get(lang) {
+				self.allow_words(code.clone(), words).await?;
+			}
+			if let Some(options) = settings.rule_options.get(lang) {
+				self.set_rule_options(code.clone(), options).await?;
+			}
+			if let Some(files) = settings.rule_files.get(lang) {
+				self.set_rule_files(code.clone(), files).await?;
+			}
+			if let Some(files) = settings.spelling_files.get(lang) {
+				self.set_spelling_files(code.clone(), files).await?;
+			}
+			// The checks are disabled last, so they can be rules of the rule files.
+			if let Some(checks) = settings.disabled_checks.get(lang) {
+				self.disable_checks(code, checks).await?;
+			}
+			Ok(())
+		}
+	}
+	/// Pass the raw result of every check to the hook, for fields of LanguageTool the
+	/// suggestions do not have. Only the servers, the command line and the JNI backend have
+	/// raw results, the other backends ignore the hook.
+	fn set_raw_hook(
+		&self,
+		_hook: Option<RawHook>,
+	) -> impl Future<Output = crate::Result<()>> + Send {
+		async { Ok(()) }
+	}
+}
+
+/// The texts joined with empty lines and the UTF-16 offset of every text in the joined text.
+fn join_texts(texts: &[&str]) -> (String, Vec<usize>) {
+	let mut joined = String::with_capacity(texts.iter().map(|text| text.len() + 2).sum());
+	let mut offsets = Vec::with_capacity(texts.len());
+	let mut offset = 0;
+	for text in texts {
+		if joined.is_empty().not() {
+			joined += "\n\n";
+			offset += 2;
+		}
+		offsets.push(offset);
+		joined += text;
+		offset += text.encode_utf16().count();
+	}
+	(joined, offsets)
+}
+
+/// Split the suggestions of joined texts into the suggestions of every text.
+fn split_suggestions(suggestions: Vec<Suggestion>, offsets: &[usize]) -> Vec<Vec<Suggestion>> {
+	let mut res = vec![Vec::new(); offsets.len()];
+	for suggestion in suggestions {
+		let index = offsets
+			.partition_point(|&offset| offset <= suggestion.start)
+			.saturating_sub(1);
+		let Some(suggestions) = res.get_mut(index) else {
+			continue;
+		};
+		suggestions.extend(cache::shift(&[suggestion], -(offsets[index] as isize)));
+	}
+	res
+}
+
+/// Oldest release of LanguageTool with the APIs used by the backends, like the picky level.
+pub const MINIMUM_VERSION: (u32, u32) = (5, 0);
+
+/// Major and minor version of a release like `6.4` or `6.5-SNAPSHOT`.
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+	let mut parts = version.split(|c: char| c.is_ascii_digit().not());
+	let major = parts.next()?.parse().ok()?;
+	let minor = parts
+		.next()
+		.and_then(|minor| minor.parse().ok())
+		.unwrap_or(0);
+	Some((major, minor))
 }
 
 #[derive(Debug)]
@@ -22,7 +251,13 @@ pub enum LanguageTool {
 	#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
 	JNI(jni::LanguageToolJNI),
 	#[cfg(feature = "remote-server")]
-	Remote(remote::LanguageToolRemote),
+	Remote(Box<remote::LanguageToolRemote>),
+	#[cfg(feature = "commandline")]
+	Commandline(commandline::LanguageToolCommandline),
+	#[cfg(feature = "docker")]
+	Docker(Box<docker::LanguageToolDocker>),
+	#[cfg(feature = "harper")]
+	Harper(Box<harper::LanguageToolHarper>),
 }
 
 impl LanguageTool {
@@ -31,116 +266,1513 @@ impl LanguageTool {
 		jar_location: Option<&String>,
 		host: Option<&String>,
 		port: Option<&String>,
-	) -> anyhow::Result<Self> {
-		let lt = match (bundled, jar_location, host, port) {
-			#[cfg(feature = "remote-server")]
+	) -> crate::Result<Self> {
+		let builder = match (bundled, jar_location, host, port) {
+			(true, None, None, None) => LanguageToolBuilder::bundled(),
+			(false, Some(path), None, None) => LanguageToolBuilder::jar(path),
 			(false, None, Some(host), Some(port)) => {
-				Self::Remote(remote::LanguageToolRemote::new(host, port)?)
+				LanguageToolBuilder::remote(format!("{}:{}", host, port))
+			},
+			// A Unix socket has no port.
+			(false, None, Some(host), None) if host.starts_with("unix:") => {
+				LanguageToolBuilder::remote(host)
 			},
-			#[cfg(not(feature = "remote-server"))]
-			(false, None, Some(_), Some(_)) => Err(anyhow::anyhow!("Feature 'remote-server' is disabled."))?,
+			_ => Err(Error::BackendUnavailable(
+				"Exactly one of 'bundled', 'jar_location' or 'host and port' must be specified."
+					.into(),
+			))?,
+		};
+		builder.backend()
+	}
 
-			#[cfg(feature = "bundle-jar")]
-			(true, None, None, None) => Self::JNI(jni::LanguageToolJNI::new_bundled()?),
+	/// Chunk size suited to the backend, see [`CheckOptions::chunk_size`].
+	/// The bundled JVM answers small requests fastest, servers are limited by the number of
+	/// requests and the command line starts a JVM for every request.
+	pub fn default_chunk_size(&self) -> usize {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(_) => 1000,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(_) => 5000,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(_) => 20000,
+			#[cfg(feature = "docker")]
+			Self::Docker(_) => 2000,
+			#[cfg(feature = "harper")]
+			Self::Harper(_) => 10000,
+		}
+	}
 
-			#[cfg(not(feature = "bundle-jar"))]
-			(true, None, None, None) => Err(anyhow::anyhow!("Feature 'bundle-jar' is disabled."))?,
+	/// Limit the requests to a remote server, local backends are not limited.
+	#[cfg_attr(not(feature = "remote-server"), allow(unused_variables))]
+	pub fn set_rate_limit(&mut self, limit: RateLimit) {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(_) => {},
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_rate_limit(limit),
+			#[cfg(feature = "commandline")]
+			Self::Commandline(_) => {},
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.set_rate_limit(limit),
+			#[cfg(feature = "harper")]
+			Self::Harper(_) => {},
+		}
+	}
 
+	/// Fail with [`Error::QuotaExceeded`] before sending a request to a remote server that
+	/// exceeds the quota, local backends have no quota.
+	#[cfg_attr(not(feature = "remote-server"), allow(unused_variables))]
+	pub fn set_quota(&mut self, quota: Option<Quota>) {
+		match self {
 			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
-			(false, Some(path), None, None) => Self::JNI(jni::LanguageToolJNI::new(path)?),
-			#[cfg(all(not(feature = "bundle-jar"), not(feature = "extern-jar")))]
-			(false, Some(_), None, None) => Err(anyhow::anyhow!(
-				"Features 'bundle-jar' and 'extern-jar' are disabled."
-			))?,
+			Self::JNI(_) => {},
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_quota(quota),
+			#[cfg(feature = "commandline")]
+			Self::Commandline(_) => {},
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.set_quota(quota),
+			#[cfg(feature = "harper")]
+			Self::Harper(_) => {},
+		}
+	}
 
-			_ => Err(anyhow::anyhow!(
-				"Exactly one of 'bundled', 'jar_location' or 'host and port' must be specified."
-			))?,
-		};
-		Ok(lt)
+	/// Account on the remote server, other backends have no accounts.
+	#[cfg_attr(not(feature = "remote-server"), allow(unused_variables))]
+	pub fn set_login(&mut self, username: &str, api_key: &str) {
+		match self {
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_login(username, api_key),
+			#[allow(unreachable_patterns)]
+			_ => {},
+		}
+	}
+
+	/// Headers sent with every request to the remote server, other backends send no requests.
+	#[cfg_attr(not(feature = "remote-server"), allow(unused_variables))]
+	pub fn set_headers(&mut self, headers: &[(String, String)]) -> crate::Result<()> {
+		match self {
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_headers(headers),
+			#[allow(unreachable_patterns)]
+			_ => Ok(()),
+		}
+	}
+
+	/// Proxy for the requests to the remote server, instead of the one of `HTTP_PROXY` and
+	/// `HTTPS_PROXY`. The browser chooses the proxy on wasm.
+	#[cfg_attr(
+		any(not(feature = "remote-server"), target_arch = "wasm32"),
+		allow(unused_variables)
+	)]
+	pub fn set_proxy(&mut self, url: &str) -> crate::Result<()> {
+		match self {
+			#[cfg(all(feature = "remote-server", not(target_arch = "wasm32")))]
+			Self::Remote(lt) => lt.set_proxy(url),
+			#[allow(unreachable_patterns)]
+			_ => Ok(()),
+		}
+	}
+
+	/// Send the text of the checks gzip compressed to the remote server, it must accept compressed requests.
+	#[cfg_attr(not(feature = "remote-server"), allow(unused_variables))]
+	pub fn set_compress_requests(&mut self, compress: bool) {
+		match self {
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_compress_requests(compress),
+			#[allow(unreachable_patterns)]
+			_ => {},
+		}
+	}
+
+	/// Add the word to the dictionary of the account on the remote server.
+	/// Returns `false` if the backend has no server dictionary.
+	#[cfg_attr(not(feature = "remote-server"), allow(unused_variables))]
+	pub async fn add_server_word(&self, word: &str) -> crate::Result<bool> {
+		match self {
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.add_word(word).await.map(|()| true),
+			#[allow(unreachable_patterns)]
+			_ => Ok(false),
+		}
+	}
+
+	/// Query the version of the backend, so an unsupported release fails before the first check
+	/// with [`Error::UnsupportedVersion`]. Versions that can not be parsed are accepted,
+	/// Harper is not checked. A backend that does not answer fails with
+	/// [`Error::BackendUnavailable`].
+	pub async fn check_version(&self) -> crate::Result<String> {
+		let version = self.version().await.map_err(|err| match err {
+			Error::RateLimited | Error::QuotaExceeded(_) | Error::Cancelled => err,
+			err => Error::BackendUnavailable(format!("Could not read the version: {}", err)),
+		})?;
+		#[cfg(feature = "harper")]
+		#[allow(irrefutable_let_patterns)]
+		if let Self::Harper(_) = self {
+			return Ok(version);
+		}
+		match parse_version(&version) {
+			Some(parsed) if parsed < MINIMUM_VERSION => Err(Error::UnsupportedVersion(version)),
+			_ => Ok(version),
+		}
+	}
+
+	/// Remove the word from the dictionary of the account on the remote server.
+	/// Returns `false` if the backend has no server dictionary.
+	#[cfg_attr(not(feature = "remote-server"), allow(unused_variables))]
+	pub async fn delete_server_word(&self, word: &str) -> crate::Result<bool> {
+		match self {
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.delete_word(word).await.map(|()| true),
+			#[allow(unreachable_patterns)]
+			_ => Ok(false),
+		}
 	}
 }
 
 impl LanguageToolBackend for LanguageTool {
-	async fn allow_words(&mut self, lang: String, words: &[String]) -> anyhow::Result<()> {
+	async fn allow_words(&self, lang: LanguageCode, words: &[String]) -> crate::Result<()> {
 		match self {
 			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
 			Self::JNI(lt) => lt.allow_words(lang, words).await,
 			#[cfg(feature = "remote-server")]
 			Self::Remote(lt) => lt.allow_words(lang, words).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.allow_words(lang, words).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.allow_words(lang, words).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.allow_words(lang, words).await,
 		}
 	}
-	async fn disable_checks(&mut self, lang: String, checks: &[String]) -> anyhow::Result<()> {
+	async fn disable_checks(&self, lang: LanguageCode, checks: &[String]) -> crate::Result<()> {
 		match self {
 			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
 			Self::JNI(lt) => lt.disable_checks(lang, checks).await,
 			#[cfg(feature = "remote-server")]
 			Self::Remote(lt) => lt.disable_checks(lang, checks).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.disable_checks(lang, checks).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.disable_checks(lang, checks).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.disable_checks(lang, checks).await,
+		}
+	}
+	async fn check_text(&self, lang: LanguageCode, text: &str) -> crate::Result<Vec<Suggestion>> {
+		let span = trace::span!(
+			"check_text",
+			lang = lang.as_str(),
+			chars = text.chars().count()
+		);
+		let check = async move {
+			match self {
+				#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+				Self::JNI(lt) => lt.check_text(lang, text).await,
+				#[cfg(feature = "remote-server")]
+				Self::Remote(lt) => lt.check_text(lang, text).await,
+				#[cfg(feature = "commandline")]
+				Self::Commandline(lt) => lt.check_text(lang, text).await,
+				#[cfg(feature = "docker")]
+				Self::Docker(lt) => lt.check_text(lang, text).await,
+				#[cfg(feature = "harper")]
+				Self::Harper(lt) => lt.check_text(lang, text).await,
+			}
+		};
+		trace::instrument(span, check).await
+	}
+	async fn check_text_with(
+		&self,
+		lang: LanguageCode,
+		text: &str,
+		options: &CheckOptions,
+	) -> crate::Result<Vec<Suggestion>> {
+		let span = trace::span!(
+			"check_text",
+			lang = lang.as_str(),
+			chars = text.chars().count()
+		);
+		let check = async move {
+			match self {
+				#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+				Self::JNI(lt) => lt.check_text_with(lang, text, options).await,
+				#[cfg(feature = "remote-server")]
+				Self::Remote(lt) => lt.check_text_with(lang, text, options).await,
+				#[cfg(feature = "commandline")]
+				Self::Commandline(lt) => lt.check_text_with(lang, text, options).await,
+				#[cfg(feature = "docker")]
+				Self::Docker(lt) => lt.check_text_with(lang, text, options).await,
+				#[cfg(feature = "harper")]
+				Self::Harper(lt) => lt.check_text_with(lang, text, options).await,
+			}
+		};
+		trace::instrument(span, check).await
+	}
+	async fn check_texts(
+		&self,
+		lang: LanguageCode,
+		texts: &[&str],
+	) -> crate::Result<Vec<Vec<Suggestion>>> {
+		let span = trace::span!("check_texts", lang = lang.as_str(), texts = texts.len());
+		let check = async move {
+			match self {
+				#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+				Self::JNI(lt) => lt.check_texts(lang, texts).await,
+				#[cfg(feature = "remote-server")]
+				Self::Remote(lt) => lt.check_texts(lang, texts).await,
+				#[cfg(feature = "commandline")]
+				Self::Commandline(lt) => lt.check_texts(lang, texts).await,
+				#[cfg(feature = "docker")]
+				Self::Docker(lt) => lt.check_texts(lang, texts).await,
+				#[cfg(feature = "harper")]
+				Self::Harper(lt) => lt.check_texts(lang, texts).await,
+			}
+		};
+		trace::instrument(span, check).await
+	}
+	async fn version(&self) -> crate::Result<String> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.version().await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.version().await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.version().await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.version().await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.version().await,
+		}
+	}
+	async fn languages(&self) -> crate::Result<Vec<Language>> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.languages().await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.languages().await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.languages().await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.languages().await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.languages().await,
+		}
+	}
+	async fn detect_language(&self, text: &str) -> crate::Result<Option<DetectedLanguage>> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.detect_language(text).await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.detect_language(text).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.detect_language(text).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.detect_language(text).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.detect_language(text).await,
+		}
+	}
+	async fn rules(&self, lang: LanguageCode) -> crate::Result<Vec<Rule>> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.rules(lang).await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.rules(lang).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.rules(lang).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.rules(lang).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.rules(lang).await,
+		}
+	}
+	async fn set_mother_tongue(&self, mother_tongue: Option<String>) -> crate::Result<()> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.set_mother_tongue(mother_tongue).await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_mother_tongue(mother_tongue).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.set_mother_tongue(mother_tongue).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.set_mother_tongue(mother_tongue).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.set_mother_tongue(mother_tongue).await,
+		}
+	}
+	async fn set_interface_language(&self, lang: Option<String>) -> crate::Result<()> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.set_interface_language(lang).await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_interface_language(lang).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.set_interface_language(lang).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.set_interface_language(lang).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.set_interface_language(lang).await,
+		}
+	}
+	async fn set_picky(&self, picky: bool) -> crate::Result<()> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.set_picky(picky).await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_picky(picky).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.set_picky(picky).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.set_picky(picky).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.set_picky(picky).await,
 		}
 	}
-	async fn check_text(&mut self, lang: String, text: &str) -> anyhow::Result<Vec<Suggestion>> {
+	async fn set_rule_options(
+		&self,
+		lang: LanguageCode,
+		options: &HashMap<String, Vec<RuleValue>>,
+	) -> crate::Result<()> {
 		match self {
 			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
-			Self::JNI(lt) => lt.check_text(lang, text).await,
+			Self::JNI(lt) => lt.set_rule_options(lang, options).await,
 			#[cfg(feature = "remote-server")]
-			Self::Remote(lt) => lt.check_text(lang, text).await,
+			Self::Remote(lt) => lt.set_rule_options(lang, options).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.set_rule_options(lang, options).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.set_rule_options(lang, options).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.set_rule_options(lang, options).await,
+		}
+	}
+	async fn set_rule_files(&self, lang: LanguageCode, files: &[PathBuf]) -> crate::Result<()> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.set_rule_files(lang, files).await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_rule_files(lang, files).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.set_rule_files(lang, files).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.set_rule_files(lang, files).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.set_rule_files(lang, files).await,
+		}
+	}
+	async fn apply_settings(
+		&self,
+		lang: &str,
+		settings: &cache::Settings<'_>,
+	) -> crate::Result<()> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.apply_settings(lang, settings).await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.apply_settings(lang, settings).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.apply_settings(lang, settings).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.apply_settings(lang, settings).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.apply_settings(lang, settings).await,
+		}
+	}
+	async fn set_spelling_files(
+		&self,
+		lang: LanguageCode,
+		files: &SpellingFiles,
+	) -> crate::Result<()> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.set_spelling_files(lang, files).await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_spelling_files(lang, files).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.set_spelling_files(lang, files).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.set_spelling_files(lang, files).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.set_spelling_files(lang, files).await,
+		}
+	}
+	async fn set_raw_hook(&self, hook: Option<RawHook>) -> crate::Result<()> {
+		match self {
+			#[cfg(any(feature = "bundle-jar", feature = "extern-jar"))]
+			Self::JNI(lt) => lt.set_raw_hook(hook).await,
+			#[cfg(feature = "remote-server")]
+			Self::Remote(lt) => lt.set_raw_hook(hook).await,
+			#[cfg(feature = "commandline")]
+			Self::Commandline(lt) => lt.set_raw_hook(hook).await,
+			#[cfg(feature = "docker")]
+			Self::Docker(lt) => lt.set_raw_hook(hook).await,
+			#[cfg(feature = "harper")]
+			Self::Harper(lt) => lt.set_raw_hook(hook).await,
 		}
 	}
 }
 
+const _: () = {
+	fn assert_send_sync<T: Send + Sync>() {}
+	let _ = assert_send_sync::<LanguageTool>;
+};
+
 pub struct FileCollector {
 	source: Source,
 	diagnostics: Vec<Diagnostic>,
+	/// Locations and rule of the collected diagnostics, the same text might be checked twice.
+	seen: HashSet<(Vec<Range<usize>>, String)>,
+	/// Number of collected diagnostics for every hash of rule and context.
+	ordinals: HashMap<u64, usize>,
+	/// Rules disabled by comments in the source.
+	suppressions: Suppressions,
+	/// Rules disabled in parts of the document.
+	contexts: Contexts,
+	/// Attach [`MappingDebug`] to the diagnostics.
+	debug: bool,
+	locations: Locations,
 }
 
 impl FileCollector {
 	pub fn new(file_id: FileId, world: &impl World) -> Self {
 		let source = world.source(file_id).unwrap();
-		Self { source, diagnostics: Vec::new() }
+		Self::from_source(source)
 	}
 
-	pub fn add(&mut self, suggestions: &[Suggestion], mapping: Mapping) {
-		let diagnostics = suggestions
-			.iter()
-			.map(|suggestion| {
-				let locations = mapping.location(suggestion, &self.source);
-				Diagnostic {
-					locations,
-					message: suggestion.message.clone(),
-					replacements: suggestion.replacements.clone(),
-					rule_description: suggestion.rule_description.clone(),
-					rule_id: suggestion.rule_id.clone(),
-				}
-			})
-			.filter(|diagnostic| diagnostic.locations.is_empty().not());
-		self.diagnostics.extend(diagnostics)
+	/// Collect the diagnostics for a source without a [`World`], like [`Source::detached`].
+	pub fn from_source(source: Source) -> Self {
+		Self {
+			suppressions: Suppressions::new(&source),
+			source,
+			diagnostics: Vec::new(),
+			seen: HashSet::new(),
+			ordinals: HashMap::new(),
+			contexts: Contexts::default(),
+			debug: false,
+			locations: Locations::default(),
+		}
+	}
+
+	/// Skip the diagnostics of rules disabled in the part of the document containing them.
+	pub fn set_contexts(&mut self, contexts: Contexts) {
+		self.contexts = contexts;
+	}
+
+	/// Attach the checked text of every diagnostic as [`Diagnostic::debug`], to trace wrong locations.
+	pub fn set_debug(&mut self, debug: bool) {
+		self.debug = debug;
+	}
+
+	/// Split or merge the locations of the diagnostics, the fingerprints stay the same.
+	pub fn set_locations(&mut self, locations: Locations) {
+		self.locations = locations;
 	}
 
-	pub fn finish(self) -> (Source, Vec<Diagnostic>) {
+	/// Add the suggestions for a paragraph, returns the new diagnostics.
+	/// Diagnostics of a rule at an already reported location are skipped,
+	/// like diagnostics of rules disabled for their line by a comment.
+	/// The strings of the suggestions are moved into the diagnostics, `text` is the text passed to
+	/// the backend.
+	pub fn add(
+		&mut self,
+		text: &str,
+		suggestions: Vec<Suggestion>,
+		mapping: Mapping,
+		language: &str,
+	) -> &[Diagnostic] {
+		let _span = trace::span!("map", suggestions = suggestions.len()).entered();
+		let start = self.diagnostics.len();
+		for suggestion in suggestions {
+			let locations = mapping.location(&suggestion, &self.source);
+			let Some(first) = locations.first() else {
+				continue;
+			};
+			let line = self.source.byte_to_line(first.start);
+			if line.is_some_and(|line| self.suppressions.suppresses(line, &suggestion.rule_id)) {
+				continue;
+			}
+			if mapping.is_spelling_only() && suggestion.is_spelling().not() {
+				continue;
+			}
+			let (rule_id, category) = (&suggestion.rule_id, &suggestion.rule_category);
+			if self
+				.contexts
+				.suppresses(&self.source, first.start, rule_id, category)
+			{
+				continue;
+			}
+			if self
+				.seen
+				.insert((locations.clone(), suggestion.rule_id.clone()))
+				.not()
+			{
+				continue;
+			}
+			let debug = self.debug.then(|| MappingDebug::new(text, &suggestion));
+			let mut diagnostic = Diagnostic {
+				locations,
+				language: language.into(),
+				message: suggestion.message,
+				sentence: suggestion.sentence,
+				replacements: suggestion.replacements,
+				previews: Vec::new(),
+				rule_description: suggestion.rule_description,
+				rule_id: suggestion.rule_id,
+				rule_category: suggestion.rule_category,
+				rule_urls: suggestion.rule_urls,
+				incorrect_examples: suggestion.incorrect_examples,
+				correct_examples: suggestion.correct_examples,
+				rule_source: suggestion.rule_source,
+				context_for_sure_match: suggestion.context_for_sure_match,
+				fingerprint: String::new(),
+				debug,
+			};
+			let hash = context_hash(&self.source, &diagnostic);
+			let ordinal = self.ordinals.entry(hash).or_default();
+			diagnostic.fingerprint = format!("{:016x}", fnv1a(hash, &ordinal.to_le_bytes()));
+			*ordinal += 1;
+			diagnostic.locations = self
+				.locations
+				.apply(self.source.text(), diagnostic.locations);
+			diagnostic.previews = (0..diagnostic.replacements.len())
+				.map(|index| fix::preview(self.source.text(), &diagnostic, index))
+				.collect();
+			self.diagnostics.push(diagnostic);
+		}
+		&self.diagnostics[start..]
+	}
+
+	pub fn source(&self) -> &Source {
+		&self.source
+	}
+
+	/// Diagnostics collected so far.
+	pub fn diagnostics(&self) -> &[Diagnostic] {
+		&self.diagnostics
+	}
+
+	/// The diagnostics are sorted by their start and rule, independent of the chunks and the
+	/// order of the checks.
+	pub fn finish(mut self) -> (Source, Vec<Diagnostic>) {
+		self.diagnostics.sort_by(|a, b| {
+			let (a_range, b_range) = (&a.locations[0], &b.locations[0]);
+			a_range
+				.start
+				.cmp(&b_range.start)
+				.then_with(|| a.rule_id.cmp(&b.rule_id))
+				.then_with(|| a_range.end.cmp(&b_range.end))
+				.then_with(|| a.message.cmp(&b.message))
+		});
 		(self.source, self.diagnostics)
 	}
 }
 
+/// Collect the diagnostics of multiple files, grouped by file.
+#[derive(Default)]
+pub struct ProjectCollector {
+	files: Vec<(FileId, FileCollector)>,
+}
+
+impl ProjectCollector {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add the file with its source from the world, files are only added once.
+	pub fn add_file(&mut self, file_id: FileId, world: &impl World) {
+		if self.contains(file_id).not() {
+			self.files
+				.push((file_id, FileCollector::new(file_id, world)));
+		}
+	}
+
+	/// Add the file with the source, files are only added once.
+	pub fn add_source(&mut self, file_id: FileId, source: Source) {
+		if self.contains(file_id).not() {
+			self.files
+				.push((file_id, FileCollector::from_source(source)));
+		}
+	}
+
+	pub fn contains(&self, file_id: FileId) -> bool {
+		self.files.iter().any(|(id, _)| *id == file_id)
+	}
+
+	/// Add the suggestions for a paragraph of the file, it must be added first.
+	pub fn add(
+		&mut self,
+		file_id: FileId,
+		text: &str,
+		suggestions: Vec<Suggestion>,
+		mapping: Mapping,
+		language: &str,
+	) {
+		let (_, collector) = self
+			.files
+			.iter_mut()
+			.find(|(id, _)| *id == file_id)
+			.expect("file is added to the collector");
+		collector.add(text, suggestions, mapping, language);
+	}
+
+	/// The files are sorted by package and path, see [`FileCollector::finish`] for their diagnostics.
+	pub fn finish(mut self) -> Vec<(FileId, Source, Vec<Diagnostic>)> {
+		self.files.sort_by_cached_key(|(file_id, _)| {
+			let package = file_id.package().map(ToString::to_string);
+			(package, file_id.vpath().as_rootless_path().to_owned())
+		});
+		self.files
+			.into_iter()
+			.map(|(file_id, collector)| {
+				let (source, diagnostics) = collector.finish();
+				(file_id, source, diagnostics)
+			})
+			.collect()
+	}
+}
+
+/// Options for [`check_file`].
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+	/// Size of the chunks sent to the backend, see [`convert::document`].
+	pub chunk_size: usize,
+	/// Language code used for all text instead of the language set in the document.
+	pub language: Option<String>,
+	/// LanguageTool codes for the languages of the document, see [`Mapping::language_code`].
+	pub languages: HashMap<String, String>,
+	/// Supported languages of the backend, unsupported codes are checked with a fallback.
+	pub fallback: LanguageFallback,
+	/// Codes tried for unsupported codes before the default ones, see [`LanguageFallback::resolve`].
+	pub language_fallbacks: HashMap<String, Vec<String>>,
+	pub convert: convert::Options,
+	/// Patterns of tokens whose diagnostics are dropped.
+	pub ignore: ignore::Ignore,
+	/// Maximum number of chunks checked by the backend at the same time,
+	/// the results are still added in the order of the text.
+	pub max_in_flight: usize,
+	/// Stops the check before the next paragraph with [`Error::Cancelled`].
+	pub cancellation: Cancellation,
+	/// Words accepted by language code in addition to the dictionary of the backend,
+	/// the words for `de` are also accepted for `de-DE`.
+	pub dictionary: HashMap<String, Vec<String>>,
+	/// Rules disabled by language code in addition to the disabled checks of the backend.
+	pub disabled_checks: HashMap<String, Vec<String>>,
+	/// Picky level instead of the one of the backend.
+	pub picky: Option<bool>,
+	/// Native language of the author instead of the one of the backend.
+	pub mother_tongue: Option<String>,
+	/// Keep the suggestions of a rule for adjacent tokens apart, see [`merge_adjacent`].
+	pub keep_adjacent: bool,
+	/// Order and number of the replacements of the suggestions.
+	pub ranking: rank::Ranking,
+	/// Attach [`Diagnostic::debug`] to the diagnostics.
+	pub debug: bool,
+	/// Hooks run on the suggestions of every chunk before they are collected.
+	pub pipeline: pipeline::Pipeline,
+	/// Rules disabled in parts of the document, like casing rules in headings.
+	pub contexts: Contexts,
+	/// Text sent by paragraph or as whole document.
+	pub submission: Submission,
+	/// Listener of the progress and status of the checks.
+	pub events: events::Events,
+}
+
+impl CheckOptions {
+	/// Words of [`CheckOptions::dictionary`] for the language and its more general codes.
+	pub fn dictionary(&self, lang: &str) -> impl Iterator<Item = &String> {
+		language_entries(&self.dictionary, lang)
+			.into_iter()
+			.flatten()
+	}
+
+	/// Rules of [`CheckOptions::disabled_checks`] for the language and its more general codes.
+	pub fn disabled_checks(&self, lang: &str) -> impl Iterator<Item = &String> {
+		language_entries(&self.disabled_checks, lang)
+			.into_iter()
+			.flatten()
+	}
+
+	/// Language code the chunk is checked with, the one of a [`convert::FILE_LANGUAGE`] comment,
+	/// [`CheckOptions::language`] or the one of its text, replaced by a supported one,
+	/// see [`LanguageFallback::resolve`].
+	pub fn chunk_language(&self, mapping: &Mapping) -> Result<LanguageCode> {
+		let lang = self.chunk_code(mapping);
+		LanguageCode::new(&self.fallback.resolve(&lang, &self.language_fallbacks))
+	}
+
+	/// Language code of the chunk before it is replaced by a supported one.
+	fn chunk_code(&self, mapping: &Mapping) -> String {
+		match (mapping.file_language(), &self.language) {
+			(Some(code), _) => code.to_owned(),
+			(None, Some(lang)) => lang.clone(),
+			(None, None) => mapping.language_code(&self.languages),
+		}
+	}
+
+	/// Remove the suggestions of disabled rules and for words of the dictionary,
+	/// `text` is the text passed to the backend.
+	pub fn retain_allowed(&self, lang: &str, text: &str, suggestions: &mut Vec<Suggestion>) {
+		let words = self
+			.dictionary(lang)
+			.map(String::as_str)
+			.collect::<HashSet<_>>();
+		let rules = self
+			.disabled_checks(lang)
+			.map(String::as_str)
+			.collect::<HashSet<_>>();
+		if words.is_empty() && rules.is_empty() {
+			return;
+		}
+		suggestions.retain(|suggestion| {
+			let word = text.get(suggestion.offsets(text).utf_8).unwrap_or_default();
+			rules.contains(suggestion.rule_id.as_str()).not() && words.contains(word).not()
+		});
+	}
+}
+
+impl Default for CheckOptions {
+	fn default() -> Self {
+		Self {
+			chunk_size: 1000,
+			language: None,
+			languages: HashMap::new(),
+			fallback: LanguageFallback::default(),
+			language_fallbacks: HashMap::new(),
+			convert: convert::Options::default(),
+			ignore: ignore::Ignore::default(),
+			max_in_flight: 1,
+			cancellation: Cancellation::default(),
+			dictionary: HashMap::new(),
+			disabled_checks: HashMap::new(),
+			picky: None,
+			mother_tongue: None,
+			keep_adjacent: false,
+			ranking: rank::Ranking::default(),
+			debug: false,
+			pipeline: pipeline::Pipeline::default(),
+			contexts: Contexts::default(),
+			submission: Submission::default(),
+			events: events::Events::default(),
+		}
+	}
+}
+
+/// How the text of a file is sent to the backend.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	Default,
+	PartialEq,
+	Eq,
+	serde::Serialize,
+	serde::Deserialize
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Submission {
+	/// Chunks of [`CheckOptions::chunk_size`], the results are cached by paragraph and only
+	/// changed paragraphs are sent again.
+	#[default]
+	Paragraphs,
+	/// All text of a language in one request, for rules across paragraphs like repeated words.
+	/// The results are cached for the whole text, every edit sends it again.
+	Document,
+}
+
+impl Submission {
+	/// Chunk size of the conversion, unlimited for whole documents.
+	pub fn chunk_size(self, chunk_size: usize) -> usize {
+		match self {
+			Self::Paragraphs => chunk_size,
+			Self::Document => usize::MAX,
+		}
+	}
+
+	/// Chunks sent to the backend, for whole documents the chunks of pages and separately
+	/// checked parts are joined while the language stays the same, see [`convert::join`].
+	pub fn chunks(self, chunks: Vec<(String, Mapping)>) -> Vec<(String, Mapping)> {
+		match self {
+			Self::Paragraphs => chunks,
+			Self::Document => convert::join(chunks),
+		}
+	}
+}
+
+impl std::str::FromStr for Submission {
+	type Err = String;
+
+	fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+		match value {
+			"paragraphs" => Ok(Self::Paragraphs),
+			"document" => Ok(Self::Document),
+			_ => Err(format!(
+				"Unknown submission `{}`, expected paragraphs or document.",
+				value
+			)),
+		}
+	}
+}
+
+/// How the locations of a diagnostic are reported, editors show ranges across lines or
+/// separated by markup differently.
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	Default,
+	PartialEq,
+	Eq,
+	serde::Serialize,
+	serde::Deserialize
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Locations {
+	/// Ranges of the flagged text as mapped, separated by markup.
+	#[default]
+	Keep,
+	/// Ranges split at line breaks, every range is on one line.
+	Lines,
+	/// Ranges on the same line merged, including the markup between them.
+	Merge,
+}
+
+impl Locations {
+	/// Normalized locations in `text`, empty ranges after a split are dropped.
+	pub fn apply(self, text: &str, locations: Vec<Range<usize>>) -> Vec<Range<usize>> {
+		match self {
+			Self::Keep => locations,
+			Self::Lines => {
+				let mut lines = Vec::new();
+				for range in &locations {
+					let mut start = range.start;
+					for (index, _) in text[range.clone()].match_indices('\n') {
+						let end = range.start + index;
+						let end = if text[..end].ends_with('\r') {
+							end - 1
+						} else {
+							end
+						};
+						lines.push(start..end);
+						start = range.start + index + 1;
+					}
+					lines.push(start..range.end);
+				}
+				lines.retain(|range| range.is_empty().not());
+				if lines.is_empty() {
+					return locations;
+				}
+				lines
+			},
+			Self::Merge => {
+				let mut merged: Vec<Range<usize>> = Vec::new();
+				for range in locations {
+					match merged.last_mut() {
+						Some(last)
+							if last.end <= range.start
+								&& text[last.end..range.start].contains(is_newline).not() =>
+						{
+							last.end = range.end;
+						},
+						_ => merged.push(range),
+					}
+				}
+				merged
+			},
+		}
+	}
+}
+
+impl std::str::FromStr for Locations {
+	type Err = String;
+
+	fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+		match value {
+			"keep" => Ok(Self::Keep),
+			"lines" => Ok(Self::Lines),
+			"merge" => Ok(Self::Merge),
+			_ => Err(format!(
+				"Unknown locations `{}`, expected keep, lines or merge.",
+				value
+			)),
+		}
+	}
+}
+
+/// Flag shared with a running check to stop it, like when the document changed again.
+/// Requests already sent to the backend are finished.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	/// Allow the next check to run.
+	pub fn reset(&self) {
+		self.0.store(false, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+
+	/// Fail with [`Error::Cancelled`] after [`Cancellation::cancel`].
+	pub fn check(&self) -> Result<()> {
+		if self.is_cancelled() {
+			return Err(Error::Cancelled);
+		}
+		Ok(())
+	}
+}
+
+/// Compile the document and check the text of the file with the backend.
+pub async fn check_file(
+	lt: &impl LanguageToolBackend,
+	world: &impl World,
+	file_id: FileId,
+	options: &CheckOptions,
+) -> Result<Vec<Diagnostic>> {
+	check_file_with(lt, world, file_id, options, |_, _| {}).await
+}
+
+/// Like [`check_file`], `on_diagnostics` is called with the new diagnostics of every checked
+/// paragraph, before the rest of the file is checked.
+pub async fn check_file_with(
+	lt: &impl LanguageToolBackend,
+	world: &impl World,
+	file_id: FileId,
+	options: &CheckOptions,
+	mut on_diagnostics: impl FnMut(&Source, &[Diagnostic]),
+) -> Result<Vec<Diagnostic>> {
+	let doc = compat::compile(world).map_err(Error::Conversion)?;
+	let Ok(source) = world.source(file_id) else {
+		return Err(Error::Conversion(format!(
+			"Missing source for {:?}.",
+			file_id
+		)));
+	};
+
+	options.events.emit(|| Event::CheckStarted { files: 1 });
+	let mut collector = FileCollector::new(file_id, world);
+	collector.set_debug(options.debug);
+	collector.set_contexts(options.contexts.clone());
+	let chunk_size = options.submission.chunk_size(options.chunk_size);
+	let paragraphs = convert::document(&doc, chunk_size, &source, &options.convert);
+	let paragraphs = options.submission.chunks(paragraphs);
+	let total = paragraphs.len();
+	options
+		.events
+		.emit(|| Event::FileConverted { file: file_id, chunks: total });
+	options.events.unsupported_languages(&paragraphs, options);
+	let mut results = requests(lt, paragraphs, options).enumerate();
+	while let Some((index, result)) = results.next().await {
+		let (text, suggestions, mapping, lang) = result?;
+		let diagnostics = collector.add(&text, suggestions, mapping, &lang);
+		if diagnostics.is_empty().not() {
+			on_diagnostics(&source, diagnostics);
+		}
+		options
+			.events
+			.emit(|| Event::ChunkChecked { file: file_id, n: index + 1, total });
+	}
+	let (_, diagnostics) = collector.finish();
+	finished(&options.events, file_id, &diagnostics);
+	Ok(diagnostics)
+}
+
+/// Emit the diagnostics of the only file of a check and the end of the check.
+fn finished(events: &Events, file: FileId, diagnostics: &[Diagnostic]) {
+	events.emit(|| Event::DiagnosticsReady { file, diagnostics: diagnostics.to_vec() });
+	events.emit(|| Event::CheckFinished { files: 1, diagnostics: diagnostics.len() });
+}
+
+/// Check a Typst string without compiling it, see [`convert::markup::document`].
+/// The locations of the diagnostics are byte ranges in `text`.
+pub async fn check_source(
+	lt: &impl LanguageToolBackend,
+	text: &str,
+	options: &CheckOptions,
+) -> Result<Vec<Diagnostic>> {
+	options.events.emit(|| Event::CheckStarted { files: 1 });
+	let source = Source::detached(text);
+	let file_id = source.id();
+	let chunk_size = options.submission.chunk_size(options.chunk_size);
+	let paragraphs =
+		convert::markup::document(&source, chunk_size, Lang::ENGLISH, &options.convert);
+	let paragraphs = options.submission.chunks(paragraphs);
+	let total = paragraphs.len();
+	options
+		.events
+		.emit(|| Event::FileConverted { file: file_id, chunks: total });
+	options.events.unsupported_languages(&paragraphs, options);
+	let mut collector = FileCollector::from_source(source);
+	collector.set_debug(options.debug);
+	collector.set_contexts(options.contexts.clone());
+	let mut results = requests(lt, paragraphs, options).enumerate();
+	while let Some((index, result)) = results.next().await {
+		let (text, suggestions, mapping, lang) = result?;
+		collector.add(&text, suggestions, mapping, &lang);
+		options
+			.events
+			.emit(|| Event::ChunkChecked { file: file_id, n: index + 1, total });
+	}
+	let (_, diagnostics) = collector.finish();
+	finished(&options.events, file_id, &diagnostics);
+	Ok(diagnostics)
+}
+
+/// Check again only the sentences of a Typst source containing `range`, like after applying a
+/// quick fix, without compiling it. Returns the byte range of the checked sentences in the source
+/// with their diagnostics, the diagnostics of the range replace the previous ones there.
+pub async fn recheck_range(
+	lt: &impl LanguageToolBackend,
+	source: &Source,
+	range: Range<usize>,
+	options: &CheckOptions,
+) -> Result<(Range<usize>, Vec<Diagnostic>)> {
+	// A position checks the sentence of the char after it.
+	let range = range.start..range.end.max(range.start + 1);
+	// Chunks longer than one code unit are split after every sentence.
+	let mut sentences = convert::markup::document(source, 1, Lang::ENGLISH, &options.convert);
+	convert::retain_ranges(&mut sentences, source, std::slice::from_ref(&range));
+	let checked = sentences
+		.iter()
+		.flat_map(|(_, mapping)| mapping.pieces(source))
+		.filter_map(|(_, location)| location)
+		.reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+		.unwrap_or(range);
+	let mut collector = FileCollector::from_source(source.clone());
+	collector.set_debug(options.debug);
+	collector.set_contexts(options.contexts.clone());
+	let mut results = requests(lt, sentences, options);
+	while let Some(result) = results.next().await {
+		let (text, suggestions, mapping, lang) = result?;
+		collector.add(&text, suggestions, mapping, &lang);
+	}
+	let (_, diagnostics) = collector.finish();
+	Ok((checked, diagnostics))
+}
+
+/// Characters of the chunks sent to detect their language.
+const DETECTION_SAMPLE: usize = 2000;
+
+/// Language detected by the backend for chunks without a language, to suggest one to the user.
+/// Chunks of documents without `#set text(lang: ..)` are English, so only another detected
+/// language is returned. With `languages` or a chunk with another language nothing is detected.
+pub async fn suggest_language(
+	lt: &impl LanguageToolBackend,
+	chunks: &[(String, Mapping)],
+	languages: &HashMap<String, String>,
+) -> Result<Option<DetectedLanguage>> {
+	if languages.is_empty().not()
+		|| chunks
+			.iter()
+			.any(|(_, mapping)| mapping.has_default_language().not())
+	{
+		return Ok(None);
+	}
+	let mut sample = String::new();
+	for (text, _) in chunks {
+		if sample.chars().count() >= DETECTION_SAMPLE {
+			break;
+		}
+		sample += text;
+		sample += "\n\n";
+	}
+	let sample = sample.chars().take(DETECTION_SAMPLE).collect::<String>();
+	if sample.trim().is_empty() {
+		return Ok(None);
+	}
+	let detected = lt.detect_language(&sample).await?;
+	Ok(detected.filter(|detected| detected.code.language() != "en"))
+}
+
+/// Check the paragraphs with up to [`CheckOptions::max_in_flight`] requests at the same time,
+/// the results are in the order of the paragraphs.
+fn requests<'a>(
+	lt: &'a impl LanguageToolBackend,
+	paragraphs: Vec<(String, Mapping)>,
+	options: &'a CheckOptions,
+) -> impl Stream<Item = Result<(String, Vec<Suggestion>, Mapping, String)>> + 'a {
+	let requests = paragraphs
+		.into_iter()
+		.map(move |(text, mapping)| async move {
+			options.cancellation.check()?;
+			let lang = options.chunk_language(&mapping)?;
+			let mut suggestions = lt.check_text_with(lang.clone(), &text, options).await?;
+			let dictionary = options.dictionary(&lang).map(String::as_str).collect();
+			refine(
+				&text,
+				&lang,
+				&mapping,
+				&mut suggestions,
+				options,
+				&dictionary,
+			);
+			Ok((text, suggestions, mapping, lang.into()))
+		});
+	stream::iter(requests).buffered(options.max_in_flight.max(1))
+}
+
+/// Drop, merge, rank and post-process the suggestions of a checked chunk, with the words of
+/// `dictionary` accepted for the ranking of the replacements.
+fn refine(
+	text: &str,
+	lang: &LanguageCode,
+	mapping: &Mapping,
+	suggestions: &mut Vec<Suggestion>,
+	options: &CheckOptions,
+	dictionary: &HashSet<&str>,
+) {
+	options.ignore.retain(text, suggestions);
+	if options.keep_adjacent.not() {
+		merge_adjacent(text, suggestions);
+	}
+	options.ranking.apply(text, suggestions, dictionary);
+	let context = pipeline::Context { text, language: lang, mapping };
+	options.pipeline.apply(suggestions, &context);
+}
+
+/// Progress of a running check, reported by the frontends after every paragraph.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+	/// File currently checked.
+	pub file: FileId,
+	pub files_done: usize,
+	pub files_total: usize,
+	/// Paragraphs checked in the current file.
+	pub paragraphs_done: usize,
+	pub paragraphs_total: usize,
+}
+
+impl Progress {
+	/// Overall progress in percent, files are weighted equally.
+	pub fn percentage(&self) -> u32 {
+		let file = if self.paragraphs_total == 0 {
+			1.0
+		} else {
+			self.paragraphs_done as f64 / self.paragraphs_total as f64
+		};
+		let total = (self.files_done as f64 + file) / self.files_total.max(1) as f64;
+		(total * 100.0).clamp(0.0, 100.0) as u32
+	}
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Diagnostic {
 	pub locations: Vec<Range<usize>>,
+	/// Language code used to check the text.
+	pub language: String,
 	pub message: String,
+	/// Sentence of the checked text containing the error, without markup.
+	pub sentence: String,
 	pub replacements: Vec<String>,
+	/// Source text of the locations with each replacement applied, including the markup between
+	/// them. `None` for replacements that can not be applied, see [`fix::preview`].
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub previews: Vec<Option<String>>,
 	pub rule_description: String,
 	pub rule_id: String,
+	pub rule_category: String,
+	/// Pages with more information about the rule.
+	pub rule_urls: Vec<String>,
+	/// Example sentences violating the rule, the error is marked with `<marker>` tags.
+	pub incorrect_examples: Vec<String>,
+	/// Example sentences following the rule.
+	pub correct_examples: Vec<String>,
+	/// See [`Suggestion::rule_source`].
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub rule_source: RuleSource,
+	/// See [`Suggestion::context_for_sure_match`].
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub context_for_sure_match: Option<isize>,
+	/// Hash of the rule, the flagged text and its sentence, and the number of earlier diagnostics
+	/// with the same ones in the file. It is the same between runs and versions and stays the same
+	/// for edits outside the sentence.
+	pub fingerprint: String,
+	/// Checked text of the diagnostic, only with [`FileCollector::set_debug`].
+	#[cfg_attr(
+		feature = "serde",
+		serde(default, skip_serializing_if = "Option::is_none")
+	)]
+	pub debug: Option<MappingDebug>,
+}
+
+/// Location of a diagnostic in the text passed to the backend, to trace diagnostics mapped to the
+/// wrong location in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MappingDebug {
+	/// Range of the suggestion in the checked chunk in UTF-16 code units, like the backend.
+	pub start: usize,
+	pub end: usize,
+	/// Flagged text of the chunk.
+	pub flagged: String,
+	/// Flagged text with up to [`MappingDebug::CONTEXT`] chars before and after it.
+	pub snippet: String,
+}
+
+impl MappingDebug {
+	pub const CONTEXT: usize = 30;
+
+	fn new(text: &str, suggestion: &Suggestion) -> Self {
+		let offsets = suggestion.offsets(text);
+		let before = text[..offsets.utf_8.start]
+			.char_indices()
+			.rev()
+			.nth(Self::CONTEXT - 1)
+			.map_or(0, |(index, _)| index);
+		let after = text[offsets.utf_8.end..]
+			.char_indices()
+			.nth(Self::CONTEXT)
+			.map_or(text.len(), |(index, _)| offsets.utf_8.end + index);
+		Self {
+			start: offsets.utf_16.start,
+			end: offsets.utf_16.end,
+			flagged: text[offsets.utf_8.clone()].to_owned(),
+			snippet: text[before..after].to_owned(),
+		}
+	}
+}
+
+impl Diagnostic {
+	/// Guess if the diagnostic comes from a spelling rule, based on the rule id.
+	pub fn is_spelling(&self) -> bool {
+		is_spelling_rule(&self.rule_id)
+	}
+
+	/// Page with more information about the rule, see [`community_url`] without one.
+	pub fn rule_url(&self) -> String {
+		match self.rule_urls.first() {
+			Some(url) => url.clone(),
+			None => community_url(&self.rule_id, &self.language),
+		}
+	}
+}
+
+/// Hash of the rule id and the flagged text and sentence, with whitespace collapsed.
+fn context_hash(source: &Source, diagnostic: &Diagnostic) -> u64 {
+	let flagged = diagnostic
+		.locations
+		.iter()
+		.filter_map(|range| source.text().get(range.clone()));
+	let mut hash = fnv1a(FNV_OFFSET, diagnostic.rule_id.as_bytes());
+	for text in flagged.chain([diagnostic.sentence.as_str()]) {
+		hash = fnv1a(hash, &[0]);
+		for word in text.split_whitespace() {
+			hash = fnv1a(hash, word.as_bytes());
+			hash = fnv1a(hash, b" ");
+		}
+	}
+	hash
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a, unlike the hashers of the standard library it is stable between Rust versions.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+	for &byte in bytes {
+		hash ^= byte as u64;
+		hash = hash.wrapping_mul(0x0100_0000_01b3);
+	}
+	hash
+}
+
+/// Merge the suggestions of a rule that overlap or are only separated by spaces, like for
+/// repeated whitespace runs, into one for the combined range. `text` is the text passed to the
+/// backend. The first replacements are joined for adjacent suggestions, contained suggestions
+/// are dropped.
+pub fn merge_adjacent(text: &str, suggestions: &mut Vec<Suggestion>) {
+	suggestions.sort_by_key(|suggestion| suggestion.start);
+	let mut merged = Vec::<Suggestion>::with_capacity(suggestions.len());
+	for suggestion in suggestions.drain(..) {
+		let Some(last) = merged
+			.last_mut()
+			.filter(|last| last.rule_id == suggestion.rule_id)
+		else {
+			merged.push(suggestion);
+			continue;
+		};
+		let (previous, next) = (last.offsets(text).utf_8, suggestion.offsets(text).utf_8);
+		if next.start < previous.end {
+			if next.end > previous.end {
+				last.end = suggestion.end;
+				last.replacements.clear();
+			}
+			continue;
+		}
+		let gap = &text[previous.end..next.start];
+		if gap.chars().all(|c| c.is_whitespace() && c != '\n').not() {
+			merged.push(suggestion);
+			continue;
+		}
+		last.replacements = match (last.replacements.first(), suggestion.replacements.first()) {
+			(Some(first), Some(second)) => vec![format!("{}{}{}", first, gap, second)],
+			_ => Vec::new(),
+		};
+		last.end = suggestion.end;
+	}
+	*suggestions = merged;
+}
+
+fn is_spelling_rule(rule_id: &str) -> bool {
+	let id = rule_id.to_uppercase();
+	["SPELL", "MORFOLOGIK", "HUNSPELL"]
+		.iter()
+		.any(|name| id.contains(name))
+}
+
+/// Split an example sentence of a rule into the text before the `<marker>` tags,
+/// the marked error and the text after them, for highlighting the error.
+/// Sentences without the tags are returned as the text before the error.
+pub fn marked_example(example: &str) -> (&str, &str, &str) {
+	let Some((before, rest)) = example.split_once("<marker>") else {
+		return (example, "", "");
+	};
+	match rest.split_once("</marker>") {
+		Some((marked, after)) => (before, marked, after),
+		None => (before, rest, ""),
+	}
+}
+
+/// Page of the rule in the LanguageTool community rule browser.
+pub fn community_url(rule_id: &str, language: &str) -> String {
+	let lang = language.split('-').next().unwrap_or_default();
+	format!(
+		"https://community.languagetool.org/rule/show/{}?lang={}",
+		rule_id, lang
+	)
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Suggestion {
+	/// Start in UTF-16 code units of the checked text, see [`Suggestion::offsets`].
 	pub start: usize,
+	/// End in UTF-16 code units of the checked text.
 	pub end: usize,
 	pub message: String,
+	/// Sentence of the checked text containing the error.
+	pub sentence: String,
 	pub replacements: Vec<String>,
 	pub rule_description: String,
 	pub rule_id: String,
+	pub rule_category: String,
+	/// Pages with more information about the rule.
+	pub rule_urls: Vec<String>,
+	/// Example sentences violating the rule, the error is marked with `<marker>` tags.
+	pub incorrect_examples: Vec<String>,
+	/// Example sentences following the rule.
+	pub correct_examples: Vec<String>,
+	/// Standard, picky or premium rule, [`RuleSource::Standard`] for backends without tags.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub rule_source: RuleSource,
+	/// Words of context LanguageTool needs around the match to be sure of it, `0` if it is sure
+	/// without any and `-1` if it is never sure. `None` for backends without it.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub context_for_sure_match: Option<isize>,
+}
+
+/// Kind of rule a match comes from, so frontends can tell certain errors from stylistic
+/// suggestions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(rename_all = "lowercase")
+)]
+pub enum RuleSource {
+	#[default]
+	Standard,
+	/// Stricter rules only reported with the picky level, tagged `picky` by LanguageTool.
+	Picky,
+	/// Rules of the premium API of LanguageTool.
+	Premium,
+}
+
+impl RuleSource {
+	/// Source of a rule with the premium flag and the tags of LanguageTool.
+	pub fn new(premium: bool, tags: &[impl AsRef<str>]) -> Self {
+		if premium {
+			Self::Premium
+		} else if tags.iter().any(|tag| tag.as_ref() == "picky") {
+			Self::Picky
+		} else {
+			Self::Standard
+		}
+	}
+}
+
+impl Suggestion {
+	/// Suggestion for the code units without a message, replacements or rule.
+	#[cfg(test)]
+	pub(crate) fn test(start: usize, end: usize) -> Self {
+		Self {
+			start,
+			end,
+			message: String::new(),
+			sentence: String::new(),
+			replacements: Vec::new(),
+			rule_description: String::new(),
+			rule_id: String::new(),
+			rule_category: String::new(),
+			rule_urls: Vec::new(),
+			incorrect_examples: Vec::new(),
+			correct_examples: Vec::new(),
+			rule_source: RuleSource::Standard,
+			context_for_sure_match: None,
+		}
+	}
+
+	/// Guess if the suggestion comes from a spelling rule, like [`Diagnostic::is_spelling`].
+	pub fn is_spelling(&self) -> bool {
+		is_spelling_rule(&self.rule_id)
+	}
+
+	/// Offsets in all encodings, `text` is the text passed to the backend.
+	pub fn offsets(&self, text: &str) -> Offsets {
+		let mut offsets = Offsets::default();
+		let (mut utf_8, mut chars, mut utf_16) = (0, 0, 0);
+		for c in text.chars().chain(std::iter::once('\0')) {
+			if utf_16 <= self.start {
+				offsets.utf_8.start = utf_8;
+				offsets.chars.start = chars;
+				offsets.utf_16.start = utf_16;
+			}
+			if utf_16 <= self.end {
+				offsets.utf_8.end = utf_8;
+				offsets.chars.end = chars;
+				offsets.utf_16.end = utf_16;
+			} else {
+				break;
+			}
+			utf_8 += c.len_utf8();
+			chars += 1;
+			utf_16 += c.len_utf16();
+		}
+		offsets
+	}
+}
+
+/// Range of a suggestion in the checked text in UTF-8 bytes, chars and UTF-16 code units.
+/// Offsets inside a char are rounded down to its start, offsets past the end are clamped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Offsets {
+	pub utf_8: Range<usize>,
+	pub chars: Range<usize>,
+	pub utf_16: Range<usize>,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
 	pub utf_8: usize,
 	pub line: usize,
@@ -176,30 +1808,28 @@ impl<'a> TextWithPosition<'a> {
 			.content
 			.utf_8_offset(char_index, stop_at_newline)
 			.unwrap_or(self.content.text.len());
+		let text = self.content.text;
 		if start < end {
-			for c in self.content.text[start..end].chars() {
-				match c {
-					'\n' => {
-						self.line += 1;
-						self.column = 0;
-					},
-					_ => {
-						self.column += 1;
-					},
-				}
-			}
-		} else if end > start {
-			for c in self.content.text[end..start].chars() {
-				match c {
-					'\n' => {
-						self.line -= 1;
-						self.column = 1;
-					},
-					_ => {
-						self.column -= 1;
-					},
+			for (index, c) in text[start..end].char_indices() {
+				if is_line_break(text, start + index) {
+					self.line += 1;
+					self.column = 0;
+				} else if is_newline(c).not() {
+					self.column += 1;
 				}
 			}
+		} else if start > end {
+			let breaks = text[end..start]
+				.char_indices()
+				.filter(|&(index, _)| is_line_break(text, end + index))
+				.count();
+			self.line -= breaks;
+			let line_start = text[..end]
+				.char_indices()
+				.rev()
+				.find(|&(_, c)| is_newline(c))
+				.map_or(0, |(index, c)| index + c.len_utf8());
+			self.column = text[line_start..end].chars().count();
 		}
 		Position {
 			utf_8: end,
@@ -213,6 +1843,16 @@ impl<'a> TextWithPosition<'a> {
 	}
 }
 
+/// Whether a line break starts at the byte index. Like in Typst, `\r\n` is a single break and
+/// Unicode separators like U+2028 are breaks too.
+fn is_line_break(text: &str, index: usize) -> bool {
+	match text[index..].chars().next() {
+		Some('\n') => text[..index].ends_with('\r').not(),
+		Some(c) => is_newline(c),
+		None => false,
+	}
+}
+
 #[derive(Debug)]
 struct StringCursor<'a> {
 	text: &'a str,
@@ -231,7 +1871,7 @@ impl<'a> StringCursor<'a> {
 				.chars()
 				.take(char_index - self.char_index)
 			{
-				if stop_at_newline && matches!(c, '\n' | '\r') {
+				if stop_at_newline && is_newline(c) {
 					return Some(self.utf_8_index);
 				}
 				self.utf_8_index += c.len_utf8();
@@ -243,7 +1883,7 @@ impl<'a> StringCursor<'a> {
 				.rev()
 				.take(self.char_index - char_index)
 			{
-				if stop_at_newline && matches!(c, '\n' | '\r') {
+				if stop_at_newline && is_newline(c) {
 					return Some(self.utf_8_index);
 				}
 				self.utf_8_index -= c.len_utf8();
@@ -280,4 +1920,409 @@ mod test {
 		assert_eq!(cursor.utf_8_offset(0, false), Some(0));
 		assert_eq!(cursor.utf_8_offset(20, true), Some(3));
 	}
+
+	#[test]
+	fn test_line_breaks() {
+		let text = "ab\r\ncd\u{2028}e\rf\ng";
+		let mut position = TextWithPosition::new(text);
+		let mut at = |char_index| {
+			let position = position.get_position(char_index, false);
+			(position.line, position.column)
+		};
+		assert_eq!(at(1), (0, 1));
+		assert_eq!(at(3), (1, 0));
+		assert_eq!(at(4), (1, 0));
+		assert_eq!(at(7), (2, 0));
+		assert_eq!(at(11), (4, 0));
+		assert_eq!(at(6), (1, 2));
+		assert_eq!(at(0), (0, 0));
+		assert_eq!(at(12), (4, 1));
+
+		let mut cursor = StringCursor::new(text);
+		assert_eq!(cursor.utf_8_offset(20, true), Some(2));
+	}
+
+	#[test]
+	fn test_offsets() {
+		let text = "Ö😀 ab";
+		let suggestion = Suggestion::test(4, 6);
+		let offsets = suggestion.offsets(text);
+		assert_eq!(offsets.utf_8, 7..9);
+		assert_eq!(offsets.chars, 3..5);
+		assert_eq!(offsets.utf_16, 4..6);
+		assert_eq!(&text[offsets.utf_8], "ab");
+	}
+
+	#[tokio::test]
+	async fn test_check_source() {
+		let lt = mock::MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let text = "= Teh *teh* title
+#let x = \"teh\"
+See `teh` and #emph[teh]~end.";
+		let diagnostics = check_source(&lt, text, &CheckOptions::default())
+			.await
+			.unwrap();
+		let locations = diagnostics
+			.iter()
+			.map(|diagnostic| &text[diagnostic.locations[0].clone()])
+			.collect::<Vec<_>>();
+		assert_eq!(locations, vec!["teh", "teh"]);
+		assert_eq!(
+			lt.checked_texts(),
+			vec!["Teh teh title\n\nSee and teh\u{a0}end."]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_check_options() {
+		let lt = mock::MockBackend::new()
+			.rule("teh", "TYPO", &["the"])
+			.rule("Some", "SOME", &[]);
+		let text = "Some teh text.";
+		let mut options = CheckOptions {
+			language: Some("en-US".into()),
+			..CheckOptions::default()
+		};
+		assert_eq!(check_source(&lt, text, &options).await.unwrap().len(), 2);
+		options.dictionary.insert("en".into(), vec!["teh".into()]);
+		options
+			.disabled_checks
+			.insert("en-GB".into(), vec!["SOME".into()]);
+		let diagnostics = check_source(&lt, text, &options).await.unwrap();
+		assert_eq!(diagnostics.len(), 1);
+		assert_eq!(diagnostics[0].rule_id, "SOME");
+		options
+			.disabled_checks
+			.insert("en-US".into(), vec!["SOME".into()]);
+		assert!(check_source(&lt, text, &options).await.unwrap().is_empty());
+		assert!(lt
+			.calls()
+			.iter()
+			.all(|call| matches!(call, mock::Call::CheckText { .. })));
+	}
+
+	#[test]
+	fn test_marked_example() {
+		assert_eq!(
+			marked_example("This is <marker>a</marker> apple."),
+			("This is ", "a", " apple.")
+		);
+		assert_eq!(marked_example("No error."), ("No error.", "", ""));
+		assert_eq!(marked_example("Open <marker>tag"), ("Open ", "tag", ""));
+	}
+
+	#[test]
+	fn test_locations() {
+		let text = "One *two\r\nthree* four\nfive";
+		let locations = vec![0..3, 5..15, 17..22];
+		assert_eq!(
+			Locations::Lines.apply(text, locations.clone()),
+			[0..3, 5..8, 10..15, 17..21]
+		);
+		assert_eq!(
+			Locations::Merge.apply(text, locations.clone()),
+			std::slice::from_ref(&(0..22))
+		);
+		assert_eq!(
+			Locations::Merge.apply(text, vec![0..3, 10..15]),
+			[0..3, 10..15]
+		);
+		assert_eq!(Locations::Keep.apply(text, locations.clone()), locations);
+		// Only line breaks are kept as they are.
+		assert_eq!(
+			Locations::Lines.apply(text, vec![8..10, 21..22]),
+			[8..10, 21..22]
+		);
+	}
+
+	#[test]
+	fn test_rule_source() {
+		assert_eq!(RuleSource::new(false, &[] as &[&str]), RuleSource::Standard);
+		assert_eq!(RuleSource::new(false, &["picky"]), RuleSource::Picky);
+		assert_eq!(RuleSource::new(true, &["picky"]), RuleSource::Premium);
+		assert_eq!(
+			RuleSource::new(false, &["academic", "picky"]),
+			RuleSource::Picky
+		);
+		assert_eq!(RuleSource::new(false, &["Picky"]), RuleSource::Standard);
+	}
+
+	#[test]
+	fn test_parse_version() {
+		assert_eq!(parse_version("6.4"), Some((6, 4)));
+		assert_eq!(parse_version("6.5-SNAPSHOT"), Some((6, 5)));
+		assert_eq!(parse_version("4"), Some((4, 0)));
+		assert_eq!(parse_version("mock"), None);
+		assert!(parse_version("4.9").unwrap() < MINIMUM_VERSION);
+	}
+
+	#[test]
+	fn test_merge_adjacent() {
+		let text = "Wait , , what  now.\n  And";
+		let suggestion = |start: usize, end: usize, rule_id: &str| Suggestion {
+			replacements: vec![text[start..end].trim().to_uppercase()],
+			rule_id: rule_id.into(),
+			..Suggestion::test(start, end)
+		};
+		let mut suggestions = vec![
+			suggestion(6, 8, "COMMA"),
+			suggestion(4, 6, "COMMA"),
+			suggestion(5, 6, "COMMA"),
+			suggestion(9, 13, "WORD"),
+			suggestion(15, 18, "WORD"),
+			suggestion(20, 22, "SPACE"),
+			suggestion(22, 25, "WORD"),
+		];
+		merge_adjacent(text, &mut suggestions);
+		let ranges = suggestions
+			.iter()
+			.map(|suggestion| (suggestion.start, suggestion.end))
+			.collect::<Vec<_>>();
+		assert_eq!(ranges, [(4, 8), (9, 18), (20, 22), (22, 25)]);
+		assert_eq!(suggestions[0].replacements, [",,"]);
+		assert_eq!(suggestions[1].replacements, ["WHAT  NOW"]);
+	}
+
+	#[tokio::test]
+	async fn test_cancellation() {
+		let lt = mock::MockBackend::new();
+		let options = CheckOptions::default();
+		options.cancellation.cancel();
+		let result = check_source(&lt, "Some text.", &options).await;
+		assert!(matches!(result, Err(Error::Cancelled)));
+		assert!(lt.checked_texts().is_empty());
+
+		options.cancellation.reset();
+		assert!(check_source(&lt, "Some text.", &options).await.is_ok());
+	}
+
+	#[tokio::test]
+	async fn test_max_in_flight() {
+		let lt = mock::MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let text = "First teh.\n\nSecond teh.\n\nThird teh.";
+		let mut options = CheckOptions { chunk_size: 1, ..CheckOptions::default() };
+		let sequential = check_source(&lt, text, &options).await.unwrap();
+		options.max_in_flight = 4;
+		let concurrent = check_source(&lt, text, &options).await.unwrap();
+		let locations = |diagnostics: &[Diagnostic]| {
+			diagnostics
+				.iter()
+				.map(|diagnostic| diagnostic.locations.clone())
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(sequential.len(), 3);
+		assert_eq!(locations(&sequential), locations(&concurrent));
+	}
+
+	#[tokio::test]
+	async fn test_duplicates() {
+		let lt = mock::MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let source = Source::detached("Some teh text.");
+		let options = convert::Options::default();
+		let paragraphs = convert::markup::document(&source, 1000, Lang::ENGLISH, &options);
+		let mut collector = FileCollector::from_source(source);
+		for _ in 0..2 {
+			for (text, mapping) in paragraphs.clone() {
+				let suggestions = lt.check_text(LanguageCode::EN_US, &text).await.unwrap();
+				collector.add(&text, suggestions, mapping, "en-US");
+			}
+		}
+		assert_eq!(collector.diagnostics().len(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_fingerprints() {
+		let lt = mock::MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let fingerprints = |text| {
+			let lt = &lt;
+			async move {
+				// The mock backend reports the whole chunk as the sentence.
+				let options = CheckOptions { chunk_size: 1, ..CheckOptions::default() };
+				let diagnostics = check_source(lt, text, &options).await.unwrap();
+				diagnostics
+					.into_iter()
+					.map(|diagnostic| diagnostic.fingerprint)
+					.collect::<Vec<_>>()
+			}
+		};
+		let before = fingerprints("Some teh text with teh.").await;
+		let after = fingerprints("A new  paragraph.\n\nSome teh text with teh.").await;
+		assert_eq!(before.len(), 2);
+		assert_ne!(before[0], before[1]);
+		assert_eq!(before, after);
+	}
+
+	#[tokio::test]
+	async fn test_previews() {
+		let lt = mock::MockBackend::new().rule("teh wrd", "TYPO", &["the word", "teh"]);
+		let text = "A *teh* wrd.";
+		let diagnostics = check_source(&lt, text, &CheckOptions::default())
+			.await
+			.unwrap();
+		assert_eq!(
+			diagnostics[0].previews,
+			[Some("the* word".into()), Some("teh*".into())]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_recheck_range() {
+		let lt = mock::MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let text = "First teh. Second *teh* word. Third teh.\n\nNext teh.";
+		let source = Source::detached(text);
+		let start = text.find("Second").unwrap();
+		let options = CheckOptions::default();
+		let (checked, diagnostics) = recheck_range(&lt, &source, start + 8..start + 8, &options)
+			.await
+			.unwrap();
+		assert_eq!(&text[checked], "Second *teh* word. ");
+		assert_eq!(diagnostics.len(), 1);
+		assert_eq!(&text[diagnostics[0].locations[0].clone()], "teh");
+		assert_eq!(lt.checked_texts(), vec!["Second teh word. "]);
+	}
+
+	#[tokio::test]
+	async fn test_suggest_language() {
+		let lt = mock::MockBackend::new().detect("German (Germany)", LanguageCode::DE_DE);
+		let text = "Das ist ein Satz.";
+		let chunks = convert::plain::document(text, 1000, Lang::ENGLISH);
+		let detected = suggest_language(&lt, &chunks, &HashMap::new())
+			.await
+			.unwrap();
+		assert_eq!(
+			detected.map(|detected| detected.code),
+			Some(LanguageCode::DE_DE)
+		);
+
+		let languages = HashMap::from([("en".into(), "en-GB".into())]);
+		let detected = suggest_language(&lt, &chunks, &languages).await.unwrap();
+		assert_eq!(detected, None);
+		let chunks = convert::plain::document(text, 1000, Lang::GERMAN);
+		let detected = suggest_language(&lt, &chunks, &HashMap::new())
+			.await
+			.unwrap();
+		assert_eq!(detected, None);
+
+		let lt = mock::MockBackend::new().detect("English (US)", LanguageCode::EN_US);
+		let chunks = convert::plain::document("This is a sentence.", 1000, Lang::ENGLISH);
+		let detected = suggest_language(&lt, &chunks, &HashMap::new())
+			.await
+			.unwrap();
+		assert_eq!(detected, None);
+	}
+
+	#[tokio::test]
+	async fn test_pipeline() {
+		let lt =
+			mock::MockBackend::new()
+				.rule("teh", "TYPO", &["the"])
+				.rule("wrd", "TYPO", &["word"]);
+		let pipeline = pipeline::Pipeline::new()
+			.with(|suggestions, context| {
+				suggestions.retain(|suggestion| {
+					&context.text[suggestion.offsets(context.text).utf_8] != "wrd"
+				})
+			})
+			.with(|suggestions, context| {
+				for suggestion in suggestions {
+					suggestion.message = format!("{} ({})", suggestion.message, context.language);
+				}
+			});
+		let options = CheckOptions { pipeline, ..CheckOptions::default() };
+		let diagnostics = check_source(&lt, "A teh wrd.", &options).await.unwrap();
+		assert_eq!(diagnostics.len(), 1);
+		let message = format!("Mock rule TYPO. ({})", diagnostics[0].language);
+		assert_eq!(diagnostics[0].message, message);
+	}
+
+	#[tokio::test]
+	async fn test_mapping_debug() {
+		let lt = mock::MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let text = "Some *bold* and teh text.";
+		let diagnostics = check_source(&lt, text, &CheckOptions::default())
+			.await
+			.unwrap();
+		assert!(diagnostics[0].debug.is_none());
+
+		let options = CheckOptions { debug: true, ..CheckOptions::default() };
+		let diagnostics = check_source(&lt, text, &options).await.unwrap();
+		let debug = diagnostics[0].debug.as_ref().unwrap();
+		assert_eq!((debug.start, debug.end), (14, 17));
+		assert_eq!(debug.flagged, "teh");
+		assert_eq!(debug.snippet, "Some bold and teh text.");
+		assert_eq!(&text[diagnostics[0].locations[0].clone()], "teh");
+
+		let long = format!("{} teh {}", "a".repeat(40), "b".repeat(40));
+		let diagnostics = check_source(&lt, &long, &options).await.unwrap();
+		let debug = diagnostics[0].debug.as_ref().unwrap();
+		let context = MappingDebug::CONTEXT - 1;
+		assert_eq!(
+			debug.snippet,
+			format!("{} teh {}", "a".repeat(context), "b".repeat(context))
+		);
+	}
+
+	#[tokio::test]
+	async fn test_sorted_diagnostics() {
+		let lt =
+			mock::MockBackend::new()
+				.rule("teh", "TYPO", &["the"])
+				.rule("teh", "A_TYPO", &["the"]);
+		let source = Source::detached("First teh.\n\nSecond teh.");
+		let options = convert::Options::default();
+		let paragraphs = convert::markup::document(&source, 1, Lang::ENGLISH, &options);
+		let mut collector = FileCollector::from_source(source.clone());
+		for (text, mapping) in paragraphs.into_iter().rev() {
+			let suggestions = lt.check_text(LanguageCode::EN_US, &text).await.unwrap();
+			collector.add(&text, suggestions, mapping, "en-US");
+		}
+		let (_, diagnostics) = collector.finish();
+		let order = diagnostics
+			.iter()
+			.map(|diagnostic| (diagnostic.locations[0].start, diagnostic.rule_id.as_str()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			order,
+			[(6, "A_TYPO"), (6, "TYPO"), (19, "A_TYPO"), (19, "TYPO")]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_project_collector() {
+		use typst::syntax::VirtualPath;
+
+		let lt = mock::MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let texts = [
+			("c.typ", "A *teh* end."),
+			("a.typ", "Start with teh."),
+			("b.typ", "No typo."),
+		];
+		let mut collector = ProjectCollector::new();
+		for (path, text) in texts {
+			let file_id = FileId::new(None, VirtualPath::new(path));
+			let source = Source::detached(text);
+			let options = convert::Options::default();
+			let paragraphs = convert::markup::document(&source, 1000, Lang::ENGLISH, &options);
+			collector.add_source(file_id, source);
+			for (text, mapping) in paragraphs {
+				let suggestions = lt.check_text(LanguageCode::EN_US, &text).await.unwrap();
+				collector.add(file_id, &text, suggestions, mapping, "en-US");
+			}
+		}
+		let files = collector.finish();
+		let counts = files
+			.iter()
+			.map(|(file_id, _, diagnostics)| {
+				(file_id.vpath().as_rootless_path(), diagnostics.len())
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			counts,
+			vec![
+				(std::path::Path::new("a.typ"), 1),
+				(std::path::Path::new("b.typ"), 0),
+				(std::path::Path::new("c.typ"), 1),
+			]
+		);
+	}
 }
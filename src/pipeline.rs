@@ -0,0 +1,61 @@
+//! Hooks run on the suggestions of every chunk after the backend answered and before they are
+//! collected, to rewrite messages, drop false positives of a project or add custom suggestions.
+
+use std::{fmt, sync::Arc};
+
+use crate::{convert::Mapping, Suggestion};
+
+/// Chunk the suggestions belong to.
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+	/// Text passed to the backend, the offsets of the suggestions are UTF-16 code units in it.
+	pub text: &'a str,
+	/// Language code used to check the text.
+	pub language: &'a str,
+	pub mapping: &'a Mapping,
+}
+
+pub type Hook = Arc<dyn Fn(&mut Vec<Suggestion>, &Context) + Send + Sync>;
+
+/// Hooks run in the order they were added, after the ignored tokens, the merging of adjacent
+/// suggestions and the ranking of the replacements.
+#[derive(Clone, Default)]
+pub struct Pipeline {
+	hooks: Vec<Hook>,
+}
+
+impl fmt::Debug for Pipeline {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Pipeline")
+			.field("hooks", &self.hooks.len())
+			.finish()
+	}
+}
+
+impl Pipeline {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with(
+		mut self,
+		hook: impl Fn(&mut Vec<Suggestion>, &Context) + Send + Sync + 'static,
+	) -> Self {
+		self.add(hook);
+		self
+	}
+
+	pub fn add(&mut self, hook: impl Fn(&mut Vec<Suggestion>, &Context) + Send + Sync + 'static) {
+		self.hooks.push(Arc::new(hook));
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.hooks.is_empty()
+	}
+
+	pub fn apply(&self, suggestions: &mut Vec<Suggestion>, context: &Context) {
+		for hook in &self.hooks {
+			hook(suggestions, context);
+		}
+	}
+}
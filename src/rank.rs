@@ -0,0 +1,134 @@
+//! Order and number of the replacements of the suggestions, so quick fixes show the best first.
+//! LanguageTool sometimes returns more than 20 replacements for a misspelled word.
+
+use std::{collections::HashSet, fmt, ops::Not, sync::Arc};
+
+use crate::Suggestion;
+
+/// Score of a replacement for the flagged text, higher scores are shown first.
+pub type Score = Arc<dyn Fn(&str, &str) -> i64 + Send + Sync>;
+
+/// Ranks the replacements with a score and keeps the best of them.
+/// Replacements with the same score stay in the order of the backend.
+#[derive(Clone, Default)]
+pub struct Ranking {
+	/// Maximum number of replacements of a suggestion, all are kept without a limit.
+	pub limit: Option<usize>,
+	/// Order the replacements by their score, see [`Ranking::score`].
+	pub rank: bool,
+	score: Option<Score>,
+}
+
+impl fmt::Debug for Ranking {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Ranking")
+			.field("limit", &self.limit)
+			.field("rank", &self.rank)
+			.field("score", &self.score.as_ref().map(|_| ".."))
+			.finish()
+	}
+}
+
+impl Ranking {
+	pub fn new(limit: Option<usize>, rank: bool) -> Self {
+		Self { limit, rank, score: None }
+	}
+
+	/// Rank with the score instead of [`Ranking::score`], it is called with the flagged text and
+	/// the replacement.
+	pub fn with_score(mut self, score: impl Fn(&str, &str) -> i64 + Send + Sync + 'static) -> Self {
+		self.score = Some(Arc::new(score));
+		self.rank = true;
+		self
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.limit.is_none() && self.rank.not()
+	}
+
+	/// Order and limit the replacements, `text` is the text passed to the backend.
+	/// Words of the dictionary are preferred by the default score.
+	pub fn apply(&self, text: &str, suggestions: &mut [Suggestion], dictionary: &HashSet<&str>) {
+		if self.is_empty() {
+			return;
+		}
+		for suggestion in suggestions {
+			if self.rank && suggestion.replacements.len() > 1 {
+				let flagged = text.get(suggestion.offsets(text).utf_8).unwrap_or_default();
+				let score = |replacement: &String| match &self.score {
+					Some(score) => score(flagged, replacement),
+					None => Self::score(flagged, replacement, dictionary),
+				};
+				suggestion
+					.replacements
+					.sort_by_cached_key(|replacement| std::cmp::Reverse(score(replacement)));
+			}
+			if let Some(limit) = self.limit {
+				suggestion.replacements.truncate(limit);
+			}
+		}
+	}
+
+	/// Prefers replacements with the case of the flagged text, then with its length,
+	/// then words of the dictionary.
+	pub fn score(flagged: &str, replacement: &str, dictionary: &HashSet<&str>) -> i64 {
+		let mut score = 0;
+		if case(flagged) == case(replacement) {
+			score += 4;
+		}
+		if flagged.chars().count() == replacement.chars().count() {
+			score += 2;
+		}
+		if dictionary.contains(replacement) {
+			score += 1;
+		}
+		score
+	}
+}
+
+#[derive(PartialEq, Eq)]
+enum Case {
+	Lower,
+	Capitalized,
+	Upper,
+	Other,
+}
+
+fn case(word: &str) -> Case {
+	let mut letters = word.chars().filter(|c| c.is_alphabetic());
+	let Some(first) = letters.next() else {
+		return Case::Other;
+	};
+	let rest = letters.collect::<Vec<_>>();
+	match (first.is_uppercase(), rest.iter().all(|c| c.is_uppercase())) {
+		(true, true) if rest.is_empty().not() => Case::Upper,
+		(true, _) if rest.iter().all(|c| c.is_lowercase()) => Case::Capitalized,
+		(false, _) if rest.iter().all(|c| c.is_lowercase()) => Case::Lower,
+		_ => Case::Other,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_ranking() {
+		let text = "Teh end.";
+		let mut suggestions = vec![Suggestion {
+			replacements: ["the", "THE", "Then", "Tea", "The", "Ten"]
+				.map(String::from)
+				.to_vec(),
+			..Suggestion::test(0, 3)
+		}];
+		let dictionary = HashSet::from(["Ten"]);
+		Ranking::new(Some(4), true).apply(text, &mut suggestions, &dictionary);
+		assert_eq!(suggestions[0].replacements, ["Ten", "Tea", "The", "Then"]);
+
+		let ranking =
+			Ranking::new(Some(1), false).with_score(|_, replacement| replacement.len() as i64);
+		ranking.apply(text, &mut suggestions, &dictionary);
+		assert_eq!(suggestions[0].replacements, ["Then"]);
+		assert!(Ranking::default().is_empty());
+	}
+}
@@ -0,0 +1,132 @@
+//! Readability of the checked text, the distribution of the sentence lengths, the diagnostics of
+//! passive voice rules and the most frequent words. The sentences are estimated from the
+//! converted text, abbreviations like `e.g.` end a sentence too.
+
+use std::{collections::HashMap, ops::Not};
+
+/// Upper bounds in words of the buckets of [`Readability::distribution`].
+pub const BUCKETS: [usize; 4] = [10, 20, 30, 40];
+
+/// Shortest word counted by [`Readability::frequent`], in letters.
+const MIN_LETTERS: usize = 4;
+
+#[derive(Debug, Clone, Default)]
+pub struct Readability {
+	/// Words of every sentence.
+	lengths: Vec<usize>,
+	/// Lowercase words with their count.
+	words: HashMap<String, usize>,
+	/// Diagnostics of passive voice rules, see [`Readability::add_rule`].
+	pub passive: usize,
+}
+
+impl Readability {
+	/// Add the sentences of a converted chunk, they end with `.`, `!` or `?` before whitespace
+	/// and at empty lines, like after headings.
+	pub fn add_text(&mut self, text: &str) {
+		for paragraph in text.split("\n\n") {
+			let mut length = 0;
+			for token in paragraph.split_whitespace() {
+				let word = token.trim_matches(|c: char| c.is_alphanumeric().not());
+				if word.is_empty() {
+					continue;
+				}
+				length += 1;
+				if word.chars().filter(|c| c.is_alphabetic()).count() >= MIN_LETTERS {
+					*self.words.entry(word.to_lowercase()).or_default() += 1;
+				}
+				let end = token.trim_end_matches(['"', '\'', '”', '’', '»', '“', ')', ']']);
+				if end.ends_with(['.', '!', '?']) {
+					self.lengths.push(length);
+					length = 0;
+				}
+			}
+			if length > 0 {
+				self.lengths.push(length);
+			}
+		}
+	}
+
+	/// Count the diagnostic if it is of a passive voice rule, like `PASSIVE_VOICE` of the
+	/// English style rules.
+	pub fn add_rule(&mut self, rule_id: &str) {
+		if rule_id.to_ascii_uppercase().contains("PASSIV") {
+			self.passive += 1;
+		}
+	}
+
+	pub fn sentences(&self) -> usize {
+		self.lengths.len()
+	}
+
+	pub fn words(&self) -> usize {
+		self.lengths.iter().sum()
+	}
+
+	/// Average words per sentence, `0.0` without sentences.
+	pub fn average(&self) -> f64 {
+		if self.lengths.is_empty() {
+			return 0.0;
+		}
+		self.words() as f64 / self.sentences() as f64
+	}
+
+	/// Words of the longest sentence.
+	pub fn longest(&self) -> usize {
+		self.lengths.iter().copied().max().unwrap_or_default()
+	}
+
+	/// Sentences by length, like `1-10` words for the first bucket of [`BUCKETS`]
+	/// and `41+` for longer sentences.
+	pub fn distribution(&self) -> Vec<(String, usize)> {
+		let mut counts = vec![0; BUCKETS.len() + 1];
+		for &length in &self.lengths {
+			counts[BUCKETS.partition_point(|&bound| bound < length)] += 1;
+		}
+		let mut start = 1;
+		let mut labels = Vec::with_capacity(counts.len());
+		for bound in BUCKETS {
+			labels.push(format!("{}-{}", start, bound));
+			start = bound + 1;
+		}
+		labels.push(format!("{}+", start));
+		labels.into_iter().zip(counts).collect()
+	}
+
+	/// Most frequent words with at least four letters, lowercase, ties by the word.
+	pub fn frequent(&self, count: usize) -> Vec<(&str, usize)> {
+		let mut words = self
+			.words
+			.iter()
+			.map(|(word, &count)| (word.as_str(), count))
+			.collect::<Vec<_>>();
+		words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+		words.truncate(count);
+		words
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_readability() {
+		let mut readability = Readability::default();
+		readability.add_text("Title\n\nTypst is fast. Is Typst \"fast\"? Yes!\n\nA list item");
+		readability.add_text(&"word ".repeat(45));
+		assert_eq!(readability.sentences(), 6);
+		assert_eq!(readability.words(), 1 + 3 + 3 + 1 + 3 + 45);
+		assert_eq!(readability.longest(), 45);
+		assert_eq!(readability.distribution()[0], ("1-10".into(), 5));
+		assert_eq!(readability.distribution()[4], ("41+".into(), 1));
+		assert_eq!(
+			readability.frequent(3),
+			[("word", 45), ("fast", 2), ("typst", 2)]
+		);
+
+		readability.add_rule("PASSIVE_VOICE");
+		readability.add_rule("TYPO");
+		assert_eq!(readability.passive, 1);
+	}
+}
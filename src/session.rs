@@ -0,0 +1,279 @@
+//! A check session owning the backend, the project configuration, the check options and the
+//! cached results, so the frontends share the orchestration of checks, fixes and added words.
+
+use std::collections::{HashMap, HashSet};
+
+use typst::{
+	model::Document,
+	syntax::{FileId, Source},
+	text::Lang,
+	World,
+};
+
+use crate::{
+	cache::Cache,
+	compat,
+	config::{Config, Synced},
+	convert::{self, Mapping},
+	events::Event,
+	fix::{self, Fixed},
+	language_entries,
+	suppress::Contexts,
+	CheckOptions, Diagnostic, Error, FileCollector, LanguageToolBackend, Result,
+};
+
+/// The configuration is applied to the backend, the results are cached by paragraph for the
+/// language and the settings of the configuration, see [`crate::cache::Settings`].
+#[derive(Debug)]
+pub struct Session<L> {
+	lt: L,
+	config: Config,
+	options: CheckOptions,
+	cache: Cache,
+	/// Settings hash by language code, the rule and spelling files are read once.
+	hashes: HashMap<String, u64>,
+	synced: Synced,
+}
+
+impl<L: LanguageToolBackend> Session<L> {
+	/// Apply the configuration to the backend, see [`Config::apply`] and [`Session::synced`].
+	/// The options of the checks are taken from the configuration, the others keep their
+	/// defaults, see [`Session::options_mut`].
+	pub async fn new(lt: L, config: Config) -> Result<Self> {
+		let synced = config.apply(&lt).await?;
+		let mut options = CheckOptions::default();
+		configure(&mut options, &config)?;
+		let mut cache = Cache::new();
+		cache.set_version(&lt.version().await?);
+		Ok(Self {
+			lt,
+			config,
+			options,
+			cache,
+			hashes: HashMap::new(),
+			synced,
+		})
+	}
+
+	/// Languages whose settings were sent to the backend and the unknown disabled checks.
+	pub fn synced(&self) -> &Synced {
+		&self.synced
+	}
+
+	pub fn backend(&self) -> &L {
+		&self.lt
+	}
+
+	pub fn config(&self) -> &Config {
+		&self.config
+	}
+
+	pub fn options(&self) -> &CheckOptions {
+		&self.options
+	}
+
+	/// Options of the frontend, like the chunk size suited to the backend or the cancellation.
+	/// The dictionary and disabled checks of the options are not used, they are part of the
+	/// configuration.
+	pub fn options_mut(&mut self) -> &mut CheckOptions {
+		&mut self.options
+	}
+
+	pub fn cache(&self) -> &Cache {
+		&self.cache
+	}
+
+	/// Like for [`Cache::set_limits`] or loading the saved results.
+	pub fn cache_mut(&mut self) -> &mut Cache {
+		&mut self.cache
+	}
+
+	/// Replace the configuration, the changes are applied to the backend,
+	/// see [`Config::apply_changes`].
+	pub async fn set_config(&mut self, config: Config) -> Result<()> {
+		self.synced = config.apply_changes(&self.config, &self.lt).await?;
+		configure(&mut self.options, &config)?;
+		self.config = config;
+		self.hashes.clear();
+		Ok(())
+	}
+
+	/// Allow the word for the language, like after the quick fix of a spelling diagnostic.
+	/// Cached results are not reused, since the word is part of their settings.
+	pub async fn add_word(&mut self, lang: &str, word: &str) -> Result<()> {
+		let words = self.config.dictionary.entry(lang.into()).or_default();
+		if words.iter().any(|known| known == word) {
+			return Ok(());
+		}
+		words.push(word.into());
+		self.lt.allow_words(lang.parse()?, words).await?;
+		self.hashes.clear();
+		Ok(())
+	}
+
+	/// The text with the replacement at `index` of the diagnostic applied,
+	/// `None` if the diagnostic can not be fixed, see [`fix::fixes`].
+	pub fn apply_fix(&self, text: &str, diagnostic: &Diagnostic, index: usize) -> Option<Fixed> {
+		let fixes = fix::fixes(text, diagnostic, index)?;
+		Some(fix::apply_fixes(text, &fixes))
+	}
+
+	/// Compile the document and check the text of the file.
+	pub async fn check_file(
+		&mut self,
+		world: &impl World,
+		file_id: FileId,
+	) -> Result<Vec<Diagnostic>> {
+		let mut files = self.check_project(world, &[file_id]).await?;
+		Ok(files
+			.pop()
+			.map(|(_, diagnostics)| diagnostics)
+			.unwrap_or_default())
+	}
+
+	/// Compile the document once and check the text of every file, in the order of `files`.
+	pub async fn check_project(
+		&mut self,
+		world: &impl World,
+		files: &[FileId],
+	) -> Result<Vec<(FileId, Vec<Diagnostic>)>> {
+		// A document that does not compile emits no events.
+		let doc = compat::compile(world).map_err(Error::Conversion)?;
+		self.options
+			.events
+			.emit(|| Event::CheckStarted { files: files.len() });
+		let mut res = Vec::with_capacity(files.len());
+		for &file_id in files {
+			let diagnostics = self.check_document(&doc, world, file_id).await?;
+			res.push((file_id, diagnostics));
+		}
+		let diagnostics = res.iter().map(|(_, diagnostics)| diagnostics.len()).sum();
+		self.options
+			.events
+			.emit(|| Event::CheckFinished { files: files.len(), diagnostics });
+		Ok(res)
+	}
+
+	/// Check a Typst string without compiling it, see [`crate::check_source`].
+	pub async fn check_source(&mut self, text: &str) -> Result<Vec<Diagnostic>> {
+		self.options
+			.events
+			.emit(|| Event::CheckStarted { files: 1 });
+		let source = Source::detached(text);
+		let chunk_size = self.options.submission.chunk_size(self.options.chunk_size);
+		let paragraphs =
+			convert::markup::document(&source, chunk_size, Lang::ENGLISH, &self.options.convert);
+		let diagnostics = self
+			.collect(FileCollector::from_source(source), paragraphs)
+			.await?;
+		let count = diagnostics.len();
+		self.options
+			.events
+			.emit(|| Event::CheckFinished { files: 1, diagnostics: count });
+		Ok(diagnostics)
+	}
+
+	async fn check_document(
+		&mut self,
+		doc: &Document,
+		world: &impl World,
+		file_id: FileId,
+	) -> Result<Vec<Diagnostic>> {
+		let Ok(source) = world.source(file_id) else {
+			return Err(Error::Conversion(format!(
+				"Missing source for {:?}.",
+				file_id
+			)));
+		};
+		let chunk_size = self.options.submission.chunk_size(self.options.chunk_size);
+		let paragraphs = convert::document(doc, chunk_size, &source, &self.options.convert);
+		self.collect(FileCollector::from_source(source), paragraphs)
+			.await
+	}
+
+	/// Check the chunks through the cache and collect their diagnostics.
+	async fn collect(
+		&mut self,
+		mut collector: FileCollector,
+		paragraphs: Vec<(String, Mapping)>,
+	) -> Result<Vec<Diagnostic>> {
+		collector.set_debug(self.options.debug);
+		collector.set_contexts(self.options.contexts.clone());
+		self.cache.set_submission(self.options.submission);
+		let file = collector.source().id();
+		let chunks = self.options.submission.chunks(paragraphs);
+		let total = chunks.len();
+		let events = self.options.events.clone();
+		events.emit(|| Event::FileConverted { file, chunks: total });
+		events.unsupported_languages(&chunks, &self.options);
+		for (index, (text, mapping)) in chunks.into_iter().enumerate() {
+			self.options.cancellation.check()?;
+			let lang = self.options.chunk_language(&mapping)?;
+			let settings = *self
+				.hashes
+				.entry(lang.to_string())
+				.or_insert_with(|| self.config.settings().hash(&lang));
+			let mut suggestions = self.cache.check(&self.lt, &lang, settings, &text).await?;
+			let dictionary = language_entries(&self.config.dictionary, &lang)
+				.into_iter()
+				.flatten()
+				.map(String::as_str)
+				.collect::<HashSet<_>>();
+			crate::refine(
+				&text,
+				&lang,
+				&mapping,
+				&mut suggestions,
+				&self.options,
+				&dictionary,
+			);
+			collector.add(&text, suggestions, mapping, &lang);
+			events.emit(|| Event::ChunkChecked { file, n: index + 1, total });
+		}
+		let (_, diagnostics) = collector.finish();
+		events.emit(|| Event::DiagnosticsReady { file, diagnostics: diagnostics.clone() });
+		Ok(diagnostics)
+	}
+}
+
+/// Take the converter options, ignored tokens, disabled contexts and languages of the
+/// configuration.
+fn configure(options: &mut CheckOptions, config: &Config) -> Result<()> {
+	options.convert = config.convert_options();
+	options.ignore = config.ignored()?;
+	options.contexts =
+		Contexts::new(config.disabled_contexts.clone()).with_fragments(&config.fragments);
+	options.keep_adjacent = config.keep_adjacent;
+	options.language = config.language.clone();
+	options.languages = config.languages.clone();
+	options.language_fallbacks = config.language_fallbacks.clone();
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::mock::MockBackend;
+
+	#[tokio::test]
+	async fn test_session() {
+		let lt = MockBackend::new().rule("teh", "TYPO", &["the"]);
+		let config = Config {
+			disabled_contexts: HashMap::from([("heading".into(), vec!["TYPO".into()])]),
+			..Config::default()
+		};
+		let mut session = Session::new(lt, config).await.unwrap();
+		let text = "= A teh title\n\nOne teh.\n\nTwo teh.";
+		let diagnostics = session.check_source(text).await.unwrap();
+		assert_eq!(diagnostics.len(), 2);
+		session.check_source(text).await.unwrap();
+		assert_eq!(session.backend().checked_texts().len(), 1);
+
+		let fixed = session.apply_fix(text, &diagnostics[0], 0).unwrap();
+		assert_eq!(fixed.text, "= A teh title\n\nOne the.\n\nTwo teh.");
+
+		session.add_word("en", "teh").await.unwrap();
+		assert!(session.check_source(text).await.unwrap().is_empty());
+		assert_eq!(session.backend().checked_texts().len(), 2);
+	}
+}
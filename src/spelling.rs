@@ -0,0 +1,425 @@
+//! Local spell checking with Hunspell dictionaries or word lists, fast enough to run on every
+//! change while the full check of the backend waits for a pause.
+
+use std::{
+	collections::{BTreeSet, HashMap, HashSet},
+	ops::Not,
+	path::Path,
+};
+
+use crate::{RuleSource, Suggestion};
+
+/// Rule id of the suggestions, a spelling rule for [`Suggestion::is_spelling`].
+pub const RULE_ID: &str = "LOCAL_SPELLING";
+
+/// Maximum number of replacements of a suggestion.
+const MAX_REPLACEMENTS: usize = 5;
+
+/// Words of a dictionary, with the affixes of Hunspell dictionaries applied.
+#[derive(Debug, Clone, Default)]
+pub struct Speller {
+	words: HashSet<String>,
+	/// Lowercase letters of the words, tried for the replacements.
+	letters: BTreeSet<char>,
+}
+
+impl Speller {
+	/// Hunspell `.dic` files are read with the `.aff` file next to them, other files have
+	/// one word per line.
+	pub fn load(path: &Path) -> crate::Result<Self> {
+		let content = std::fs::read_to_string(path)?;
+		if path.extension().is_some_and(|extension| extension == "dic") {
+			let affixes = match std::fs::read_to_string(path.with_extension("aff")) {
+				Ok(affixes) => Some(affixes),
+				Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+				Err(err) => return Err(err.into()),
+			};
+			return Ok(Self::hunspell(&content, affixes.as_deref()));
+		}
+		let mut speller = Self::default();
+		speller.add_words(content.lines().map(str::trim));
+		Ok(speller)
+	}
+
+	/// Words of a Hunspell dictionary like `house/S`, expanded with the prefixes and
+	/// suffixes of the affix file. Compounds and other options of the affix file are ignored.
+	pub fn hunspell(dictionary: &str, affixes: Option<&str>) -> Self {
+		let affixes = affixes.map(Affixes::parse).unwrap_or_default();
+		let mut speller = Self::default();
+		let mut lines = dictionary.lines();
+		// The first line is the number of words.
+		if let Some(first) = lines.next() {
+			if first.trim().parse::<usize>().is_err() {
+				speller.add_words(affixes.expand(first));
+			}
+		}
+		for line in lines {
+			speller.add_words(affixes.expand(line));
+		}
+		speller
+	}
+
+	pub fn add_words<T: AsRef<str>>(&mut self, words: impl IntoIterator<Item = T>) {
+		for word in words {
+			let word = word.as_ref().replace('’', "'");
+			if word.is_empty() || word.starts_with('#') {
+				continue;
+			}
+			self.letters.extend(
+				word.chars()
+					.filter(|c| c.is_alphabetic())
+					.flat_map(char::to_lowercase),
+			);
+			self.words.insert(word);
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.words.is_empty()
+	}
+
+	/// Suggestions for the words of the text missing from the dictionary and `allowed`.
+	/// Single letters and words next to digits are skipped.
+	pub fn check(&self, text: &str, allowed: &HashSet<&str>) -> Vec<Suggestion> {
+		let mut suggestions = Vec::new();
+		for (start, word) in words(text) {
+			let known = |word: &str| allowed.contains(word) || self.knows(word);
+			let possessive = word.strip_suffix("'s").or_else(|| word.strip_suffix("’s"));
+			if known(word) || possessive.is_some_and(known) {
+				continue;
+			}
+			let start_utf16 = text[..start].encode_utf16().count();
+			suggestions.push(Suggestion {
+				start: start_utf16,
+				end: start_utf16 + word.encode_utf16().count(),
+				message: "Possible spelling mistake found.".into(),
+				sentence: String::new(),
+				replacements: self.replacements(word),
+				rule_description: "Spelling of the local dictionary".into(),
+				rule_id: RULE_ID.into(),
+				rule_category: "Possible Typo".into(),
+				rule_urls: Vec::new(),
+				incorrect_examples: Vec::new(),
+				correct_examples: Vec::new(),
+				rule_source: RuleSource::Standard,
+				context_for_sure_match: None,
+			});
+		}
+		suggestions
+	}
+
+	/// The word is in the dictionary, lowercase words also capitalized or in uppercase.
+	fn knows(&self, word: &str) -> bool {
+		let word = word.replace('’', "'");
+		if self.words.contains(&word) {
+			return true;
+		}
+		let mut chars = word.chars();
+		let first = chars.next().is_some_and(char::is_uppercase);
+		let rest_lower = chars.clone().all(|c| c.is_uppercase().not());
+		let upper = word.chars().all(|c| c.is_lowercase().not());
+		if first && (rest_lower || upper) && self.words.contains(&word.to_lowercase()) {
+			return true;
+		}
+		// Uppercase words of capitalized dictionary words, like `TYPST` for `Typst`.
+		upper && {
+			let mut chars = word.chars();
+			let capitalized = chars
+				.next()
+				.into_iter()
+				.chain(chars.flat_map(char::to_lowercase))
+				.collect::<String>();
+			self.words.contains(&capitalized)
+		}
+	}
+
+	/// Words of the dictionary one deletion, transposition, replacement or insertion away,
+	/// in alphabetical order.
+	fn replacements(&self, word: &str) -> Vec<String> {
+		let chars = word.chars().collect::<Vec<_>>();
+		let mut candidates = BTreeSet::new();
+		let mut add = |candidate: Vec<char>| {
+			let candidate = candidate.into_iter().collect::<String>();
+			if self.knows(&candidate) {
+				candidates.insert(candidate);
+			}
+		};
+		let mut capitalized = chars.clone();
+		if let Some(first) = capitalized.first_mut() {
+			*first = first.to_uppercase().next().unwrap_or(*first);
+		}
+		add(capitalized);
+		for index in 0..chars.len() {
+			let mut deleted = chars.clone();
+			deleted.remove(index);
+			add(deleted);
+			if index + 1 < chars.len() {
+				let mut swapped = chars.clone();
+				swapped.swap(index, index + 1);
+				add(swapped);
+			}
+		}
+		for index in 0..=chars.len() {
+			for &letter in &self.letters {
+				if index < chars.len() && chars[index] != letter {
+					let mut replaced = chars.clone();
+					replaced[index] = letter;
+					add(replaced);
+				}
+				let mut inserted = chars.clone();
+				inserted.insert(index, letter);
+				add(inserted);
+			}
+		}
+		candidates.into_iter().take(MAX_REPLACEMENTS).collect()
+	}
+}
+
+/// Byte offsets and words of the text, runs of letters with apostrophes between them.
+fn words(text: &str) -> Vec<(usize, &str)> {
+	let mut words = Vec::new();
+	let mut start = None;
+	let mut chars = text.char_indices().peekable();
+	while let Some((index, c)) = chars.next() {
+		let next = chars.peek().map(|&(_, c)| c);
+		let apostrophe =
+			matches!(c, '\'' | '’') && start.is_some() && next.is_some_and(char::is_alphabetic);
+		if c.is_alphabetic() || apostrophe {
+			start.get_or_insert(index);
+			continue;
+		}
+		if let Some(start) = start.take() {
+			words.push((start, &text[start..index]));
+		}
+	}
+	if let Some(start) = start {
+		words.push((start, &text[start..]));
+	}
+	words.retain(|&(start, word)| {
+		let before = text[..start].chars().next_back();
+		let after = text[start + word.len()..].chars().next();
+		let digit = |c: Option<char>| c.is_some_and(|c| c.is_numeric() || c == '_');
+		word.chars().count() > 1 && digit(before).not() && digit(after).not()
+	});
+	words
+}
+
+/// Prefixes and suffixes of a Hunspell affix file by their flag.
+#[derive(Debug, Default)]
+struct Affixes {
+	flags: FlagType,
+	rules: HashMap<String, Vec<Affix>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum FlagType {
+	/// One char per flag.
+	#[default]
+	Char,
+	/// Two chars per flag.
+	Long,
+	/// Numbers separated by `,`.
+	Number,
+}
+
+#[derive(Debug)]
+struct Affix {
+	prefix: bool,
+	/// Combines with affixes of the other kind.
+	cross: bool,
+	strip: String,
+	add: String,
+	condition: Vec<Condition>,
+}
+
+/// Char of the condition, like `.`, `y` or `[^aeiou]`.
+#[derive(Debug)]
+enum Condition {
+	Any,
+	OneOf(Vec<char>),
+	NoneOf(Vec<char>),
+}
+
+impl Condition {
+	fn matches(&self, c: char) -> bool {
+		match self {
+			Self::Any => true,
+			Self::OneOf(chars) => chars.contains(&c),
+			Self::NoneOf(chars) => chars.contains(&c).not(),
+		}
+	}
+}
+
+impl Affixes {
+	fn parse(content: &str) -> Self {
+		let mut affixes = Self::default();
+		let mut cross = HashMap::new();
+		for line in content.lines() {
+			let fields = line.split_whitespace().collect::<Vec<_>>();
+			match fields.as_slice() {
+				["FLAG", "long", ..] => affixes.flags = FlagType::Long,
+				["FLAG", "num", ..] => affixes.flags = FlagType::Number,
+				[kind @ ("PFX" | "SFX"), flag, combine, count]
+					if count.parse::<usize>().is_ok() =>
+				{
+					cross.insert((*kind, flag.to_string()), *combine == "Y");
+				},
+				[kind @ ("PFX" | "SFX"), flag, strip, add, rest @ ..] => {
+					let zero = |value: &str| {
+						if value == "0" {
+							String::new()
+						} else {
+							value.into()
+						}
+					};
+					// Flags of the affixed word like `s/M` are ignored.
+					let add = add.split('/').next().unwrap_or_default();
+					let affix = Affix {
+						prefix: *kind == "PFX",
+						cross: cross
+							.get(&(*kind, flag.to_string()))
+							.copied()
+							.unwrap_or_default(),
+						strip: zero(strip),
+						add: zero(add),
+						condition: parse_condition(rest.first().copied().unwrap_or(".")),
+					};
+					affixes
+						.rules
+						.entry(flag.to_string())
+						.or_default()
+						.push(affix);
+				},
+				_ => {},
+			}
+		}
+		affixes
+	}
+
+	/// The word of a dictionary line and its forms with the affixes of its flags.
+	fn expand(&self, line: &str) -> Vec<String> {
+		let entry = line.split(['\t', ' ']).next().unwrap_or_default();
+		let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+		let affixes = self
+			.flags(flags)
+			.into_iter()
+			.filter_map(|flag| self.rules.get(&flag))
+			.flatten()
+			.collect::<Vec<_>>();
+		let mut words = vec![word.to_owned()];
+		let mut suffixed = Vec::new();
+		for affix in affixes.iter().filter(|affix| affix.prefix.not()) {
+			if let Some(suffixed_word) = affix.apply(word) {
+				suffixed.push((suffixed_word, affix.cross));
+			}
+		}
+		for affix in affixes.iter().filter(|affix| affix.prefix) {
+			words.extend(affix.apply(word));
+			if affix.cross {
+				let crossed = suffixed.iter().filter(|(_, cross)| *cross);
+				words.extend(crossed.filter_map(|(word, _)| affix.apply(word)));
+			}
+		}
+		words.extend(suffixed.into_iter().map(|(word, _)| word));
+		words
+	}
+
+	fn flags(&self, flags: &str) -> Vec<String> {
+		match self.flags {
+			FlagType::Char => flags.chars().map(String::from).collect(),
+			FlagType::Long => {
+				let chars = flags.chars().collect::<Vec<_>>();
+				chars.chunks(2).map(|flag| flag.iter().collect()).collect()
+			},
+			FlagType::Number => flags.split(',').map(str::to_owned).collect(),
+		}
+	}
+}
+
+impl Affix {
+	fn apply(&self, word: &str) -> Option<String> {
+		let chars = word.chars().collect::<Vec<_>>();
+		if chars.len() < self.condition.len() {
+			return None;
+		}
+		let part = if self.prefix {
+			&chars[..self.condition.len()]
+		} else {
+			&chars[chars.len() - self.condition.len()..]
+		};
+		let matches = part
+			.iter()
+			.zip(&self.condition)
+			.all(|(&c, condition)| condition.matches(c));
+		if matches.not() {
+			return None;
+		}
+		if self.prefix {
+			let rest = word.strip_prefix(self.strip.as_str())?;
+			Some(format!("{}{}", self.add, rest))
+		} else {
+			let rest = word.strip_suffix(self.strip.as_str())?;
+			Some(format!("{}{}", rest, self.add))
+		}
+	}
+}
+
+fn parse_condition(condition: &str) -> Vec<Condition> {
+	let mut conditions = Vec::new();
+	let mut chars = condition.chars();
+	while let Some(c) = chars.next() {
+		let condition = match c {
+			'.' => Condition::Any,
+			'[' => {
+				let set = chars.by_ref().take_while(|&c| c != ']').collect::<Vec<_>>();
+				match set.split_first() {
+					Some(('^', rest)) => Condition::NoneOf(rest.to_vec()),
+					_ => Condition::OneOf(set),
+				}
+			},
+			c => Condition::OneOf(vec![c]),
+		};
+		conditions.push(condition);
+	}
+	conditions
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_speller() {
+		let affixes = "SET UTF-8\nPFX U Y 1\nPFX U 0 un .\nSFX S Y 2\nSFX S y ies [^aeiou]y\nSFX S 0 s [^y]\n";
+		let speller = Speller::hunspell("4\nhouse/S\ncity/S\ndo/U\nTypst\n", Some(affixes));
+		let check = |text: &str| {
+			let allowed = HashSet::from(["LanguageTool"]);
+			speller
+				.check(text, &allowed)
+				.into_iter()
+				.map(|suggestion| {
+					let range = suggestion.offsets(text).utf_8;
+					(text[range].to_owned(), suggestion.replacements)
+				})
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(
+			check("Houses, cities and TYPST undo LanguageTool’s housse 2cities."),
+			[
+				("and".to_owned(), Vec::new()),
+				(
+					"housse".to_owned(),
+					vec!["house".to_owned(), "houses".to_owned()]
+				)
+			]
+		);
+		assert_eq!(
+			check("typst citys"),
+			[
+				("typst".to_owned(), vec!["Typst".to_owned()]),
+				("citys".to_owned(), vec!["city".to_owned()]),
+			]
+		);
+		assert!(Speller::default().is_empty());
+	}
+}
@@ -0,0 +1,288 @@
+//! Inline comments disabling rules for the next line, like `// lt-disable-next-line WHITESPACE_RULE`.
+//! A comment without rules disables all rules of the line.
+//! Rules can also be disabled in parts of the document, like casing rules in headings.
+
+use std::{
+	collections::HashMap,
+	ops::{Not, Range},
+};
+
+use typst::syntax::{
+	ast::{self, AstNode},
+	LinkedNode, Source, SyntaxKind,
+};
+
+pub const DISABLE_NEXT_LINE: &str = "lt-disable-next-line";
+
+/// Rules expecting complete sentences, disabled in the parts of [`Contexts::with_fragments`].
+pub const FRAGMENT_RULES: [&str; 3] = [
+	"UPPERCASE_SENTENCE_START",
+	"PUNCTUATION_PARAGRAPH_END",
+	"SENTENCE_FRAGMENT",
+];
+
+/// Rules disabled by the comments of a source.
+#[derive(Debug, Clone, Default)]
+pub struct Suppressions {
+	/// Disabled rules and the range of the comment for every disabling line.
+	lines: HashMap<usize, (Range<usize>, Vec<String>)>,
+}
+
+impl Suppressions {
+	pub fn new(source: &Source) -> Self {
+		let mut suppressions = Self::default();
+		suppressions.collect(source, &LinkedNode::new(source.root()));
+		suppressions
+	}
+
+	fn collect(&mut self, source: &Source, node: &LinkedNode) {
+		if node.kind() == SyntaxKind::LineComment {
+			let Some(rules) = node
+				.text()
+				.trim_start_matches('/')
+				.trim()
+				.strip_prefix(DISABLE_NEXT_LINE)
+			else {
+				return;
+			};
+			if rules.starts_with(|c: char| c.is_whitespace()).not() && rules.is_empty().not() {
+				return;
+			}
+			let Some(line) = source.byte_to_line(node.offset()) else {
+				return;
+			};
+			let rules = rules
+				.split(|c: char| c.is_whitespace() || c == ',')
+				.filter(|rule| rule.is_empty().not())
+				.map(String::from)
+				.collect();
+			self.lines.insert(line + 1, (node.range(), rules));
+			return;
+		}
+		for child in node.children() {
+			self.collect(source, &child);
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.lines.is_empty()
+	}
+
+	/// The rule is disabled for the line by a comment on the previous line.
+	pub fn suppresses(&self, line: usize, rule_id: &str) -> bool {
+		self.lines
+			.get(&line)
+			.is_some_and(|(_, rules)| rules.is_empty() || rules.iter().any(|rule| rule == rule_id))
+	}
+
+	/// Edit disabling the rule for the line containing `offset`, as the replaced range and text.
+	/// The rule is added to an existing comment, otherwise a comment with the indentation of the
+	/// line is inserted above it. Lines starting in raw text, a string or a block comment can not
+	/// be preceded by a comment.
+	pub fn disable(
+		&self,
+		source: &Source,
+		offset: usize,
+		rule_id: &str,
+	) -> Option<(Range<usize>, String)> {
+		let line = source.byte_to_line(offset)?;
+		if let Some((comment, _)) = self.lines.get(&line) {
+			return Some((comment.end..comment.end, format!(" {}", rule_id)));
+		}
+		let range = source.line_to_range(line)?;
+		let text = &source.text()[range.clone()];
+		let indent = &text[..text.len() - text.trim_start_matches([' ', '\t']).len()];
+		let root = LinkedNode::new(source.root());
+		if let Some(leaf) = root.leaf_at(range.start + 1) {
+			let mut node = Some(leaf);
+			while let Some(current) = node {
+				if matches!(
+					current.kind(),
+					SyntaxKind::Raw | SyntaxKind::Str | SyntaxKind::BlockComment
+				) {
+					return None;
+				}
+				node = current.parent().cloned();
+			}
+		}
+		Some((
+			range.start..range.start,
+			format!("{}// {} {}\n", indent, DISABLE_NEXT_LINE, rule_id),
+		))
+	}
+}
+
+/// Rules or categories disabled by the name of the part of the document containing the flagged
+/// text, see [`Contexts::names`].
+#[derive(Debug, Clone, Default)]
+pub struct Contexts {
+	rules: HashMap<String, Vec<String>>,
+}
+
+impl Contexts {
+	pub fn new(rules: HashMap<String, Vec<String>>) -> Self {
+		Self { rules }
+	}
+
+	/// Also disable the [`FRAGMENT_RULES`] in the named parts, like `figure.caption` or `list`,
+	/// whose text is often not a complete sentence.
+	pub fn with_fragments(mut self, names: &[String]) -> Self {
+		for name in names {
+			let rules = self.rules.entry(name.clone()).or_default();
+			for rule in FRAGMENT_RULES {
+				if rules.iter().any(|existing| existing == rule).not() {
+					rules.push(rule.to_owned());
+				}
+			}
+		}
+		self
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.rules.values().all(Vec::is_empty)
+	}
+
+	/// The rule or its category, ignoring the case, is disabled in a part containing `offset`.
+	pub fn suppresses(
+		&self,
+		source: &Source,
+		offset: usize,
+		rule_id: &str,
+		category: &str,
+	) -> bool {
+		if self.is_empty() {
+			return false;
+		}
+		Self::names(source, offset).iter().any(|name| {
+			self.rules.get(name).is_some_and(|rules| {
+				rules
+					.iter()
+					.any(|rule| rule == rule_id || rule.eq_ignore_ascii_case(category))
+			})
+		})
+	}
+
+	/// Names of the parts containing `offset`, innermost first. The markup of headings, list items,
+	/// strong and emphasized text and links is named `heading`, `list`, `enum`, `term`, `strong`,
+	/// `emph` and `link`, function calls by the function, like `table` or `table.cell`, and their
+	/// named arguments with the function, like `figure.caption`.
+	pub fn names(source: &Source, offset: usize) -> Vec<String> {
+		let root = LinkedNode::new(source.root());
+		let Some(leaf) = root.leaf_at(offset + 1) else {
+			return Vec::new();
+		};
+		let mut names = Vec::new();
+		let mut node = Some(leaf);
+		while let Some(current) = node {
+			let name = match current.kind() {
+				SyntaxKind::Heading => Some("heading".to_owned()),
+				SyntaxKind::ListItem => Some("list".to_owned()),
+				SyntaxKind::EnumItem => Some("enum".to_owned()),
+				SyntaxKind::TermItem => Some("term".to_owned()),
+				SyntaxKind::Strong => Some("strong".to_owned()),
+				SyntaxKind::Emph => Some("emph".to_owned()),
+				SyntaxKind::Link => Some("link".to_owned()),
+				SyntaxKind::FuncCall => current
+					.cast::<ast::FuncCall>()
+					.map(|call| callee_name(call.callee())),
+				SyntaxKind::Named => {
+					let named = current.cast::<ast::Named>();
+					let call = current
+						.parent()
+						.and_then(|args| args.parent())
+						.and_then(|call| call.cast::<ast::FuncCall>());
+					named.zip(call).map(|(named, call)| {
+						format!("{}.{}", callee_name(call.callee()), named.name().as_str())
+					})
+				},
+				_ => None,
+			};
+			names.extend(name);
+			node = current.parent().cloned();
+		}
+		names
+	}
+}
+
+/// Name of a called function like `table` or `table.cell`, without whitespace.
+fn callee_name(callee: ast::Expr) -> String {
+	callee
+		.to_untyped()
+		.clone()
+		.into_text()
+		.chars()
+		.filter(|c| c.is_whitespace().not())
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_suppressions() {
+		let source = Source::detached(
+			"Some text.\n  // lt-disable-next-line A, B\n  Flagged line.\n// lt-disable-next-line\nAll rules.\n// lt-disable-next-lineC\nNot disabled.\n",
+		);
+		let suppressions = Suppressions::new(&source);
+		assert!(suppressions.suppresses(2, "A"));
+		assert!(suppressions.suppresses(2, "B"));
+		assert!(suppressions.suppresses(2, "C").not());
+		assert!(suppressions.suppresses(4, "C"));
+		assert!(suppressions.suppresses(6, "C").not());
+		assert!(suppressions.suppresses(1, "A").not());
+
+		let offset = source.text().find("Flagged").unwrap();
+		let (range, text) = suppressions.disable(&source, offset, "C").unwrap();
+		assert_eq!(
+			&source.text()[..range.start],
+			"Some text.\n  // lt-disable-next-line A, B"
+		);
+		assert_eq!(text, " C");
+
+		let (range, text) = suppressions.disable(&source, 3, "C").unwrap();
+		assert_eq!(range, 0..0);
+		assert_eq!(text, "// lt-disable-next-line C\n");
+
+		let source = Source::detached("```\nraw text\n```\n");
+		let suppressions = Suppressions::new(&source);
+		assert!(suppressions.disable(&source, 5, "C").is_none());
+	}
+
+	#[test]
+	fn test_contexts() {
+		let source = Source::detached(
+			"= A Heading\n\n#table([*Cell*])\n\n#figure(image(\"a.png\"), caption: [A caption])\n\n- item\n",
+		);
+		let names = |text: &str| Contexts::names(&source, source.text().find(text).unwrap());
+		assert_eq!(names("Heading"), ["heading"]);
+		assert_eq!(names("Cell"), ["strong", "table"]);
+		assert_eq!(names("caption]"), ["figure.caption", "figure"]);
+		assert_eq!(names("item"), ["list"]);
+
+		let contexts = Contexts::new(HashMap::from([
+			("heading".into(), vec!["capitalization".into()]),
+			("table".into(), vec!["COMMA_RULE".into()]),
+		]));
+		let offset = source.text().find("Heading").unwrap();
+		assert!(contexts.suppresses(&source, offset, "UPPERCASE", "Capitalization"));
+		assert!(contexts
+			.suppresses(&source, offset, "COMMA_RULE", "Punctuation")
+			.not());
+		let offset = source.text().find("Cell").unwrap();
+		assert!(contexts.suppresses(&source, offset, "COMMA_RULE", "Punctuation"));
+		assert!(contexts
+			.suppresses(&source, offset, "UPPERCASE_SENTENCE_START", "Casing")
+			.not());
+
+		let contexts = contexts.with_fragments(&["table".into(), "list".into()]);
+		assert!(contexts.suppresses(&source, offset, "UPPERCASE_SENTENCE_START", "Casing"));
+		assert!(contexts.suppresses(&source, offset, "COMMA_RULE", "Punctuation"));
+		let offset = source.text().find("item").unwrap();
+		assert!(contexts.suppresses(&source, offset, "PUNCTUATION_PARAGRAPH_END", "Punctuation"));
+		let offset = source.text().find("caption]").unwrap();
+		assert!(contexts
+			.suppresses(&source, offset, "UPPERCASE_SENTENCE_START", "Casing")
+			.not());
+	}
+}
@@ -0,0 +1,160 @@
+//! Spans for the compilation, conversion, backend requests and mapping,
+//! recorded with the `tracing` feature and empty without it.
+
+use std::future::Future;
+
+#[cfg(feature = "tracing")]
+pub use timings::print_timings;
+#[cfg(feature = "tracing")]
+pub use tracing::Span;
+
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone)]
+pub struct Span;
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+	pub fn entered(self) -> Self {
+		self
+	}
+}
+
+/// Span at info level, like `span!("convert", pages = doc.pages.len())`.
+macro_rules! span {
+	($name:literal $(, $field:ident = $value:expr)* $(,)?) => {{
+		#[cfg(feature = "tracing")]
+		let span = tracing::info_span!($name $(, $field = $value)*);
+		#[cfg(not(feature = "tracing"))]
+		let span = {
+			$(let _ = &$value;)*
+			$crate::trace::Span
+		};
+		span
+	}};
+}
+pub(crate) use span;
+
+/// Run the future in the span, entering a span across `.await` is not possible.
+pub async fn instrument<F: Future>(span: Span, future: F) -> F::Output {
+	#[cfg(feature = "tracing")]
+	{
+		use tracing::Instrument;
+		future.instrument(span).await
+	}
+	#[cfg(not(feature = "tracing"))]
+	{
+		let _ = span;
+		future.await
+	}
+}
+
+#[cfg(feature = "tracing")]
+mod timings {
+	use std::{
+		collections::HashMap,
+		fmt::{self, Write},
+		sync::{
+			atomic::{AtomicU64, Ordering},
+			Mutex,
+		},
+		time::Instant,
+	};
+
+	use tracing::{
+		field::{Field, Visit},
+		span::{Attributes, Id, Record},
+		Event, Metadata, Subscriber,
+	};
+
+	/// Print the duration and fields of every span of this crate to stderr when it is closed.
+	/// Fails if a global subscriber is already set.
+	pub fn print_timings() -> Result<(), tracing::subscriber::SetGlobalDefaultError> {
+		tracing::subscriber::set_global_default(Timings::default())
+	}
+
+	#[derive(Default)]
+	struct Timings {
+		next_id: AtomicU64,
+		spans: Mutex<HashMap<u64, Timing>>,
+	}
+
+	struct Timing {
+		name: &'static str,
+		fields: String,
+		start: Instant,
+		/// Handles of the span, it is closed when the last one is dropped.
+		references: usize,
+	}
+
+	struct Fields<'a>(&'a mut String);
+
+	impl Visit for Fields<'_> {
+		fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+			let _ = write!(self.0, " {}={:?}", field.name(), value);
+		}
+
+		fn record_str(&mut self, field: &Field, value: &str) {
+			let _ = write!(self.0, " {}={}", field.name(), value);
+		}
+	}
+
+	impl Subscriber for Timings {
+		fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+			metadata.target().starts_with("typst_languagetool")
+		}
+
+		fn new_span(&self, span: &Attributes<'_>) -> Id {
+			let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+			let mut fields = String::new();
+			span.record(&mut Fields(&mut fields));
+			let timing = Timing {
+				name: span.metadata().name(),
+				fields,
+				start: Instant::now(),
+				references: 1,
+			};
+			self.spans.lock().unwrap().insert(id, timing);
+			Id::from_u64(id)
+		}
+
+		fn record(&self, span: &Id, values: &Record<'_>) {
+			if let Some(timing) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+				values.record(&mut Fields(&mut timing.fields));
+			}
+		}
+
+		fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+		fn event(&self, _event: &Event<'_>) {}
+
+		fn enter(&self, _span: &Id) {}
+
+		fn exit(&self, _span: &Id) {}
+
+		fn clone_span(&self, id: &Id) -> Id {
+			if let Some(timing) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+				timing.references += 1;
+			}
+			id.clone()
+		}
+
+		fn try_close(&self, id: Id) -> bool {
+			let mut spans = self.spans.lock().unwrap();
+			let Some(timing) = spans.get_mut(&id.into_u64()) else {
+				return false;
+			};
+			timing.references -= 1;
+			if timing.references > 0 {
+				return false;
+			}
+			let timing = spans.remove(&id.into_u64()).unwrap();
+			eprintln!(
+				"[trace] {:>10.2?} {}{}",
+				timing.start.elapsed(),
+				timing.name,
+				timing.fields
+			);
+			true
+		}
+	}
+}